@@ -0,0 +1,61 @@
+use criterion::{criterion_group, criterion_main, Criterion, Throughput};
+use prose::translator::render::render_events;
+use prose::translator::HtmlRenderer;
+
+// a section with most of the syntax, repeated until the document is a few megabytes
+const SECTION: &str = "## Section
+
+Some *italic*, **bold** and `code` in a paragraph with a [link](https://example.com \"title\"),
+which goes on to a second line & has a few <characters> to \"escape\".
+
+- an item
+- another with ~~strike~~
+  1. nested
+  2. and ==marked==
+
+> a quote with a footnote[^1]
+
+| a | b |
+|---|:-:|
+| 1 | 2 |
+
+```rust
+fn main() {
+    println!(\"<hello>\");
+}
+```
+
+[^1]: the note
+";
+
+fn document(size: usize) -> String {
+    SECTION.repeat(size / SECTION.len() + 1)
+}
+
+fn bench_convert(c: &mut Criterion) {
+    let content = document(4 << 20);
+    let markdown = prose::parse(&content).unwrap();
+    let mut group = c.benchmark_group("convert");
+    group.sample_size(10);
+    group.throughput(Throughput::Bytes(content.len() as u64));
+    group.bench_function("parse", |b| b.iter(|| prose::parse(&content).unwrap()));
+    group.bench_function("to_html", |b| b.iter(|| prose::to_html(&markdown)));
+    group.bench_function("render_events", |b| {
+        b.iter(|| render_events(prose::Parser::new(&content), &mut HtmlRenderer::new()))
+    });
+    group.finish();
+}
+
+// a paragraph of a few very long lines, which are mostly plain text
+fn bench_long_lines(c: &mut Criterion) {
+    let line = "plain words, snake_case and an *emphasis* now and then; ".repeat(1 << 14);
+    let content = format!("{}\n", line).repeat(4);
+    let mut group = c.benchmark_group("long_lines");
+    group.sample_size(10);
+    group.throughput(Throughput::Bytes(content.len() as u64));
+    group.bench_function("parse", |b| b.iter(|| prose::parse(&content).unwrap()));
+    group.finish();
+}
+
+criterion_group!(benches, bench_convert, bench_long_lines);
+criterion_main!(benches);
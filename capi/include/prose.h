@@ -0,0 +1,99 @@
+#ifndef PROSE_H
+#define PROSE_H
+
+/* Generated by cbindgen from src/lib.rs. Do not edit by hand. */
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+#define PROSE_DIALECT_PROSE 0
+
+#define PROSE_DIALECT_COMMONMARK 1
+
+#define PROSE_DIALECT_GFM 2
+
+/*
+ The options of `prose_to_html`, which `prose_options_default` fills with the defaults.
+ The strings are UTF-8 and NUL-terminated, or NULL for none.
+ */
+typedef struct ProseOptions {
+  /*
+   One of the `PROSE_DIALECT_` constants.
+   */
+  uint32_t dialect;
+  /*
+   The syntax extensions turned on and off on top of the dialect, separated by commas,
+   such as `"math,wikilinks"`.
+   */
+  const char *enable;
+  const char *disable;
+  /*
+   Escape `&`, `<`, `>` and quotes in the text. Turn this off only for trusted input.
+   */
+  bool escape;
+  /*
+   A complete HTML document instead of a fragment.
+   */
+  bool standalone;
+  bool xhtml;
+  bool pretty;
+  /*
+   Keep only the HTML which cannot run scripts, for untrusted input.
+   */
+  bool sanitize;
+  bool heading_anchors;
+  /*
+   The relative URLs of the links and the images are resolved against this.
+   */
+  const char *base_url;
+} ProseOptions;
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+/*
+ The default options, which the fields can be changed on.
+ */
+struct ProseOptions prose_options_default(void);
+
+/*
+ The HTML of `len` bytes of Markdown at `input`, which need not end with NUL,
+ or NULL when they are not UTF-8 or the options are invalid.
+ The options are the defaults when `options` is NULL.
+
+ # Safety
+
+ `input` points to `len` readable bytes, and `options` is NULL or points to options
+ whose strings are NULL or NUL-terminated. The result is freed with `prose_free`.
+ */
+char *prose_to_html(const char *input, size_t len, const struct ProseOptions *options);
+
+/*
+ Frees a string returned by prose. NULL is fine.
+
+ # Safety
+
+ `s` is NULL or a string returned by prose which is not freed yet.
+ */
+void prose_free(char *s);
+
+/*
+ Why the last call on this thread returned NULL, or NULL when none has.
+ The message is owned by prose, and is valid until the next call on the thread.
+ */
+const char *prose_last_error(void);
+
+/*
+ The version of prose, which is owned by prose.
+ */
+const char *prose_version(void);
+
+#ifdef __cplusplus
+}  // extern "C"
+#endif  // __cplusplus
+
+#endif  /* PROSE_H */
@@ -0,0 +1,240 @@
+//! The C API of prose. `include/prose.h` is generated from this file with
+//! `cbindgen --config cbindgen.toml --output include/prose.h`.
+//!
+//! The strings prose returns are owned by the caller, who gives them back to `prose_free`.
+//! No panic crosses the boundary: a call which fails returns NULL, and `prose_last_error` tells why.
+
+use prose::translator;
+use prose::{Dialect, ParseOptions};
+
+use std::cell::RefCell;
+use std::ffi::{c_char, CStr, CString};
+use std::panic::{self, AssertUnwindSafe};
+use std::ptr;
+
+pub const PROSE_DIALECT_PROSE: u32 = 0;
+pub const PROSE_DIALECT_COMMONMARK: u32 = 1;
+pub const PROSE_DIALECT_GFM: u32 = 2;
+
+/// The options of `prose_to_html`, which `prose_options_default` fills with the defaults.
+/// The strings are UTF-8 and NUL-terminated, or NULL for none.
+#[repr(C)]
+pub struct ProseOptions {
+    /// One of the `PROSE_DIALECT_` constants.
+    pub dialect: u32,
+    /// The syntax extensions turned on and off on top of the dialect, separated by commas,
+    /// such as `"math,wikilinks"`.
+    pub enable: *const c_char,
+    pub disable: *const c_char,
+    /// Escape `&`, `<`, `>` and quotes in the text. Turn this off only for trusted input.
+    pub escape: bool,
+    /// A complete HTML document instead of a fragment.
+    pub standalone: bool,
+    pub xhtml: bool,
+    pub pretty: bool,
+    /// Keep only the HTML which cannot run scripts, for untrusted input.
+    pub sanitize: bool,
+    pub heading_anchors: bool,
+    /// The relative URLs of the links and the images are resolved against this.
+    pub base_url: *const c_char,
+}
+
+thread_local! {
+    static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+}
+
+fn set_error(message: &str) {
+    let message = CString::new(message.replace('\0', "")).unwrap_or_default();
+    LAST_ERROR.with(|error| *error.borrow_mut() = Some(message));
+}
+
+/// The default options, which the fields can be changed on.
+#[no_mangle]
+pub extern "C" fn prose_options_default() -> ProseOptions {
+    ProseOptions {
+        dialect: PROSE_DIALECT_PROSE,
+        enable: ptr::null(),
+        disable: ptr::null(),
+        escape: true,
+        standalone: false,
+        xhtml: false,
+        pretty: false,
+        sanitize: false,
+        heading_anchors: false,
+        base_url: ptr::null(),
+    }
+}
+
+/// The HTML of `len` bytes of Markdown at `input`, which need not end with NUL,
+/// or NULL when they are not UTF-8 or the options are invalid.
+/// The options are the defaults when `options` is NULL.
+///
+/// # Safety
+///
+/// `input` points to `len` readable bytes, and `options` is NULL or points to options
+/// whose strings are NULL or NUL-terminated. The result is freed with `prose_free`.
+#[no_mangle]
+pub unsafe extern "C" fn prose_to_html(
+    input: *const c_char,
+    len: usize,
+    options: *const ProseOptions,
+) -> *mut c_char {
+    let result = panic::catch_unwind(AssertUnwindSafe(|| {
+        let bytes = match (input.is_null(), len) {
+            (_, 0) => &[][..],
+            (true, _) => return Err(String::from("The input is NULL")),
+            (false, _) => std::slice::from_raw_parts(input as *const u8, len),
+        };
+        let content =
+            std::str::from_utf8(bytes).map_err(|err| format!("The input is not UTF-8: {}", err))?;
+        let (parse, html) = match options.as_ref() {
+            Some(options) => convert_options(options)?,
+            None => (ParseOptions::default(), translator::Options::default()),
+        };
+        let (markdown, warnings) = prose::parse_with_options(content, parse);
+        if let Some(error) = warnings.iter().find(|warning| warning.limit.is_some()) {
+            return Err(error.to_string());
+        }
+        Ok(translator::translate(markdown, &html))
+    }));
+    let message = match result {
+        // a NUL in the input is written as U+FFFD, as CommonMark does, so it cannot end the string
+        Ok(Ok(html)) => match CString::new(html.replace('\0', "\u{fffd}")) {
+            Ok(html) => return html.into_raw(),
+            Err(_) => String::from("The HTML has a NUL in it"),
+        },
+        Ok(Err(message)) => message,
+        Err(_) => String::from("prose panicked"),
+    };
+    set_error(&message);
+    ptr::null_mut()
+}
+
+/// Frees a string returned by prose. NULL is fine.
+///
+/// # Safety
+///
+/// `s` is NULL or a string returned by prose which is not freed yet.
+#[no_mangle]
+pub unsafe extern "C" fn prose_free(s: *mut c_char) {
+    if !s.is_null() {
+        drop(CString::from_raw(s));
+    }
+}
+
+/// Why the last call on this thread returned NULL, or NULL when none has.
+/// The message is owned by prose, and is valid until the next call on the thread.
+#[no_mangle]
+pub extern "C" fn prose_last_error() -> *const c_char {
+    LAST_ERROR.with(|error| error.borrow().as_ref().map_or(ptr::null(), |e| e.as_ptr()))
+}
+
+/// The version of prose, which is owned by prose.
+#[no_mangle]
+pub extern "C" fn prose_version() -> *const c_char {
+    concat!(env!("CARGO_PKG_VERSION"), "\0").as_ptr() as *const c_char
+}
+
+unsafe fn string(s: *const c_char, name: &str) -> Result<Option<String>, String> {
+    if s.is_null() {
+        return Ok(None);
+    }
+    match CStr::from_ptr(s).to_str() {
+        Ok(s) => Ok(Some(s.to_string())),
+        Err(_) => Err(format!("The option {} is not UTF-8", name)),
+    }
+}
+
+unsafe fn convert_options(
+    options: &ProseOptions,
+) -> Result<(ParseOptions, translator::Options), String> {
+    let dialect = match options.dialect {
+        PROSE_DIALECT_PROSE => Dialect::Prose,
+        PROSE_DIALECT_COMMONMARK => Dialect::CommonMark,
+        PROSE_DIALECT_GFM => Dialect::Gfm,
+        n => return Err(format!("Unknown dialect {}", n)),
+    };
+    let extensions = |names: Option<String>| {
+        names
+            .unwrap_or_default()
+            .split(',')
+            .map(str::trim)
+            .filter(|name| !name.is_empty())
+            .map(str::parse)
+            .collect::<Result<_, String>>()
+    };
+    let parse = ParseOptions {
+        dialect,
+        enabled: extensions(string(options.enable, "enable")?)?,
+        disabled: extensions(string(options.disable, "disable")?)?,
+        ..ParseOptions::default()
+    };
+    let html = translator::Options {
+        escape: options.escape,
+        standalone: options.standalone,
+        xhtml: options.xhtml,
+        pretty: options.pretty,
+        sanitize: options.sanitize,
+        heading_anchors: options.heading_anchors,
+        base_url: string(options.base_url, "base_url")?,
+        ..translator::Options::default()
+    };
+    Ok((parse, html))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    unsafe fn to_html(input: &str, options: *const ProseOptions) -> Result<String, String> {
+        let html = prose_to_html(input.as_ptr() as *const c_char, input.len(), options);
+        if html.is_null() {
+            let error = CStr::from_ptr(prose_last_error());
+            return Err(error.to_str().unwrap().to_string());
+        }
+        let out = CStr::from_ptr(html).to_str().unwrap().to_string();
+        prose_free(html);
+        Ok(out)
+    }
+
+    #[test]
+    fn test_to_html() {
+        unsafe {
+            assert_eq!(
+                to_html("# a\0b\n", ptr::null()),
+                Ok(String::from("<h1 id=\"ab\">a\u{fffd}b</h1>"))
+            );
+            let enable = CString::new("math").unwrap();
+            let options = ProseOptions {
+                dialect: PROSE_DIALECT_GFM,
+                enable: enable.as_ptr(),
+                xhtml: true,
+                ..prose_options_default()
+            };
+            assert_eq!(
+                to_html("a  \nb $x$", &options),
+                Ok(String::from(
+                    "<p>a<br />b <span class=\"math inline\">\\(x\\)</span></p>"
+                ))
+            );
+            let options = ProseOptions {
+                dialect: 7,
+                ..prose_options_default()
+            };
+            assert_eq!(
+                to_html("a", &options),
+                Err(String::from("Unknown dialect 7"))
+            );
+            let bytes = [0xffu8];
+            let html = prose_to_html(bytes.as_ptr() as *const c_char, 1, ptr::null());
+            assert!(html.is_null());
+            assert!(CStr::from_ptr(prose_last_error())
+                .to_str()
+                .unwrap()
+                .starts_with("The input is not UTF-8"));
+            assert_eq!(to_html("", ptr::null()), Ok(String::new()));
+            prose_free(ptr::null_mut());
+            assert_eq!(CStr::from_ptr(prose_version()).to_str(), Ok("0.0.1"));
+        }
+    }
+}
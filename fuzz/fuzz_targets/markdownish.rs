@@ -0,0 +1,28 @@
+#![no_main]
+
+// the bytes pick pieces of markup, which finds the corners of the syntax
+// much sooner than random characters do
+use libfuzzer_sys::fuzz_target;
+use prose::translator::{self, Options};
+
+#[rustfmt::skip]
+const PIECES: &[&str] = &[
+    "\n", "\n\n", "\r\n", "\r", " ", "  ", "    ", "\t", "a", "b", "0", "1. ", ".", "!", "é", "🎉",
+    "\u{3000}", "*", "**", "_", "__", "`", "``", "```", "~~~", "~", "~~", "==", "^", "++", "$",
+    "$$", ":", ":smile:", "[", "]", "(", ")", "](", "![", "[[", "]]", "[^1]", "[^1]: ",
+    "[FOOTNOTES]", "{", "}", "{#a .b}", "<", ">", "> ", "<div>", "</div>", "<!--", "-->",
+    "<http://a>", "http://", "www.", "&", "&amp;", "&#", "&#x1F600;", ";", "\\", "\\\n", "\"",
+    "'", "#", "# ", "-", "- ", "* ", "+", "=", "---", "===", "|", "|---|", ":::", "::: note",
+    "???", "???+ s", ": ",
+];
+
+fuzz_target!(|data: &[u8]| {
+    let content: String = data
+        .iter()
+        .map(|&k| PIECES[k as usize % PIECES.len()])
+        .collect();
+    let (markdown, _) = prose::parse_with_warnings(&content);
+    translator::translate(markdown.clone(), &Options::default());
+    translator::latex::translate(markdown.clone(), &Options::default());
+    translator::markdown::translate(markdown);
+});
@@ -0,0 +1,11 @@
+#![no_main]
+
+// any bytes, with the ones which are not UTF-8 replaced
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    let content = String::from_utf8_lossy(data);
+    prose::parse_with_warnings(&content);
+    prose::parse_spanned_with_options(&content, prose::ParseOptions::default());
+    prose::Parser::new(&content).count();
+});
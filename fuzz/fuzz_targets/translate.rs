@@ -0,0 +1,32 @@
+#![no_main]
+
+// every backend, with what the parser makes of any text
+use libfuzzer_sys::fuzz_target;
+use prose::translator::{self, render::render_events, HtmlRenderer, Options};
+
+fuzz_target!(|data: &[u8]| {
+    let content = String::from_utf8_lossy(data);
+    let (markdown, _) = prose::parse_with_warnings(&content);
+    let options = Options {
+        standalone: true,
+        heading_anchors: true,
+        ..Options::default()
+    };
+    translator::translate(markdown.clone(), &options);
+    let sanitized = Options {
+        sanitize: true,
+        ..Options::default()
+    };
+    translator::translate(markdown.clone(), &sanitized);
+    translator::gemtext::translate(markdown.clone());
+    translator::latex::translate(markdown.clone(), &options);
+    translator::man::translate(markdown.clone());
+    translator::term::translate(markdown.clone());
+    translator::text::translate(markdown.clone());
+    // what the markdown backend writes can be read again
+    let written = translator::markdown::translate(markdown.clone());
+    prose::parse_with_warnings(&written);
+    let json = prose::json::to_json(&markdown);
+    assert_eq!(prose::json::from_json(&json), Ok(markdown));
+    render_events(prose::Parser::new(&content), &mut HtmlRenderer::new());
+});
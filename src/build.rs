@@ -0,0 +1,216 @@
+use crate::cache::{self, Cache, Entry};
+use crate::json::Json;
+
+use rayon::prelude::*;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+// what a build has done: the written files, the ones which were left as they were
+// since their sources have not changed, and the sources which could not be converted with why
+#[derive(Debug, Default)]
+pub struct Summary {
+    pub written: Vec<PathBuf>,
+    pub reused: Vec<PathBuf>,
+    pub failed: Vec<(PathBuf, String)>,
+}
+
+impl Summary {
+    // a file of the output, which is written or else reused
+    pub(crate) fn record(&mut self, target: PathBuf, written: bool) {
+        if written {
+            self.written.push(target);
+        } else {
+            self.reused.push(target);
+        }
+    }
+}
+
+// a file written or reused, with the hash of its source and of the other files it was made of
+pub(crate) struct Output {
+    pub target: PathBuf,
+    pub hash: u64,
+    pub reads: BTreeMap<String, u64>,
+    pub reused: bool,
+}
+
+// a file to convert, or to copy as it is
+enum Job {
+    Convert(PathBuf, PathBuf),
+    Copy(PathBuf, PathBuf),
+}
+
+// converts every `.md` file under `dir` into `out_dir`, keeping the relative paths.
+// hidden entries (`.git` etc.) and `out_dir` itself are skipped,
+// and other files are copied as they are only when `copy_assets` is set.
+// `convert` is given the path of each file with its content.
+// the files are converted in parallel, and one which fails does not stop the others;
+// only a directory which cannot be read fails the whole build.
+// the hashes of the sources are kept in `out_dir/.prose-cache`, and a source which has
+// not changed since the last build with the same `key`, which stands for the options
+// of `convert`, is not converted again
+pub fn build<F>(
+    dir: &Path,
+    out_dir: &Path,
+    extension: &str,
+    copy_assets: bool,
+    key: &str,
+    convert: &F,
+) -> Result<Summary, String>
+where
+    F: Fn(&Path, &str) -> Result<String, String> + Sync,
+{
+    let mut jobs = vec![];
+    for path in sources(dir, out_dir)? {
+        let relative = path.strip_prefix(dir).unwrap_or(&path);
+        let target = out_dir.join(relative);
+        if path.extension().and_then(|ext| ext.to_str()) == Some("md") {
+            jobs.push(Job::Convert(path, target.with_extension(extension)));
+        } else if copy_assets {
+            jobs.push(Job::Copy(path, target));
+        }
+    }
+    let mut cache = Cache::load(out_dir, &format!("{}\n{}", extension, key));
+    let results: Vec<(PathBuf, Result<Output, String>)> = jobs
+        .into_par_iter()
+        .map(|job| {
+            let (Job::Convert(path, target) | Job::Copy(path, target)) = &job;
+            let result = fs::read(path)
+                .map_err(|err| format!("Cannot read {}: {}", path.display(), err))
+                .and_then(|content| {
+                    let hash = cache::hash(&[&content]);
+                    let source = cache::source_key(dir, path);
+                    if let Some(entry) = cache.get(&source, hash).filter(|_| target.exists()) {
+                        return Ok(Output {
+                            target: target.clone(),
+                            hash,
+                            reads: entry.reads.clone(),
+                            reused: true,
+                        });
+                    }
+                    let (written, reads) = match job {
+                        Job::Convert(..) => cache::recording(|| {
+                            String::from_utf8(content)
+                                .map_err(|err| format!("Cannot read {}: {}", path.display(), err))
+                                .and_then(|content| convert(path, &content))
+                                .and_then(|output| {
+                                    write(target, format!("{}\n", output).as_bytes())
+                                })
+                        }),
+                        Job::Copy(..) => (write(target, &content), BTreeMap::new()),
+                    };
+                    written.map(|_| Output {
+                        target: target.clone(),
+                        hash,
+                        reads,
+                        reused: false,
+                    })
+                });
+            let (Job::Convert(path, _) | Job::Copy(path, _)) = job;
+            (path, result)
+        })
+        .collect();
+    let mut summary = Summary::default();
+    for (path, result) in results {
+        match result {
+            Ok(output) => {
+                let (hash, data, reads) = (output.hash, Json::Null, output.reads);
+                cache.insert(cache::source_key(dir, &path), Entry { hash, data, reads });
+                summary.record(output.target, !output.reused);
+            }
+            Err(err) => summary.failed.push((path, err)),
+        }
+    }
+    cache.save()?;
+    Ok(summary)
+}
+
+// the files under `dir`, without the hidden entries and `out_dir`
+pub(crate) fn sources(dir: &Path, out_dir: &Path) -> Result<Vec<PathBuf>, String> {
+    let out_dir_abs = fs::canonicalize(out_dir).ok();
+    let mut files = vec![];
+    let mut dirs = vec![dir.to_path_buf()];
+    while let Some(current) = dirs.pop() {
+        let entries = fs::read_dir(&current)
+            .map_err(|err| format!("Cannot read {}: {}", current.display(), err))?;
+        let mut paths = entries
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .collect::<Vec<PathBuf>>();
+        paths.sort();
+        for path in paths {
+            let hidden = path
+                .file_name()
+                .and_then(|name| name.to_str())
+                .is_some_and(|name| name.starts_with('.'));
+            if hidden || (out_dir_abs.is_some() && fs::canonicalize(&path).ok() == out_dir_abs) {
+                continue;
+            }
+            if path.is_dir() {
+                dirs.push(path);
+            } else {
+                files.push(path);
+            }
+        }
+    }
+    Ok(files)
+}
+
+pub(crate) fn write(path: &Path, content: &[u8]) -> Result<(), String> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)
+            .map_err(|err| format!("Cannot create {}: {}", parent.display(), err))?;
+    }
+    fs::write(path, content).map_err(|err| format!("Cannot write {}: {}", path.display(), err))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_build() {
+        let root = std::env::temp_dir().join(format!("prose-build-{}", std::process::id()));
+        let src = root.join("src");
+        let out = src.join("out");
+        fs::create_dir_all(src.join("posts/.drafts")).unwrap();
+        fs::write(src.join("index.md"), "# index\n").unwrap();
+        fs::write(src.join("posts/a.md"), "a\n").unwrap();
+        fs::write(src.join("posts/.drafts/b.md"), "b\n").unwrap();
+        fs::write(src.join("posts/image.png"), "png").unwrap();
+        let convert = |_: &Path, content: &str| {
+            crate::parse(content)
+                .map(|md| crate::to_html(&md))
+                .map_err(|err| err.to_string())
+        };
+
+        let summary = build(&src, &out, "html", false, "", &convert).unwrap();
+        assert_eq!(
+            summary.written,
+            vec![out.join("index.html"), out.join("posts/a.html")]
+        );
+        assert_eq!(
+            fs::read_to_string(out.join("posts/a.html")).unwrap(),
+            "<p>a</p>\n"
+        );
+
+        assert!(summary.failed.is_empty());
+
+        // the output of the first run is not converted again, the unchanged files are reused,
+        // and a file which cannot be parsed does not stop the others
+        fs::write(src.join("posts/bad.md"), "> ".repeat(100) + "b\n").unwrap();
+        fs::write(src.join("posts/a.md"), "a2\n").unwrap();
+        let summary = build(&src, &out, "html", true, "", &convert).unwrap();
+        assert_eq!(
+            summary.written,
+            vec![out.join("posts/a.html"), out.join("posts/image.png")]
+        );
+        assert_eq!(summary.reused, vec![out.join("index.html")]);
+        assert_eq!(summary.failed.len(), 1);
+        assert_eq!(summary.failed[0].0, src.join("posts/bad.md"));
+
+        // other options convert everything again
+        let summary = build(&src, &out, "html", true, "--standalone", &convert).unwrap();
+        assert!(summary.reused.is_empty());
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
@@ -0,0 +1,186 @@
+use crate::json::Json;
+
+use serde::{Deserialize, Serialize};
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+// the file in the output directory which keeps the hashes of the sources of the last build
+pub const FILE: &str = ".prose-cache";
+
+// what a build has kept of a source: the hash of its content, with what it needs
+// to reuse the source without converting it again.
+// `reads` are the hashes of the other files its conversion has read, as the embedded images
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Entry {
+    pub hash: u64,
+    #[serde(default, skip_serializing_if = "Json::is_null")]
+    pub data: Json,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub reads: BTreeMap<String, u64>,
+}
+
+// the content of `FILE`
+#[derive(Serialize, Deserialize)]
+struct CacheFile {
+    key: u64,
+    files: BTreeMap<String, Entry>,
+}
+
+thread_local! {
+    // the files read on this thread inside of `recording`
+    static READS: RefCell<Option<BTreeMap<String, u64>>> = const { RefCell::new(None) };
+}
+
+// the sources of the last build by their paths relative to the directory built.
+// `key` stands for the options, so that other options make the whole cache stale
+#[derive(Debug)]
+pub struct Cache {
+    path: PathBuf,
+    key: u64,
+    old: BTreeMap<String, Entry>,
+    new: BTreeMap<String, Entry>,
+}
+
+// FNV-1a, which is the same for every build of prose, unlike the hasher of std
+pub fn hash(parts: &[&[u8]]) -> u64 {
+    let mut hash = 0xcbf29ce484222325u64;
+    for part in parts {
+        for byte in part.iter().chain(&[0xff]) {
+            hash ^= *byte as u64;
+            hash = hash.wrapping_mul(0x100000001b3);
+        }
+    }
+    hash
+}
+
+// the hash of the content of a file, which is another one for a file which cannot be read
+pub fn hash_file(path: &Path) -> u64 {
+    fs::read(path).map_or(hash(&[]), |content| hash(&[&content]))
+}
+
+// tells the build that the output being made depends on the file
+pub fn read(path: &Path) {
+    READS.with(|reads| {
+        if let Some(reads) = reads.borrow_mut().as_mut() {
+            reads.insert(path.display().to_string(), hash_file(path));
+        }
+    });
+}
+
+// the result of `f`, with the hashes of the files it has `read`
+pub fn recording<T>(f: impl FnOnce() -> T) -> (T, BTreeMap<String, u64>) {
+    let outer = READS.with(|reads| reads.replace(Some(BTreeMap::new())));
+    let result = f();
+    let reads = READS.with(|reads| reads.replace(outer)).unwrap_or_default();
+    (result, reads)
+}
+
+impl Cache {
+    // the cache in `out_dir`, which is empty when there is none or it was made with other options
+    pub fn load(out_dir: &Path, key: &str) -> Cache {
+        let key = hash(&[env!("CARGO_PKG_VERSION").as_bytes(), key.as_bytes()]);
+        let path = out_dir.join(FILE);
+        let old = fs::read_to_string(&path)
+            .ok()
+            .and_then(|content| serde_json::from_str::<CacheFile>(&content).ok())
+            .filter(|file| file.key == key)
+            .map(|file| file.files)
+            .unwrap_or_default();
+        Cache {
+            path,
+            key,
+            old,
+            new: BTreeMap::new(),
+        }
+    }
+
+    // the entry of the source at the last build, when its content has the hash still
+    // and so do the files it has read
+    pub fn get(&self, source: &str, hash: u64) -> Option<&Entry> {
+        self.old.get(source).filter(|entry| {
+            entry.hash == hash
+                && entry
+                    .reads
+                    .iter()
+                    .all(|(path, hash)| hash_file(Path::new(path)) == *hash)
+        })
+    }
+
+    // the entry of the source at this build. the sources which are not inserted are dropped
+    pub fn insert(&mut self, source: String, entry: Entry) {
+        self.new.insert(source, entry);
+    }
+
+    pub fn save(&self) -> Result<(), String> {
+        let file = CacheFile {
+            key: self.key,
+            files: self.new.clone(),
+        };
+        let json = serde_json::to_string(&file).map_err(|err| err.to_string())?;
+        crate::build::write(&self.path, json.as_bytes())
+    }
+}
+
+// the path of a source relative to the directory built, with `/` on every platform
+pub fn source_key(dir: &Path, path: &Path) -> String {
+    let relative = path.strip_prefix(dir).unwrap_or(path);
+    let names: Vec<_> = relative.iter().map(|name| name.to_string_lossy()).collect();
+    names.join("/")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cache() {
+        let out = std::env::temp_dir().join(format!("prose-cache-{}", std::process::id()));
+        let mut cache = Cache::load(&out, "--standalone");
+        assert_eq!(cache.get("a.md", 1), None);
+        let entry = Entry {
+            hash: hash(&[b"# a"]),
+            data: Json::String(String::from("x")),
+            reads: BTreeMap::new(),
+        };
+        cache.insert(String::from("a.md"), entry.clone());
+        cache.save().unwrap();
+        let cache = Cache::load(&out, "--standalone");
+        assert_eq!(cache.get("a.md", hash(&[b"# a"])), Some(&entry));
+        assert_eq!(cache.get("a.md", hash(&[b"# b"])), None);
+        assert_eq!(Cache::load(&out, "").get("a.md", entry.hash), None);
+        assert_ne!(hash(&[b"ab", b"c"]), hash(&[b"a", b"bc"]));
+        fs::remove_dir_all(&out).unwrap();
+    }
+
+    #[test]
+    fn test_reads() {
+        let out = std::env::temp_dir().join(format!("prose-cache-reads-{}", std::process::id()));
+        let image = out.join("a.png");
+        fs::create_dir_all(&out).unwrap();
+        fs::write(&image, b"1").unwrap();
+        let (result, reads) = recording(|| {
+            read(&image);
+            1
+        });
+        assert_eq!((result, reads.len()), (1, 1));
+        // nothing is recorded outside of `recording`
+        read(&out.join("b.png"));
+        let mut cache = Cache::load(&out, "");
+        let entry = Entry {
+            hash: 0,
+            data: Json::Null,
+            reads,
+        };
+        cache.insert(String::from("a.md"), entry.clone());
+        cache.save().unwrap();
+        let cache = Cache::load(&out, "");
+        assert_eq!(cache.get("a.md", 0), Some(&entry));
+        fs::write(&image, b"2").unwrap();
+        assert_eq!(cache.get("a.md", 0), None);
+        fs::remove_file(&image).unwrap();
+        assert_eq!(cache.get("a.md", 0), None);
+        fs::remove_dir_all(&out).unwrap();
+    }
+}
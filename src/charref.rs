@@ -0,0 +1,237 @@
+// the named character references which are decoded, with the ones of ASCII punctuation,
+// typography, arrows, math, greek and accented latin letters.
+// sorted by name for the binary search, where the uppercase ones come first
+const NAMED: [(&str, &str); 185] = [
+    ("AElig", "Æ"),
+    ("Aacute", "Á"),
+    ("Acirc", "Â"),
+    ("Agrave", "À"),
+    ("Alpha", "Α"),
+    ("Aring", "Å"),
+    ("Atilde", "Ã"),
+    ("Auml", "Ä"),
+    ("Beta", "Β"),
+    ("Ccedil", "Ç"),
+    ("Dagger", "‡"),
+    ("Delta", "Δ"),
+    ("ETH", "Ð"),
+    ("Eacute", "É"),
+    ("Ecirc", "Ê"),
+    ("Egrave", "È"),
+    ("Euml", "Ë"),
+    ("Gamma", "Γ"),
+    ("Lambda", "Λ"),
+    ("Ntilde", "Ñ"),
+    ("Omega", "Ω"),
+    ("Oslash", "Ø"),
+    ("Ouml", "Ö"),
+    ("Phi", "Φ"),
+    ("Pi", "Π"),
+    ("Prime", "″"),
+    ("Psi", "Ψ"),
+    ("Sigma", "Σ"),
+    ("THORN", "Þ"),
+    ("Theta", "Θ"),
+    ("Uuml", "Ü"),
+    ("aacute", "á"),
+    ("acirc", "â"),
+    ("acute", "´"),
+    ("aelig", "æ"),
+    ("agrave", "à"),
+    ("alpha", "α"),
+    ("amp", "&"),
+    ("and", "∧"),
+    ("apos", "'"),
+    ("aring", "å"),
+    ("asymp", "≈"),
+    ("atilde", "ã"),
+    ("auml", "ä"),
+    ("beta", "β"),
+    ("brvbar", "¦"),
+    ("bull", "•"),
+    ("cap", "∩"),
+    ("ccedil", "ç"),
+    ("cedil", "¸"),
+    ("cent", "¢"),
+    ("check", "✓"),
+    ("chi", "χ"),
+    ("clubs", "♣"),
+    ("copy", "©"),
+    ("cross", "✗"),
+    ("cup", "∪"),
+    ("curren", "¤"),
+    ("dagger", "†"),
+    ("darr", "↓"),
+    ("deg", "°"),
+    ("delta", "δ"),
+    ("diams", "♦"),
+    ("divide", "÷"),
+    ("eacute", "é"),
+    ("ecirc", "ê"),
+    ("egrave", "è"),
+    ("empty", "∅"),
+    ("emsp", "\u{2003}"),
+    ("ensp", "\u{2002}"),
+    ("epsilon", "ε"),
+    ("equiv", "≡"),
+    ("eta", "η"),
+    ("eth", "ð"),
+    ("euml", "ë"),
+    ("euro", "€"),
+    ("exist", "∃"),
+    ("forall", "∀"),
+    ("frac12", "½"),
+    ("frac14", "¼"),
+    ("frac34", "¾"),
+    ("frasl", "⁄"),
+    ("gamma", "γ"),
+    ("ge", "≥"),
+    ("gt", ">"),
+    ("hArr", "⇔"),
+    ("harr", "↔"),
+    ("hearts", "♥"),
+    ("hellip", "…"),
+    ("iacute", "í"),
+    ("icirc", "î"),
+    ("iexcl", "¡"),
+    ("igrave", "ì"),
+    ("infin", "∞"),
+    ("iota", "ι"),
+    ("iquest", "¿"),
+    ("isin", "∈"),
+    ("iuml", "ï"),
+    ("kappa", "κ"),
+    ("lArr", "⇐"),
+    ("lambda", "λ"),
+    ("laquo", "«"),
+    ("larr", "←"),
+    ("ldquo", "“"),
+    ("le", "≤"),
+    ("lsquo", "‘"),
+    ("lt", "<"),
+    ("macr", "¯"),
+    ("mdash", "—"),
+    ("micro", "µ"),
+    ("middot", "·"),
+    ("minus", "−"),
+    ("mu", "μ"),
+    ("nabla", "∇"),
+    ("nbsp", "\u{a0}"),
+    ("ndash", "–"),
+    ("ne", "≠"),
+    ("not", "¬"),
+    ("notin", "∉"),
+    ("ntilde", "ñ"),
+    ("nu", "ν"),
+    ("oacute", "ó"),
+    ("ocirc", "ô"),
+    ("ograve", "ò"),
+    ("oline", "‾"),
+    ("omega", "ω"),
+    ("omicron", "ο"),
+    ("or", "∨"),
+    ("ordf", "ª"),
+    ("ordm", "º"),
+    ("oslash", "ø"),
+    ("otilde", "õ"),
+    ("ouml", "ö"),
+    ("para", "¶"),
+    ("part", "∂"),
+    ("permil", "‰"),
+    ("phi", "φ"),
+    ("pi", "π"),
+    ("plusmn", "±"),
+    ("pound", "£"),
+    ("prime", "′"),
+    ("prod", "∏"),
+    ("psi", "ψ"),
+    ("quot", "\u{22}"),
+    ("rArr", "⇒"),
+    ("radic", "√"),
+    ("raquo", "»"),
+    ("rarr", "→"),
+    ("rdquo", "”"),
+    ("reg", "®"),
+    ("rho", "ρ"),
+    ("rsquo", "’"),
+    ("sect", "§"),
+    ("shy", "\u{ad}"),
+    ("sigma", "σ"),
+    ("spades", "♠"),
+    ("star", "☆"),
+    ("starf", "★"),
+    ("sub", "⊂"),
+    ("sum", "∑"),
+    ("sup", "⊃"),
+    ("sup1", "¹"),
+    ("sup2", "²"),
+    ("sup3", "³"),
+    ("szlig", "ß"),
+    ("tau", "τ"),
+    ("theta", "θ"),
+    ("thinsp", "\u{2009}"),
+    ("thorn", "þ"),
+    ("times", "×"),
+    ("trade", "™"),
+    ("uacute", "ú"),
+    ("uarr", "↑"),
+    ("ucirc", "û"),
+    ("ugrave", "ù"),
+    ("uml", "¨"),
+    ("upsilon", "υ"),
+    ("uuml", "ü"),
+    ("xi", "ξ"),
+    ("yacute", "ý"),
+    ("yen", "¥"),
+    ("yuml", "ÿ"),
+    ("zeta", "ζ"),
+    ("zwj", "\u{200d}"),
+    ("zwnj", "\u{200c}"),
+];
+
+pub fn lookup(name: &str) -> Option<&'static str> {
+    NAMED
+        .binary_search_by(|(key, _)| (*key).cmp(name))
+        .ok()
+        .map(|k| NAMED[k].1)
+}
+
+// `&#123;` or `&#x7b;`, without `&#` and `;`.
+// zero and the code points which are not characters become U+FFFD as in CommonMark
+pub fn numeric(digits: &str) -> Option<char> {
+    let code = match digits.strip_prefix(['x', 'X']) {
+        Some(hex) if (1..=6).contains(&hex.len()) => u32::from_str_radix(hex, 16).ok()?,
+        None if (1..=7).contains(&digits.len()) => digits.parse().ok()?,
+        _ => return None,
+    };
+    Some(match code {
+        0 => '\u{fffd}',
+        code => char::from_u32(code).unwrap_or('\u{fffd}'),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lookup() {
+        assert!(NAMED.windows(2).all(|w| w[0].0 < w[1].0));
+        assert_eq!(lookup("amp"), Some("&"));
+        assert_eq!(lookup("copy"), Some("©"));
+        assert_eq!(lookup("Omega"), Some("Ω"));
+        assert_eq!(lookup("nope"), None);
+    }
+
+    #[test]
+    fn test_numeric() {
+        assert_eq!(numeric("35"), Some('#'));
+        assert_eq!(numeric("x1F600"), Some('😀'));
+        assert_eq!(numeric("X22"), Some('"'));
+        assert_eq!(numeric("0"), Some('\u{fffd}'));
+        assert_eq!(numeric("xD800"), Some('\u{fffd}'));
+        assert_eq!(numeric("12345678"), None);
+        assert_eq!(numeric("x"), None);
+        assert_eq!(numeric("1a"), None);
+    }
+}
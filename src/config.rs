@@ -0,0 +1,273 @@
+use std::path::{Path, PathBuf};
+
+// the name of the configuration file, looked for in the current directory and the ones above it
+pub const FILE_NAME: &str = "prose.toml";
+
+// the values of `prose.toml`, which is the part of TOML the flags need:
+// `key = value` lines with strings, integers, booleans and arrays of them, without tables
+#[derive(Clone, Debug, PartialEq)]
+pub enum Value {
+    String(String),
+    Integer(i64),
+    Boolean(bool),
+    Array(Vec<Value>),
+}
+
+impl Value {
+    // the flag `--key=value` of this value: `true` is the flag alone,
+    // `false` is no flag at all, and an array gives the flag once for each item
+    pub fn to_args(&self, key: &str) -> Vec<String> {
+        match self {
+            Value::String(s) => vec![format!("--{}={}", key, s)],
+            Value::Integer(n) => vec![format!("--{}={}", key, n)],
+            Value::Boolean(true) => vec![format!("--{}", key)],
+            Value::Boolean(false) => vec![],
+            Value::Array(items) => items.iter().flat_map(|item| item.to_args(key)).collect(),
+        }
+    }
+}
+
+// the `prose.toml` of `dir` or of the nearest directory above it
+pub fn find(dir: &Path) -> Option<PathBuf> {
+    dir.ancestors()
+        .map(|dir| dir.join(FILE_NAME))
+        .find(|path| path.is_file())
+}
+
+// the keys and the values in the order they are written
+pub fn parse(content: &str) -> Result<Vec<(String, Value)>, String> {
+    let mut entries: Vec<(String, Value)> = vec![];
+    let mut lines = content.lines().enumerate();
+    while let Some((n, line)) = lines.next() {
+        let error = |message: &str| format!("line {}: {}", n + 1, message);
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        if line.starts_with('[') {
+            return Err(error("tables are not supported"));
+        }
+        let (key, rest) = parse_key(line).ok_or_else(|| error("expected key = value"))?;
+        let mut rest = rest
+            .trim_start()
+            .strip_prefix('=')
+            .ok_or_else(|| error("expected key = value"))?
+            .to_string();
+        // an array goes on until its closing bracket, over several lines
+        let value = loop {
+            match parse_value(rest.trim_start()) {
+                Some((value, after)) => {
+                    let after = after.trim();
+                    if !after.is_empty() && !after.starts_with('#') {
+                        return Err(error("unexpected text after the value"));
+                    }
+                    break value;
+                }
+                None if rest.trim_start().starts_with('[') => match lines.next() {
+                    Some((_, line)) => {
+                        rest.push('\n');
+                        rest.push_str(line);
+                    }
+                    None => return Err(error("the array is not closed")),
+                },
+                None => return Err(error("invalid value")),
+            }
+        };
+        if entries.iter().any(|(k, _)| *k == key) {
+            return Err(error(&format!("{} is given twice", key)));
+        }
+        entries.push((key, value));
+    }
+    Ok(entries)
+}
+
+// a bare key such as `base-url`, or a quoted one
+fn parse_key(i: &str) -> Option<(String, &str)> {
+    if i.starts_with(['"', '\'']) {
+        return match parse_value(i)? {
+            (Value::String(key), rest) => Some((key, rest)),
+            _ => None,
+        };
+    }
+    let end = i
+        .find(|c: char| !c.is_ascii_alphanumeric() && c != '-' && c != '_')
+        .unwrap_or(i.len());
+    match end {
+        0 => None,
+        _ => Some((i[..end].to_string(), &i[end..])),
+    }
+}
+
+fn parse_value(i: &str) -> Option<(Value, &str)> {
+    if let Some(rest) = i.strip_prefix('"') {
+        let (s, rest) = parse_basic_string(rest)?;
+        Some((Value::String(s), rest))
+    } else if let Some(rest) = i.strip_prefix('\'') {
+        let end = rest.find(['\'', '\n'])?;
+        rest[end..].starts_with('\'').then_some(())?;
+        Some((Value::String(rest[..end].to_string()), &rest[end + 1..]))
+    } else if let Some(rest) = i.strip_prefix('[') {
+        parse_array(rest)
+    } else if let Some(rest) = i.strip_prefix("true") {
+        Some((Value::Boolean(true), rest))
+    } else if let Some(rest) = i.strip_prefix("false") {
+        Some((Value::Boolean(false), rest))
+    } else {
+        let end = i
+            .find(|c: char| !c.is_ascii_digit() && !"+-_".contains(c))
+            .unwrap_or(i.len());
+        let digits = i[..end].replace('_', "");
+        let n = digits.parse().ok()?;
+        Some((Value::Integer(n), &i[end..]))
+    }
+}
+
+// the rest of `"..."` after its opening quote
+fn parse_basic_string(i: &str) -> Option<(String, &str)> {
+    let mut s = String::new();
+    let mut chars = i.char_indices();
+    while let Some((k, c)) = chars.next() {
+        match c {
+            '"' => return Some((s, &i[k + 1..])),
+            '\n' => return None,
+            '\\' => {
+                let c = match chars.next()?.1 {
+                    'b' => '\u{8}',
+                    't' => '\t',
+                    'n' => '\n',
+                    'f' => '\u{c}',
+                    'r' => '\r',
+                    '"' => '"',
+                    '\\' => '\\',
+                    u @ ('u' | 'U') => {
+                        let len = if u == 'u' { 4 } else { 8 };
+                        let hex: String = (0..len)
+                            .filter_map(|_| chars.next())
+                            .map(|(_, c)| c)
+                            .collect();
+                        char::from_u32(u32::from_str_radix(&hex, 16).ok()?)?
+                    }
+                    _ => return None,
+                };
+                s.push(c);
+            }
+            c => s.push(c),
+        }
+    }
+    None
+}
+
+// the rest of `[a, b, ...]` after its opening bracket, where the comments and the newlines
+// between the items are skipped, and a comma may follow the last item
+fn parse_array(i: &str) -> Option<(Value, &str)> {
+    let mut items = vec![];
+    let mut rest = skip_blank(i);
+    loop {
+        if let Some(after) = rest.strip_prefix(']') {
+            return Some((Value::Array(items), after));
+        }
+        let (item, after) = parse_value(rest)?;
+        items.push(item);
+        rest = skip_blank(after);
+        match rest.strip_prefix(',') {
+            Some(after) => rest = skip_blank(after),
+            None if rest.starts_with(']') => {}
+            None => return None,
+        }
+    }
+}
+
+fn skip_blank(mut i: &str) -> &str {
+    loop {
+        i = i.trim_start();
+        match i.strip_prefix('#') {
+            Some(comment) => i = comment.find('\n').map_or("", |k| &comment[k..]),
+            None => return i,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let content = r#"
+# the defaults of the docs
+dialect = "gfm"
+base-url = 'https://example.com/docs/'   # a literal string
+standalone = true
+sanitize = false
+heading-offset = 1_0
+"wiki-base" = "/wiki/é"
+css = [
+    "a.css", # the main one
+    "b.css",
+]
+"#;
+        assert_eq!(
+            parse(content),
+            Ok(vec![
+                (String::from("dialect"), Value::String(String::from("gfm"))),
+                (
+                    String::from("base-url"),
+                    Value::String(String::from("https://example.com/docs/"))
+                ),
+                (String::from("standalone"), Value::Boolean(true)),
+                (String::from("sanitize"), Value::Boolean(false)),
+                (String::from("heading-offset"), Value::Integer(10)),
+                (
+                    String::from("wiki-base"),
+                    Value::String(String::from("/wiki/é"))
+                ),
+                (
+                    String::from("css"),
+                    Value::Array(vec![
+                        Value::String(String::from("a.css")),
+                        Value::String(String::from("b.css"))
+                    ])
+                ),
+            ])
+        );
+        assert_eq!(
+            parse("[build]\n"),
+            Err(String::from("line 1: tables are not supported"))
+        );
+        assert_eq!(
+            parse("a = 1\na = 2\n"),
+            Err(String::from("line 2: a is given twice"))
+        );
+        assert_eq!(
+            parse("\na = \"b\n"),
+            Err(String::from("line 2: invalid value"))
+        );
+        assert_eq!(
+            parse("a = [1,\n"),
+            Err(String::from("line 1: the array is not closed"))
+        );
+        assert_eq!(
+            parse("a = 1 b\n"),
+            Err(String::from("line 1: unexpected text after the value"))
+        );
+        assert_eq!(
+            parse("= 1\n"),
+            Err(String::from("line 1: expected key = value"))
+        );
+    }
+
+    #[test]
+    fn test_to_args() {
+        let args = |value: Value| value.to_args("css");
+        assert_eq!(args(Value::Boolean(true)), ["--css"]);
+        assert!(args(Value::Boolean(false)).is_empty());
+        assert_eq!(args(Value::Integer(-1)), ["--css=-1"]);
+        assert_eq!(
+            args(Value::Array(vec![
+                Value::String(String::from("a.css")),
+                Value::String(String::from("b.css"))
+            ])),
+            ["--css=a.css", "--css=b.css"]
+        );
+    }
+}
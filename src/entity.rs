@@ -1,22 +1,205 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
 pub type MarkdownText = Vec<MarkdownInline>;
 
-#[derive(Clone, Debug, PartialEq)]
+// the text of a list item and the blocks nested under it (e.g. a sub-list)
+pub type ListItem = (MarkdownText, Vec<Markdown>);
+
+// a term of a definition list and its definitions
+pub type Definition = (MarkdownText, Vec<MarkdownText>);
+
+// `{"t": "Heading", "c": [1, text, attributes]}` in the JSON of the AST, see `json`
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "t", content = "c")]
 pub enum Markdown {
-    Heading(usize, MarkdownText),
-    OrderedList(Vec<MarkdownText>),
-    UnorderedList(Vec<MarkdownText>),
+    // the level, the text and its attributes, where the id is given or generated
+    Heading(usize, MarkdownText, Attributes),
+    // the number of the first item and the items
+    OrderedList(usize, Vec<ListItem>),
+    UnorderedList(Vec<ListItem>),
+    DefinitionList(Vec<Definition>),
     Line(MarkdownText),
-    Codeblock(String, String),
+    Paragraph(Vec<MarkdownText>),
+    // the language, the code and the rest of the info string, e.g. `ignore` of ```rust,ignore
+    Codeblock(String, String, String),
     HorizontalRule,
+    Table(Vec<MarkdownText>, Vec<Align>, Vec<Vec<MarkdownText>>),
+    Blockquote(Vec<Markdown>),
+    FootnoteDefinition(String, MarkdownText),
+    FrontMatter(BTreeMap<String, String>),
+    Html(String),
+    // the text between `<!--` and `-->`, which is not published unless asked
+    Comment(String),
+    // the TeX source of a display formula
+    MathBlock(String),
+    // a callout such as `::: warning`, with its kind, its title if given and its content
+    Admonition(String, Option<String>, Vec<Markdown>),
+    // `::: {#id .class}`, a container for the blocks which only carries attributes
+    Div(Attributes, Vec<Markdown>),
+    // `??? summary`, a collapsible section with its summary, whether it is open and its content
+    Details(MarkdownText, bool, Vec<Markdown>),
+    // `[FOOTNOTES]`, where the notes are placed instead of the end of the document
+    Footnotes,
 }
 
-#[derive(Clone, Debug, PartialEq)]
+impl Markdown {
+    // `f` on this block and then on every block in it, such as the ones of a quote or a list item
+    pub fn walk<F>(&self, f: &mut F)
+    where
+        F: FnMut(&Markdown),
+    {
+        f(self);
+        match self {
+            Markdown::OrderedList(_, items) | Markdown::UnorderedList(items) => items
+                .iter()
+                .flat_map(|(_, children)| children)
+                .for_each(|child| child.walk(f)),
+            Markdown::Blockquote(inner)
+            | Markdown::Admonition(_, _, inner)
+            | Markdown::Div(_, inner)
+            | Markdown::Details(_, _, inner) => inner.iter().for_each(|child| child.walk(f)),
+            _ => {}
+        }
+    }
+
+    // the texts of this block, without the ones of the blocks in it
+    pub fn texts(&self) -> Vec<&MarkdownText> {
+        match self {
+            Markdown::Heading(_, text, _)
+            | Markdown::Line(text)
+            | Markdown::FootnoteDefinition(_, text)
+            | Markdown::Details(text, _, _) => vec![text],
+            Markdown::Paragraph(lines) => lines.iter().collect(),
+            Markdown::OrderedList(_, items) | Markdown::UnorderedList(items) => {
+                items.iter().map(|(text, _)| text).collect()
+            }
+            Markdown::DefinitionList(definitions) => definitions
+                .iter()
+                .flat_map(|(term, texts)| std::iter::once(term).chain(texts))
+                .collect(),
+            Markdown::Table(header, _, rows) => {
+                header.iter().chain(rows.iter().flatten()).collect()
+            }
+            _ => vec![],
+        }
+    }
+}
+
+// `f` on every inline of the text, and then on the ones in it, such as the text of a link
+pub fn walk_inlines<F>(text: &MarkdownText, f: &mut F)
+where
+    F: FnMut(&MarkdownInline),
+{
+    for part in text {
+        f(part);
+        match part {
+            MarkdownInline::Bold(text)
+            | MarkdownInline::Italic(text)
+            | MarkdownInline::Mark(text)
+            | MarkdownInline::Link(text, _, _)
+            | MarkdownInline::Image(text, _, _) => walk_inlines(text, f),
+            _ => {}
+        }
+    }
+}
+
+// `{#id .class key=value}`, which is `[id, [classes], [[key, value]]]` in the JSON of the AST
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(from = "AttributesTuple", into = "AttributesTuple")]
+pub struct Attributes {
+    pub id: Option<String>,
+    pub classes: Vec<String>,
+    pub pairs: Vec<(String, String)>,
+}
+
+type AttributesTuple = (Option<String>, Vec<String>, Vec<(String, String)>);
+
+impl From<AttributesTuple> for Attributes {
+    fn from((id, classes, pairs): AttributesTuple) -> Self {
+        Attributes { id, classes, pairs }
+    }
+}
+
+impl From<Attributes> for AttributesTuple {
+    fn from(attributes: Attributes) -> Self {
+        (attributes.id, attributes.classes, attributes.pairs)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Align {
+    None,
+    Left,
+    Center,
+    Right,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "t", content = "c")]
 pub enum MarkdownInline {
-    Link(String, String),
-    Image(String, String),
+    // the text (or alt text), the url and the title
+    Link(MarkdownText, String, Option<String>),
+    Image(MarkdownText, String, Option<String>),
     InlineCode(String),
-    Bold(String),
-    Italic(String),
+    Bold(MarkdownText),
+    Italic(MarkdownText),
     Strike(String),
+    // `==text==`, highlighted
+    Mark(MarkdownText),
+    // `^text^` and `~text~`
+    Superscript(String),
+    Subscript(String),
+    // `++Ctrl+C++`, the keys to press together
+    Kbd(Vec<String>),
+    // `{漢字|かんじ}`, the base text and its reading,
+    // or `{漢字|かん|じ}` with one reading for each character
+    Ruby(String, Vec<String>),
     Plaintext(String),
+    FootnoteRef(String),
+    Html(String),
+    // `<!-- ... -->` within a line
+    Comment(String),
+    // `[[target]]` or `[[target|label]]`, a link to another page of a wiki
+    WikiLink(String, Option<String>),
+    // the TeX source of an inline formula
+    Math(String),
+    // the name of a shortcode such as `:tada:` which is in the emoji table
+    Emoji(String),
+    // a hard line break, from two spaces or a backslash at the end of a line
+    LineBreak,
+}
+
+// a place in the source: the offset in bytes, and the 1-based line and column in characters
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Position {
+    pub offset: usize,
+    pub line: usize,
+    pub column: usize,
+}
+
+impl Position {
+    pub fn at(content: &str, offset: usize) -> Position {
+        let offset = offset.min(content.len());
+        let start = content[..offset].rfind('\n').map_or(0, |k| k + 1);
+        Position {
+            offset,
+            line: content[..offset].matches('\n').count() + 1,
+            column: content[start..offset].chars().count() + 1,
+        }
+    }
+}
+
+// where a node is written, from `start` up to but not including `end`
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Span {
+    pub start: Position,
+    pub end: Position,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Spanned<T> {
+    pub node: T,
+    pub span: Span,
 }
@@ -0,0 +1,382 @@
+use crate::cache;
+use crate::entity::{Markdown, MarkdownInline, MarkdownText};
+use crate::sanitize;
+use crate::split::{chapters, Page};
+use crate::translator::{self, map_text};
+use crate::zip;
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const CONTAINER: &str = r#"<?xml version="1.0" encoding="utf-8"?>
+<container version="1.0" xmlns="urn:oasis:names:tc:opendocument:xmlns:container">
+<rootfiles>
+<rootfile full-path="EPUB/content.opf" media-type="application/oebps-package+xml"/>
+</rootfiles>
+</container>
+"#;
+
+// a local image in the EPUB, by its name there
+struct Image {
+    name: String,
+    media_type: &'static str,
+    content: Vec<u8>,
+}
+
+// an EPUB 3 of the document: a chapter for each heading of `level` and above as `split` makes,
+// the table of contents of them both as `nav.xhtml` and the older `toc.ncx`, the `title`,
+// `author`, `date`, `description` and `lang` of the front matter, and the local images,
+// which are read from the `base_dir` of the options
+pub fn epub(
+    md: Vec<Markdown>,
+    options: &translator::Options,
+    level: usize,
+) -> Result<Vec<u8>, String> {
+    let front_matter = md
+        .iter()
+        .find_map(|bit| match bit {
+            Markdown::FrontMatter(fields) => Some(fields.clone()),
+            _ => None,
+        })
+        .unwrap_or_default();
+    let pages = chapters(md, level, "xhtml");
+    let title = front_matter
+        .get("title")
+        .cloned()
+        .or_else(|| {
+            pages
+                .iter()
+                .map(|page| page.title.clone())
+                .find(|title| !title.is_empty())
+        })
+        .unwrap_or_else(|| String::from("Untitled"));
+    let lang = front_matter
+        .get("lang")
+        .cloned()
+        .unwrap_or_else(|| String::from("en"));
+
+    let mut images = vec![];
+    let mut failed = None;
+    let pages: Vec<Page> = pages
+        .into_iter()
+        .map(|page| {
+            let blocks = map_text(page.blocks, &mut |text| {
+                relocate(text, &mut |url| match image(
+                    url,
+                    options.base_dir.as_deref(),
+                    images.len() + 1,
+                ) {
+                    Ok(Some(image)) => {
+                        let url = format!("../{}", image.name);
+                        images.push(image);
+                        url
+                    }
+                    Ok(None) => url.to_string(),
+                    Err(err) => {
+                        failed.get_or_insert(err);
+                        url.to_string()
+                    }
+                })
+            });
+            Page { blocks, ..page }
+        })
+        .collect();
+    if let Some(err) = failed {
+        return Err(err);
+    }
+
+    let options = translator::Options {
+        standalone: false,
+        xhtml: true,
+        link_extension: None,
+        ..options.clone()
+    };
+    let mut files = vec![
+        (String::from("mimetype"), b"application/epub+zip".to_vec()),
+        (
+            String::from("META-INF/container.xml"),
+            CONTAINER.as_bytes().to_vec(),
+        ),
+    ];
+    let mut toc = vec![];
+    for page in pages {
+        let title = if page.title.is_empty() {
+            &title
+        } else {
+            &page.title
+        };
+        // the raw html of a chapter may not be XML, as `<br>` or an unclosed `<div>`
+        let body = sanitize::xhtml(&translator::translate(page.blocks, &options));
+        let path = format!("text/{}", page.name);
+        files.push((
+            format!("EPUB/{}", path),
+            xhtml(title, &lang, &body).into_bytes(),
+        ));
+        toc.push((path, title.clone()));
+    }
+    let contents: Vec<&[u8]> = files.iter().map(|(_, content)| &content[..]).collect();
+    let identifier = format!("urn:prose:{:016x}", cache::hash(&contents));
+
+    files.push((
+        String::from("EPUB/nav.xhtml"),
+        xhtml(&title, &lang, &nav(&toc, &title)).into_bytes(),
+    ));
+    files.push((
+        String::from("EPUB/toc.ncx"),
+        ncx(&toc, &title, &identifier).into_bytes(),
+    ));
+    files.push((
+        String::from("EPUB/content.opf"),
+        package(&front_matter, &title, &lang, &identifier, &toc, &images).into_bytes(),
+    ));
+    for image in images {
+        files.push((format!("EPUB/{}", image.name), image.content));
+    }
+    Ok(zip::write(&files))
+}
+
+// `f` on the url of each image, which is replaced by what it returns
+fn relocate<F>(text: MarkdownText, f: &mut F) -> MarkdownText
+where
+    F: FnMut(&str) -> String,
+{
+    text.into_iter()
+        .map(|part| match part {
+            MarkdownInline::Image(alt, url, title) => MarkdownInline::Image(alt, f(&url), title),
+            MarkdownInline::Link(text, url, title) => {
+                MarkdownInline::Link(relocate(text, f), url, title)
+            }
+            MarkdownInline::Bold(text) => MarkdownInline::Bold(relocate(text, f)),
+            MarkdownInline::Italic(text) => MarkdownInline::Italic(relocate(text, f)),
+            MarkdownInline::Mark(text) => MarkdownInline::Mark(relocate(text, f)),
+            part => part,
+        })
+        .collect()
+}
+
+// the local image of the url as the `number`th, or none for an image elsewhere
+fn image(url: &str, base_dir: Option<&Path>, number: usize) -> Result<Option<Image>, String> {
+    if url.contains("://") || url.starts_with("data:") || url.starts_with('/') {
+        return Ok(None);
+    }
+    let extension = url
+        .rsplit_once('.')
+        .map(|(_, extension)| extension.to_ascii_lowercase())
+        .unwrap_or_default();
+    let media_type = match extension.as_str() {
+        "gif" => "image/gif",
+        "jpg" | "jpeg" => "image/jpeg",
+        "png" => "image/png",
+        "svg" => "image/svg+xml",
+        "webp" => "image/webp",
+        _ => return Err(format!("Cannot put the image {} in an EPUB", url)),
+    };
+    let path = base_dir.unwrap_or_else(|| Path::new("")).join(url);
+    let content = fs::read(&path)
+        .map_err(|err| format!("Cannot read the image {}: {}", path.display(), err))?;
+    Ok(Some(Image {
+        name: format!("images/{}.{}", number, extension),
+        media_type,
+        content,
+    }))
+}
+
+fn xhtml(title: &str, lang: &str, body: &str) -> String {
+    format!(
+        "<?xml version=\"1.0\" encoding=\"utf-8\"?>\n\
+         <!DOCTYPE html>\n\
+         <html xmlns=\"http://www.w3.org/1999/xhtml\" xmlns:epub=\"http://www.idpf.org/2007/ops\" lang=\"{0}\" xml:lang=\"{0}\">\n\
+         <head>\n<meta charset=\"utf-8\" />\n<title>{1}</title>\n</head>\n\
+         <body>\n{2}\n</body>\n</html>\n",
+        escape(lang),
+        escape(title),
+        body
+    )
+}
+
+fn nav(toc: &[(String, String)], title: &str) -> String {
+    let mut nav = format!(
+        "<nav epub:type=\"toc\" id=\"toc\">\n<h1>{}</h1>\n<ol>\n",
+        escape(title)
+    );
+    for (path, title) in toc {
+        nav.push_str(&format!(
+            "<li><a href=\"{}\">{}</a></li>\n",
+            escape(path),
+            escape(title)
+        ));
+    }
+    nav.push_str("</ol>\n</nav>");
+    nav
+}
+
+fn ncx(toc: &[(String, String)], title: &str, identifier: &str) -> String {
+    let mut ncx = format!(
+        "<?xml version=\"1.0\" encoding=\"utf-8\"?>\n\
+         <ncx xmlns=\"http://www.daisy.org/z3986/2005/ncx/\" version=\"2005-1\">\n\
+         <head>\n<meta name=\"dtb:uid\" content=\"{}\"/>\n</head>\n\
+         <docTitle><text>{}</text></docTitle>\n<navMap>\n",
+        escape(identifier),
+        escape(title)
+    );
+    for (k, (path, title)) in toc.iter().enumerate() {
+        ncx.push_str(&format!(
+            "<navPoint id=\"navpoint-{0}\" playOrder=\"{0}\"><navLabel><text>{1}</text></navLabel><content src=\"{2}\"/></navPoint>\n",
+            k + 1,
+            escape(title),
+            escape(path)
+        ));
+    }
+    ncx.push_str("</navMap>\n</ncx>\n");
+    ncx
+}
+
+fn package(
+    front_matter: &BTreeMap<String, String>,
+    title: &str,
+    lang: &str,
+    identifier: &str,
+    toc: &[(String, String)],
+    images: &[Image],
+) -> String {
+    let mut metadata = format!(
+        "<dc:identifier id=\"uid\">{}</dc:identifier>\n<dc:title>{}</dc:title>\n<dc:language>{}</dc:language>\n",
+        escape(identifier),
+        escape(title),
+        escape(lang)
+    );
+    for (key, element) in [
+        ("author", "creator"),
+        ("date", "date"),
+        ("description", "description"),
+    ] {
+        if let Some(value) = front_matter.get(key) {
+            metadata.push_str(&format!("<dc:{0}>{1}</dc:{0}>\n", element, escape(value)));
+        }
+    }
+    let modified = match front_matter.get("date") {
+        Some(date) if date.len() == 10 => format!("{}T00:00:00Z", date),
+        _ => now(),
+    };
+    metadata.push_str(&format!(
+        "<meta property=\"dcterms:modified\">{}</meta>\n",
+        escape(&modified)
+    ));
+
+    let mut manifest = String::from(
+        "<item id=\"nav\" href=\"nav.xhtml\" media-type=\"application/xhtml+xml\" properties=\"nav\"/>\n\
+         <item id=\"ncx\" href=\"toc.ncx\" media-type=\"application/x-dtbncx+xml\"/>\n",
+    );
+    let mut spine = String::new();
+    for (k, (path, _)) in toc.iter().enumerate() {
+        manifest.push_str(&format!(
+            "<item id=\"chapter-{}\" href=\"{}\" media-type=\"application/xhtml+xml\"/>\n",
+            k + 1,
+            escape(path)
+        ));
+        spine.push_str(&format!("<itemref idref=\"chapter-{}\"/>\n", k + 1));
+    }
+    for (k, image) in images.iter().enumerate() {
+        manifest.push_str(&format!(
+            "<item id=\"image-{}\" href=\"{}\" media-type=\"{}\"/>\n",
+            k + 1,
+            escape(&image.name),
+            image.media_type
+        ));
+    }
+    format!(
+        "<?xml version=\"1.0\" encoding=\"utf-8\"?>\n\
+         <package xmlns=\"http://www.idpf.org/2007/opf\" version=\"3.0\" unique-identifier=\"uid\" xml:lang=\"{}\">\n\
+         <metadata xmlns:dc=\"http://purl.org/dc/elements/1.1/\">\n{}</metadata>\n\
+         <manifest>\n{}</manifest>\n<spine toc=\"ncx\">\n{}</spine>\n</package>\n",
+        escape(lang),
+        metadata,
+        manifest,
+        spine
+    )
+}
+
+// the time now such as `2024-01-31T12:34:56Z`
+fn now() -> String {
+    let seconds = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs() as i64)
+        .unwrap_or_default();
+    let (days, seconds) = (seconds.div_euclid(86400), seconds.rem_euclid(86400));
+    // the civil date of the days since 1970-01-01, by the eras of 400 years from 0000-03-01
+    let days = days + 719468;
+    let era = days.div_euclid(146097);
+    let day_of_era = days.rem_euclid(146097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month + 2) / 5 + 1;
+    let month = if month < 10 { month + 3 } else { month - 9 };
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+        year,
+        month,
+        day,
+        seconds / 3600,
+        seconds / 60 % 60,
+        seconds % 60
+    )
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_epub() {
+        let md = crate::parse(
+            "---\ntitle: Book\nauthor: A & B\ndate: 2024-01-02\n---\n# One\n\nsee [two](#two)\n\n# Two\n\n![x](https://a.com/x.png)\n",
+        )
+        .unwrap();
+        let zip = epub(md, &translator::Options::default(), 1).unwrap();
+        let text = String::from_utf8_lossy(&zip);
+        assert_eq!(&zip[30..38], b"mimetype");
+        assert_eq!(&zip[38..58], b"application/epub+zip");
+        assert!(text.contains("<a href=\"two.xhtml#two\">two</a>"));
+        assert!(text.contains("<dc:title>Book</dc:title>"));
+        assert!(text.contains("<dc:creator>A &amp; B</dc:creator>"));
+        assert!(text.contains("<meta property=\"dcterms:modified\">2024-01-02T00:00:00Z</meta>"));
+        assert!(text.contains("<li><a href=\"text/two.xhtml\">Two</a></li>"));
+        assert!(text.contains("<itemref idref=\"chapter-2\"/>"));
+        assert!(text.contains("<img src=\"https://a.com/x.png\" alt=\"x\" />"));
+        let md = crate::parse("# One\n\n<div>x<br>\n\n&nbsp; <b>a</i>\n\n# Two\n\n</p>\n").unwrap();
+        let zip = epub(md, &translator::Options::default(), 1).unwrap();
+        let text = String::from_utf8_lossy(&zip);
+        let chapters: Vec<&str> = text
+            .split("<?xml")
+            .filter_map(|file| {
+                file.split("</html>")
+                    .next()
+                    .filter(|_| file.contains("<html"))
+            })
+            .collect();
+        assert_eq!(chapters.len(), 3);
+        for chapter in chapters {
+            let chapter = format!("<?xml{}</html>", chapter);
+            let options = roxmltree::ParsingOptions {
+                allow_dtd: true,
+                ..roxmltree::ParsingOptions::default()
+            };
+            let parsed = roxmltree::Document::parse_with_options(&chapter, options);
+            assert_eq!(parsed.err().map(|err| err.to_string()), None);
+        }
+        let md = crate::parse("![x](missing.png)\n").unwrap();
+        assert!(epub(md, &translator::Options::default(), 1).is_err());
+    }
+}
@@ -0,0 +1,469 @@
+use crate::entity::Align;
+use crate::entity::Attributes;
+use crate::entity::ListItem;
+use crate::entity::Markdown;
+use crate::entity::MarkdownInline;
+use crate::entity::MarkdownText;
+use crate::entity::{Position, Span, Spanned};
+use crate::normalize::normalize;
+use crate::parser;
+use crate::parser::{Limit, ParseOptions};
+use crate::slug::Slugs;
+use crate::translator::plain_text;
+use crate::Error;
+
+use std::borrow::Cow;
+use std::collections::{BTreeMap, VecDeque};
+
+// the containers, which are opened by `Event::Start` and closed by `Event::End` with the same tag
+#[derive(Clone, Debug, PartialEq)]
+pub enum Tag {
+    // the level and the attributes, where the id is given or generated
+    Heading(usize, Attributes),
+    Paragraph,
+    // the number of the first item of an ordered list, `None` for an unordered one
+    List(Option<usize>),
+    // the text of the item, then the blocks nested under it
+    Item,
+    DefinitionList,
+    Term,
+    Definition,
+    Table(Vec<Align>),
+    TableHead,
+    TableRow,
+    TableCell,
+    Blockquote,
+    Admonition(String, Option<String>),
+    Div(Attributes),
+    // whether it is open, its `Summary` comes first
+    Details(bool),
+    Summary,
+    FootnoteDefinition(String),
+    Bold,
+    Italic,
+    Mark,
+    // the url and the title
+    Link(String, Option<String>),
+    Image(String, Option<String>),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Event {
+    Start(Tag),
+    End(Tag),
+    Text(String),
+    Code(String),
+    Strike(String),
+    Superscript(String),
+    Subscript(String),
+    Kbd(Vec<String>),
+    Ruby(String, Vec<String>),
+    WikiLink(String, Option<String>),
+    Math(String),
+    Emoji(String),
+    // the label as written, footnotes are not numbered in a stream
+    FootnoteRef(String),
+    InlineHtml(String),
+    InlineComment(String),
+    // between the lines of a paragraph, unless the first one ends with a `HardBreak`
+    SoftBreak,
+    HardBreak,
+    // the language, the code and the rest of the info string
+    Codeblock(String, String, String),
+    MathBlock(String),
+    HorizontalRule,
+    Html(String),
+    Comment(String),
+    FrontMatter(BTreeMap<String, String>),
+    Footnotes,
+}
+
+// reads the top-level blocks of a document one at a time, with where they are written.
+// the lines which cannot be parsed are kept as plain text and reported, as `parse_with_warnings` does
+pub(crate) struct Blocks<'a> {
+    content: Cow<'a, str>,
+    offset: usize,
+    options: ParseOptions,
+    usage: parser::Usage,
+    // the lines of the paragraph being read, and where each of them starts and ends
+    lines: Vec<(Markdown, usize, usize)>,
+    blocks: VecDeque<Spanned<Markdown>>,
+    // the offset and the line of the last position, which the next one is counted from
+    cursor: (usize, usize),
+    pub(crate) warnings: Vec<Error>,
+}
+
+impl<'a> Blocks<'a> {
+    pub(crate) fn new(content: &'a str, options: ParseOptions) -> Self {
+        let content = if content.is_empty() || content.ends_with('\n') {
+            Cow::Borrowed(content)
+        } else {
+            Cow::Owned(format!("{}\n", content))
+        };
+        let mut blocks = Blocks {
+            content,
+            offset: 0,
+            options,
+            usage: parser::Usage::default(),
+            lines: vec![],
+            blocks: VecDeque::new(),
+            cursor: (0, 1),
+            warnings: vec![],
+        };
+        let front_matter =
+            parser::with_options(options, || parser::parse_front_matter(&blocks.content));
+        if let Ok((rest, front_matter)) = front_matter {
+            let end = blocks.content.len() - rest.len();
+            blocks.push(Markdown::FrontMatter(front_matter), 0, end);
+            blocks.offset = end;
+        }
+        let max_line_length = options.limits.max_line_length;
+        if let Some(offset) = parser::find_long_line(&blocks.content, max_line_length) {
+            let limit = Limit::LineLength(max_line_length);
+            blocks
+                .warnings
+                .push(Error::limit(&blocks.content, offset, limit));
+            blocks.offset = blocks.content.len();
+        }
+        blocks
+    }
+
+    // the next block, or the line as plain text when nothing can be parsed there.
+    // nothing is read after a block over the limits
+    fn read_block(&mut self) -> Markdown {
+        let rest = &self.content[self.offset..];
+        let in_paragraph = !self.lines.is_empty();
+        let block = parser::with_usage(self.options, &mut self.usage, || {
+            parser::parse_next_block(rest, in_paragraph)
+                .and_then(|(next, block)| parser::count_block(next).map(|(next, _)| (next, block)))
+        });
+        if let Some((limit, _)) = self.usage.exceeded {
+            self.warnings
+                .push(Error::limit(&self.content, self.offset, limit));
+            self.offset = self.content.len();
+            return Markdown::Line(vec![]);
+        }
+        match block {
+            Ok((next, block)) if next.len() < rest.len() => {
+                self.offset = self.content.len() - next.len();
+                block
+            }
+            _ => {
+                // the blocks stop at the beginning of the line, the inline text tells where in it
+                let end = rest.find('\n').map_or(rest.len(), |k| k + 1);
+                let (inner, text) = parser::with_usage(self.options, &mut self.usage, || {
+                    crate::recover_line(&rest[..end])
+                });
+                let offset = self.offset + end - inner.len();
+                self.warnings.push(Error::at(&self.content, offset));
+                self.offset += end;
+                Markdown::Line(text)
+            }
+        }
+    }
+
+    // the trailing blank lines are not part of a block
+    fn push(&mut self, block: Markdown, start: usize, end: usize) {
+        let end = start + self.content[start..end].trim_end().len();
+        let span = Span {
+            start: self.position(start),
+            end: self.position(end),
+        };
+        self.blocks.push_back(Spanned { node: block, span });
+    }
+
+    // the same as `Position::at`, without counting the lines from the beginning every time
+    fn position(&mut self, offset: usize) -> Position {
+        let (from, line) = if offset >= self.cursor.0 {
+            self.cursor
+        } else {
+            (0, 1)
+        };
+        let line = line + self.content[from..offset].matches('\n').count();
+        self.cursor = (offset, line);
+        let start = self.content[..offset].rfind('\n').map_or(0, |k| k + 1);
+        Position {
+            offset,
+            line,
+            column: self.content[start..offset].chars().count() + 1,
+        }
+    }
+
+    // the paragraph is over
+    fn flush(&mut self) {
+        let lines = std::mem::take(&mut self.lines);
+        if let (Some((_, start, _)), Some((_, _, end))) = (lines.first(), lines.last()) {
+            let (start, end) = (*start, *end);
+            let md = lines.into_iter().map(|(line, _, _)| line).collect();
+            for bit in normalize(md) {
+                self.push(bit, start, end);
+            }
+        }
+    }
+}
+
+impl<'a> Iterator for Blocks<'a> {
+    type Item = Spanned<Markdown>;
+
+    fn next(&mut self) -> Option<Spanned<Markdown>> {
+        loop {
+            if let Some(block) = self.blocks.pop_front() {
+                return Some(block);
+            }
+            if self.offset >= self.content.len() {
+                if self.lines.is_empty() {
+                    return None;
+                }
+                self.flush();
+                continue;
+            }
+            let start = self.offset;
+            match self.read_block() {
+                Markdown::Line(line) if !parser::is_blank(&line) => {
+                    self.lines.push((Markdown::Line(line), start, self.offset))
+                }
+                Markdown::Line(_) => self.flush(),
+                block => {
+                    self.flush();
+                    for bit in normalize(vec![block]) {
+                        self.push(bit, start, self.offset);
+                    }
+                }
+            }
+        }
+    }
+}
+
+// a stream of events, read one block at a time so only the block being read is ever held as a tree.
+// the headings without an id are given one as they come,
+// so it may differ from the whole document's when an explicit id comes later
+pub struct Parser<'a> {
+    blocks: Blocks<'a>,
+    events: VecDeque<Event>,
+    ids: Slugs,
+}
+
+impl<'a> Parser<'a> {
+    pub fn new(content: &'a str) -> Self {
+        Parser::new_with_options(content, ParseOptions::default())
+    }
+
+    pub fn new_with_options(content: &'a str, options: ParseOptions) -> Self {
+        Parser {
+            blocks: Blocks::new(content, options),
+            events: VecDeque::new(),
+            ids: Slugs::default(),
+        }
+    }
+
+    fn push_block(&mut self, bit: Markdown) {
+        match bit {
+            Markdown::Heading(level, text, mut attributes) => {
+                match &attributes.id {
+                    Some(id) => self.ids.insert(id.clone()),
+                    None => attributes.id = Some(self.ids.unique(&plain_text(&text))),
+                }
+                self.wrap(Tag::Heading(level, attributes), |p| p.push_text(text));
+            }
+            Markdown::OrderedList(start, items) => self.push_list(Some(start), items),
+            Markdown::UnorderedList(items) => self.push_list(None, items),
+            Markdown::DefinitionList(definitions) => self.wrap(Tag::DefinitionList, |p| {
+                for (term, texts) in definitions {
+                    p.wrap(Tag::Term, |p| p.push_text(term));
+                    for text in texts {
+                        p.wrap(Tag::Definition, |p| p.push_text(text));
+                    }
+                }
+            }),
+            Markdown::Line(text) => {
+                if !text.is_empty() {
+                    self.wrap(Tag::Paragraph, |p| p.push_text(text));
+                }
+            }
+            Markdown::Paragraph(lines) => self.wrap(Tag::Paragraph, |p| {
+                let mut hard_break = true;
+                for line in lines {
+                    if !hard_break {
+                        p.events.push_back(Event::SoftBreak);
+                    }
+                    hard_break = line.last() == Some(&MarkdownInline::LineBreak);
+                    p.push_text(line);
+                }
+            }),
+            Markdown::Codeblock(lang, code, info) => {
+                self.events.push_back(Event::Codeblock(lang, code, info))
+            }
+            Markdown::HorizontalRule => self.events.push_back(Event::HorizontalRule),
+            Markdown::Table(header, aligns, rows) => self.wrap(Tag::Table(aligns), |p| {
+                p.wrap(Tag::TableHead, |p| p.push_cells(header));
+                for row in rows {
+                    p.wrap(Tag::TableRow, |p| p.push_cells(row));
+                }
+            }),
+            Markdown::Blockquote(inner) => self.wrap(Tag::Blockquote, |p| p.push_blocks(inner)),
+            Markdown::FootnoteDefinition(label, text) => {
+                self.wrap(Tag::FootnoteDefinition(label), |p| p.push_text(text))
+            }
+            Markdown::FrontMatter(front_matter) => {
+                self.events.push_back(Event::FrontMatter(front_matter))
+            }
+            Markdown::Html(html) => self.events.push_back(Event::Html(html)),
+            Markdown::Comment(text) => self.events.push_back(Event::Comment(text)),
+            Markdown::MathBlock(tex) => self.events.push_back(Event::MathBlock(tex)),
+            Markdown::Admonition(kind, title, inner) => {
+                self.wrap(Tag::Admonition(kind, title), |p| p.push_blocks(inner))
+            }
+            Markdown::Div(attributes, inner) => {
+                self.wrap(Tag::Div(attributes), |p| p.push_blocks(inner))
+            }
+            Markdown::Details(summary, open, inner) => self.wrap(Tag::Details(open), |p| {
+                p.wrap(Tag::Summary, |p| p.push_text(summary));
+                p.push_blocks(inner);
+            }),
+            Markdown::Footnotes => self.events.push_back(Event::Footnotes),
+        }
+    }
+
+    fn push_blocks(&mut self, md: Vec<Markdown>) {
+        for bit in md {
+            self.push_block(bit);
+        }
+    }
+
+    fn push_list(&mut self, start: Option<usize>, items: Vec<ListItem>) {
+        self.wrap(Tag::List(start), |p| {
+            for (text, children) in items {
+                p.wrap(Tag::Item, |p| {
+                    p.push_text(text);
+                    p.push_blocks(children);
+                });
+            }
+        })
+    }
+
+    fn push_cells(&mut self, cells: Vec<MarkdownText>) {
+        for cell in cells {
+            self.wrap(Tag::TableCell, |p| p.push_text(cell));
+        }
+    }
+
+    fn push_text(&mut self, text: MarkdownText) {
+        for part in text {
+            let event = match part {
+                MarkdownInline::Link(text, url, title) => {
+                    self.wrap(Tag::Link(url, title), |p| p.push_text(text));
+                    continue;
+                }
+                MarkdownInline::Image(text, url, title) => {
+                    self.wrap(Tag::Image(url, title), |p| p.push_text(text));
+                    continue;
+                }
+                MarkdownInline::Bold(text) => {
+                    self.wrap(Tag::Bold, |p| p.push_text(text));
+                    continue;
+                }
+                MarkdownInline::Italic(text) => {
+                    self.wrap(Tag::Italic, |p| p.push_text(text));
+                    continue;
+                }
+                MarkdownInline::Mark(text) => {
+                    self.wrap(Tag::Mark, |p| p.push_text(text));
+                    continue;
+                }
+                MarkdownInline::InlineCode(code) => Event::Code(code),
+                MarkdownInline::Strike(text) => Event::Strike(text),
+                MarkdownInline::Superscript(text) => Event::Superscript(text),
+                MarkdownInline::Subscript(text) => Event::Subscript(text),
+                MarkdownInline::Kbd(keys) => Event::Kbd(keys),
+                MarkdownInline::Ruby(base, readings) => Event::Ruby(base, readings),
+                MarkdownInline::Plaintext(text) => Event::Text(text),
+                MarkdownInline::FootnoteRef(label) => Event::FootnoteRef(label),
+                MarkdownInline::Html(html) => Event::InlineHtml(html),
+                MarkdownInline::Comment(text) => Event::InlineComment(text),
+                MarkdownInline::WikiLink(target, label) => Event::WikiLink(target, label),
+                MarkdownInline::Math(tex) => Event::Math(tex),
+                MarkdownInline::Emoji(name) => Event::Emoji(name),
+                MarkdownInline::LineBreak => Event::HardBreak,
+            };
+            self.events.push_back(event);
+        }
+    }
+
+    fn wrap<F>(&mut self, tag: Tag, f: F)
+    where
+        F: FnOnce(&mut Self),
+    {
+        self.events.push_back(Event::Start(tag.clone()));
+        f(self);
+        self.events.push_back(Event::End(tag));
+    }
+}
+
+impl<'a> Iterator for Parser<'a> {
+    type Item = Event;
+
+    fn next(&mut self) -> Option<Event> {
+        while self.events.is_empty() {
+            let block = self.blocks.next()?;
+            self.push_block(block.node);
+        }
+        self.events.pop_front()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parser() {
+        let text = |s: &str| Event::Text(String::from(s));
+        assert_eq!(
+            Parser::new("# A\n\nb **c**\nd  \ne\n\n- f\n").collect::<Vec<Event>>(),
+            vec![
+                Event::Start(Tag::Heading(
+                    1,
+                    Attributes {
+                        id: Some(String::from("a")),
+                        ..Attributes::default()
+                    }
+                )),
+                text("A"),
+                Event::End(Tag::Heading(
+                    1,
+                    Attributes {
+                        id: Some(String::from("a")),
+                        ..Attributes::default()
+                    }
+                )),
+                Event::Start(Tag::Paragraph),
+                text("b "),
+                Event::Start(Tag::Bold),
+                text("c"),
+                Event::End(Tag::Bold),
+                Event::SoftBreak,
+                text("d"),
+                Event::HardBreak,
+                text("e"),
+                Event::End(Tag::Paragraph),
+                Event::Start(Tag::List(None)),
+                Event::Start(Tag::Item),
+                text("f"),
+                Event::End(Tag::Item),
+                Event::End(Tag::List(None)),
+            ]
+        );
+        assert_eq!(
+            Parser::new("---\ntitle: x\n---\n```\ncode\n```").collect::<Vec<Event>>(),
+            vec![
+                Event::FrontMatter(
+                    vec![(String::from("title"), String::from("x"))]
+                        .into_iter()
+                        .collect()
+                ),
+                Event::Codeblock(String::new(), String::from("code\n"), String::new()),
+            ]
+        );
+        assert_eq!(Parser::new("").next(), None);
+    }
+}
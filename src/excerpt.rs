@@ -0,0 +1,36 @@
+use crate::entity::Markdown;
+
+// the summary of a document for the listings of a blog: the blocks before a `<!-- more -->`,
+// or else the first paragraph. the front matter is left out
+pub fn excerpt(md: &[Markdown]) -> Vec<Markdown> {
+    let blocks = md
+        .iter()
+        .filter(|bit| !matches!(bit, Markdown::FrontMatter(_)));
+    if md.iter().any(is_more) {
+        return blocks.take_while(|bit| !is_more(bit)).cloned().collect();
+    }
+    blocks
+        .filter(|bit| matches!(bit, Markdown::Paragraph(_)))
+        .take(1)
+        .cloned()
+        .collect()
+}
+
+fn is_more(bit: &Markdown) -> bool {
+    matches!(bit, Markdown::Comment(text) if text.trim() == "more")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_excerpt() {
+        let md = crate::parse("---\ntitle: a\n---\n# A\n\nfirst\n\n- x\n\n<!-- more -->\n\nrest\n")
+            .unwrap();
+        assert_eq!(excerpt(&md), crate::parse("# A\n\nfirst\n\n- x\n").unwrap());
+        let md = crate::parse("# A\n\n> quote\n\nfirst\n\nsecond\n").unwrap();
+        assert_eq!(excerpt(&md), crate::parse("first\n").unwrap());
+        assert_eq!(excerpt(&crate::parse("# A\n").unwrap()), vec![]);
+    }
+}
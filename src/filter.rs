@@ -0,0 +1,70 @@
+use crate::entity::Markdown;
+use crate::json;
+
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+// the document written to the stdin of `command` as the JSON of `--to json`,
+// and read back from its stdout. as with Pandoc, the output format is its first argument.
+// the command is run by the shell, so that it can be `python3 filter.py` and the like
+pub fn run(md: &[Markdown], command: &str, format: &str) -> Result<Vec<Markdown>, String> {
+    let mut child = shell(command)
+        .arg(format)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .map_err(|err| format!("Cannot run the filter {}: {}", command, err))?;
+    let input = json::to_json(md);
+    let mut stdin = child.stdin.take().expect("the stdin is piped");
+    // written while the output is read, which could fill the pipe otherwise
+    let writer = std::thread::spawn(move || stdin.write_all(input.as_bytes()));
+    let output = child
+        .wait_with_output()
+        .map_err(|err| format!("Cannot run the filter {}: {}", command, err))?;
+    // a filter which does not read its input is fine, as long as it succeeds
+    let _ = writer.join();
+    if !output.status.success() {
+        return Err(format!("The filter {} failed: {}", command, output.status));
+    }
+    let output = String::from_utf8(output.stdout)
+        .map_err(|_| format!("The filter {} wrote invalid UTF-8", command))?;
+    json::from_json(&output)
+        .map_err(|err| format!("The filter {} wrote an invalid JSON AST: {}", command, err))
+}
+
+#[cfg(unix)]
+fn shell(command: &str) -> Command {
+    let mut shell = Command::new("sh");
+    // `$0` is `sh`, and the format is `$1`
+    shell.args(["-c", &format!("{} \"$@\"", command), "sh"]);
+    shell
+}
+
+#[cfg(windows)]
+fn shell(command: &str) -> Command {
+    let mut shell = Command::new("cmd");
+    shell.args(["/C", command]);
+    shell
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_run() {
+        let md = crate::parse("# hello\n").unwrap();
+        assert_eq!(run(&md, "cat #", "html"), Ok(md.clone()));
+        assert_eq!(
+            run(&md, "sed s/hello/$1/ #", "latex"),
+            Ok(crate::parse("# latex\n").unwrap())
+        );
+        assert_eq!(
+            run(&md, "exit 1 #", "html"),
+            Err(String::from("The filter exit 1 # failed: exit status: 1"))
+        );
+        assert!(run(&md, "echo nope #", "html")
+            .unwrap_err()
+            .starts_with("The filter echo nope # wrote an invalid JSON AST"));
+    }
+}
@@ -0,0 +1,94 @@
+// the highlighting of fenced code blocks with syntect, by the syntax definitions and the themes
+// bundled with it. the code is written in `<span>`s with `hl-` classes, which the css of a theme colors
+
+use syntect::highlighting::ThemeSet;
+use syntect::html::{css_for_theme_with_class_style, ClassStyle, ClassedHTMLGenerator};
+use syntect::parsing::SyntaxSet;
+use syntect::util::LinesWithEndings;
+
+use std::sync::OnceLock;
+
+const CLASS_STYLE: ClassStyle = ClassStyle::SpacedPrefixed { prefix: "hl-" };
+
+// the short names of two of the bundled themes
+const ALIASES: [(&str, &str); 2] = [("light", "InspiredGitHub"), ("dark", "base16-ocean.dark")];
+
+fn syntaxes() -> &'static SyntaxSet {
+    static SYNTAXES: OnceLock<SyntaxSet> = OnceLock::new();
+    SYNTAXES.get_or_init(SyntaxSet::load_defaults_newlines)
+}
+
+// the bundled themes by name, and their css
+fn themes() -> &'static [(String, String)] {
+    static THEMES: OnceLock<Vec<(String, String)>> = OnceLock::new();
+    THEMES.get_or_init(|| {
+        ThemeSet::load_defaults()
+            .themes
+            .iter()
+            .filter_map(|(name, theme)| {
+                let css = css_for_theme_with_class_style(theme, CLASS_STYLE).ok()?;
+                Some((name.clone(), css))
+            })
+            .collect()
+    })
+}
+
+// the names `theme_css` knows, the aliases first
+pub fn theme_names() -> Vec<&'static str> {
+    ALIASES
+        .iter()
+        .map(|(alias, _)| *alias)
+        .chain(themes().iter().map(|(name, _)| name.as_str()))
+        .collect()
+}
+
+pub fn theme_css(name: &str) -> Option<&'static str> {
+    let name = ALIASES
+        .iter()
+        .find(|(alias, _)| *alias == name)
+        .map_or(name, |(_, theme)| *theme);
+    themes()
+        .iter()
+        .find(|(theme, _)| theme == name)
+        .map(|(_, css)| css.as_str())
+}
+
+// the code as escaped html in `<span>`s, or None for unknown languages.
+// the language is looked up by its name or its file extension, as `rust` or `rs`
+pub fn highlight(lang: &str, code: &str) -> Option<String> {
+    if lang.is_empty() {
+        return None;
+    }
+    let syntaxes = syntaxes();
+    let syntax = syntaxes.find_syntax_by_token(lang)?;
+    let mut html = ClassedHTMLGenerator::new_with_class_style(syntax, syntaxes, CLASS_STYLE);
+    for line in LinesWithEndings::from(code) {
+        html.parse_html_for_line_which_includes_newline(line).ok()?;
+    }
+    Some(html.finalize())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_highlight() {
+        let html = highlight("rust", "let s = \"<\"; // c\n").unwrap();
+        assert!(html.contains("<span class=\"hl-storage hl-type hl-rust\">let</span>"));
+        assert!(html.contains("&lt;"));
+        assert!(html.contains("<span class=\"hl-comment hl-line hl-double-slash hl-rust\">"));
+        assert!(highlight("py", "def f(): pass\n").is_some());
+        assert_eq!(highlight("brainfuck", "+++"), None);
+        assert_eq!(highlight("", "x"), None);
+    }
+
+    #[test]
+    fn test_theme_css() {
+        assert_eq!(theme_css("light"), theme_css("InspiredGitHub"));
+        assert!(theme_css("dark").unwrap().contains(".hl-"));
+        assert!(theme_css("Solarized (light)").is_some());
+        assert_eq!(theme_css("unknown"), None);
+        assert!(theme_names().starts_with(&["light", "dark"]));
+    }
+}
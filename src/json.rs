@@ -0,0 +1,144 @@
+use crate::entity::Markdown;
+
+use serde::Deserialize;
+
+// the AST is exchanged as JSON in a Pandoc-like shape, given by the serde derives of `entity`:
+// every node is an object `{"t": <variant name>, "c": <fields>}`,
+// where a single field is given as is and several fields as an array.
+// `"c"` is omitted for variants without fields
+
+pub use serde_json::{Map, Value as Json};
+
+// arrays and objects nested this deep are refused, rather than overflowing the stack.
+// it is the recursion limit of serde_json, which every `parse` and `from_json` runs under
+pub const MAX_DEPTH: usize = 128;
+
+pub fn parse(i: &str) -> Result<Json, String> {
+    serde_json::from_str(i).map_err(|err| err.to_string())
+}
+
+pub fn write(json: &Json) -> String {
+    json.to_string()
+}
+
+pub fn encode(md: &[Markdown]) -> Json {
+    serde_json::to_value(md).unwrap_or(Json::Null)
+}
+
+pub fn decode(json: &Json) -> Result<Vec<Markdown>, String> {
+    Vec::<Markdown>::deserialize(json).map_err(|err| err.to_string())
+}
+
+pub fn to_json(md: &[Markdown]) -> String {
+    serde_json::to_string(md).unwrap_or_default()
+}
+
+pub fn from_json(i: &str) -> Result<Vec<Markdown>, String> {
+    serde_json::from_str(i).map_err(|err| err.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::entity::{Align, Attributes, MarkdownInline};
+    use std::collections::BTreeMap;
+
+    #[test]
+    fn test_to_json() {
+        assert_eq!(
+            to_json(&[
+                Markdown::Heading(1, vec![MarkdownInline::Plaintext(String::from("T"))], Attributes::default()),
+                Markdown::HorizontalRule,
+            ]),
+            "[{\"t\":\"Heading\",\"c\":[1,[{\"t\":\"Plaintext\",\"c\":\"T\"}],[null,[],[]]]},{\"t\":\"HorizontalRule\"}]"
+        );
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let mut front_matter = BTreeMap::new();
+        front_matter.insert(String::from("title"), String::from("x"));
+        let text = |s: &str| vec![MarkdownInline::Plaintext(String::from(s))];
+        let md = vec![
+            Markdown::FrontMatter(front_matter),
+            Markdown::Heading(2, text("h"), Attributes::default()),
+            Markdown::UnorderedList(vec![(
+                vec![
+                    MarkdownInline::Bold(vec![MarkdownInline::Plaintext(String::from("b"))]),
+                    MarkdownInline::Link(
+                        vec![MarkdownInline::Plaintext(String::from("l"))],
+                        String::from("u"),
+                        Some(String::from("t")),
+                    ),
+                ],
+                vec![Markdown::OrderedList(1, vec![(text("n"), vec![])])],
+            )]),
+            Markdown::Paragraph(vec![text("a"), text("b")]),
+            Markdown::Codeblock(
+                String::from("rust"),
+                String::from("fn main() {}\n"),
+                String::new(),
+            ),
+            Markdown::Table(vec![text("a")], vec![Align::Center], vec![vec![text("1")]]),
+            Markdown::Blockquote(vec![Markdown::Line(text("q"))]),
+            Markdown::FootnoteDefinition(String::from("1"), text("note")),
+            Markdown::Footnotes,
+            Markdown::DefinitionList(vec![(text("t"), vec![text("d"), text("e")])]),
+            Markdown::Html(String::from("<div>")),
+            Markdown::Comment(String::from(" todo\n")),
+            Markdown::Line(vec![MarkdownInline::Comment(String::from("x"))]),
+            Markdown::Line(vec![
+                MarkdownInline::WikiLink(String::from("a"), None),
+                MarkdownInline::WikiLink(String::from("b"), Some(String::from("c"))),
+            ]),
+            Markdown::MathBlock(String::from("x^2")),
+            Markdown::Div(
+                Attributes {
+                    id: Some(String::from("i")),
+                    classes: vec![String::from("c")],
+                    pairs: vec![(String::from("k"), String::from("v"))],
+                },
+                vec![Markdown::HorizontalRule],
+            ),
+            Markdown::Admonition(
+                String::from("note"),
+                Some(String::from("N")),
+                vec![Markdown::Line(text("n"))],
+            ),
+            Markdown::Details(text("s"), true, vec![Markdown::Line(text("d"))]),
+            Markdown::Line(vec![MarkdownInline::Math(String::from("y"))]),
+            Markdown::Line(vec![MarkdownInline::Emoji(String::from("tada"))]),
+            Markdown::Line(vec![
+                MarkdownInline::Mark(text("m")),
+                MarkdownInline::Superscript(String::from("2")),
+                MarkdownInline::Subscript(String::from("i")),
+                MarkdownInline::Kbd(vec![String::from("Ctrl"), String::from("C")]),
+                MarkdownInline::Ruby(String::from("漢字"), vec![String::from("かんじ")]),
+                MarkdownInline::LineBreak,
+            ]),
+        ];
+        assert_eq!(from_json(&to_json(&md)), Ok(md.clone()));
+        assert_eq!(decode(&encode(&md)), Ok(md));
+    }
+
+    #[test]
+    fn test_from_json_errors() {
+        assert!(from_json("[{\"t\":\"Nope\"}]")
+            .unwrap_err()
+            .starts_with("unknown variant `Nope`"));
+        assert!(from_json("[{\"t\":\"Codeblock\",\"c\":[\"a\"]}]").is_err());
+        assert!(from_json("{}").is_err());
+        // far deeper than the stack would take
+        let deep = format!(
+            "[{}]",
+            "{\"t\":\"Blockquote\",\"c\":[".repeat(100_000) + &"]}".repeat(100_000)
+        );
+        assert!(from_json(&deep)
+            .unwrap_err()
+            .starts_with("recursion limit exceeded"));
+        assert!(parse(&deep).is_err());
+        let nested = |depth| format!("{}1{}", "[".repeat(depth), "]".repeat(depth));
+        assert!(parse(&nested(MAX_DEPTH - 1)).is_ok());
+        assert!(parse(&nested(MAX_DEPTH)).is_err());
+    }
+}
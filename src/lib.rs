@@ -0,0 +1,398 @@
+pub mod build;
+pub mod cache;
+pub mod charref;
+pub mod config;
+pub mod emoji;
+pub mod entity;
+pub mod epub;
+pub mod event;
+pub mod excerpt;
+pub mod filter;
+#[cfg(feature = "highlight")]
+pub mod highlight;
+pub mod json;
+pub mod lint;
+pub mod lsp;
+#[cfg(feature = "lua")]
+pub mod lua;
+pub mod merge;
+pub mod normalize;
+pub mod outline;
+pub mod parser;
+#[cfg(feature = "pdf")]
+pub mod pdf;
+#[cfg(feature = "plugins")]
+pub mod plugin;
+pub mod pretty;
+pub mod sanitize;
+pub mod server;
+pub mod site;
+pub mod slug;
+pub mod split;
+pub mod stats;
+pub mod tangle;
+pub mod template;
+pub mod translator;
+pub mod url;
+#[cfg(feature = "wasm")]
+pub mod wasm;
+pub mod zip;
+
+use entity::{Markdown, MarkdownInline, Position, Spanned};
+pub use event::{Event, Parser, Tag};
+pub use parser::{Dialect, Extension, Extensions, Limit, Limits, ParseOptions};
+use std::fmt;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Error {
+    // byte offset of the character which cannot be parsed
+    pub offset: usize,
+    // 1-based line and column (in characters) of that offset
+    pub line: usize,
+    pub column: usize,
+    // the whole line containing the error
+    pub snippet: String,
+    // the limit which the document goes over there, when it is not a syntax error
+    pub limit: Option<Limit>,
+}
+
+impl Error {
+    pub(crate) fn at(content: &str, offset: usize) -> Error {
+        let position = Position::at(content, offset);
+        let offset = position.offset;
+        let start = content[..offset].rfind('\n').map_or(0, |k| k + 1);
+        let end = content[offset..]
+            .find('\n')
+            .map_or(content.len(), |k| offset + k);
+        Error {
+            offset,
+            line: position.line,
+            column: position.column,
+            snippet: content[start..end].trim_end_matches('\r').to_string(),
+            limit: None,
+        }
+    }
+
+    pub(crate) fn limit(content: &str, offset: usize, limit: Limit) -> Error {
+        Error {
+            limit: Some(limit),
+            ..Error::at(content, offset)
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "cannot parse the markdown at line {}, column {}",
+            self.line, self.column
+        )?;
+        match self.limit {
+            Some(limit) => writeln!(f, ": {}", limit)?,
+            None => writeln!(f)?,
+        }
+        // only a window of the line around the column, which can be a megabyte long
+        let column = self.column - 1;
+        let start = column.saturating_sub(SNIPPET_WINDOW);
+        let mut chars = self.snippet.chars().skip(start);
+        let shown: String = chars
+            .by_ref()
+            .take(column - start + 1 + SNIPPET_WINDOW)
+            .collect();
+        let before = if start > 0 { "..." } else { "" };
+        let after = if chars.next().is_some() { "..." } else { "" };
+        writeln!(f, "  | {}{}{}", before, shown, after)?;
+        write!(f, "  | {}^", " ".repeat(before.len() + column - start))
+    }
+}
+
+// the characters of the snippet shown on each side of the column
+const SNIPPET_WINDOW: usize = 40;
+
+impl std::error::Error for Error {}
+
+// parses a whole document into normalized blocks, ready to be translated.
+// a delimiter which opens nothing is plain text, so this fails only over the limits
+pub fn parse(content: &str) -> Result<Vec<Markdown>, Error> {
+    let (markdown, mut warnings) = parse_with_warnings(content);
+    if warnings.is_empty() {
+        Ok(markdown)
+    } else {
+        Err(warnings.remove(0))
+    }
+}
+
+// parses a whole document, keeping the text which cannot be parsed as plain text.
+// each line of it is reported as a warning
+pub fn parse_with_warnings(content: &str) -> (Vec<Markdown>, Vec<Error>) {
+    parse_with_options(content, ParseOptions::default())
+}
+
+pub fn parse_with_options(content: &str, options: ParseOptions) -> (Vec<Markdown>, Vec<Error>) {
+    parser::with_options(options, || parse_recovering(content))
+}
+
+// the top-level blocks with where they are written, read one at a time.
+// a paragraph spans its lines, and the blocks nested in others are not given their own
+pub fn parse_spanned(content: &str) -> Result<Vec<Spanned<Markdown>>, Error> {
+    let (markdown, mut warnings) = parse_spanned_with_options(content, ParseOptions::default());
+    if warnings.is_empty() {
+        Ok(markdown)
+    } else {
+        Err(warnings.remove(0))
+    }
+}
+
+pub fn parse_spanned_with_options(
+    content: &str,
+    options: ParseOptions,
+) -> (Vec<Spanned<Markdown>>, Vec<Error>) {
+    let mut blocks = event::Blocks::new(content, options);
+    let markdown = blocks.by_ref().collect();
+    (markdown, blocks.warnings)
+}
+
+// a document over the limits is not read any further,
+// and the error is the last of the warnings
+fn parse_recovering(content: &str) -> (Vec<Markdown>, Vec<Error>) {
+    let content = if content.ends_with('\n') {
+        content.to_string()
+    } else {
+        format!("{}\n", content)
+    };
+    let max_line_length = parser::options().limits.max_line_length;
+    if let Some(offset) = parser::find_long_line(&content, max_line_length) {
+        let error = Error::limit(&content, offset, Limit::LineLength(max_line_length));
+        return (vec![], vec![error]);
+    }
+    let (mut rest, mut markdown) = match parser::parse_markdown(&content) {
+        Ok(result) => result,
+        Err(nom::Err::Error(e)) | Err(nom::Err::Failure(e)) => {
+            // the failure can be anywhere in the first line, which is where we start over
+            let offset = content.len() - e.input.len();
+            let start = content[..offset].rfind('\n').map_or(0, |k| k + 1);
+            (&content[start..], vec![])
+        }
+        Err(nom::Err::Incomplete(_)) => (content.as_str(), vec![]),
+    };
+    let mut warnings = vec![];
+    while !rest.is_empty() && parser::exceeded().is_none() {
+        // the blocks stop at the beginning of the line, the inline text tells where in it
+        let (line, next) = rest.split_at(rest.find('\n').map_or(rest.len(), |k| k + 1));
+        let (inner, text) = recover_line(line);
+        warnings.push(Error::at(
+            &content,
+            content.len() - rest.len() + line.len() - inner.len(),
+        ));
+        markdown.push(Markdown::Line(text));
+        rest = match parser::parse_blocks(next) {
+            Ok((next, blocks)) => {
+                markdown.extend(blocks);
+                next
+            }
+            Err(_) => next,
+        };
+    }
+    if let Some((limit, top)) = parser::exceeded() {
+        warnings.push(Error::limit(&content, content.len() - top, limit));
+    }
+    (normalize::normalize(markdown), warnings)
+}
+
+// the inline text of a line, which is kept as plain text from where it cannot be parsed on,
+// and the part of the line from there
+pub(crate) fn recover_line(line: &str) -> (&str, Vec<MarkdownInline>) {
+    let (inner, mut text) = match parser::parse_inline_prefix(line) {
+        Ok((inner, text)) => (inner, text),
+        Err(_) => (line, vec![]),
+    };
+    let unparsed = inner.trim_end_matches(['\r', '\n']);
+    match text.last_mut() {
+        _ if unparsed.is_empty() => {}
+        Some(MarkdownInline::Plaintext(last)) => last.push_str(unparsed),
+        _ => text.push(MarkdownInline::Plaintext(unparsed.to_string())),
+    }
+    (inner, text)
+}
+
+// an html fragment with the default options
+pub fn to_html(md: &[Markdown]) -> String {
+    translator::translate(md.to_vec(), &translator::Options::default())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(
+            parse("a\nb"),
+            Ok(vec![Markdown::Paragraph(vec![
+                vec![MarkdownInline::Plaintext(String::from("a"))],
+                vec![MarkdownInline::Plaintext(String::from("b"))],
+            ])])
+        );
+        // an unmatched delimiter or a backslash before a letter is plain text
+        assert_eq!(
+            parse("ok\nbad \\ line\n"),
+            Ok(vec![Markdown::Paragraph(vec![
+                vec![MarkdownInline::Plaintext(String::from("ok"))],
+                vec![MarkdownInline::Plaintext(String::from("bad \\ line"))],
+            ])])
+        );
+        assert_eq!(
+            parse("5 * 3 = 15"),
+            Ok(vec![Markdown::Paragraph(vec![vec![
+                MarkdownInline::Plaintext(String::from("5 * 3 = 15"))
+            ]])])
+        );
+        // only what goes over the limits is an error
+        let deep = format!("ok\n\n{}b\n", "> ".repeat(100));
+        assert_eq!(
+            parse(&deep).map_err(|e| (e.line, e.column, e.limit)),
+            Err((3, 1, Some(Limit::Depth(64))))
+        );
+    }
+
+    #[test]
+    fn test_parse_with_warnings() {
+        let (markdown, warnings) = parse_with_warnings(
+            "# Title with *x* and [y]\n\n| a | *b |\n|---|---|\n\n> **q** and 2*3\n",
+        );
+        assert!(warnings.is_empty());
+        assert_eq!(
+            to_html(&markdown),
+            "<h1 id=\"title-with-x-and-y\">Title with <i>x</i> and [y]</h1>\
+             <table><thead><tr><th>a</th><th>*b</th></tr></thead></table>\
+             <blockquote><p><b>q</b> and 2*3</p></blockquote>"
+        );
+        let (markdown, warnings) = parse_with_warnings("**bold** and 5 * 3, \\x [x `y\n");
+        assert!(warnings.is_empty());
+        assert_eq!(
+            to_html(&markdown),
+            "<p><b>bold</b> and 5 * 3, \\x [x `y</p>"
+        );
+    }
+
+    #[test]
+    fn test_parse_spanned() {
+        let content = "a\nb \\ c\n\n```\nx\n```\n\n\n# d\n";
+        let (markdown, warnings) = parse_spanned_with_options(content, ParseOptions::default());
+        assert_eq!(
+            markdown
+                .iter()
+                .map(|bit| bit.node.clone())
+                .collect::<Vec<Markdown>>(),
+            parse_with_warnings(content).0
+        );
+        assert_eq!(
+            markdown
+                .iter()
+                .map(|bit| (
+                    (bit.span.start.line, bit.span.start.column),
+                    (bit.span.end.line, bit.span.end.column)
+                ))
+                .collect::<Vec<_>>(),
+            vec![((1, 1), (2, 6)), ((4, 1), (6, 4)), ((9, 1), (9, 4))]
+        );
+        assert_eq!(markdown[1].span.start.offset, 9);
+        assert_eq!(warnings, parse_with_warnings(content).1);
+    }
+
+    #[test]
+    fn test_parse_limits() {
+        let limited = |content: &str, limits: Limits| {
+            let options = ParseOptions {
+                limits,
+                ..ParseOptions::default()
+            };
+            let (_, warnings) = parse_with_options(content, options);
+            let (_, spanned) = parse_spanned_with_options(content, options);
+            assert_eq!(
+                warnings.last().and_then(|w| w.limit),
+                spanned.last().and_then(|w| w.limit)
+            );
+            warnings.last().map(|w| (w.line, w.limit))
+        };
+        let defaults = Limits::default();
+        // far deeper than the stack would take
+        let quotes = format!("a\n\n{}b\n", "> ".repeat(10_000));
+        assert_eq!(
+            limited(&quotes, defaults),
+            Some((3, Some(Limit::Depth(64))))
+        );
+        let list: String = (0..100)
+            .map(|k| format!("{}- {}\n", "  ".repeat(k), k))
+            .collect();
+        assert_eq!(limited(&list, defaults), Some((1, Some(Limit::Depth(64)))));
+        assert_eq!(limited("> > a\n", defaults), None);
+        let shallow = Limits {
+            max_depth: 2,
+            ..defaults
+        };
+        assert_eq!(limited("> a\n", shallow), None);
+        assert_eq!(
+            limited("x\n\n> > a\n", shallow),
+            Some((3, Some(Limit::Depth(2))))
+        );
+        let short = Limits {
+            max_line_length: 3,
+            ..defaults
+        };
+        assert_eq!(limited("abc\r\nabc\n", short), None);
+        assert_eq!(
+            limited("abc\nabcd\n", short),
+            Some((2, Some(Limit::LineLength(3))))
+        );
+        let few = Limits {
+            max_blocks: 3,
+            ..defaults
+        };
+        assert_eq!(
+            limited("a\nb\n\n# c\n", few),
+            Some((4, Some(Limit::Blocks(3))))
+        );
+        assert_eq!(limited("- a\n- b\n", few), None);
+        assert_eq!(
+            parse_with_options("abcd\n", ParseOptions { limits: short, ..ParseOptions::default() })
+                .1[0]
+                .to_string(),
+            "cannot parse the markdown at line 1, column 1: the line is longer than 3 bytes\n  | abcd\n  | ^"
+        );
+        let long = format!("{}b{}\n", "a".repeat(100), "c".repeat(100));
+        let mut error = Error::at(&long, 100);
+        assert_eq!(
+            error.to_string(),
+            format!(
+                "cannot parse the markdown at line 1, column 101\n  | ...{}b{}...\n  | {}^",
+                "a".repeat(40),
+                "c".repeat(40),
+                " ".repeat(43)
+            )
+        );
+        error.column = 1;
+        assert!(error
+            .to_string()
+            .ends_with(&format!("  | {}...\n  | ^", "a".repeat(41))));
+    }
+
+    #[test]
+    fn test_parse_crlf() {
+        let content = std::fs::read_to_string("./examples/crlf.md").unwrap();
+        assert!(content.contains("\r\n"));
+        assert_eq!(parse(&content), parse(&content.replace("\r\n", "\n")));
+        assert!(parse(&content).is_ok());
+        // mixed line endings
+        assert_eq!(parse("a\r\nb\n\r\n# c\r\n"), parse("a\nb\n\n# c\n"));
+    }
+
+    #[test]
+    fn test_to_html() {
+        assert_eq!(
+            to_html(&parse("# T\n\n*x* & y\n").unwrap()),
+            "<h1 id=\"t\">T</h1><p><i>x</i> &amp; y</p>"
+        );
+    }
+}
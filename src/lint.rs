@@ -0,0 +1,415 @@
+use crate::entity::{walk_inlines, Markdown, MarkdownInline, Spanned};
+use crate::parser::ParseOptions;
+use crate::slug::slugify;
+use crate::translator::plain_text;
+
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+
+// the style rules, each with a code of its own
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Rule {
+    // `-`, `*` and `+` are all used as the markers of unordered lists
+    ListMarker,
+    // a heading more than one level below the one before it, such as `###` after `#`
+    HeadingLevel,
+    // spaces or tabs at the end of a line, except the two spaces of a hard line break,
+    // or more than two spaces for one
+    TrailingSpace,
+    // a line longer than `max_line_length`, outside the code blocks
+    LineLength,
+    // a heading with the same anchor as one before it, which its links never reach
+    DuplicateAnchor,
+    // an image without alt text
+    ImageAlt,
+}
+
+impl Rule {
+    pub fn code(self) -> &'static str {
+        match self {
+            Rule::ListMarker => "L001",
+            Rule::HeadingLevel => "L002",
+            Rule::TrailingSpace => "L003",
+            Rule::LineLength => "L004",
+            Rule::DuplicateAnchor => "L005",
+            Rule::ImageAlt => "L006",
+        }
+    }
+
+    // whether `fix` can put it right
+    pub fn fixable(self) -> bool {
+        matches!(self, Rule::ListMarker | Rule::TrailingSpace)
+    }
+}
+
+// a problem at a line of the document, and the column it starts at, both 1-based
+#[derive(Clone, Debug, PartialEq)]
+pub struct Finding {
+    pub rule: Rule,
+    pub line: usize,
+    pub column: usize,
+    pub message: String,
+}
+
+impl fmt::Display for Finding {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}:{}: {} {}",
+            self.line,
+            self.column,
+            self.rule.code(),
+            self.message
+        )
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct LintOptions {
+    // in characters
+    pub max_line_length: usize,
+    pub parse: ParseOptions,
+}
+
+impl Default for LintOptions {
+    fn default() -> Self {
+        LintOptions {
+            max_line_length: 80,
+            parse: ParseOptions::default(),
+        }
+    }
+}
+
+// the findings of every rule, in the order of the lines
+pub fn lint(content: &str, options: &LintOptions) -> Vec<Finding> {
+    let (blocks, _) = crate::parse_spanned_with_options(content, options.parse);
+    let mut findings = vec![];
+    check_lines(content, &blocks, options, &mut findings);
+    check_headings(&blocks, &mut findings);
+    check_images(content, &blocks, &mut findings);
+    findings.sort_by_key(|finding| (finding.line, finding.column));
+    findings
+}
+
+// the document with the findings of the fixable rules put right
+pub fn fix(content: &str, options: &LintOptions) -> String {
+    let findings = lint(content, options);
+    let marker = first_marker(
+        content,
+        &crate::parse_spanned_with_options(content, options.parse).0,
+    );
+    let mut out = String::with_capacity(content.len());
+    for (n, line) in content.split_inclusive('\n').enumerate() {
+        let (text, ending) = split_ending(line);
+        let mut text = text.to_string();
+        for finding in findings.iter().filter(|finding| finding.line == n + 1) {
+            match (finding.rule, marker) {
+                (Rule::TrailingSpace, _) => {
+                    let hard_break = is_hard_break(&text);
+                    text.truncate(text.trim_end().len());
+                    if hard_break {
+                        text.push_str("  ");
+                    }
+                }
+                (Rule::ListMarker, Some(marker)) => {
+                    let k = text
+                        .char_indices()
+                        .nth(finding.column - 1)
+                        .map_or(0, |(k, _)| k);
+                    text.replace_range(k..k + 1, &marker.to_string());
+                }
+                _ => {}
+            }
+        }
+        out.push_str(&text);
+        out.push_str(ending);
+    }
+    out
+}
+
+// two or more spaces after the text, which all make the same hard line break
+fn is_hard_break(line: &str) -> bool {
+    !line.trim().is_empty() && line.ends_with("  ")
+}
+
+fn split_ending(line: &str) -> (&str, &str) {
+    let text = line.trim_end_matches(['\r', '\n']);
+    (text, &line[text.len()..])
+}
+
+// the lines and where each of them starts, without their endings
+fn lines(content: &str) -> impl Iterator<Item = (usize, &str)> {
+    content.split_inclusive('\n').scan(0, |offset, line| {
+        let start = *offset;
+        *offset += line.len();
+        Some((start, split_ending(line).0))
+    })
+}
+
+// whether the line starting at `offset` is in one of the blocks
+fn in_blocks<F>(offset: usize, blocks: &[Spanned<Markdown>], f: F) -> bool
+where
+    F: Fn(&Markdown) -> bool,
+{
+    blocks
+        .iter()
+        .any(|bit| f(&bit.node) && bit.span.start.offset <= offset && offset < bit.span.end.offset)
+}
+
+fn is_code(block: &Markdown) -> bool {
+    matches!(
+        block,
+        Markdown::Codeblock(..) | Markdown::MathBlock(_) | Markdown::Html(_)
+    )
+}
+
+// where the lines of the code blocks, the math blocks and the html start, nested ones too.
+// a nested block has no span of its own, so its lines are found in the span of the top-level one:
+// the lines which end with its lines in order, after the indent and the `>` of the containers
+fn code_lines(content: &str, blocks: &[Spanned<Markdown>]) -> HashSet<usize> {
+    let mut code = HashSet::new();
+    for bit in blocks {
+        let start = bit.span.start.offset;
+        let lines: Vec<(usize, &str)> = lines(&content[start..bit.span.end.offset])
+            .map(|(offset, line)| (start + offset, line))
+            .collect();
+        if is_code(&bit.node) {
+            code.extend(lines.iter().map(|(offset, _)| *offset));
+            continue;
+        }
+        let mut cursor = 0;
+        bit.node.walk(&mut |block| {
+            let text = match block {
+                Markdown::Codeblock(_, text, _)
+                | Markdown::MathBlock(text)
+                | Markdown::Html(text) => text,
+                _ => return,
+            };
+            let text: Vec<&str> = text.lines().collect();
+            let found = (cursor..lines.len().saturating_sub(text.len()) + 1).find(|&k| {
+                text.iter().zip(&lines[k..]).all(|(text, (_, line))| {
+                    line.strip_suffix(text)
+                        .is_some_and(|indent| indent.chars().all(|c| matches!(c, ' ' | '\t' | '>')))
+                })
+            });
+            if let Some(k) = found {
+                code.extend(lines[k..k + text.len()].iter().map(|(offset, _)| *offset));
+                cursor = k + text.len();
+            }
+        });
+    }
+    code
+}
+
+// the marker of an item of an unordered list written on this line, and its column
+fn list_marker(line: &str) -> Option<(char, usize)> {
+    let indent = line.len() - line.trim_start_matches([' ', '\t']).len();
+    let mut rest = line[indent..].chars();
+    match (rest.next(), rest.next()) {
+        (Some(marker @ ('-' | '*' | '+')), Some(' ' | '\t')) => {
+            Some((marker, line[..indent].chars().count() + 1))
+        }
+        _ => None,
+    }
+}
+
+fn is_list(block: &Markdown) -> bool {
+    matches!(block, Markdown::UnorderedList(_))
+}
+
+// the marker of the first item of an unordered list, which the others should use
+fn first_marker(content: &str, blocks: &[Spanned<Markdown>]) -> Option<char> {
+    lines(content)
+        .filter(|(offset, _)| in_blocks(*offset, blocks, is_list))
+        .find_map(|(_, line)| list_marker(line))
+        .map(|(marker, _)| marker)
+}
+
+fn check_lines(
+    content: &str,
+    blocks: &[Spanned<Markdown>],
+    options: &LintOptions,
+    findings: &mut Vec<Finding>,
+) {
+    let marker = first_marker(content, blocks);
+    let code = code_lines(content, blocks);
+    for (n, (offset, line)) in lines(content).enumerate() {
+        let finding = |rule, column, message: String| Finding {
+            rule,
+            line: n + 1,
+            column,
+            message,
+        };
+        if code.contains(&offset) {
+            continue;
+        }
+        let text = line.trim_end();
+        let trailing = &line[text.len()..];
+        // two spaces after the text are a hard line break
+        if !trailing.is_empty() && (trailing != "  " || text.is_empty()) {
+            let column = text.chars().count() + 1;
+            findings.push(finding(
+                Rule::TrailingSpace,
+                column,
+                String::from("trailing whitespace"),
+            ));
+        }
+        let length = line.chars().count();
+        if length > options.max_line_length {
+            findings.push(finding(
+                Rule::LineLength,
+                options.max_line_length + 1,
+                format!(
+                    "the line is {} characters long, more than {}",
+                    length, options.max_line_length
+                ),
+            ));
+        }
+        match (marker, list_marker(line)) {
+            (Some(expected), Some((found, column)))
+                if found != expected && in_blocks(offset, blocks, is_list) =>
+            {
+                findings.push(finding(
+                    Rule::ListMarker,
+                    column,
+                    format!("the list item is marked with {}, not {}", found, expected),
+                ));
+            }
+            _ => {}
+        }
+    }
+}
+
+fn check_headings(blocks: &[Spanned<Markdown>], findings: &mut Vec<Finding>) {
+    let mut previous = None;
+    // the anchors so far, and the lines of their headings
+    let mut anchors: HashMap<String, usize> = HashMap::new();
+    for bit in blocks {
+        let Markdown::Heading(level, text, attributes) = &bit.node else {
+            continue;
+        };
+        let line = bit.span.start.line;
+        let finding = |rule, message: String| Finding {
+            rule,
+            line,
+            column: bit.span.start.column,
+            message,
+        };
+        match previous {
+            Some(previous) if *level > previous + 1 => findings.push(finding(
+                Rule::HeadingLevel,
+                format!("the heading goes from level {} to {}", previous, level),
+            )),
+            _ => {}
+        }
+        previous = Some(*level);
+        let anchor = attributes
+            .id
+            .clone()
+            .unwrap_or_else(|| slugify(&plain_text(text)));
+        match anchors.get(&anchor) {
+            Some(first) => findings.push(finding(
+                Rule::DuplicateAnchor,
+                format!(
+                    "#{} is the anchor of the heading at line {} already",
+                    anchor, first
+                ),
+            )),
+            None => {
+                anchors.insert(anchor, line);
+            }
+        }
+    }
+}
+
+fn check_images(content: &str, blocks: &[Spanned<Markdown>], findings: &mut Vec<Finding>) {
+    for bit in blocks {
+        let mut count = 0;
+        bit.node.walk(&mut |block| {
+            for text in block.texts() {
+                walk_inlines(text, &mut |part| {
+                    if let MarkdownInline::Image(alt, _, _) = part {
+                        count += plain_text(alt).trim().is_empty() as usize;
+                    }
+                });
+            }
+        });
+
+        // the images are found again in the source, for their lines
+        let source = &content[bit.span.start.offset..bit.span.end.offset];
+        let mut starts = source.match_indices("![").map(|(k, _)| k).filter(|k| {
+            let alt = &source[k + 2..];
+            alt.find(']')
+                .is_some_and(|end| alt[..end].trim().is_empty())
+        });
+        for _ in 0..count {
+            let offset = bit.span.start.offset + starts.next().unwrap_or(0);
+            let position = crate::entity::Position::at(content, offset);
+            findings.push(Finding {
+                rule: Rule::ImageAlt,
+                line: position.line,
+                column: position.column,
+                message: String::from("the image has no alt text"),
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn codes(content: &str) -> Vec<(usize, usize, &'static str)> {
+        lint(content, &LintOptions::default())
+            .iter()
+            .map(|finding| (finding.line, finding.column, finding.rule.code()))
+            .collect()
+    }
+
+    #[test]
+    fn test_lint() {
+        let content = "# A\n\n### B \n\n- a\n* b\n  + c\n\n## A\n\n![](x.png) ![y](y.png) ![ ](z.png)\n\nbreak  \nhere\n";
+        assert_eq!(
+            codes(content),
+            vec![
+                (3, 1, "L002"),
+                (3, 6, "L003"),
+                (6, 1, "L001"),
+                (7, 3, "L001"),
+                (9, 1, "L005"),
+                (11, 1, "L006"),
+                (11, 24, "L006"),
+            ]
+        );
+        let long = format!("{}\n\n```\n{} \n```\n", "a ".repeat(45), "b".repeat(90));
+        assert_eq!(codes(&long), vec![(1, 81, "L004"), (1, 90, "L003")]);
+        assert_eq!(codes("# A {#x}\n\n# X\n\n## Y\n"), vec![(3, 1, "L005")]);
+        assert_eq!(
+            lint("# A\n\n### B\n", &LintOptions::default())[0].to_string(),
+            "3:1: L002 the heading goes from level 1 to 3"
+        );
+    }
+
+    #[test]
+    fn test_fix() {
+        let content = "- a \n* b\r\n  + c\t\n\n```\nx \n```\n\nbreak  \nhere\n";
+        let fixed = fix(content, &LintOptions::default());
+        assert_eq!(
+            fixed,
+            "- a\n- b\r\n  - c\n\n```\nx \n```\n\nbreak  \nhere\n"
+        );
+        let content = "# A \n\none   \ntwo\t\n\n- x\n  ```\n  y  \n  ```\n\n> ```\n> z \n> ```\n>\n> q   \n> r\n\n<div>  \n</div>\n";
+        let fixed = fix(content, &LintOptions::default());
+        assert_eq!(
+            fixed,
+            "# A\n\none  \ntwo\n\n- x\n  ```\n  y  \n  ```\n\n> ```\n> z \n> ```\n>\n> q  \n> r\n\n<div>  \n</div>\n"
+        );
+        let html = |content: &str| {
+            crate::translator::translate(crate::parse(content).unwrap(), &Default::default())
+        };
+        assert_eq!(html(&fixed), html(content));
+        assert!(lint(&fixed, &LintOptions::default())
+            .iter()
+            .all(|finding| !finding.rule.fixable()));
+    }
+}
@@ -0,0 +1,550 @@
+use crate::entity::{Markdown, Position};
+use crate::json::{self, Json};
+use crate::lint::{self, LintOptions};
+use crate::outline::{self, Section};
+use crate::translator;
+
+use std::collections::HashMap;
+use std::io::{self, BufRead, Write};
+
+// the request for the html of a document, for the preview panes:
+// `{"textDocument": {"uri": ...}}` gives `{"html": ...}`
+pub const PREVIEW: &str = "prose/preview";
+
+// the documents open in the editor, and what is sent back for each message
+pub struct Server {
+    documents: HashMap<String, String>,
+    options: LintOptions,
+    // after `shutdown`, the requests are errors
+    shutdown: bool,
+}
+
+// what `handle` asks of the loop besides the messages to send
+#[derive(Debug, PartialEq)]
+pub enum Next {
+    Continue,
+    // `exit`, with whether `shutdown` came before it
+    Exit(bool),
+}
+
+// a minimal Language Server Protocol server on stdin and stdout: the findings of `prose lint`
+// as diagnostics, the headings as the symbols, the sections and the code blocks as the folding
+// ranges, and `prose/preview`. the documents are synced in full
+pub fn serve<R: BufRead, W: Write>(
+    mut input: R,
+    mut output: W,
+    options: LintOptions,
+) -> io::Result<bool> {
+    let mut server = Server::new(options);
+    while let Some(message) = read_message(&mut input)? {
+        let (replies, next) = match message.and_then(|message| parse_message(&message)) {
+            Ok(message) => server.handle(&message),
+            Err(reply) => (vec![reply], Next::Continue),
+        };
+        for reply in replies {
+            write_message(&mut output, &reply)?;
+        }
+        if let Next::Exit(clean) = next {
+            return Ok(clean);
+        }
+    }
+    Ok(false)
+}
+
+// the longest message which is read, in bytes
+pub const MAX_MESSAGE_LENGTH: usize = 64 << 20;
+
+// the longest line of a header which is kept, in bytes; the rest of a longer line is skipped
+const MAX_HEADER_LENGTH: u64 = 1 << 10;
+
+// the content of a message after its `Content-Length` header, or none at the end of the input.
+// a message longer than `MAX_MESSAGE_LENGTH`, or without a length, is skipped,
+// and is the error to reply with instead
+pub fn read_message<R: BufRead>(input: &mut R) -> io::Result<Option<Result<String, Json>>> {
+    let mut length = None;
+    loop {
+        let mut line = vec![];
+        if io::Read::take(&mut *input, MAX_HEADER_LENGTH).read_until(b'\n', &mut line)? == 0 {
+            return Ok(None);
+        }
+        if line.last() != Some(&b'\n') {
+            input.skip_until(b'\n')?;
+        }
+        let line = String::from_utf8_lossy(&line);
+        let line = line.trim_end();
+        if line.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            if name.eq_ignore_ascii_case("content-length") {
+                length = value.trim().parse::<usize>().ok();
+            }
+        }
+    }
+    let Some(length) = length else {
+        return Ok(Some(Err(error_reply(
+            Json::Null,
+            -32600,
+            "no Content-Length in the header",
+        ))));
+    };
+    if length > MAX_MESSAGE_LENGTH {
+        io::copy(
+            &mut io::Read::take(&mut *input, length as u64),
+            &mut io::sink(),
+        )?;
+        let message = format!("the message is longer than {} bytes", MAX_MESSAGE_LENGTH);
+        return Ok(Some(Err(error_reply(Json::Null, -32600, &message))));
+    }
+    let mut content = vec![0; length];
+    input.read_exact(&mut content)?;
+    Ok(Some(String::from_utf8(content).map_err(|err| {
+        error_reply(Json::Null, -32700, &err.to_string())
+    })))
+}
+
+// the JSON of a message, or the error to reply with when it is not JSON,
+// or nested deeper than `json::MAX_DEPTH`
+pub(crate) fn parse_message(message: &str) -> Result<Json, Json> {
+    json::parse(message).map_err(|err| error_reply(Json::Null, -32700, &err))
+}
+
+pub fn write_message<W: Write>(output: &mut W, message: &Json) -> io::Result<()> {
+    let content = json::write(message);
+    write!(
+        output,
+        "Content-Length: {}\r\n\r\n{}",
+        content.len(),
+        content
+    )?;
+    output.flush()
+}
+
+impl Server {
+    pub fn new(options: LintOptions) -> Server {
+        Server {
+            documents: HashMap::new(),
+            options,
+            shutdown: false,
+        }
+    }
+
+    // the replies and the notifications to send for a message from the editor
+    pub fn handle(&mut self, message: &Json) -> (Vec<Json>, Next) {
+        let method = match message.get("method") {
+            Some(Json::String(method)) => method.as_str(),
+            _ => return (vec![], Next::Continue),
+        };
+        let params = message.get("params").unwrap_or(&Json::Null);
+        let Some(id) = message.get("id").cloned() else {
+            if method == "exit" {
+                return (vec![], Next::Exit(self.shutdown));
+            }
+            return (self.notify(method, params), Next::Continue);
+        };
+        if self.shutdown {
+            return (
+                vec![error_reply(id, -32600, "the server is shut down")],
+                Next::Continue,
+            );
+        }
+        let uri = || text_document(params);
+        let result = match method {
+            "initialize" => Some(capabilities()),
+            "shutdown" => {
+                self.shutdown = true;
+                Some(Json::Null)
+            }
+            "textDocument/documentSymbol" => uri().map(|uri| self.symbols(&uri)),
+            "textDocument/foldingRange" => uri().map(|uri| self.folding_ranges(&uri)),
+            PREVIEW => uri().map(|uri| self.preview(&uri)),
+            _ => {
+                let message = format!("unknown method {}", method);
+                return (vec![error_reply(id, -32601, &message)], Next::Continue);
+            }
+        };
+        let reply = match result {
+            Some(result) => object(vec![
+                ("jsonrpc", string("2.0")),
+                ("id", id),
+                ("result", result),
+            ]),
+            None => error_reply(id, -32602, "no textDocument.uri in the params"),
+        };
+        (vec![reply], Next::Continue)
+    }
+
+    fn notify(&mut self, method: &str, params: &Json) -> Vec<Json> {
+        let Some(uri) = text_document(params) else {
+            return vec![];
+        };
+        match method {
+            "textDocument/didOpen" => {
+                let text = match params.get("textDocument").and_then(|doc| doc.get("text")) {
+                    Some(Json::String(text)) => text.clone(),
+                    _ => String::new(),
+                };
+                self.documents.insert(uri.clone(), text);
+                vec![self.diagnostics(&uri)]
+            }
+            "textDocument/didChange" => {
+                let text = match params.get("contentChanges") {
+                    Some(Json::Array(changes)) => {
+                        changes.last().and_then(|change| change.get("text"))
+                    }
+                    _ => None,
+                };
+                if let Some(Json::String(text)) = text {
+                    self.documents.insert(uri.clone(), text.clone());
+                }
+                vec![self.diagnostics(&uri)]
+            }
+            "textDocument/didClose" => {
+                self.documents.remove(&uri);
+                // the diagnostics of a closed document are cleared
+                vec![publish(&uri, vec![])]
+            }
+            _ => vec![],
+        }
+    }
+
+    fn document(&self, uri: &str) -> &str {
+        self.documents.get(uri).map_or("", String::as_str)
+    }
+
+    fn diagnostics(&self, uri: &str) -> Json {
+        let content = self.document(uri);
+        let lines: Vec<&str> = content.lines().collect();
+        let (_, warnings) = crate::parse_with_options(content, self.options.parse);
+        let warnings = warnings.into_iter().map(|warning| {
+            let message = match warning.limit {
+                Some(limit) => format!("cannot parse the markdown: {}", limit),
+                None => String::from("cannot parse the markdown"),
+            };
+            (warning.line, warning.column, None, 1, message)
+        });
+        let findings = lint::lint(content, &self.options)
+            .into_iter()
+            .map(|finding| {
+                let code = Some(finding.rule.code());
+                (finding.line, finding.column, code, 2, finding.message)
+            });
+        let diagnostics = warnings
+            .chain(findings)
+            .map(|(line, column, code, severity, message)| {
+                let text = lines.get(line - 1).copied().unwrap_or("");
+                let mut entries = vec![
+                    (
+                        "range",
+                        range(
+                            (line, utf16_column(text, column)),
+                            (line, utf16_column(text, usize::MAX)),
+                        ),
+                    ),
+                    ("severity", Json::from(severity)),
+                    ("source", string("prose")),
+                    ("message", Json::String(message)),
+                ];
+                if let Some(code) = code {
+                    entries.push(("code", string(code)));
+                }
+                object(entries)
+            })
+            .collect();
+        publish(uri, diagnostics)
+    }
+
+    fn symbols(&self, uri: &str) -> Json {
+        let content = self.document(uri);
+        let lines: Vec<&str> = content.lines().collect();
+        let (sections, _) = outline::outline(content, self.options.parse);
+        let ends = section_ends(content, &sections);
+        fn symbol(section: &Section, ends: &HashMap<usize, usize>, lines: &[&str]) -> Json {
+            let line_end = |line: usize| utf16_column(lines[line - 1], usize::MAX);
+            let end = ends[&section.line];
+            let heading = range((section.line, 0), (section.line, line_end(section.line)));
+            let children = section.children.iter();
+            object(vec![
+                ("name", Json::String(section.text.trim().to_string())),
+                ("detail", Json::String(format!("#{}", section.anchor))),
+                // `String`, as the other Markdown servers give the headings
+                ("kind", Json::from(15)),
+                ("range", range((section.line, 0), (end, line_end(end)))),
+                ("selectionRange", heading),
+                (
+                    "children",
+                    Json::Array(children.map(|s| symbol(s, ends, lines)).collect()),
+                ),
+            ])
+        }
+        Json::Array(sections.iter().map(|s| symbol(s, &ends, &lines)).collect())
+    }
+
+    fn folding_ranges(&self, uri: &str) -> Json {
+        let content = self.document(uri);
+        let (sections, _) = outline::outline(content, self.options.parse);
+        let ends = section_ends(content, &sections);
+        let mut ranges: Vec<(usize, usize)> = ends
+            .iter()
+            .filter(|(start, end)| start < end)
+            .map(|(start, end)| (*start, *end))
+            .collect();
+        let (blocks, _) = crate::parse_spanned_with_options(content, self.options.parse);
+        for bit in blocks {
+            if let Markdown::Codeblock(..) = bit.node {
+                let end = Position::at(content, bit.span.end.offset.saturating_sub(1)).line;
+                if bit.span.start.line < end {
+                    ranges.push((bit.span.start.line, end));
+                }
+            }
+        }
+        ranges.sort();
+        Json::Array(
+            ranges
+                .into_iter()
+                .map(|(start, end)| {
+                    object(vec![
+                        ("startLine", Json::from(start - 1)),
+                        ("endLine", Json::from(end - 1)),
+                        ("kind", string("region")),
+                    ])
+                })
+                .collect(),
+        )
+    }
+
+    fn preview(&self, uri: &str) -> Json {
+        let (markdown, _) = crate::parse_with_options(self.document(uri), self.options.parse);
+        let html = translator::translate(markdown, &translator::Options::default());
+        object(vec![("html", Json::String(html))])
+    }
+}
+
+fn capabilities() -> Json {
+    object(vec![
+        (
+            "capabilities",
+            object(vec![
+                // the whole document on every change
+                ("textDocumentSync", Json::from(1)),
+                ("documentSymbolProvider", Json::Bool(true)),
+                ("foldingRangeProvider", Json::Bool(true)),
+                ("experimental", object(vec![("preview", string(PREVIEW))])),
+            ]),
+        ),
+        (
+            "serverInfo",
+            object(vec![
+                ("name", string("prose")),
+                ("version", string(env!("CARGO_PKG_VERSION"))),
+            ]),
+        ),
+    ])
+}
+
+// the last line of each section by the line of its heading: the line before the next heading
+// of its level or above, or the end of the document, without the blank lines
+fn section_ends(content: &str, sections: &[Section]) -> HashMap<usize, usize> {
+    let lines: Vec<&str> = content.lines().collect();
+    let mut headings = vec![];
+    fn flatten(sections: &[Section], out: &mut Vec<(usize, usize)>) {
+        for section in sections {
+            out.push((section.line, section.level));
+            flatten(&section.children, out);
+        }
+    }
+    flatten(sections, &mut headings);
+    headings
+        .iter()
+        .enumerate()
+        .map(|(k, (line, level))| {
+            let next = headings[k + 1..]
+                .iter()
+                .find(|(_, other)| other <= level)
+                .map_or(lines.len() + 1, |(next, _)| *next);
+            let mut end = next - 1;
+            while end > *line && lines.get(end - 1).is_some_and(|l| l.trim().is_empty()) {
+                end -= 1;
+            }
+            (*line, end)
+        })
+        .collect()
+}
+
+// the column of LSP, which counts the UTF-16 units, of a 1-based column in characters.
+// `usize::MAX` is the end of the line
+fn utf16_column(line: &str, column: usize) -> usize {
+    line.chars()
+        .take(column.saturating_sub(1))
+        .map(char::len_utf16)
+        .sum()
+}
+
+fn text_document(params: &Json) -> Option<String> {
+    match params.get("textDocument").and_then(|doc| doc.get("uri")) {
+        Some(Json::String(uri)) => Some(uri.clone()),
+        _ => None,
+    }
+}
+
+fn publish(uri: &str, diagnostics: Vec<Json>) -> Json {
+    object(vec![
+        ("jsonrpc", string("2.0")),
+        ("method", string("textDocument/publishDiagnostics")),
+        (
+            "params",
+            object(vec![
+                ("uri", string(uri)),
+                ("diagnostics", Json::Array(diagnostics)),
+            ]),
+        ),
+    ])
+}
+
+pub(crate) fn error_reply(id: Json, code: i64, message: &str) -> Json {
+    object(vec![
+        ("jsonrpc", string("2.0")),
+        ("id", id),
+        (
+            "error",
+            object(vec![
+                ("code", Json::from(code)),
+                ("message", string(message)),
+            ]),
+        ),
+    ])
+}
+
+// 1-based lines, and columns in UTF-16 units
+fn range(start: (usize, usize), end: (usize, usize)) -> Json {
+    let position = |(line, character): (usize, usize)| {
+        object(vec![
+            ("line", Json::from(line - 1)),
+            ("character", Json::from(character)),
+        ])
+    };
+    object(vec![("start", position(start)), ("end", position(end))])
+}
+
+pub(crate) fn object(entries: Vec<(&str, Json)>) -> Json {
+    Json::Object(
+        entries
+            .into_iter()
+            .map(|(key, value)| (key.to_string(), value))
+            .collect(),
+    )
+}
+
+pub(crate) fn string(s: &str) -> Json {
+    Json::String(s.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(server: &mut Server, message: &str) -> Vec<String> {
+        let (replies, _) = server.handle(&json::parse(message).unwrap());
+        replies.iter().map(json::write).collect()
+    }
+
+    #[test]
+    fn test_server() {
+        let mut server = Server::new(LintOptions::default());
+        let content = "# A\\n\\n### B \\n\\n```\\nx\\n```\\n\\n# C\\n";
+        let open = format!(
+            r#"{{"jsonrpc":"2.0","method":"textDocument/didOpen","params":{{"textDocument":{{"uri":"file:///a.md","text":"{}"}}}}}}"#,
+            content
+        );
+        assert_eq!(
+            request(&mut server, &open),
+            ["{\"jsonrpc\":\"2.0\",\"method\":\"textDocument/publishDiagnostics\",\"params\":{\"uri\":\"file:///a.md\",\"diagnostics\":[\
+              {\"range\":{\"start\":{\"line\":2,\"character\":0},\"end\":{\"line\":2,\"character\":6}},\"severity\":2,\"source\":\"prose\",\"message\":\"the heading goes from level 1 to 3\",\"code\":\"L002\"},\
+              {\"range\":{\"start\":{\"line\":2,\"character\":5},\"end\":{\"line\":2,\"character\":6}},\"severity\":2,\"source\":\"prose\",\"message\":\"trailing whitespace\",\"code\":\"L003\"}]}}"]
+        );
+        let params = r#""params":{"textDocument":{"uri":"file:///a.md"}}"#;
+        assert_eq!(
+            request(
+                &mut server,
+                &format!(
+                    r#"{{"id":1,"method":"textDocument/foldingRange",{}}}"#,
+                    params
+                )
+            ),
+            ["{\"jsonrpc\":\"2.0\",\"id\":1,\"result\":[\
+              {\"startLine\":0,\"endLine\":6,\"kind\":\"region\"},\
+              {\"startLine\":2,\"endLine\":6,\"kind\":\"region\"},\
+              {\"startLine\":4,\"endLine\":6,\"kind\":\"region\"}]}"]
+        );
+        let symbols = request(
+            &mut server,
+            &format!(
+                r#"{{"id":"s","method":"textDocument/documentSymbol",{}}}"#,
+                params
+            ),
+        );
+        assert!(symbols[0].starts_with("{\"jsonrpc\":\"2.0\",\"id\":\"s\",\"result\":[{\"name\":\"A\",\"detail\":\"#a\",\"kind\":15,\"range\":{\"start\":{\"line\":0,\"character\":0},\"end\":{\"line\":6,\"character\":3}}"));
+        assert!(symbols[0].contains("\"children\":[{\"name\":\"B\""));
+        assert!(symbols[0].contains("{\"name\":\"C\",\"detail\":\"#c\""));
+        assert_eq!(
+            request(&mut server, &format!(r#"{{"id":2,"method":"prose/preview",{}}}"#, params)),
+            ["{\"jsonrpc\":\"2.0\",\"id\":2,\"result\":{\"html\":\"<h1 id=\\\"a\\\">A</h1><h3 id=\\\"b\\\">B</h3><pre><code>x\\n</code></pre><h1 id=\\\"c\\\">C</h1>\"}}"]
+        );
+        assert_eq!(
+            request(&mut server, r#"{"id":3,"method":"nope"}"#),
+            ["{\"jsonrpc\":\"2.0\",\"id\":3,\"error\":{\"code\":-32601,\"message\":\"unknown method nope\"}}"]
+        );
+        assert_eq!(
+            server
+                .handle(&json::parse(r#"{"method":"exit"}"#).unwrap())
+                .1,
+            Next::Exit(false)
+        );
+    }
+
+    #[test]
+    fn test_serve() {
+        let body = |s: &str| format!("Content-Length: {}\r\n\r\n{}", s.len(), s);
+        let input = [
+            body(r#"{"jsonrpc":"2.0","id":1,"method":"initialize","params":{}}"#),
+            body(r#"{"jsonrpc":"2.0","id":2,"method":"shutdown"}"#),
+            body(r#"{"jsonrpc":"2.0","method":"exit"}"#),
+        ]
+        .concat();
+        let mut output = vec![];
+        assert!(serve(input.as_bytes(), &mut output, LintOptions::default()).unwrap());
+        let output = String::from_utf8(output).unwrap();
+        assert!(output.starts_with("Content-Length: "));
+        assert!(output.contains("\"documentSymbolProvider\":true"));
+        assert!(output.ends_with(&body(r#"{"jsonrpc":"2.0","id":2,"result":null}"#)));
+    }
+
+    #[test]
+    fn test_serve_untrusted() {
+        let body = |s: &str| format!("Content-Length: {}\r\n\r\n{}", s.len(), s);
+        let input = [
+            body(&"[".repeat(100_000)),
+            format!("Content-Length: {}\r\n\r\n", MAX_MESSAGE_LENGTH + 1),
+            " ".repeat(MAX_MESSAGE_LENGTH + 1),
+            format!("X-{}: 1\r\n\r\n", "a".repeat(100_000)),
+            body(r#"{"jsonrpc":"2.0","id":1,"method":"shutdown"}"#),
+            body(r#"{"jsonrpc":"2.0","method":"exit"}"#),
+        ]
+        .concat();
+        let mut output = vec![];
+        assert!(serve(input.as_bytes(), &mut output, LintOptions::default()).unwrap());
+        let output = String::from_utf8(output).unwrap();
+        let replies: Vec<&str> = output.split("Content-Length: ").skip(1).collect();
+        assert_eq!(replies.len(), 4);
+        assert!(replies[0].contains("\"code\":-32700,\"message\":\"recursion limit exceeded"));
+        assert!(replies[1].contains("\"code\":-32600,\"message\":\"the message is longer than"));
+        assert!(replies[2].contains("\"message\":\"no Content-Length in the header\""));
+        assert!(replies[3].ends_with("{\"jsonrpc\":\"2.0\",\"id\":1,\"result\":null}"));
+    }
+
+    #[test]
+    fn test_utf16_column() {
+        assert_eq!(utf16_column("a😀b", 3), 3);
+        assert_eq!(utf16_column("a😀b", usize::MAX), 4);
+    }
+}
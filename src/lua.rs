@@ -0,0 +1,232 @@
+use crate::entity::Markdown;
+use crate::json::{self, Json};
+
+use mlua::{Lua, Table, Value};
+
+// what a filter is given besides its script: `prose` with the constructors of the nodes,
+// and `prose.walk`, which runs the functions of a filter on the nodes of their types
+const PRELUDE: &str = r##"
+-- prose.Heading(1, text, attributes) is {t = "Heading", c = {1, text, attributes}}:
+-- the fields are those of `--to json`, where one field is not in a list and none is no `c`
+setmetatable(prose, {
+  __index = function(_, t)
+    return function(...)
+      local n = select("#", ...)
+      if n == 0 then
+        return {t = t}
+      elseif n == 1 then
+        return {t = t, c = (...)}
+      end
+      return {t = t, c = {...}}
+    end
+  end,
+})
+
+-- the nodes are changed from the inside out. a function which returns nothing keeps the node,
+-- and one which returns a list of nodes puts them in its place
+function prose.walk(value, filter)
+  if type(value) ~= "table" then
+    return value
+  end
+  if value.t == nil then
+    local out = {}
+    for _, item in ipairs(value) do
+      local new = prose.walk(item, filter)
+      if type(item) == "table" and item.t ~= nil and type(new) == "table" and new.t == nil then
+        for _, node in ipairs(new) do
+          table.insert(out, node)
+        end
+      else
+        table.insert(out, new)
+      end
+    end
+    return out
+  end
+  if value.c ~= nil then
+    value.c = prose.walk(value.c, filter)
+  end
+  local f = filter[value.t]
+  if type(f) == "function" then
+    local new = f(value)
+    if new ~= nil then
+      return new
+    end
+  end
+  return value
+end
+"##;
+
+// runs the Lua script on the document, in the way of the Lua filters of Pandoc:
+// a function named after a type of node, such as `Link` or `Heading`, is given each node
+// of that type as a table, and `Document` the list of the blocks at the end.
+// the functions are the globals of the script, or the fields of the table it returns.
+// `FORMAT` is the output format, and `prose.null` the null of the AST
+pub fn run(
+    md: &[Markdown],
+    script: &str,
+    name: &str,
+    format: &str,
+) -> Result<Vec<Markdown>, String> {
+    let error = |err: mlua::Error| format!("The Lua filter {} failed: {}", name, err);
+    let lua = Lua::new();
+    let globals = lua.globals();
+    let prose = lua.create_table().map_err(error)?;
+    prose.set("null", Value::NULL).map_err(error)?;
+    globals.set("prose", prose.clone()).map_err(error)?;
+    globals.set("FORMAT", format).map_err(error)?;
+    lua.load(PRELUDE)
+        .set_name("prelude")
+        .exec()
+        .map_err(error)?;
+    let filter = match lua.load(script).set_name(name).eval().map_err(error)? {
+        Value::Table(filter) => filter,
+        _ => globals,
+    };
+    let walk: mlua::Function = prose.get("walk").map_err(error)?;
+    let blocks = to_lua(&lua, &json::encode(md)).map_err(error)?;
+    let mut blocks: Value = walk.call((blocks, filter.clone())).map_err(error)?;
+    if let Ok(document) = filter.get::<_, mlua::Function>("Document") {
+        match document.call(blocks.clone()).map_err(error)? {
+            Value::Nil => {}
+            new => blocks = new,
+        }
+    }
+    let json = from_lua(&blocks)
+        .map_err(|err| format!("The Lua filter {} returned an invalid AST: {}", name, err))?;
+    json::decode(&json)
+        .map_err(|err| format!("The Lua filter {} returned an invalid AST: {}", name, err))
+}
+
+fn to_lua<'lua>(lua: &'lua Lua, json: &Json) -> mlua::Result<Value<'lua>> {
+    Ok(match json {
+        Json::Null => Value::NULL,
+        Json::Bool(b) => Value::Boolean(*b),
+        Json::Number(n) => match n.as_i64() {
+            Some(n) => Value::Integer(n),
+            None => Value::Number(n.as_f64().unwrap_or_default()),
+        },
+        Json::String(s) => Value::String(lua.create_string(s)?),
+        Json::Array(items) => {
+            let table = lua.create_table_with_capacity(items.len(), 0)?;
+            for item in items {
+                table.raw_push(to_lua(lua, item)?)?;
+            }
+            Value::Table(table)
+        }
+        Json::Object(entries) => {
+            let table = lua.create_table_with_capacity(0, entries.len())?;
+            for (key, value) in entries {
+                table.raw_set(key.as_str(), to_lua(lua, value)?)?;
+            }
+            Value::Table(table)
+        }
+    })
+}
+
+fn from_lua(value: &Value) -> Result<Json, String> {
+    Ok(match value {
+        Value::Nil => Json::Null,
+        Value::LightUserData(data) if data.0.is_null() => Json::Null,
+        Value::Boolean(b) => Json::Bool(*b),
+        Value::Integer(n) => Json::from(*n),
+        // `2 * level` is a float in Lua, and an integer in the AST
+        Value::Number(n) if n.fract() == 0.0 && n.abs() < 1e15 => Json::from(*n as i64),
+        Value::Number(n) => Json::from(*n),
+        Value::String(s) => Json::String(
+            s.to_str()
+                .map_err(|_| String::from("a string is not UTF-8"))?
+                .to_string(),
+        ),
+        Value::Table(table) => from_table(table)?,
+        value => return Err(format!("a {} cannot be in the AST", value.type_name())),
+    })
+}
+
+// a table with the keys 1 to n is a list, and the others are objects.
+// `{}` is an empty list, but the empty front matter, which is an object
+fn from_table(table: &Table) -> Result<Json, String> {
+    let len = table.raw_len();
+    let mut entries = vec![];
+    for pair in table.clone().pairs::<Value, Value>() {
+        let (key, value) = pair.map_err(|err| err.to_string())?;
+        entries.push((key, value));
+    }
+    if entries.len() == len {
+        let items = (1..=len)
+            .map(|k| from_lua(&table.raw_get(k).map_err(|err| err.to_string())?))
+            .collect::<Result<_, String>>()?;
+        return Ok(Json::Array(items));
+    }
+    let front_matter = matches!(table.raw_get("t"), Ok(Value::String(t)) if t == "FrontMatter");
+    let mut object = json::Map::new();
+    for (key, value) in entries {
+        let Value::String(key) = key else {
+            return Err(String::from("a table has keys which are not strings"));
+        };
+        let key = key
+            .to_str()
+            .map_err(|_| String::from("a key is not UTF-8"))?
+            .to_string();
+        let value = match (&value, front_matter && key == "c") {
+            (Value::Table(table), true) if table.raw_len() == 0 => Json::Object(json::Map::new()),
+            _ => from_lua(&value)?,
+        };
+        object.insert(key, value);
+    }
+    Ok(Json::Object(object))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_run() {
+        let md = crate::parse("---\n---\n# Hello\n\n[a](a.md) [b](b.md)\n").unwrap();
+        let script = r#"
+            function Link(link)
+              link.c[2] = link.c[2]:gsub("%.md$", ".html")
+            end
+            function Heading(heading)
+              heading.c[1] = heading.c[1] + 1
+              return {heading, prose.HorizontalRule()}
+            end
+            function Document(blocks)
+              table.insert(blocks, prose.Comment(" " .. FORMAT .. " "))
+              return blocks
+            end
+        "#;
+        let expected =
+            crate::parse("---\n---\n## Hello\n\n---\n\n[a](a.html) [b](b.html)\n\n<!-- html -->\n")
+                .unwrap();
+        assert_eq!(run(&md, script, "test.lua", "html"), Ok(expected));
+        assert_eq!(
+            run(
+                &md,
+                "return {Paragraph = function() return {} end}",
+                "drop.lua",
+                "html"
+            )
+            .map(|md| md.len()),
+            Ok(2)
+        );
+    }
+
+    #[test]
+    fn test_run_errors() {
+        assert!(run(&[], "error('no')", "a.lua", "html")
+            .unwrap_err()
+            .starts_with("The Lua filter a.lua failed: "));
+        assert_eq!(
+            run(
+                &[],
+                "function Document() return {print} end",
+                "b.lua",
+                "html"
+            ),
+            Err(String::from(
+                "The Lua filter b.lua returned an invalid AST: a function cannot be in the AST"
+            ))
+        );
+    }
+}
@@ -1,58 +1,1113 @@
-pub mod entity;
-pub mod parser;
-pub mod translator;
-
-use std::io::{self, Read};
+use prose::entity::{Markdown, Span};
+#[cfg(feature = "highlight")]
+use prose::highlight;
+use prose::parser::{self, Syntax};
+use prose::{
+    config, excerpt, filter, json, lint, lsp, normalize, outline, server, site, split, stats,
+    tangle, template, translator, url,
+};
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
 use structopt::StructOpt;
 
-fn read() -> String {
+// reads stdin when no file is given, or `-` among the files.
+// several files are read as one document, separated by blank lines
+fn read(inputs: &[PathBuf]) -> String {
+    if inputs.is_empty() {
+        return read_stdin();
+    }
+    inputs
+        .iter()
+        .map(|path| {
+            if path.to_str() == Some("-") {
+                return read_stdin();
+            }
+            match std::fs::read_to_string(path) {
+                Ok(content) => content,
+                Err(err) => {
+                    eprintln!("Cannot read {}: {}", path.display(), err);
+                    std::process::exit(1);
+                }
+            }
+        })
+        .map(|content| content.trim_end_matches('\n').to_string())
+        .collect::<Vec<String>>()
+        .join("\n\n")
+}
+
+fn read_stdin() -> String {
     let mut content = String::new();
     let stdin = io::stdin();
     let mut handle = stdin.lock();
     handle.read_to_string(&mut content).unwrap();
-    if !content.ends_with('\n') {
-        content += "\n"
-    }
     content
 }
 
-fn write(buf: &String) {
-    println!("{}", buf);
+fn write(buf: &[u8], output: &Option<PathBuf>) {
+    let written = match output {
+        Some(path) => std::fs::write(path, buf),
+        None => io::stdout().write_all(buf),
+    };
+    if let Err(err) = written {
+        let path = output.as_deref().unwrap_or_else(|| Path::new("stdout"));
+        eprintln!("Cannot write {}: {}", path.display(), err);
+        std::process::exit(1);
+    }
+}
+
+// the flags of the command line, after which come the ones of `prose.toml`
+// which the command line does not give
+fn parse_args() -> Opt {
+    let args: Vec<String> = std::env::args().collect();
+    let matches = Opt::clap().get_matches_from(&args);
+    let opt = Opt::from_clap(&matches);
+    let path = match &opt.config {
+        Some(path) => path.clone(),
+        None => match std::env::current_dir()
+            .ok()
+            .and_then(|dir| config::find(&dir))
+        {
+            Some(path) => path,
+            None => return opt,
+        },
+    };
+    let entries = std::fs::read_to_string(&path)
+        .map_err(|err| err.to_string())
+        .and_then(|content| config::parse(&content));
+    let entries = match entries {
+        Ok(entries) => entries,
+        Err(err) => {
+            eprintln!("Cannot read {}: {}", path.display(), err);
+            std::process::exit(1);
+        }
+    };
+    // the flags go before the subcommand, whose arguments are the last ones
+    let mut defaults = vec![args[0].clone()];
+    for (key, value) in entries {
+        if matches.occurrences_of(key.replace('-', "_")) == 0 {
+            defaults.extend(value.to_args(&key));
+        }
+    }
+    defaults.extend(args.into_iter().skip(1));
+    Opt::from_iter_safe(defaults).unwrap_or_else(|err| {
+        eprintln!("{}\n(with the defaults of {})", err.message, path.display());
+        std::process::exit(1);
+    })
 }
 
-#[derive(Debug, StructOpt)]
+#[derive(Clone, Debug, StructOpt)]
 struct Opt {
     #[structopt(long = "debug")]
     pub debug: bool,
+    /// Configuration file with the defaults of the flags, instead of the prose.toml of the current
+    /// directory or the nearest one above it
+    #[structopt(long = "config", parse(from_os_str))]
+    pub config: Option<PathBuf>,
+    /// Do not escape the text, only for trusted input
+    #[structopt(long = "no-escape")]
+    pub no_escape: bool,
+    /// Drop the raw HTML elements, attributes and URLs which could run scripts, for untrusted input
+    #[structopt(long = "sanitize")]
+    pub sanitize: bool,
+    /// Drop the `javascript:`, `vbscript:` and `data:` URLs of links and images
+    #[structopt(long = "block-script-urls")]
+    pub block_script_urls: bool,
+    /// Resolve the relative URLs of links and images against this URL, or in a build against
+    /// the directory of each page under it
+    #[structopt(long = "base-url")]
+    pub base_url: Option<String>,
+    /// Rewrite `http://` URLs of links and images to `https://`
+    #[structopt(long = "force-https")]
+    pub force_https: bool,
+    /// Attribute of the links to other sites than --base-url, as `name=value` such as `target=_blank`
+    #[structopt(long = "external-link-attribute", number_of_values = 1)]
+    pub external_link_attribute: Vec<String>,
+    /// Class of the links to other sites than --base-url
+    #[structopt(long = "external-link-class")]
+    pub external_link_class: Option<String>,
+    /// Load the images only when they are scrolled to, with `loading="lazy"`
+    #[structopt(long = "lazy-images")]
+    pub lazy_images: bool,
+    /// Write an image alone in a paragraph as a figure, captioned with its title or alt text
+    #[structopt(long = "figures")]
+    pub figures: bool,
+    /// Give the local images their `width` and `height`, read from the files
+    #[cfg(feature = "image-size")]
+    #[structopt(long = "image-sizes")]
+    pub image_sizes: bool,
+    /// Output a complete HTML document instead of a fragment
+    #[structopt(short = "s", long = "standalone")]
+    pub standalone: bool,
+    /// Stylesheet URL to link from the standalone document
+    #[structopt(long = "css")]
+    pub css: Vec<String>,
+    /// Output a standalone document with the local images and stylesheets embedded in it
+    #[structopt(long = "self-contained")]
+    pub self_contained: bool,
+    /// Value of a `{{ key }}` placeholder in the text, as `key=value`; front matter is used otherwise
+    #[structopt(long = "define", short = "D", number_of_values = 1)]
+    pub define: Vec<String>,
+    /// Command which is given the AST as JSON and the output format, and writes the AST back; repeatable
+    #[structopt(long = "filter", number_of_values = 1)]
+    pub filter: Vec<String>,
+    /// Keep running and render the JSON-RPC requests on stdin, each after a Content-Length header,
+    /// with the other flags
+    #[structopt(long = "server")]
+    pub server: bool,
+    /// Lua script whose functions, named after the types of nodes, rewrite the AST; repeatable
+    #[cfg(feature = "lua")]
+    #[structopt(long = "lua-filter", number_of_values = 1, parse(from_os_str))]
+    pub lua_filter: Vec<PathBuf>,
+    /// WebAssembly plugin which reads syntax of its own, renders code blocks of its languages or transforms the AST; repeatable
+    #[cfg(feature = "plugins")]
+    #[structopt(long = "plugin", number_of_values = 1, parse(from_os_str))]
+    pub plugin: Vec<PathBuf>,
+    /// Tera template of the HTML with `{{ body }}`, `{{ title }}` and the front matter fields
+    #[structopt(long = "template")]
+    pub template: Option<String>,
+    /// Highlight the syntax of fenced code blocks
+    #[cfg(feature = "highlight")]
+    #[structopt(long = "highlight")]
+    pub highlight: bool,
+    /// Theme of the highlighting embedded in the standalone document: light, dark or a theme of syntect
+    #[cfg(feature = "highlight")]
+    #[structopt(long = "highlight-theme", default_value = "light")]
+    pub highlight_theme: String,
+    /// Mark code blocks with `language-*` classes for client-side highlighters
+    #[structopt(long = "language-class")]
+    pub language_class: bool,
+    /// Render the math with KaTeX in the standalone document
+    #[structopt(long = "katex")]
+    pub katex: bool,
+    /// Load mermaid.js in the standalone document to draw `mermaid` code blocks
+    #[structopt(long = "diagrams")]
+    pub diagrams: bool,
+    /// Put a `¶` link to each heading after its text
+    #[structopt(long = "heading-anchors")]
+    pub heading_anchors: bool,
+    /// Mark footnotes with `*`, `†`, `‡`, ... instead of numbers
+    #[structopt(long = "footnote-symbols")]
+    pub footnote_symbols: bool,
+    /// Draw emoji shortcodes such as `:tada:` with GitHub's images instead of the characters
+    #[structopt(long = "emoji-images")]
+    pub emoji_images: bool,
+    /// Keep the `<!-- -->` comments in the HTML instead of dropping them
+    #[structopt(long = "keep-comments")]
+    pub keep_comments: bool,
+    /// URL of `[[Page Name]]` wiki links, with `{}` for the slug of the page such as `/wiki/{}.html`
+    #[structopt(long = "wiki-base", default_value = "")]
+    pub wiki_base: String,
+    /// Write XHTML, where every void element is closed as `<br />`, instead of HTML5 as `<br>`
+    #[structopt(long = "xhtml")]
+    pub xhtml: bool,
+    /// Put every HTML block on a line of its own, indented by how deep it is
+    #[structopt(long = "pretty")]
+    pub pretty: bool,
+    /// Wrap the text of every list item in `<p>`
+    #[structopt(long = "wrap-lists")]
+    pub wrap_lists: bool,
+    /// Add this to the level of every heading, in every output format, within `<h1>` to `<h6>`
+    #[structopt(
+        long = "shift-heading-level",
+        default_value = "0",
+        allow_hyphen_values = true
+    )]
+    pub shift_heading_level: isize,
+    /// Deprecated: the same as --shift-heading-level, which it is added to when both are given
+    #[structopt(long = "heading-offset", default_value = "0")]
+    pub heading_offset: usize,
+    /// Prefix of the classes prose generates, such as `footnotes`
+    #[structopt(long = "class-prefix", default_value = "")]
+    pub class_prefix: String,
+    /// Write another HTML element instead of one, as `from=to` such as `b=strong`
+    #[structopt(long = "tag", number_of_values = 1)]
+    pub tag: Vec<String>,
+    /// Mark the top-level HTML blocks with `data-sourcepos="line:column-line:column"`
+    #[structopt(long = "sourcepos")]
+    pub sourcepos: bool,
+    /// Fail on the first text which cannot be parsed, instead of keeping it as plain text
+    #[structopt(long = "strict")]
+    pub strict: bool,
+    /// Read `~text~` as strikethrough too, as older versions did
+    #[structopt(long = "single-tilde-strike")]
+    pub single_tilde_strike: bool,
+    /// Syntax to read: CommonMark, with the GitHub extensions, or everything prose knows
+    #[structopt(long = "dialect", default_value = "prose", possible_values = &["commonmark", "gfm", "prose"])]
+    pub dialect: prose::Dialect,
+    /// Syntax to read on top of the dialect, such as `math,wikilinks`
+    #[structopt(long = "enable", require_delimiter = true)]
+    pub enable: Vec<prose::Extension>,
+    /// Syntax of the dialect not to read, such as `tables,footnotes`
+    #[structopt(long = "disable", require_delimiter = true)]
+    pub disable: Vec<prose::Extension>,
+    /// Refuse the documents with blocks or inline markup nested deeper than this
+    #[structopt(long = "max-depth")]
+    pub max_depth: Option<usize>,
+    /// Refuse the documents with a line longer than this, in bytes
+    #[structopt(long = "max-line-length")]
+    pub max_line_length: Option<usize>,
+    /// Refuse the documents with more blocks than this, counting each line of a paragraph
+    #[structopt(long = "max-blocks")]
+    pub max_blocks: Option<usize>,
+    /// Input format; `json` reads the AST written by `--to json`
+    #[structopt(long = "from", default_value = "markdown", possible_values = &["markdown", "json"])]
+    pub from: String,
+    /// Output format
+    #[structopt(long = "to", default_value = "html", possible_values = &["asciidoc", "confluence", "docbook", "epub", "gemtext", "html", "jira", "json", "latex", "man", "markdown", "pdf", "rst", "term", "text"])]
+    pub to: String,
+    /// TeX engine which typesets `--to pdf`
+    #[cfg(feature = "pdf")]
+    #[structopt(long = "pdf-engine", default_value = "pdflatex")]
+    pub pdf_engine: String,
+    /// Paper of `--to pdf`
+    #[cfg(feature = "pdf")]
+    #[structopt(long = "paper", default_value = "a4", possible_values = &["a4", "a5", "b5", "letter", "legal"])]
+    pub paper: String,
+    /// Margins of the pages of `--to pdf`, such as `1in`
+    #[cfg(feature = "pdf")]
+    #[structopt(long = "margin", default_value = "2cm")]
+    pub margin: String,
+    /// Output file, instead of stdout; the directory of the pages with `--split-level`
+    #[structopt(short = "o", long = "output", parse(from_os_str))]
+    pub output: Option<PathBuf>,
+    /// Split the document into pages at its headings of this level and above,
+    /// which are written to the directory of `-o`, which is required
+    #[structopt(long = "split-level")]
+    pub split_level: Option<usize>,
+    /// Input files; stdin is read when none is given
+    #[structopt(parse(from_os_str))]
+    pub inputs: Vec<PathBuf>,
+    #[structopt(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Clone, Debug, StructOpt)]
+enum Command {
+    /// Convert every Markdown file under a directory
+    Build {
+        #[structopt(parse(from_os_str))]
+        dir: PathBuf,
+        /// Directory to write the converted files into
+        #[structopt(long = "out-dir", default_value = "_site", parse(from_os_str))]
+        out_dir: PathBuf,
+        /// Copy the other files as well, such as images and stylesheets
+        #[structopt(long = "copy-assets")]
+        copy_assets: bool,
+        /// Number of files converted at once; as many as the cores by default
+        #[structopt(short = "j", long = "jobs")]
+        jobs: Option<usize>,
+    },
+    /// Check the style of Markdown files, or stdin, and exit with 1 when anything is found
+    Lint {
+        #[structopt(parse(from_os_str))]
+        files: Vec<PathBuf>,
+        /// Rewrite the files with the trailing whitespace and the list markers put right
+        #[structopt(long = "fix")]
+        fix: bool,
+        /// Longest line allowed, in characters
+        #[structopt(long = "line-length", default_value = "80")]
+        line_length: usize,
+    },
+    /// Count the words, the headings, the code blocks and the links of Markdown files, or stdin
+    Stats {
+        #[structopt(parse(from_os_str))]
+        files: Vec<PathBuf>,
+        /// Print the counts as JSON
+        #[structopt(long = "json")]
+        json: bool,
+    },
+    /// Print the headings of a Markdown file, or stdin, with their anchors and lines
+    Outline {
+        #[structopt(parse(from_os_str))]
+        file: Option<PathBuf>,
+        /// Output format; `markdown` is a list of links to the headings
+        #[structopt(long = "format", default_value = "text", possible_values = &["text", "json", "markdown", "opml"])]
+        format: String,
+    },
+    /// Print the summary of a Markdown file, or stdin: the part before `<!-- more -->`,
+    /// or else the first paragraph
+    Excerpt {
+        #[structopt(parse(from_os_str))]
+        files: Vec<PathBuf>,
+        /// Output format
+        #[structopt(long = "format", default_value = "html", possible_values = &["html", "text"])]
+        format: String,
+    },
+    /// Convert Markdown files as one document, such as the chapters of a book to print
+    Merge {
+        #[structopt(
+            parse(from_os_str),
+            required_unless = "summary",
+            conflicts_with = "summary"
+        )]
+        files: Vec<PathBuf>,
+        /// A `SUMMARY.md` which lists the files to merge, the nested ones with their headings demoted
+        #[structopt(long = "summary", parse(from_os_str))]
+        summary: Option<PathBuf>,
+        /// Demote the headings of every file by this many levels
+        #[structopt(long = "demote", default_value = "0")]
+        demote: usize,
+        /// Start each file on a new page when printed
+        #[structopt(long = "page-breaks")]
+        page_breaks: bool,
+    },
+    /// Write the code blocks with `file=path` in their info string to those files
+    Tangle {
+        #[structopt(parse(from_os_str))]
+        files: Vec<PathBuf>,
+        /// Only the code blocks of this language; the ones without `file=` are printed
+        #[structopt(long = "lang")]
+        lang: Option<String>,
+        /// Directory the paths are relative to
+        #[structopt(long = "out-dir", default_value = ".", parse(from_os_str))]
+        out_dir: PathBuf,
+    },
+    /// Build a static site
+    Site(SiteCommand),
+    /// Run a Language Server Protocol server on stdin and stdout, for editors
+    Lsp {
+        /// Longest line allowed, in characters
+        #[structopt(long = "line-length", default_value = "80")]
+        line_length: usize,
+    },
+}
+
+#[derive(Clone, Debug, StructOpt)]
+enum SiteCommand {
+    /// Convert every Markdown file under a directory into the pages of a site, with templates,
+    /// an index page and links to the previous and next pages
+    Build {
+        #[structopt(parse(from_os_str))]
+        dir: PathBuf,
+        /// Directory to write the site into
+        #[structopt(long = "out-dir", default_value = "_site", parse(from_os_str))]
+        out_dir: PathBuf,
+        /// Title of the site, for the index page and `{{ site_title }}`
+        #[structopt(long = "title", default_value = "Index")]
+        title: String,
+        /// Write search-index.json with the title, the headings and the text of every page,
+        /// for a search on the client such as lunr
+        #[structopt(long = "search-index")]
+        search_index: bool,
+    },
+}
+
+// the file extension for the output format, used by `build`
+fn extension(to: &str) -> &'static str {
+    match to {
+        "asciidoc" => "adoc",
+        "confluence" => "xml",
+        "docbook" => "xml",
+        "epub" => "epub",
+        "pdf" => "pdf",
+        "rst" => "rst",
+        "gemtext" => "gmi",
+        "json" => "json",
+        "latex" => "tex",
+        "man" => "man",
+        "markdown" => "md",
+        "jira" | "term" | "text" => "txt",
+        _ => "html",
+    }
+}
+
+fn parse_options(opt: &Opt) -> prose::ParseOptions {
+    let defaults = prose::Limits::default();
+    prose::ParseOptions {
+        single_tilde_strike: opt.single_tilde_strike,
+        dialect: opt.dialect,
+        enabled: opt.enable.iter().copied().collect(),
+        disabled: opt.disable.iter().copied().collect(),
+        limits: prose::Limits {
+            max_depth: opt.max_depth.unwrap_or(defaults.max_depth),
+            max_line_length: opt.max_line_length.unwrap_or(defaults.max_line_length),
+            max_blocks: opt.max_blocks.unwrap_or(defaults.max_blocks),
+        },
+    }
+}
+
+// prints the findings as `file:line:column: code message`, and fixes the files with `fix`.
+// stdin is written out fixed instead. true when nothing is left to find
+fn lint(files: &[PathBuf], fix: bool, options: &lint::LintOptions) -> bool {
+    let stdin = [PathBuf::from("-")];
+    let files = if files.is_empty() { &stdin[..] } else { files };
+    let mut clean = true;
+    for path in files {
+        // read as it is, since `read` drops the last newlines which `fix` keeps
+        let mut content = if path.to_str() == Some("-") {
+            read_stdin()
+        } else {
+            std::fs::read_to_string(path).unwrap_or_else(|err| {
+                eprintln!("Cannot read {}: {}", path.display(), err);
+                std::process::exit(1);
+            })
+        };
+        if fix {
+            content = lint::fix(&content, options);
+            if path.to_str() == Some("-") {
+                print!("{}", content);
+            } else if let Err(err) = std::fs::write(path, &content) {
+                eprintln!("Cannot write {}: {}", path.display(), err);
+                std::process::exit(1);
+            }
+        }
+        let name = match path.to_str() {
+            Some("-") => String::from("<stdin>"),
+            _ => path.display().to_string(),
+        };
+        for finding in lint::lint(&content, options) {
+            clean = false;
+            eprintln!("{}:{}", name, finding);
+        }
+    }
+    clean
+}
+
+// the document after the filters and the substitutions, with the options of the html.
+// `dir` is the directory of the document, which the local images are read from
+fn prepare(
+    content: &str,
+    dir: Option<&Path>,
+    opt: &Opt,
+) -> Result<(Vec<Markdown>, Vec<Span>, translator::Options), String> {
+    let (markdown, spans) = parse(content, opt)?;
+    prepare_parsed(markdown, spans, dir, opt)
+}
+
+// the blocks of the document in the format of `--from`, with their spans for `--sourcepos`
+fn parse(content: &str, opt: &Opt) -> Result<(Vec<Markdown>, Vec<Span>), String> {
+    let parsed = match opt.from.as_str() {
+        "json" => (
+            json::from_json(content).map_err(|err| format!("Invalid JSON AST: {}", err))?,
+            vec![],
+        ),
+        _ => {
+            let options = parse_options(opt);
+            let (markdown, warnings, spans) = parser::with_syntax(syntax(opt)?, || {
+                if opt.sourcepos {
+                    let (markdown, warnings) = prose::parse_spanned_with_options(content, options);
+                    let (markdown, spans) =
+                        markdown.into_iter().map(|bit| (bit.node, bit.span)).unzip();
+                    (markdown, warnings, spans)
+                } else {
+                    let (markdown, warnings) = prose::parse_with_options(content, options);
+                    (markdown, warnings, vec![])
+                }
+            })?;
+            if opt.strict && !warnings.is_empty() {
+                return Err(warnings[0].to_string());
+            }
+            // the rest of a document over the limits is not there to convert
+            if let Some(error) = warnings.iter().find(|warning| warning.limit.is_some()) {
+                return Err(error.to_string());
+            }
+            for warning in warnings {
+                eprintln!("warning: {}", warning);
+            }
+            (markdown, spans)
+        }
+    };
+    Ok(parsed)
+}
+
+// the syntax of the plugins, which is read along with the markdown
+#[cfg(feature = "plugins")]
+fn syntax(opt: &Opt) -> Result<Vec<Rc<RefCell<dyn Syntax>>>, String> {
+    opt.plugin
+        .iter()
+        .map(|path| {
+            let plugin = prose::plugin::Plugin::load(path)?;
+            Ok(Rc::new(RefCell::new(plugin)) as Rc<RefCell<dyn Syntax>>)
+        })
+        .collect()
+}
+
+#[cfg(not(feature = "plugins"))]
+fn syntax(_opt: &Opt) -> Result<Vec<Rc<RefCell<dyn Syntax>>>, String> {
+    Ok(vec![])
+}
+
+// `prepare` of the blocks which are parsed already
+fn prepare_parsed(
+    mut markdown: Vec<Markdown>,
+    mut spans: Vec<Span>,
+    dir: Option<&Path>,
+    opt: &Opt,
+) -> Result<(Vec<Markdown>, Vec<Span>, translator::Options), String> {
+    #[cfg(feature = "plugins")]
+    for path in &opt.plugin {
+        markdown = prose::plugin::Plugin::load(path)?.apply(markdown)?;
+        spans = vec![];
+    }
+    #[cfg(feature = "lua")]
+    for path in &opt.lua_filter {
+        let script = std::fs::read_to_string(path)
+            .map_err(|err| format!("Cannot read the Lua filter {}: {}", path.display(), err))?;
+        markdown = prose::lua::run(&markdown, &script, &path.display().to_string(), &opt.to)?;
+        spans = vec![];
+    }
+    for command in &opt.filter {
+        markdown = filter::run(&markdown, command, &opt.to)?;
+        // the blocks are not the ones which were parsed anymore
+        spans = vec![];
+    }
+    if opt.debug {
+        println!(">>> markdown = {:?}", &markdown);
+    }
+    let mut tags = BTreeMap::new();
+    for tag in &opt.tag {
+        let (from, to) = tag
+            .split_once('=')
+            .ok_or_else(|| format!("Invalid --tag {}, expected from=to", tag))?;
+        tags.insert(from.trim().to_string(), to.trim().to_string());
+    }
+    let mut external_link_attributes = BTreeMap::new();
+    for attribute in &opt.external_link_attribute {
+        let (name, value) = attribute.split_once('=').ok_or_else(|| {
+            format!(
+                "Invalid --external-link-attribute {}, expected name=value",
+                attribute
+            )
+        })?;
+        external_link_attributes.insert(name.trim().to_string(), value.to_string());
+    }
+    let options = translator::Options {
+        escape: !opt.no_escape,
+        standalone: opt.standalone || opt.self_contained,
+        css: opt.css.clone(),
+        #[cfg(feature = "highlight")]
+        highlight: if opt.highlight {
+            Some(opt.highlight_theme.clone())
+        } else {
+            None
+        },
+        language_class: opt.language_class,
+        katex: opt.katex,
+        diagrams: opt.diagrams,
+        heading_anchors: opt.heading_anchors,
+        footnote_symbols: opt.footnote_symbols,
+        emoji_images: opt.emoji_images,
+        keep_comments: opt.keep_comments,
+        wiki_base: opt.wiki_base.clone(),
+        xhtml: opt.xhtml,
+        pretty: opt.pretty,
+        wrap_lists: opt.wrap_lists,
+        // `--heading-offset` shifts the headings of every format with `--shift-heading-level`
+        heading_offset: 0,
+        class_prefix: opt.class_prefix.clone(),
+        tags,
+        sourcepos: opt.sourcepos,
+        sanitize: opt.sanitize,
+        // the links between the files of a build point to the converted files
+        link_extension: match &opt.command {
+            Some(Command::Build { .. }) => Some(extension(&opt.to).to_string()),
+            Some(Command::Site(_)) => Some(String::from("html")),
+            _ => None,
+        },
+        block_script_urls: opt.block_script_urls,
+        base_url: opt.base_url.clone(),
+        force_https: opt.force_https,
+        external_link_attributes,
+        external_link_class: opt.external_link_class.clone(),
+        rewrite_link: None,
+        lazy_images: opt.lazy_images,
+        figures: opt.figures,
+        #[cfg(feature = "image-size")]
+        image_sizes: opt.image_sizes,
+        base_dir: dir.map(Path::to_path_buf),
+        self_contained: opt.self_contained,
+    };
+    let mut defines = BTreeMap::new();
+    for define in &opt.define {
+        let (key, value) = define
+            .split_once('=')
+            .ok_or_else(|| format!("Invalid --define {}, expected key=value", define))?;
+        defines.insert(key.trim().to_string(), value.to_string());
+    }
+    let markdown = normalize::shift_headings(normalize::normalize(markdown), heading_shift(opt));
+    let markdown = template::substitute(markdown, &defines);
+    Ok((markdown, spans, options))
+}
+
+fn convert(content: &str, dir: Option<&Path>, opt: &Opt) -> Result<String, String> {
+    let (markdown, spans, options) = prepare(content, dir, opt)?;
+    render(markdown, &spans, &options, opt)
+}
+
+// what is written for a prepared document: the archive of `--to epub`,
+// whose chapters are split at `--split-level`, the PDF of `--to pdf`, or else the output of `render`
+fn output(
+    (markdown, spans, options): (Vec<Markdown>, Vec<Span>, translator::Options),
+    opt: &Opt,
+) -> Result<Vec<u8>, String> {
+    if opt.to == "epub" {
+        return prose::epub::epub(markdown, &options, opt.split_level.unwrap_or(1));
+    }
+    if opt.to == "pdf" {
+        #[cfg(feature = "pdf")]
+        return prose::pdf::pdf(
+            markdown,
+            &options,
+            &prose::pdf::Options {
+                engine: opt.pdf_engine.clone(),
+                paper: opt.paper.clone(),
+                margin: opt.margin.clone(),
+            },
+        );
+        #[cfg(not(feature = "pdf"))]
+        return Err(String::from(
+            "--to pdf needs prose built with --features pdf",
+        ));
+    }
+    Ok(format!("{}\n", render(markdown, &spans, &options, opt)?).into_bytes())
+}
+
+// the output of a prepared document in the format of `--to`
+fn render(
+    markdown: Vec<Markdown>,
+    spans: &[Span],
+    options: &translator::Options,
+    opt: &Opt,
+) -> Result<String, String> {
+    let output = match (opt.to.as_str(), &opt.template) {
+        ("epub" | "pdf", _) => {
+            return Err(format!(
+                "--to {} converts a single document or a merge",
+                opt.to
+            ))
+        }
+        ("asciidoc", _) => translator::asciidoc::translate(markdown),
+        ("confluence", _) => translator::confluence::translate(markdown, options),
+        ("docbook", _) => translator::docbook::translate(markdown, options),
+        ("gemtext", _) => translator::gemtext::translate(markdown),
+        ("jira", _) => translator::jira::translate(markdown),
+        ("json", _) => json::to_json(&markdown),
+        ("latex", _) => translator::latex::translate(markdown, options),
+        ("man", _) => translator::man::translate(markdown),
+        ("markdown", _) => translator::markdown::translate(markdown),
+        ("rst", _) => translator::rst::translate(markdown),
+        ("term", _) => translator::term::translate(markdown),
+        ("text", _) => translator::text::translate(markdown),
+        (_, Some(path)) => {
+            let page = std::fs::read_to_string(path)
+                .map_err(|err| format!("Cannot read the template {}: {}", path, err))?;
+            let context = translator::translate_context_with_spans(markdown, spans, options);
+            template::render(&page, &context)
+                .map_err(|err| format!("Cannot render the template {}: {}", path, err))?
+        }
+        (_, None) => translator::translate_with_spans(markdown, spans, options),
+    };
+    Ok(output)
+}
+
+// the document of `prose merge`, where the local images are read from the directory of the summary,
+// or else of the first file
+fn merge(
+    files: &[PathBuf],
+    summary: Option<&Path>,
+    demote: usize,
+    page_breaks: bool,
+    opt: &Opt,
+) -> Result<(Vec<Markdown>, Vec<Span>, translator::Options), String> {
+    let read = |path: &Path| {
+        std::fs::read_to_string(path)
+            .map_err(|err| format!("Cannot read {}: {}", path.display(), err))
+    };
+    let (dir, files): (_, Vec<(PathBuf, String, usize)>) = match summary {
+        Some(summary) => {
+            let dir = summary.parent().unwrap_or_else(|| Path::new(""));
+            let (markdown, _) = parse(&read(summary)?, opt)?;
+            let files = prose::merge::manifest(&markdown)
+                .into_iter()
+                .map(|(path, depth)| (dir.join(&path), path, depth))
+                .collect();
+            (Some(dir), files)
+        }
+        None => {
+            let dir = files.first().and_then(|path| path.parent());
+            let base = dir.unwrap_or_else(|| Path::new(""));
+            let files = files
+                .iter()
+                .map(|path| (path.clone(), relative(path, base), 0))
+                .collect();
+            (dir, files)
+        }
+    };
+    let mut documents = vec![];
+    for (path, name, depth) in files {
+        let (markdown, _) = parse(&read(&path)?, opt)?;
+        documents.push((name, markdown, demote + depth));
+    }
+    let markdown = prose::merge::merge(documents, page_breaks);
+    // the spans are of each file, not of the whole
+    prepare_parsed(markdown, vec![], dir, opt)
+}
+
+// the path from `dir` to the file with `/`, as `../b/c.md` from `a` to `b/c.md`
+fn relative(path: &Path, dir: &Path) -> String {
+    let mut dir = dir.components().peekable();
+    let mut path = path.components().peekable();
+    while dir.peek().is_some() && dir.peek() == path.peek() {
+        dir.next();
+        path.next();
+    }
+    let up = dir.map(|_| String::from(".."));
+    let down = path.map(|part| part.as_os_str().to_string_lossy().into_owned());
+    up.chain(down).collect::<Vec<_>>().join("/")
+}
+
+// writes the pages of `--split-level` into `out_dir`
+fn write_pages(
+    content: &str,
+    dir: Option<&Path>,
+    level: usize,
+    out_dir: &Path,
+    opt: &Opt,
+) -> Result<prose::build::Summary, String> {
+    if out_dir.is_file() {
+        return Err(format!(
+            "{} is a file, while --split-level writes a directory",
+            out_dir.display()
+        ));
+    }
+    let (markdown, _, options) = prepare(content, dir, opt)?;
+    let mut summary = prose::build::Summary::default();
+    for page in split::split(markdown, level, extension(&opt.to)) {
+        // the spans are of the whole document, not of the page
+        let output = render(page.blocks, &[], &options, opt)?;
+        let path = out_dir.join(&page.name);
+        std::fs::create_dir_all(out_dir)
+            .and_then(|_| std::fs::write(&path, format!("{}\n", output)))
+            .map_err(|err| format!("Cannot write {}: {}", path.display(), err))?;
+        summary.written.push(path);
+    }
+    Ok(summary)
+}
+
+// the flags of the page at `path` of a build of `dir`, whose relative urls are resolved
+// against the directory of the page under `--base-url`, as `sub/` for `sub/b.md`
+fn page_opt(opt: &Opt, dir: &Path, path: &Path) -> Opt {
+    let source = prose::cache::source_key(dir, path);
+    let base_url = match (&opt.base_url, source.rsplit_once('/')) {
+        (Some(base), Some((directory, _))) => Some(url::resolve(base, &format!("{}/", directory))),
+        (base, _) => base.clone(),
+    };
+    Opt {
+        base_url,
+        ..opt.clone()
+    }
+}
+
+// what the output of a build depends on besides the sources and the files they embed:
+// the flags which change the output, and the content of the template, the Lua filters and the plugins
+fn cache_key(opt: &Opt) -> String {
+    let flags = Opt {
+        debug: false,
+        config: None,
+        server: false,
+        output: None,
+        inputs: vec![],
+        command: None,
+        ..opt.clone()
+    };
+    let mut files: Vec<PathBuf> = vec![];
+    files.extend(opt.template.iter().map(PathBuf::from));
+    #[cfg(feature = "lua")]
+    files.extend(opt.lua_filter.iter().cloned());
+    #[cfg(feature = "plugins")]
+    files.extend(opt.plugin.iter().cloned());
+    let hashes: Vec<String> = files
+        .iter()
+        .map(|path| format!("{:016x}", prose::cache::hash_file(path)))
+        .collect();
+    format!("{:?}\n{}", flags, hashes.join(" "))
+}
+
+// prints what a build has done, and exits with 1 when anything has failed
+fn report(summary: Result<prose::build::Summary, String>, out_dir: &Path) {
+    match summary {
+        Ok(summary) => {
+            for (path, err) in &summary.failed {
+                eprintln!("{}: {}", path.display(), err);
+            }
+            eprintln!(
+                "{} files written to {}, {} reused, {} failed",
+                summary.written.len(),
+                out_dir.display(),
+                summary.reused.len(),
+                summary.failed.len()
+            );
+            if !summary.failed.is_empty() {
+                std::process::exit(1);
+            }
+        }
+        Err(err) => {
+            eprintln!("{}", err);
+            std::process::exit(1);
+        }
+    }
+}
+
+// how far the headings are moved, by `--shift-heading-level` and the deprecated `--heading-offset`
+fn heading_shift(opt: &Opt) -> isize {
+    opt.shift_heading_level
+        .saturating_add(opt.heading_offset.min(isize::MAX as usize) as isize)
 }
 
 fn main() {
-    let opt = Opt::from_args();
+    let mut opt = parse_args();
+    // the root of the site, which the pages, the sitemap and the feed are all under
+    if let Some(Command::Build { .. } | Command::Site(_)) = &opt.command {
+        opt.base_url = opt.base_url.as_deref().map(url::directory);
+    }
+    if opt.heading_offset != 0 {
+        eprintln!("--heading-offset is deprecated, use --shift-heading-level");
+    }
     if opt.debug {
         println!(">>> opt = {:?}", &opt);
     }
-    let content = read();
-    if let Ok((_, markdown)) = parser::parse_markdown(content.as_str()) {
-        if opt.debug {
-            println!(">>> markdown = {:?}", &markdown);
+    #[cfg(feature = "highlight")]
+    if highlight::theme_css(&opt.highlight_theme).is_none() {
+        eprintln!(
+            "Unknown highlight theme: {}, not one of {}",
+            opt.highlight_theme,
+            highlight::theme_names().join(", ")
+        );
+        std::process::exit(1);
+    }
+    if let Some(Command::Lint {
+        files,
+        fix,
+        line_length,
+    }) = &opt.command
+    {
+        let options = lint::LintOptions {
+            max_line_length: *line_length,
+            parse: parse_options(&opt),
+        };
+        if !lint(files, *fix, &options) {
+            std::process::exit(1);
+        }
+        return;
+    }
+    if let Some(Command::Lsp { line_length }) = &opt.command {
+        let options = lint::LintOptions {
+            max_line_length: *line_length,
+            parse: parse_options(&opt),
+        };
+        let stdin = io::stdin();
+        match lsp::serve(stdin.lock(), io::stdout(), options) {
+            Ok(true) => {}
+            // `exit` without `shutdown` before it, or the end of stdin
+            Ok(false) => std::process::exit(1),
+            Err(err) => {
+                eprintln!("{}", err);
+                std::process::exit(1);
+            }
+        }
+        return;
+    }
+    if let Some(Command::Stats { files, json }) = &opt.command {
+        let (markdown, warnings) = prose::parse_with_options(&read(files), parse_options(&opt));
+        for warning in warnings {
+            eprintln!("warning: {}", warning);
+        }
+        let stats = stats::stats(&normalize::normalize(markdown));
+        if *json {
+            println!("{}", json::write(&stats.to_json()));
+        } else {
+            println!("{}", stats);
+        }
+        return;
+    }
+    if let Some(Command::Outline { file, format }) = &opt.command {
+        // read as it is, so that the lines are the ones of the file
+        let content = match file {
+            Some(path) => std::fs::read_to_string(path).unwrap_or_else(|err| {
+                eprintln!("Cannot read {}: {}", path.display(), err);
+                std::process::exit(1);
+            }),
+            None => read_stdin(),
+        };
+        let (sections, warnings) = outline::outline(&content, parse_options(&opt));
+        for warning in warnings {
+            eprintln!("warning: {}", warning);
+        }
+        match format.as_str() {
+            "json" => println!("{}", json::write(&outline::to_json(&sections))),
+            "markdown" => print!("{}", outline::to_markdown(&sections)),
+            "opml" => print!("{}", outline::to_opml(&sections)),
+            _ => print!("{}", outline::to_text(&sections)),
+        }
+        return;
+    }
+    if let Some(Command::Merge {
+        files,
+        summary,
+        demote,
+        page_breaks,
+    }) = &opt.command
+    {
+        match merge(files, summary.as_deref(), *demote, *page_breaks, &opt)
+            .and_then(|document| output(document, &opt))
+        {
+            Ok(output) => write(&output, &opt.output),
+            Err(err) => {
+                eprintln!("{}", err);
+                std::process::exit(1);
+            }
+        }
+        return;
+    }
+    if let Some(Command::Excerpt { files, format }) = &opt.command {
+        let (markdown, _, options) = prepare(&read(files), None, &opt).unwrap_or_else(|err| {
+            eprintln!("{}", err);
+            std::process::exit(1);
+        });
+        let markdown = excerpt::excerpt(&markdown);
+        match format.as_str() {
+            "text" => println!("{}", translator::text::translate(markdown)),
+            _ => {
+                let options = translator::Options {
+                    standalone: false,
+                    ..options
+                };
+                println!("{}", translator::translate(markdown, &options));
+            }
+        }
+        return;
+    }
+    if let Some(Command::Tangle {
+        files,
+        lang,
+        out_dir,
+    }) = &opt.command
+    {
+        let (markdown, warnings) = prose::parse_with_options(&read(files), parse_options(&opt));
+        for warning in warnings {
+            eprintln!("warning: {}", warning);
+        }
+        let tangled = tangle::tangle(&markdown, lang.as_deref()).unwrap_or_else(|err| {
+            eprintln!("{}", err);
+            std::process::exit(1);
+        });
+        for (path, code) in tangled {
+            let Some(path) = path else {
+                print!("{}", code);
+                continue;
+            };
+            let path = out_dir.join(path);
+            let written = match path.parent() {
+                Some(dir) => std::fs::create_dir_all(dir),
+                None => Ok(()),
+            }
+            .and_then(|_| std::fs::write(&path, code));
+            if let Err(err) = written {
+                eprintln!("Cannot write {}: {}", path.display(), err);
+                std::process::exit(1);
+            }
+        }
+        return;
+    }
+    if let Some(Command::Build {
+        dir,
+        out_dir,
+        copy_assets,
+        jobs,
+    }) = &opt.command
+    {
+        if let Some(jobs) = jobs {
+            if let Err(err) = rayon::ThreadPoolBuilder::new()
+                .num_threads(*jobs)
+                .build_global()
+            {
+                eprintln!("Cannot start {} jobs: {}", jobs, err);
+                std::process::exit(1);
+            }
+        }
+        let convert = |path: &Path, content: &str| {
+            convert(content, path.parent(), &page_opt(&opt, dir, path))
+        };
+        let key = cache_key(&opt);
+        let summary = prose::build::build(
+            dir,
+            out_dir,
+            extension(&opt.to),
+            *copy_assets,
+            &key,
+            &convert,
+        );
+        report(summary, out_dir);
+        return;
+    }
+    if let Some(Command::Site(SiteCommand::Build {
+        dir,
+        out_dir,
+        title,
+        search_index,
+    })) = &opt.command
+    {
+        let convert = |path: &Path, content: &str| {
+            prepare(content, path.parent(), &page_opt(&opt, dir, path))
+        };
+        let options = site::Options {
+            title: title.clone(),
+            key: cache_key(&opt),
+            search_index: *search_index,
+            base_url: opt.base_url.clone(),
+        };
+        report(site::build(dir, out_dir, &options, &convert), out_dir);
+        return;
+    }
+    if opt.server {
+        let stdin = io::stdin();
+        let render = |text: &str| convert(text, None, &opt);
+        if let Err(err) = server::serve(stdin.lock(), io::stdout(), render) {
+            eprintln!("{}", err);
+            std::process::exit(1);
+        }
+        return;
+    }
+    let content = read(&opt.inputs);
+    let dir = opt
+        .inputs
+        .first()
+        .filter(|path| path.to_str() != Some("-"))
+        .and_then(|path| path.parent());
+    if let Some(level) = opt.split_level.filter(|_| opt.to != "epub") {
+        let Some(out_dir) = &opt.output else {
+            eprintln!("--split-level writes a page for each part, into the directory of -o");
+            std::process::exit(1);
+        };
+        report(write_pages(&content, dir, level, out_dir, &opt), out_dir);
+        return;
+    }
+    match prepare(&content, dir, &opt).and_then(|document| output(document, &opt)) {
+        Ok(output) => write(&output, &opt.output),
+        Err(err) => {
+            eprintln!("{}", err);
+            std::process::exit(1);
         }
-        let html = translator::translate(markdown);
-        write(&html);
-    } else {
-        eprintln!("Something critical error");
     }
 }
 
 #[cfg(test)]
 mod test_main {
 
-    use crate::parser;
-    use crate::translator;
+    use prose::normalize;
+    use prose::parser;
+    use prose::translator;
 
     macro_rules! assert_convert {
         ($markdown:expr, $html:expr) => {
             assert_eq!(
-                translator::translate(parser::parse_markdown($markdown).unwrap().1),
+                translator::translate(
+                    normalize::normalize(parser::parse_markdown($markdown).unwrap().1),
+                    &translator::Options::default()
+                ),
                 String::from($html)
             );
         };
@@ -60,9 +1115,140 @@ mod test_main {
 
     #[test]
     fn test_convert() {
-        assert_convert!("# h1\n", "<h1>h1</h1>");
-        assert_convert!("## h2\n", "<h2>h2</h2>");
+        assert_convert!("# h1\n", "<h1 id=\"h1\">h1</h1>");
+        assert_convert!("## h2\n", "<h2 id=\"h2\">h2</h2>");
         assert_convert!("- a\n- b\n- c\n", "<ul><li>a</li><li>b</li><li>c</li></ul>");
+        assert_convert!(
+            "first line\nsecond line\n\nanother paragraph\n",
+            "<p>first line second line</p><p>another paragraph</p>"
+        );
+        assert_convert!(
+            "<details>\n<summary>*s*</summary>\n</details>\n\na<br>b\n",
+            "<details>\n<summary>*s*</summary>\n</details><p>a<br>b</p>"
+        );
+        assert_convert!(
+            "**bold *it* x** and snake_case, _u_ __b__\n",
+            "<p><b>bold <i>it</i> x</b> and snake_case, <i>u</i> <b>b</b></p>"
+        );
+        assert_convert!(
+            "# with `code`\n[**bold** link](u) ![*alt*](i.png)\n",
+            "<h1 id=\"with-code\">with <code>code</code></h1><p><a href=\"u\"><b>bold</b> link</a> <img src=\"i.png\" alt=\"alt\"></p>"
+        );
+        assert_convert!(
+            "one  \ntwo\\\nthree\nfour  \n",
+            "<p>one<br>two<br>three four</p>"
+        );
+        assert_convert!(
+            "see <https://a.com> or www.b.com.\n",
+            "<p>see <a href=\"https://a.com\">https://a.com</a> or <a href=\"http://www.b.com\">www.b.com</a>.</p>"
+        );
+        assert_convert!(
+            "a < b & c\n```\n<script>\n```\n",
+            "<p>a &lt; b &amp; c</p><pre><code>&lt;script&gt;\n</code></pre>"
+        );
+        assert_convert!("---\ntitle: x\n---\n# a\n", "<h1 id=\"a\">a</h1>");
+        assert_convert!(
+            "a[^1]\n\n[^1]: note\n",
+            "<p>a<sup class=\"footnote-ref\"><a href=\"#fn-1\" id=\"fnref-1\">1</a></sup></p><section class=\"footnotes\"><ol><li id=\"fn-1\">note <a href=\"#fnref-1\" class=\"footnote-backref\">↩</a></li></ol></section>"
+        );
+        assert_convert!(
+            "- a\n  - b\n    1. c\n- d\n",
+            "<ul><li>a<ul><li>b<ol><li>c</li></ol></li></ul></li><li>d</li></ul>"
+        );
+        assert_convert!(
+            "> a\n>> b\n",
+            "<blockquote><p>a</p><blockquote><p>b</p></blockquote></blockquote>"
+        );
+        assert_convert!(
+            "| a | b |\n|---|--:|\n| 1 | 2 |\n",
+            "<table><thead><tr><th>a</th><th align=\"right\">b</th></tr></thead><tbody><tr><td>1</td><td align=\"right\">2</td></tr></tbody></table>"
+        );
+        assert_convert!(
+            "[a](u \"hint\") ![b](i.png 'alt')\n",
+            "<p><a href=\"u\" title=\"hint\">a</a> <img src=\"i.png\" alt=\"b\" title=\"alt\"></p>"
+        );
+        assert_convert!(
+            "Title\n=====\n\nSub\n---\ntext\n\n---\n",
+            "<h1 id=\"title\">Title</h1><h2 id=\"sub\">Sub</h2><p>text</p><hr>"
+        );
+        assert_convert!("***\n\n- - -\n\n__________\n", "<hr><hr><hr>");
+        assert_convert!(
+            "5. five\n6. six\n\n1) one\n",
+            "<ol start=\"5\"><li>five</li><li>six</li></ol><ol><li>one</li></ol>"
+        );
+        assert_convert!(
+            "* a\n* b\n+ c\n\n*d*\n",
+            "<ul><li>a</li><li>b</li></ul><ul><li>c</li></ul><p><i>d</i></p>"
+        );
+        assert_convert!(
+            "text\n    more\n\n    a < b\n\n    c\n",
+            "<p>text more</p><pre><code>a &lt; b\n\nc\n</code></pre>"
+        );
+        assert_convert!(
+            "````\n```\n````\n~~~\nx\n",
+            "<pre><code>```\n</code></pre><pre><code>x\n</code></pre>"
+        );
+        assert_convert!(
+            "```objective-c {linenos=true}\nx\n```\n",
+            "<pre><code class=\"objective-c\">x\n</code></pre>"
+        );
+        assert_convert!(
+            "$e^{i\\pi} = -1$ costs $5 or $6\n\n$$\na < b\n$$\n",
+            "<p><span class=\"math inline\">\\(e^{i\\pi} = -1\\)</span> costs $5 or $6</p><div class=\"math display\">\\[a &lt; b\\]</div>"
+        );
+        assert_convert!(
+            "```mermaid\na --> b\n```\n```dot\ndigraph {}\n```\n",
+            "<div class=\"mermaid\">a --&gt; b\n</div><pre class=\"language-dot\">digraph {}\n</pre>"
+        );
+        assert_convert!(
+            "::: note\nSome *text*.\n\n- a\n:::\n::: warning Mind the gap\n:::\n",
+            "<div class=\"admonition note\"><p class=\"admonition-title\">Note</p><p>Some <i>text</i>.</p><ul><li>a</li></ul></div><div class=\"admonition warning\"><p class=\"admonition-title\">Mind the gap</p></div>"
+        );
+        assert_convert!(
+            "??? Show *more*\nhidden\n???\n???+ Open\n???\n",
+            "<details><summary>Show <i>more</i></summary><p>hidden</p></details><details open><summary>Open</summary></details>"
+        );
+        assert_convert!(
+            "::: {#side .note data-x=\"a b\"}\nhi\n:::\n",
+            "<div id=\"side\" class=\"note\" data-x=\"a b\"><p>hi</p></div>"
+        );
+        assert_convert!(
+            "# Über uns\n## Setup\n## Setup\n",
+            "<h1 id=\"uber-uns\">Über uns</h1><h2 id=\"setup\">Setup</h2><h2 id=\"setup-1\">Setup</h2>"
+        );
+        assert_convert!(
+            "# Intro\n## Usage {#intro .big}\nTitle {data-x=1}\n=====\n",
+            "<h1 id=\"intro-1\">Intro</h1><h2 id=\"intro\" class=\"big\">Usage</h2><h1 id=\"title\" data-x=\"1\">Title</h1>"
+        );
+        assert_convert!(
+            "a[^1]\n\n[FOOTNOTES]\n\nb\n\n[^1]: n\n",
+            "<p>a<sup class=\"footnote-ref\"><a href=\"#fn-1\" id=\"fnref-1\">1</a></sup></p><section class=\"footnotes\"><ol><li id=\"fn-1\">n <a href=\"#fnref-1\" class=\"footnote-backref\">↩</a></li></ol></section><p>b</p>"
+        );
+        assert_convert!(
+            "apple\n: a *fruit*\n: a company\n\npear\n: a fruit\n\nafter\n",
+            "<dl><dt>apple</dt><dd>a <i>fruit</i></dd><dd>a company</dd><dt>pear</dt><dd>a fruit</dd></dl><p>after</p>"
+        );
+        assert_convert!("Shipped :tada: :nope:\n", "<p>Shipped 🎉 :nope:</p>");
+        assert_convert!(
+            "AT&amp;T &copy; &lt;b&gt; &#x1F600; &bogus;\n",
+            "<p>AT&amp;T © &lt;b&gt; 😀 &amp;bogus;</p>"
+        );
+        assert_convert!(
+            "<!-- draft:\n  rewrite this -->\nText <!-- todo --> here.\n",
+            "<p>Text  here.</p>"
+        );
+        assert_convert!(
+            "==Note== H~2~O, E=mc^2^, ++Ctrl+C++\n",
+            "<p><mark>Note</mark> H<sub>2</sub>O, E=mc<sup>2</sup>, <kbd>Ctrl</kbd>+<kbd>C</kbd></p>"
+        );
+        assert_convert!(
+            "{漢字|かんじ}と{東京|とう|きょう}\n",
+            "<p><ruby>漢字<rt>かんじ</rt></ruby>と<ruby>東<rt>とう</rt>京<rt>きょう</rt></ruby></p>"
+        );
+        assert_convert!(
+            "~~old~~ new, see ~/.bashrc\n",
+            "<p><s>old</s> new, see ~/.bashrc</p>"
+        );
     }
 
     #[test]
@@ -72,4 +1258,85 @@ mod test_main {
         let expected = read_to_string("./examples/full.html").unwrap();
         assert_convert!(content.as_str(), expected.as_str());
     }
+
+    #[test]
+    fn test_heading_shift() {
+        use structopt::StructOpt;
+        let shift = |args: &[&str]| super::heading_shift(&super::Opt::from_iter(args));
+        assert_eq!(shift(&["prose", "--shift-heading-level", "-1"]), -1);
+        assert_eq!(shift(&["prose", "--heading-offset", "2"]), 2);
+        assert_eq!(
+            shift(&[
+                "prose",
+                "--heading-offset",
+                "2",
+                "--shift-heading-level",
+                "-1"
+            ]),
+            1
+        );
+    }
+
+    #[test]
+    fn test_page_opt() {
+        use std::path::Path;
+        use structopt::StructOpt;
+        let opt =
+            super::Opt::from_iter(["prose", "--base-url", "https://ex.com/blog/", "build", "d"]);
+        let page = |path: &str| super::page_opt(&opt, Path::new("d"), Path::new(path));
+        assert_eq!(
+            page("d/a.md").base_url.as_deref(),
+            Some("https://ex.com/blog/")
+        );
+        let html = super::convert("[c](c.html) ![i](p.png)\n", None, &page("d/sub/b.md"));
+        assert_eq!(
+            html,
+            Ok(String::from(
+                "<p><a href=\"https://ex.com/blog/sub/c.html\">c</a> <img src=\"https://ex.com/blog/sub/p.png\" alt=\"i\"></p>"
+            ))
+        );
+    }
+
+    #[test]
+    fn test_write_pages() {
+        use structopt::StructOpt;
+        let opt = super::Opt::from_iter(["prose", "--split-level", "1"]);
+        let file = std::env::temp_dir().join(format!("prose-pages-{}.html", std::process::id()));
+        std::fs::write(&file, "").unwrap();
+        let pages = super::write_pages("# A\n", None, 1, &file, &opt);
+        std::fs::remove_file(&file).unwrap();
+        assert_eq!(
+            pages.err(),
+            Some(format!(
+                "{} is a file, while --split-level writes a directory",
+                file.display()
+            ))
+        );
+    }
+
+    #[test]
+    fn test_cache_key() {
+        use structopt::StructOpt;
+        let key = |args: &[&str]| super::cache_key(&super::Opt::from_iter(args));
+        let build = key(&["prose", "build", "docs"]);
+        assert_eq!(key(&["prose", "build", "docs", "-j", "2"]), build);
+        assert_eq!(
+            key(&["prose", "--debug", "build", "src", "--out-dir", "o"]),
+            build
+        );
+        assert_ne!(key(&["prose", "--standalone", "build", "docs"]), build);
+        let template = std::env::temp_dir().join(format!("prose-key-{}.html", std::process::id()));
+        std::fs::write(&template, "{{ body }}").unwrap();
+        let args = [
+            "prose",
+            "--template",
+            template.to_str().unwrap(),
+            "build",
+            "docs",
+        ];
+        let before = key(&args);
+        std::fs::write(&template, "<main>{{ body }}</main>").unwrap();
+        assert_ne!(key(&args), before);
+        std::fs::remove_file(&template).unwrap();
+    }
 }
@@ -0,0 +1,271 @@
+use crate::entity::{Markdown, MarkdownInline, MarkdownText};
+use crate::normalize::shift_headings;
+use crate::translator::{assign_heading_ids, map_text};
+use crate::url::local_path;
+
+use std::collections::HashMap;
+
+// what is put between the documents for `page_breaks`, so that each starts on a new page when printed
+pub const PAGE_BREAK: &str = "<div class=\"page-break\" style=\"break-after: page\"></div>";
+
+// the files listed by a `SUMMARY.md` as in mdBook, with how deep each is nested in its list.
+// the links which are not to local Markdown files, such as the ones to sites, are left out
+pub fn manifest(md: &[Markdown]) -> Vec<(String, usize)> {
+    fn items(md: &[Markdown], depth: usize, files: &mut Vec<(String, usize)>) {
+        for bit in md {
+            if let Markdown::OrderedList(_, list) | Markdown::UnorderedList(list) = bit {
+                for (text, children) in list {
+                    for part in text {
+                        if let MarkdownInline::Link(_, url, _) = part {
+                            if url.ends_with(".md") && !url.contains("://") {
+                                files.push((url.clone(), depth));
+                            }
+                        }
+                    }
+                    items(children, depth + 1, files);
+                }
+            }
+        }
+    }
+    let mut files = vec![];
+    items(md, 0, &mut files);
+    files
+}
+
+// the documents one after another, each with its path relative to the merged one, and the
+// headings of each shifted down by its number. only the front matter of the first document
+// is kept, as the one of the whole. the footnote labels of each are prefixed with its number,
+// its relative urls are rebased on its directory, and its links to the other documents are
+// made links to their anchors in the merged one
+pub fn merge(documents: Vec<(String, Vec<Markdown>, usize)>, page_breaks: bool) -> Vec<Markdown> {
+    let paths: Vec<String> = documents
+        .iter()
+        .map(|(path, _, _)| normalize(path))
+        .collect();
+    let documents: Vec<(String, Vec<Markdown>)> = documents
+        .into_iter()
+        .enumerate()
+        .map(|(k, (path, md, shift))| {
+            let md: Vec<Markdown> = md
+                .into_iter()
+                .filter(|bit| k == 0 || !matches!(bit, Markdown::FrontMatter(_)))
+                .collect();
+            (path, shift_headings(md, shift as isize))
+        })
+        .collect();
+
+    // the ids the headings of each document have alone, and the ones they have in the whole
+    let mut ranges = vec![];
+    let mut merged = vec![];
+    let mut own = vec![];
+    for (k, (_, md)) in documents.into_iter().enumerate() {
+        if k > 0 && page_breaks {
+            merged.push(Markdown::Html(String::from(PAGE_BREAK)));
+        }
+        own.push(heading_ids(&assign_heading_ids(md.clone())));
+        ranges.push(merged.len()..merged.len() + md.len());
+        merged.extend(md);
+    }
+    let merged = assign_heading_ids(merged);
+    let anchors: Vec<HashMap<String, String>> = ranges
+        .iter()
+        .zip(own)
+        .map(|(range, own)| {
+            own.into_iter()
+                .zip(heading_ids(&merged[range.clone()]))
+                .collect()
+        })
+        .collect();
+    let firsts: Vec<Option<String>> = ranges
+        .iter()
+        .map(|range| heading_ids(&merged[range.clone()]).into_iter().next())
+        .collect();
+
+    let mut blocks = merged.into_iter();
+    let mut result = vec![];
+    for (k, range) in ranges.iter().enumerate() {
+        if k > 0 && page_breaks {
+            result.extend(blocks.next());
+        }
+        let md: Vec<Markdown> = blocks.by_ref().take(range.len()).collect();
+        let dir = &paths[k][..paths[k].rfind('/').map_or(0, |n| n + 1)];
+        let md = relabel(md, k + 1);
+        let md = map_text(md, &mut |text| {
+            relink(text, &mut |url| {
+                let Some(path) = local_path(url) else {
+                    return url.to_string();
+                };
+                let target = normalize(&format!("{}{}", dir, path));
+                match paths.iter().position(|path| *path == target) {
+                    Some(n) => {
+                        let anchor = match url.split_once('#') {
+                            Some((_, fragment)) => anchors[n].get(fragment).cloned(),
+                            None => firsts[n].clone(),
+                        };
+                        format!("#{}", anchor.unwrap_or_default())
+                    }
+                    None => normalize(&format!("{}{}", dir, url)),
+                }
+            })
+        });
+        result.extend(md);
+    }
+    result
+}
+
+// the ids of the headings, in the order of the document
+fn heading_ids(md: &[Markdown]) -> Vec<String> {
+    let mut ids = vec![];
+    for bit in md {
+        bit.walk(&mut |bit| {
+            if let Markdown::Heading(_, _, attributes) = bit {
+                ids.extend(attributes.id.clone());
+            }
+        });
+    }
+    ids
+}
+
+// the footnote labels of the document prefixed with `number`, as `2-1` for `[^1]`
+fn relabel(md: Vec<Markdown>, number: usize) -> Vec<Markdown> {
+    let md = map_text(md, &mut |text| {
+        map_inlines(text, &mut |part| match part {
+            MarkdownInline::FootnoteRef(label) => {
+                MarkdownInline::FootnoteRef(format!("{}-{}", number, label))
+            }
+            part => part,
+        })
+    });
+    md.into_iter()
+        .map(|bit| match bit {
+            Markdown::FootnoteDefinition(label, text) => {
+                Markdown::FootnoteDefinition(format!("{}-{}", number, label), text)
+            }
+            Markdown::Blockquote(inner) => Markdown::Blockquote(relabel(inner, number)),
+            Markdown::Admonition(kind, title, inner) => {
+                Markdown::Admonition(kind, title, relabel(inner, number))
+            }
+            Markdown::Div(attributes, inner) => Markdown::Div(attributes, relabel(inner, number)),
+            Markdown::Details(summary, open, inner) => {
+                Markdown::Details(summary, open, relabel(inner, number))
+            }
+            bit => bit,
+        })
+        .collect()
+}
+
+// the urls of the links and the images of the text made by `f`
+fn relink<F>(text: MarkdownText, f: &mut F) -> MarkdownText
+where
+    F: FnMut(&str) -> String,
+{
+    map_inlines(text, &mut |part| match part {
+        MarkdownInline::Link(text, url, title) => MarkdownInline::Link(text, f(&url), title),
+        MarkdownInline::Image(text, url, title) => MarkdownInline::Image(text, f(&url), title),
+        part => part,
+    })
+}
+
+// `f` on every inline of the text, after the ones in it
+fn map_inlines<F>(text: MarkdownText, f: &mut F) -> MarkdownText
+where
+    F: FnMut(MarkdownInline) -> MarkdownInline,
+{
+    text.into_iter()
+        .map(|part| {
+            let part = match part {
+                MarkdownInline::Link(text, url, title) => {
+                    MarkdownInline::Link(map_inlines(text, f), url, title)
+                }
+                MarkdownInline::Image(text, url, title) => {
+                    MarkdownInline::Image(map_inlines(text, f), url, title)
+                }
+                MarkdownInline::Bold(text) => MarkdownInline::Bold(map_inlines(text, f)),
+                MarkdownInline::Italic(text) => MarkdownInline::Italic(map_inlines(text, f)),
+                MarkdownInline::Mark(text) => MarkdownInline::Mark(map_inlines(text, f)),
+                part => part,
+            };
+            f(part)
+        })
+        .collect()
+}
+
+// `./guide/../a.md` is `a.md`, while the `..` out of the directory are kept
+fn normalize(path: &str) -> String {
+    let (path, rest) = path.split_at(path.find(['?', '#']).unwrap_or(path.len()));
+    let mut kept: Vec<&str> = vec![];
+    for segment in path.split('/') {
+        match segment {
+            "." => {}
+            ".." if kept.last().is_some_and(|last| *last != "..") => {
+                kept.pop();
+            }
+            segment => kept.push(segment),
+        }
+    }
+    format!("{}{}", kept.join("/"), rest)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_merge() {
+        let summary = crate::parse(
+            "# Summary\n\n- [Intro](intro.md)\n- [Guide](guide/index.md)\n  - [Setup](guide/setup.md)\n- [Site](https://a.com/b.md)\n",
+        )
+        .unwrap();
+        assert_eq!(
+            manifest(&summary),
+            vec![
+                (String::from("intro.md"), 0),
+                (String::from("guide/index.md"), 0),
+                (String::from("guide/setup.md"), 1),
+            ]
+        );
+        let a = crate::parse("---\ntitle: a\n---\n# A\n\ntext\n").unwrap();
+        let b = crate::parse("---\ntitle: b\n---\n# B\n").unwrap();
+        assert_eq!(
+            merge(
+                vec![(String::from("a.md"), a, 0), (String::from("b.md"), b, 1)],
+                true
+            ),
+            assign_heading_ids(
+                crate::parse(&format!(
+                    "---\ntitle: a\n---\n# A\n\ntext\n\n{}\n\n## B\n",
+                    PAGE_BREAK
+                ))
+                .unwrap()
+            )
+        );
+    }
+
+    #[test]
+    fn test_merge_links() {
+        let intro = crate::parse(
+            "# Intro\n\nSee [setup](guide/setup.md#usage), [guide](./guide/index.md) and [^1].\n\n[^1]: a\n",
+        )
+        .unwrap();
+        let index =
+            crate::parse("# Usage\n\n![](img.png) [up](../intro.md) [^1]\n\n[^1]: b\n").unwrap();
+        let setup = crate::parse("# Usage\n\n[web](https://a.com/) [x](../other.md#y)\n").unwrap();
+        let merged = merge(
+            vec![
+                (String::from("intro.md"), intro, 0),
+                (String::from("guide/index.md"), index, 0),
+                (String::from("guide/setup.md"), setup, 0),
+            ],
+            false,
+        );
+        assert_eq!(
+            merged,
+            crate::parse(
+                "# Intro {#intro}\n\nSee [setup](#usage-1), [guide](#usage) and [^1-1].\n\n[^1-1]: a\n\n\
+                 # Usage {#usage}\n\n![](guide/img.png) [up](#intro) [^2-1]\n\n[^2-1]: b\n\n\
+                 # Usage {#usage-1}\n\n[web](https://a.com/) [x](other.md#y)\n"
+            )
+            .unwrap()
+        );
+    }
+}
@@ -0,0 +1,184 @@
+use crate::entity::ListItem;
+use crate::entity::Markdown;
+use crate::entity::MarkdownInline;
+use crate::entity::MarkdownText;
+
+// the parser emits one `Markdown::Line` per source line.
+// this pass merges consecutive non-blank lines into a single `Markdown::Paragraph`,
+// blank lines only separate paragraphs and are dropped.
+// the verbatim blocks keep the `\r\n` of the source, which become `\n` here
+pub fn normalize(md: Vec<Markdown>) -> Vec<Markdown> {
+    let mut blocks = vec![];
+    let mut lines: Vec<MarkdownText> = vec![];
+    for bit in md {
+        match bit {
+            Markdown::Line(line) if !is_blank(&line) => lines.push(line),
+            Markdown::Line(_) => flush_paragraph(&mut blocks, &mut lines),
+            block => {
+                flush_paragraph(&mut blocks, &mut lines);
+                blocks.push(normalize_block(block));
+            }
+        }
+    }
+    flush_paragraph(&mut blocks, &mut lines);
+    blocks
+}
+
+// a hard line break at the end of the paragraph breaks nothing, so it is dropped
+fn flush_paragraph(blocks: &mut Vec<Markdown>, lines: &mut Vec<MarkdownText>) {
+    if let Some(last) = lines.last_mut() {
+        if last.last() == Some(&MarkdownInline::LineBreak) {
+            last.pop();
+        }
+    }
+    if !lines.is_empty() {
+        blocks.push(Markdown::Paragraph(std::mem::take(lines)));
+    }
+}
+
+fn is_blank(line: &MarkdownText) -> bool {
+    line.iter().all(|part| match part {
+        MarkdownInline::Plaintext(text) => text.trim().is_empty(),
+        _ => false,
+    })
+}
+
+fn normalize_list(items: Vec<ListItem>) -> Vec<ListItem> {
+    items
+        .into_iter()
+        .map(|(line, children)| (line, normalize(children)))
+        .collect()
+}
+
+fn normalize_block(block: Markdown) -> Markdown {
+    match block {
+        Markdown::Blockquote(inner) => Markdown::Blockquote(normalize(inner)),
+        Markdown::Admonition(kind, title, inner) => {
+            Markdown::Admonition(kind, title, normalize(inner))
+        }
+        Markdown::Div(attributes, inner) => Markdown::Div(attributes, normalize(inner)),
+        Markdown::Details(summary, open, inner) => {
+            Markdown::Details(summary, open, normalize(inner))
+        }
+        Markdown::UnorderedList(items) => Markdown::UnorderedList(normalize_list(items)),
+        Markdown::OrderedList(start, items) => Markdown::OrderedList(start, normalize_list(items)),
+        Markdown::Codeblock(lang, code, attributes) => {
+            Markdown::Codeblock(lang, code.replace("\r\n", "\n"), attributes)
+        }
+        Markdown::MathBlock(tex) => Markdown::MathBlock(tex.replace("\r\n", "\n")),
+        Markdown::Html(html) => Markdown::Html(html.replace("\r\n", "\n")),
+        Markdown::Comment(text) => Markdown::Comment(text.replace("\r\n", "\n")),
+        block => block,
+    }
+}
+
+// every heading moved down by `shift` levels, or up when it is negative, within 1 to 6:
+// the `#` of a fragment becomes a second level heading under the title of the page with 1
+pub fn shift_headings(md: Vec<Markdown>, shift: isize) -> Vec<Markdown> {
+    md.into_iter()
+        .map(|block| shift_block(block, shift))
+        .collect()
+}
+
+fn shift_list(items: Vec<ListItem>, shift: isize) -> Vec<ListItem> {
+    items
+        .into_iter()
+        .map(|(line, children)| (line, shift_headings(children, shift)))
+        .collect()
+}
+
+fn shift_block(block: Markdown, shift: isize) -> Markdown {
+    match block {
+        Markdown::Heading(level, text, attributes) => {
+            let level = (level as isize).saturating_add(shift).clamp(1, 6);
+            Markdown::Heading(level as usize, text, attributes)
+        }
+        Markdown::Blockquote(inner) => Markdown::Blockquote(shift_headings(inner, shift)),
+        Markdown::Admonition(kind, title, inner) => {
+            Markdown::Admonition(kind, title, shift_headings(inner, shift))
+        }
+        Markdown::Div(attributes, inner) => Markdown::Div(attributes, shift_headings(inner, shift)),
+        Markdown::Details(summary, open, inner) => {
+            Markdown::Details(summary, open, shift_headings(inner, shift))
+        }
+        Markdown::UnorderedList(items) => Markdown::UnorderedList(shift_list(items, shift)),
+        Markdown::OrderedList(start, items) => {
+            Markdown::OrderedList(start, shift_list(items, shift))
+        }
+        block => block,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn line(s: &str) -> MarkdownText {
+        vec![MarkdownInline::Plaintext(String::from(s))]
+    }
+
+    #[test]
+    fn test_normalize() {
+        assert_eq!(
+            normalize(vec![
+                Markdown::Line(line("a")),
+                Markdown::Line(line("b")),
+                Markdown::Line(vec![]),
+                Markdown::Line(line("  ")),
+                Markdown::Line(line("c")),
+                Markdown::HorizontalRule,
+                Markdown::Line(line("d")),
+            ]),
+            vec![
+                Markdown::Paragraph(vec![line("a"), line("b")]),
+                Markdown::Paragraph(vec![line("c")]),
+                Markdown::HorizontalRule,
+                Markdown::Paragraph(vec![line("d")]),
+            ]
+        );
+        assert_eq!(normalize(vec![Markdown::Line(vec![])]), vec![]);
+    }
+
+    #[test]
+    fn test_normalize_nested() {
+        assert_eq!(
+            normalize(vec![Markdown::Blockquote(vec![
+                Markdown::Line(line("a")),
+                Markdown::UnorderedList(vec![(line("b"), vec![Markdown::Line(line("c"))])]),
+            ])]),
+            vec![Markdown::Blockquote(vec![
+                Markdown::Paragraph(vec![line("a")]),
+                Markdown::UnorderedList(vec![(
+                    line("b"),
+                    vec![Markdown::Paragraph(vec![line("c")])]
+                )]),
+            ])]
+        );
+    }
+
+    #[test]
+    fn test_shift_headings() {
+        let heading = |level: usize| Markdown::Heading(level, line("a"), Default::default());
+        assert_eq!(
+            shift_headings(
+                vec![
+                    heading(1),
+                    heading(5),
+                    Markdown::Blockquote(vec![heading(2)]),
+                    Markdown::UnorderedList(vec![(line("b"), vec![heading(3)])]),
+                ],
+                2
+            ),
+            vec![
+                heading(3),
+                heading(6),
+                Markdown::Blockquote(vec![heading(4)]),
+                Markdown::UnorderedList(vec![(line("b"), vec![heading(5)])]),
+            ]
+        );
+        assert_eq!(
+            shift_headings(vec![heading(1), heading(3)], -1),
+            vec![heading(1), heading(2)]
+        );
+    }
+}
@@ -0,0 +1,197 @@
+use crate::entity::{Markdown, Spanned};
+use crate::json::Json;
+use crate::parser::ParseOptions;
+use crate::translator::{assign_heading_ids, plain_text};
+use crate::Error;
+
+// a heading and the ones under it, up to the next heading of its level or above
+#[derive(Clone, Debug, PartialEq)]
+pub struct Section {
+    pub level: usize,
+    pub text: String,
+    // the id of the heading, the same as the html is given
+    pub anchor: String,
+    // 1-based, where the heading is written
+    pub line: usize,
+    pub children: Vec<Section>,
+}
+
+// the headings of the document as a tree. only the headings at the top level are in it,
+// not the ones in quotes and the like, but their ids are taken all the same
+pub fn outline(content: &str, options: ParseOptions) -> (Vec<Section>, Vec<Error>) {
+    let (blocks, warnings) = crate::parse_spanned_with_options(content, options);
+    let (md, spans): (Vec<Markdown>, Vec<_>) =
+        blocks.into_iter().map(|bit| (bit.node, bit.span)).unzip();
+    let blocks = assign_heading_ids(md)
+        .into_iter()
+        .zip(spans)
+        .map(|(node, span)| Spanned { node, span });
+    let mut sections = vec![];
+    for bit in blocks {
+        if let Markdown::Heading(level, text, attributes) = bit.node {
+            let section = Section {
+                level,
+                text: plain_text(&text),
+                anchor: attributes.id.unwrap_or_default(),
+                line: bit.span.start.line,
+                children: vec![],
+            };
+            insert(&mut sections, section);
+        }
+    }
+    (sections, warnings)
+}
+
+// under the last section when it is of a higher level, next to it otherwise
+fn insert(sections: &mut Vec<Section>, section: Section) {
+    match sections.last_mut() {
+        Some(last) if last.level < section.level => insert(&mut last.children, section),
+        _ => sections.push(section),
+    }
+}
+
+// one heading a line, indented by how deep it is: `Install #install (line 3)`
+pub fn to_text(sections: &[Section]) -> String {
+    let mut out = String::new();
+    walk(sections, 0, &mut |section, depth| {
+        out.push_str(&format!(
+            "{}{} #{} (line {})\n",
+            "  ".repeat(depth),
+            section.text,
+            section.anchor,
+            section.line
+        ));
+    });
+    out
+}
+
+// a nested list of links to the headings, for a table of contents or a sidebar
+pub fn to_markdown(sections: &[Section]) -> String {
+    let mut out = String::new();
+    walk(sections, 0, &mut |section, depth| {
+        out.push_str(&format!(
+            "{}- [{}](#{})\n",
+            "  ".repeat(depth),
+            escape_markdown(&section.text),
+            section.anchor
+        ));
+    });
+    out
+}
+
+pub fn to_json(sections: &[Section]) -> Json {
+    Json::Array(
+        sections
+            .iter()
+            .map(|section| {
+                Json::Object(
+                    [
+                        (String::from("level"), Json::from(section.level)),
+                        (String::from("text"), Json::String(section.text.clone())),
+                        (String::from("anchor"), Json::String(section.anchor.clone())),
+                        (String::from("line"), Json::from(section.line)),
+                        (String::from("children"), to_json(&section.children)),
+                    ]
+                    .into_iter()
+                    .collect(),
+                )
+            })
+            .collect(),
+    )
+}
+
+// OPML 2.0, where each heading is an `<outline>` of the type `link` to its anchor
+pub fn to_opml(sections: &[Section]) -> String {
+    fn outlines(sections: &[Section], depth: usize, out: &mut String) {
+        for section in sections {
+            let indent = "  ".repeat(depth);
+            out.push_str(&format!(
+                "{}<outline text=\"{}\" type=\"link\" url=\"#{}\" line=\"{}\"",
+                indent,
+                escape_xml(&section.text),
+                escape_xml(&section.anchor),
+                section.line
+            ));
+            if section.children.is_empty() {
+                out.push_str("/>\n");
+            } else {
+                out.push_str(">\n");
+                outlines(&section.children, depth + 1, out);
+                out.push_str(&format!("{}</outline>\n", indent));
+            }
+        }
+    }
+    let mut out =
+        String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<opml version=\"2.0\">\n");
+    out.push_str("  <head/>\n  <body>\n");
+    outlines(sections, 2, &mut out);
+    out.push_str("  </body>\n</opml>\n");
+    out
+}
+
+// the sections in order, with how deep each of them is
+fn walk<F: FnMut(&Section, usize)>(sections: &[Section], depth: usize, f: &mut F) {
+    for section in sections {
+        f(section, depth);
+        walk(&section.children, depth + 1, f);
+    }
+}
+
+fn escape_markdown(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        if matches!(c, '\\' | '[' | ']' | '*' | '_' | '`') {
+            out.push('\\');
+        }
+        out.push(c);
+    }
+    out
+}
+
+fn escape_xml(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_outline() {
+        let content = "# Guide\n\ntext\n\n## Install\n\n#### Deep\n\n## Use *it*\n\n> ## Install\n\n## Install\n\n# A & B {#ab}\n";
+        let (sections, warnings) = outline(content, ParseOptions::default());
+        assert!(warnings.is_empty());
+        assert_eq!(
+            to_text(&sections),
+            "Guide #guide (line 1)\n  Install #install (line 5)\n    Deep #deep (line 7)\n  Use it #use-it (line 9)\n  Install #install-2 (line 13)\nA & B #ab (line 15)\n"
+        );
+        assert_eq!(to_markdown(&sections[1..]), "- [A & B](#ab)\n");
+        assert_eq!(
+            to_markdown(&sections[0].children[..1]),
+            "- [Install](#install)\n  - [Deep](#deep)\n"
+        );
+        assert_eq!(
+            crate::json::write(&to_json(&sections[1..])),
+            "[{\"level\":1,\"text\":\"A & B\",\"anchor\":\"ab\",\"line\":15,\"children\":[]}]"
+        );
+        assert_eq!(
+            to_opml(&sections[1..]),
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<opml version=\"2.0\">\n  <head/>\n  <body>\n    <outline text=\"A &amp; B\" type=\"link\" url=\"#ab\" line=\"15\"/>\n  </body>\n</opml>\n"
+        );
+    }
+
+    #[test]
+    fn test_escape_markdown() {
+        assert_eq!(escape_markdown("a [b] *c*"), "a \\[b\\] \\*c\\*");
+    }
+}
@@ -1,656 +1,3586 @@
+use crate::charref;
+use crate::emoji;
+use crate::entity::Align;
+use crate::entity::Attributes;
+use crate::entity::Definition;
+use crate::entity::ListItem;
 use crate::entity::Markdown;
 use crate::entity::MarkdownInline;
 use crate::entity::MarkdownText;
 
+use std::cell::{Cell, RefCell};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::rc::Rc;
+
 use nom::{
     branch::alt,
-    bytes::complete::{is_not, tag, take, take_until, take_while1},
-    character::complete::alphanumeric0,
-    character::complete::line_ending,
+    bytes::complete::{is_not, tag, take, take_till, take_until, take_while1},
+    character::complete::{alpha1, alphanumeric1},
+    character::complete::{line_ending, none_of, one_of},
+    character::complete::{space0, space1},
     character::is_digit,
-    combinator::{map, not},
+    combinator::{all_consuming, map, map_opt, not, opt, peek, recognize, verify},
+    error::{Error, ErrorKind},
     multi::{many0, many1},
-    sequence::{delimited, pair, preceded, terminated, tuple},
+    sequence::{delimited, pair, preceded, separated_pair, terminated, tuple},
     IResult,
 };
 
-pub fn parse_markdown(i: &str) -> IResult<&str, Vec<Markdown>> {
-    many1(alt((
-        map(parse_horizontal_rule, |_| Markdown::HorizontalRule),
-        map(parse_header, |e| Markdown::Heading(e.0, e.1)),
-        map(parse_unordered_list, |e| Markdown::UnorderedList(e)),
-        map(parse_ordered_list, |e| Markdown::OrderedList(e)),
-        map(parse_code_block, |(lang, code)| {
-            Markdown::Codeblock(lang.to_string(), code.to_string())
-        }),
-        map(parse_markdown_text, |e| Markdown::Line(e)),
-    )))(i)
+// switches of the syntax which are not on by default
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct ParseOptions {
+    // `~text~` is strikethrough as well as `~~text~~`, as in older versions
+    pub single_tilde_strike: bool,
+    pub dialect: Dialect,
+    // the extensions turned on and off on top of the dialect, where off wins
+    pub enabled: Extensions,
+    pub disabled: Extensions,
+    pub limits: Limits,
 }
 
-fn parse_horizontal_rule(i: &str) -> IResult<&str, &str> {
-    preceded(tag("---"), line_ending)(i)
+impl ParseOptions {
+    pub fn has(self, extension: Extension) -> bool {
+        !self.disabled.contains(extension)
+            && (self.enabled.contains(extension) || self.dialect.has(extension))
+    }
 }
 
-fn parse_boldtext(i: &str) -> IResult<&str, &str> {
-    delimited(tag("**"), is_not("**"), tag("**"))(i)
+// the rule sets: CommonMark, with GitHub's extensions to it, or all the syntax prose reads
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Dialect {
+    CommonMark,
+    Gfm,
+    #[default]
+    Prose,
 }
 
-fn parse_italics(i: &str) -> IResult<&str, &str> {
-    delimited(tag("*"), is_not("*"), tag("*"))(i)
+impl std::str::FromStr for Dialect {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "commonmark" => Ok(Dialect::CommonMark),
+            "gfm" => Ok(Dialect::Gfm),
+            "prose" => Ok(Dialect::Prose),
+            _ => Err(format!(
+                "Unknown dialect {}, expected commonmark, gfm or prose",
+                s
+            )),
+        }
+    }
 }
 
-fn parse_strike(i: &str) -> IResult<&str, &str> {
-    delimited(tag("~"), is_not("~"), tag("~"))(i)
+// the syntax on top of CommonMark
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Extension {
+    Tables,
+    Strikethrough,
+    // bare `https://` and `www.` urls
+    Autolinks,
+    Footnotes,
+    Math,
+    Emoji,
+    Mark,
+    // `^superscript^` and `~subscript~`
+    Scripts,
+    Kbd,
+    Ruby,
+    WikiLinks,
+    // `::: note` admonitions and `::: {.class}` divs
+    FencedDivs,
+    Details,
+    DefinitionLists,
+    // `{#id .class}` after a heading
+    Attributes,
+    FrontMatter,
 }
 
-fn parse_inline_code(i: &str) -> IResult<&str, &str> {
-    delimited(tag("`"), is_not("`"), tag("`"))(i)
+impl Extension {
+    pub const ALL: [Extension; 16] = [
+        Extension::Tables,
+        Extension::Strikethrough,
+        Extension::Autolinks,
+        Extension::Footnotes,
+        Extension::Math,
+        Extension::Emoji,
+        Extension::Mark,
+        Extension::Scripts,
+        Extension::Kbd,
+        Extension::Ruby,
+        Extension::WikiLinks,
+        Extension::FencedDivs,
+        Extension::Details,
+        Extension::DefinitionLists,
+        Extension::Attributes,
+        Extension::FrontMatter,
+    ];
+
+    // the name of `--enable` and `--disable`
+    pub fn name(self) -> &'static str {
+        match self {
+            Extension::Tables => "tables",
+            Extension::Strikethrough => "strikethrough",
+            Extension::Autolinks => "autolinks",
+            Extension::Footnotes => "footnotes",
+            Extension::Math => "math",
+            Extension::Emoji => "emoji",
+            Extension::Mark => "mark",
+            Extension::Scripts => "scripts",
+            Extension::Kbd => "kbd",
+            Extension::Ruby => "ruby",
+            Extension::WikiLinks => "wikilinks",
+            Extension::FencedDivs => "fenced-divs",
+            Extension::Details => "details",
+            Extension::DefinitionLists => "definition-lists",
+            Extension::Attributes => "attributes",
+            Extension::FrontMatter => "front-matter",
+        }
+    }
 }
 
-fn parse_link(i: &str) -> IResult<&str, (&str, &str)> {
-    pair(
-        delimited(tag("["), is_not("]"), tag("]")),
-        delimited(tag("("), is_not(")"), tag(")")),
-    )(i)
+impl std::str::FromStr for Extension {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Extension::ALL
+            .into_iter()
+            .find(|extension| extension.name() == s)
+            .ok_or_else(|| {
+                let names: Vec<&str> = Extension::ALL.iter().map(|e| e.name()).collect();
+                format!(
+                    "Unknown extension {}, expected one of {}",
+                    s,
+                    names.join(", ")
+                )
+            })
+    }
 }
 
-fn parse_image(i: &str) -> IResult<&str, (&str, &str)> {
-    pair(
-        delimited(tag("!["), is_not("]"), tag("]")),
-        delimited(tag("("), is_not(")"), tag(")")),
-    )(i)
+// a set of extensions, which is `Copy` as the options are
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Extensions(u32);
+
+impl Extensions {
+    pub fn insert(&mut self, extension: Extension) {
+        self.0 |= 1 << extension as u32;
+    }
+
+    pub fn contains(self, extension: Extension) -> bool {
+        self.0 & (1 << extension as u32) != 0
+    }
 }
 
-// we want to match many things that are not any of our specail tags
-// but since we have no tools available to match and consume in the negative case (without regex)
-// we need to match against our tags, then consume one char
-// we repeat this until we run into one of our special characters
-// then we join our array of characters into a String
-fn parse_plaintext(i: &str) -> IResult<&str, String> {
-    let safe_one_char = preceded(
-        not(alt((
-            tag("*"),
-            tag("`"),
-            tag("~"),
-            tag("["),
-            tag("!["),
-            tag("\\"),
-            tag("\n"),
-            tag("\r"),
-        ))),
-        take(1u8),
-    );
-    let escaped_char = map(
-        alt((
-            tag("\\*"),
-            tag("\\`"),
-            tag("\\["),
-            tag("\\]"),
-            tag("\\~"),
-            tag("\\!"),
-        )),
-        |e: &str| &e[1..2],
-    );
+impl FromIterator<Extension> for Extensions {
+    fn from_iter<I: IntoIterator<Item = Extension>>(iter: I) -> Self {
+        let mut extensions = Extensions::default();
+        for extension in iter {
+            extensions.insert(extension);
+        }
+        extensions
+    }
+}
+
+impl Dialect {
+    pub fn has(self, extension: Extension) -> bool {
+        match self {
+            Dialect::CommonMark => false,
+            Dialect::Gfm => matches!(
+                extension,
+                Extension::Tables
+                    | Extension::Strikethrough
+                    | Extension::Autolinks
+                    | Extension::Footnotes
+            ),
+            Dialect::Prose => true,
+        }
+    }
+}
 
-    map(many1(alt((safe_one_char, escaped_char))), |v| v.join(""))(i)
+// how much a document can ask of the parser, so that adversarial input
+// is refused with an error instead of overflowing the stack or eating the memory
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Limits {
+    // blocks in blocks, such as quotes and list items, and inline markup in inline markup
+    pub max_depth: usize,
+    // in bytes, without the line ending
+    pub max_line_length: usize,
+    // the nested blocks and each line of a paragraph count as well
+    pub max_blocks: usize,
 }
 
-fn parse_markdown_inline(i: &str) -> IResult<&str, MarkdownInline> {
+impl Default for Limits {
+    fn default() -> Self {
+        Limits {
+            max_depth: 64,
+            max_line_length: 1 << 20,
+            max_blocks: 1_000_000,
+        }
+    }
+}
+
+// the limit a document goes over, with its value
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Limit {
+    Depth(usize),
+    LineLength(usize),
+    Blocks(usize),
+}
+
+impl fmt::Display for Limit {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Limit::Depth(max) => write!(f, "nested deeper than {} levels", max),
+            Limit::LineLength(max) => write!(f, "the line is longer than {} bytes", max),
+            Limit::Blocks(max) => write!(f, "more than {} blocks", max),
+        }
+    }
+}
+
+// what the document being parsed has used of the limits so far
+#[derive(Clone, Copy, Debug, Default)]
+pub(crate) struct Usage {
+    depth: usize,
+    blocks: usize,
+    // the length of the input left at the top-level block being read
+    top: usize,
+    pub(crate) exceeded: Option<(Limit, usize)>,
+}
+
+thread_local! {
+    static OPTIONS: Cell<ParseOptions> = Cell::new(ParseOptions::default());
+    // nothing is counted outside of `with_options`
+    static USAGE: Cell<Option<Usage>> = const { Cell::new(None) };
+}
+
+pub(crate) fn options() -> ParseOptions {
+    OPTIONS.with(|options| options.get())
+}
+
+// runs `f` with the options in effect for every parser called inside.
+// the combinators are plain functions, so the options are kept for the current thread
+pub fn with_options<T, F>(options: ParseOptions, f: F) -> T
+where
+    F: FnOnce() -> T,
+{
+    with_usage(options, &mut Usage::default(), f)
+}
+
+// the same, carrying on with what a document has used of the limits,
+// when it is parsed a block at a time
+pub(crate) fn with_usage<T, F>(options: ParseOptions, usage: &mut Usage, f: F) -> T
+where
+    F: FnOnce() -> T,
+{
+    let saved = OPTIONS.with(|cell| cell.replace(options));
+    let saved_usage = USAGE.with(|cell| cell.replace(Some(*usage)));
+    let result = f();
+    if let Some(used) = USAGE.with(|cell| cell.replace(saved_usage)) {
+        *usage = used;
+    }
+    OPTIONS.with(|cell| cell.set(saved));
+    result
+}
+
+// the limit which stopped the parsing, and the length of the input left at its top-level block.
+// once a limit is exceeded, every parser fails from then on
+pub(crate) fn exceeded() -> Option<(Limit, usize)> {
+    USAGE.with(|cell| cell.get().and_then(|usage| usage.exceeded))
+}
+
+// syntax which is read outside of the parser, such as by a plugin.
+// it is asked wherever the text starts with one of its prefixes, an inline with the rest of the line
+// and a block with the rest of the document, and gives how many bytes it reads there and their html
+pub trait Syntax {
+    fn inline_prefixes(&self) -> Vec<String>;
+    fn block_prefixes(&self) -> Vec<String>;
+    fn inline(&mut self, text: &str) -> Result<Option<(usize, String)>, String>;
+    fn block(&mut self, text: &str) -> Result<Option<(usize, String)>, String>;
+}
+
+// the syntax in effect, with the prefixes of each, and the first error of any
+#[derive(Default)]
+struct Registry {
+    handlers: Vec<Rc<RefCell<dyn Syntax>>>,
+    inline: Vec<(String, usize)>,
+    block: Vec<(String, usize)>,
+    error: Option<String>,
+}
+
+thread_local! {
+    static SYNTAX: RefCell<Registry> = const {
+        RefCell::new(Registry {
+            handlers: vec![],
+            inline: vec![],
+            block: vec![],
+            error: None,
+        })
+    };
+}
+
+// runs `f` with the syntax of `handlers` read by every parser called inside,
+// the first in the list first. once one of them fails, it is the error of the whole
+pub fn with_syntax<T, F>(handlers: Vec<Rc<RefCell<dyn Syntax>>>, f: F) -> Result<T, String>
+where
+    F: FnOnce() -> T,
+{
+    let mut registry = Registry::default();
+    for (k, handler) in handlers.iter().enumerate() {
+        let handler = handler.borrow();
+        let prefixes = |prefixes: Vec<String>| {
+            prefixes
+                .into_iter()
+                .filter(|prefix| !prefix.is_empty())
+                .map(move |prefix| (prefix, k))
+        };
+        registry.inline.extend(prefixes(handler.inline_prefixes()));
+        registry.block.extend(prefixes(handler.block_prefixes()));
+    }
+    registry.handlers = handlers;
+    let saved = SYNTAX.with(|cell| cell.replace(registry));
+    let result = f();
+    match SYNTAX.with(|cell| cell.replace(saved)).error {
+        Some(err) => Err(err),
+        None => Ok(result),
+    }
+}
+
+// whether the text starts with a prefix of the syntax
+fn starts_syntax(text: &str, block: bool) -> bool {
+    SYNTAX.with(|cell| {
+        let registry = cell.borrow();
+        let prefixes = if block {
+            &registry.block
+        } else {
+            &registry.inline
+        };
+        prefixes
+            .iter()
+            .any(|(prefix, _)| text.starts_with(prefix.as_str()))
+    })
+}
+
+// the length and the html of what the first of the handlers whose prefix the text starts with reads
+fn read_syntax(text: &str, block: bool) -> Option<(usize, String)> {
+    if !starts_syntax(text, block) {
+        return None;
+    }
+    let handlers: Vec<Rc<RefCell<dyn Syntax>>> = SYNTAX.with(|cell| {
+        let registry = cell.borrow();
+        let prefixes = if block {
+            &registry.block
+        } else {
+            &registry.inline
+        };
+        prefixes
+            .iter()
+            .filter(|(prefix, _)| registry.error.is_none() && text.starts_with(prefix.as_str()))
+            .map(|(_, k)| registry.handlers[*k].clone())
+            .collect()
+    });
+    for handler in handlers {
+        let mut handler = handler.borrow_mut();
+        let read = if block {
+            handler.block(text)
+        } else {
+            handler.inline(text)
+        };
+        match read {
+            Ok(Some((len, html))) if len > 0 && text.is_char_boundary(len) => {
+                return Some((len, html))
+            }
+            Ok(_) => {}
+            Err(err) => {
+                SYNTAX.with(|cell| {
+                    cell.borrow_mut().error.get_or_insert(err);
+                });
+                return None;
+            }
+        }
+    }
+    None
+}
+
+// an inline of the syntax from outside, on the rest of the line
+fn parse_syntax_inline(i: &str) -> IResult<&str, MarkdownInline> {
+    if !starts_syntax(i, false) {
+        return Err(nom::Err::Error(Error::new(i, ErrorKind::Verify)));
+    }
+    let line = &i[..i.find(['\r', '\n']).unwrap_or(i.len())];
+    match read_syntax(line, false) {
+        Some((len, html)) => Ok((&i[len..], MarkdownInline::Html(html))),
+        None => Err(nom::Err::Error(Error::new(i, ErrorKind::Verify))),
+    }
+}
+
+// a block of the syntax from outside, which runs to the end of its last line
+fn parse_syntax_block(i: &str) -> IResult<&str, Markdown> {
+    match read_syntax(i, true) {
+        Some((len, html)) => {
+            let end = match i[..len].ends_with('\n') {
+                true => len,
+                false => i[len..].find('\n').map_or(i.len(), |n| len + n + 1),
+            };
+            Ok((&i[end..], Markdown::Html(html)))
+        }
+        None => Err(nom::Err::Error(Error::new(i, ErrorKind::Verify))),
+    }
+}
+
+// changes what is used of the limits, when it is counted
+fn update_usage<T, F>(f: F) -> Option<T>
+where
+    F: FnOnce(&mut Usage) -> T,
+{
+    USAGE.with(|cell| {
+        let mut usage = cell.get()?;
+        let result = f(&mut usage);
+        cell.set(Some(usage));
+        Some(result)
+    })
+}
+
+fn exceed<T>(i: &str, limit: Limit) -> IResult<&str, T> {
+    update_usage(|usage| usage.exceeded = usage.exceeded.or(Some((limit, usage.top))));
+    Err(nom::Err::Failure(Error::new(i, ErrorKind::TooLarge)))
+}
+
+// `f` one level deeper, unless it is too deep.
+// the top-level blocks are at the first level, and the text in their inline markup at the second
+fn nested<'a, T, F>(i: &'a str, f: F) -> IResult<&'a str, T>
+where
+    F: FnOnce(&'a str) -> IResult<&'a str, T>,
+{
+    let max_depth = options().limits.max_depth;
+    let exceeded = update_usage(|usage| match usage.exceeded {
+        Some((limit, _)) => Some(limit),
+        None if usage.depth >= max_depth => Some(Limit::Depth(max_depth)),
+        None => {
+            usage.depth += 1;
+            if usage.depth == 1 {
+                usage.top = i.len();
+            }
+            None
+        }
+    });
+    match exceeded {
+        Some(Some(limit)) => exceed(i, limit),
+        Some(None) => {
+            let result = f(i);
+            update_usage(|usage| usage.depth -= 1);
+            result
+        }
+        None => f(i),
+    }
+}
+
+// fails unless the extension is on, whose markup is then plain text
+fn extension(i: &str, extension: Extension) -> IResult<&str, ()> {
+    if options().has(extension) {
+        Ok((i, ()))
+    } else {
+        Err(nom::Err::Error(Error::new(i, ErrorKind::Not)))
+    }
+}
+
+// the nested blocks or text cannot be parsed,
+// which ends the parsing rather than letting the others try when a limit is exceeded
+fn not_parsed(i: &str) -> nom::Err<Error<&str>> {
+    match exceeded() {
+        Some(_) => nom::Err::Failure(Error::new(i, ErrorKind::TooLarge)),
+        None => nom::Err::Error(Error::new(i, ErrorKind::Verify)),
+    }
+}
+
+// one more block read, unless there are too many
+pub(crate) fn count_block(i: &str) -> IResult<&str, ()> {
+    let max_blocks = options().limits.max_blocks;
+    let exceeded = update_usage(|usage| {
+        usage.blocks += 1;
+        usage.blocks > max_blocks
+    });
+    match exceeded {
+        Some(true) => exceed(i, Limit::Blocks(max_blocks)),
+        _ => Ok((i, ())),
+    }
+}
+
+// the offset of the first line longer than `max` bytes
+pub(crate) fn find_long_line(content: &str, max: usize) -> Option<usize> {
+    let mut offset = 0;
+    for line in content.split('\n') {
+        if line.trim_end_matches('\r').len() > max {
+            return Some(offset);
+        }
+        offset += line.len() + 1;
+    }
+    None
+}
+
+// the front matter is only recognized at the very beginning of the document
+pub fn parse_markdown(i: &str) -> IResult<&str, Vec<Markdown>> {
+    let (i, front_matter) = opt(parse_front_matter)(i)?;
+    let (i, mut markdown) = match front_matter {
+        Some(_) if i.is_empty() => (i, vec![]),
+        _ => parse_blocks(i)?,
+    };
+    if let Some(front_matter) = front_matter {
+        markdown.insert(0, Markdown::FrontMatter(front_matter));
+    }
+    Ok((i, markdown))
+}
+
+// an indented code block cannot interrupt a paragraph,
+// so it is tried only when the previous block is not a line of text
+pub fn parse_blocks(i: &str) -> IResult<&str, Vec<Markdown>> {
+    let mut blocks: Vec<Markdown> = vec![];
+    let mut rest = i;
+    loop {
+        let in_paragraph = matches!(blocks.last(), Some(Markdown::Line(line)) if !is_blank(line));
+        match parse_next_block(rest, in_paragraph) {
+            Ok((next, block)) if next.len() < rest.len() => {
+                count_block(rest)?;
+                blocks.push(block);
+                rest = next;
+            }
+            Ok(_) => return Err(nom::Err::Error(Error::new(rest, ErrorKind::Many1))),
+            Err(nom::Err::Error(_)) if !blocks.is_empty() => return Ok((rest, blocks)),
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+// the block after a line of a paragraph, when `in_paragraph`, or after anything else
+pub(crate) fn parse_next_block(i: &str, in_paragraph: bool) -> IResult<&str, Markdown> {
+    nested(i, |i| {
+        if in_paragraph {
+            parse_block(i)
+        } else {
+            alt((
+                map(parse_indented_code_block, |code| {
+                    Markdown::Codeblock(String::new(), code, String::new())
+                }),
+                parse_block,
+            ))(i)
+        }
+    })
+}
+
+fn parse_block(i: &str) -> IResult<&str, Markdown> {
+    // the syntax from outside comes first. it is tried apart from the `alt`,
+    // where one more parser slows down every block
+    if let Ok(parsed) = parse_syntax_block(i) {
+        return Ok(parsed);
+    }
     alt((
-        map(parse_italics, |s: &str| {
-            MarkdownInline::Italic(s.to_string())
+        map(parse_horizontal_rule, |_| Markdown::HorizontalRule),
+        map(parse_header, |(size, text)| {
+            let (text, attributes) = split_attributes(text);
+            Markdown::Heading(size, text, attributes)
         }),
-        map(parse_strike, |s: &str| {
-            MarkdownInline::Strike(s.to_string())
+        map(parse_unordered_list, Markdown::UnorderedList),
+        map(parse_ordered_list, |(start, items)| {
+            Markdown::OrderedList(start, items)
         }),
-        map(parse_inline_code, |s: &str| {
-            MarkdownInline::InlineCode(s.to_string())
+        map(parse_code_block, |((lang, attributes), code)| {
+            Markdown::Codeblock(lang.to_string(), code.to_string(), attributes.to_string())
+        }),
+        map(parse_math_block, |tex: &str| {
+            Markdown::MathBlock(tex.to_string())
         }),
-        map(parse_boldtext, |s: &str| {
-            MarkdownInline::Bold(s.to_string())
+        map(parse_comment_block, |text: &str| {
+            Markdown::Comment(text.to_string())
         }),
-        map(parse_image, |(tag, url): (&str, &str)| {
-            MarkdownInline::Image(tag.to_string(), url.to_string())
+        map(parse_html_block, |html: &str| {
+            Markdown::Html(html.to_string())
         }),
-        map(parse_link, |(tag, url): (&str, &str)| {
-            MarkdownInline::Link(tag.to_string(), url.to_string())
+        map(parse_table, |(header, aligns, rows)| {
+            Markdown::Table(header, aligns, rows)
         }),
-        map(parse_plaintext, |s| MarkdownInline::Plaintext(s)),
+        map(parse_blockquote, Markdown::Blockquote),
+        map(parse_fenced_div, |(info, inner)| {
+            if let Ok(("", attributes)) = parse_attributes(info) {
+                return Markdown::Div(attributes, inner);
+            }
+            let (kind, title) = info.split_once(' ').unwrap_or((info, ""));
+            let title = Some(title.trim().to_string()).filter(|title| !title.is_empty());
+            Markdown::Admonition(kind.to_lowercase(), title, inner)
+        }),
+        map(parse_details, |((open, summary), inner)| {
+            Markdown::Details(parse_inner_text(summary), open, inner)
+        }),
+        map(parse_footnotes_marker, |_| Markdown::Footnotes),
+        map(parse_footnote_definition, |(label, text)| {
+            Markdown::FootnoteDefinition(label.to_string(), text)
+        }),
+        map(parse_definition_list, Markdown::DefinitionList),
+        map(parse_setext_header, |(size, text)| {
+            let (text, attributes) = split_attributes(text);
+            Markdown::Heading(size, text, attributes)
+        }),
+        map(parse_paragraph_line, Markdown::Line),
     ))(i)
 }
 
-fn parse_markdown_text(i: &str) -> IResult<&str, MarkdownText> {
-    terminated(many0(parse_markdown_inline), tag("\n"))(i)
+pub(crate) fn is_blank(text: &MarkdownText) -> bool {
+    text.iter()
+        .all(|part| matches!(part, MarkdownInline::Plaintext(s) if s.trim().is_empty()))
 }
 
-// this guy matches the literal character #
-fn parse_header_tag(i: &str) -> IResult<&str, usize> {
+// only flat `key: value` pairs are read, quotes around the value are removed.
+// comments, nested mappings and lists are skipped
+fn parse_front_matter_line(i: &str) -> IResult<&str, Option<(String, String)>> {
+    map(preceded(not(tag("---")), parse_line), |line: &str| {
+        if line.starts_with([' ', '\t', '#', '-']) {
+            return None;
+        }
+        let (key, value) = line.split_once(':')?;
+        let value = value.trim();
+        let value = value
+            .strip_prefix('"')
+            .and_then(|v| v.strip_suffix('"'))
+            .or_else(|| value.strip_prefix('\'').and_then(|v| v.strip_suffix('\'')))
+            .unwrap_or(value);
+        Some((key.trim().to_string(), value.to_string()))
+    })(i)
+}
+
+pub(crate) fn parse_front_matter(i: &str) -> IResult<&str, BTreeMap<String, String>> {
+    extension(i, Extension::FrontMatter)?;
     map(
-        terminated(take_while1(|c| c == '#'), tag(" ")),
-        |s: &str| s.len(),
+        delimited(
+            pair(tag("---"), line_ending),
+            many0(parse_front_matter_line),
+            pair(tag("---"), line_ending),
+        ),
+        |entries| entries.into_iter().flatten().collect(),
     )(i)
 }
 
-// this combines a tuple of the header tag and the rest of the line
-fn parse_header(i: &str) -> IResult<&str, (usize, MarkdownText)> {
-    tuple((parse_header_tag, parse_markdown_text))(i)
+// three or more `-`, `*` or `_`, which can be spaced out and indented by up to three spaces
+fn parse_horizontal_rule(i: &str) -> IResult<&str, &str> {
+    let (rest, line) = terminated(take_till(|c| c == '\n' || c == '\r'), line_ending)(i)?;
+    let indent = line.len() - line.trim_start_matches(' ').len();
+    let marks: String = line.chars().filter(|c| !c.is_whitespace()).collect();
+    match marks.chars().next() {
+        Some(rule)
+            if indent < 4
+                && marks.len() >= 3
+                && "-*_".contains(rule)
+                && marks.chars().all(|c| c == rule) =>
+        {
+            Ok((rest, line))
+        }
+        _ => Err(nom::Err::Error(Error::new(i, ErrorKind::Verify))),
+    }
 }
 
-fn parse_unordered_list_tag(i: &str) -> IResult<&str, &str> {
-    terminated(tag("-"), tag(" "))(i)
+// the text between `delim` and its closing pair on the same line.
+// inline code and emphasis of the other strength are skipped over,
+// so `*a **b** c*` closes at the last `*` and `**a *b* c**` at the last `**`.
+// a closing `_` must not be followed by a letter, which keeps `snake_case` as it is
+fn take_emphasis<'a>(i: &'a str, delim: &str) -> IResult<&'a str, &'a str> {
+    let (rest, _) = tag(delim)(i)?;
+    let c = &delim[..1];
+    let double = format!("{}{}", c, c);
+    let strong = delim.len() == 2;
+    let fail = || Err(nom::Err::Error(Error::new(i, ErrorKind::TakeUntil)));
+    if rest.starts_with(c) {
+        return fail();
+    }
+    let mut k = 0;
+    while k < rest.len() {
+        let tail = &rest[k..];
+        if tail.starts_with('\n') {
+            return fail();
+        } else if tail.starts_with('\\') {
+            k += tail.chars().take(2).map(char::len_utf8).sum::<usize>();
+        } else if let Some(code) = tail.strip_prefix('`') {
+            k += code.find('`').map_or(1, |n| n + 2);
+        } else if tail.starts_with(&double) || tail.starts_with(c) {
+            let len = if tail.starts_with(&double) { 2 } else { 1 };
+            let after = tail[len..].chars().next();
+            let intraword = c == "_" && after.is_some_and(char::is_alphanumeric);
+            if (len == 2) == strong && k > 0 {
+                if !intraword {
+                    return Ok((&tail[len..], &rest[..k]));
+                }
+                k += len;
+                continue;
+            }
+            // skip over the nested emphasis of the other strength,
+            // or over the delimiter alone when it is not closed, which is then plain text
+            let nested = if len == 2 { double.as_str() } else { c };
+            match find_unescaped(&tail[len..], nested) {
+                Some(n) => k += len + n + nested.len(),
+                None => k += len,
+            }
+        } else {
+            k += tail.chars().next().map_or(1, char::len_utf8);
+        }
+    }
+    fail()
 }
 
-fn parse_unordered_list_element(i: &str) -> IResult<&str, MarkdownText> {
-    preceded(parse_unordered_list_tag, parse_markdown_text)(i)
+// the first `pat` in `s` which is not escaped with a backslash
+fn find_unescaped(s: &str, pat: &str) -> Option<usize> {
+    let mut k = 0;
+    while k < s.len() {
+        let tail = &s[k..];
+        if tail.starts_with('\\') {
+            k += tail.chars().take(2).map(char::len_utf8).sum::<usize>();
+        } else if tail.starts_with(pat) {
+            return Some(k);
+        } else {
+            k += tail.chars().next().map_or(1, char::len_utf8);
+        }
+    }
+    None
 }
 
-fn parse_unordered_list(i: &str) -> IResult<&str, Vec<MarkdownText>> {
-    many1(parse_unordered_list_element)(i)
+// the content of an emphasis is parsed again as inline text
+fn parse_emphasis<'a>(i: &'a str, delim: &str) -> IResult<&'a str, MarkdownText> {
+    let (rest, inner) = take_emphasis(i, delim)?;
+    match nested(inner, |inner| {
+        all_consuming(verify(parse_inline_text, |text: &MarkdownText| {
+            !text.is_empty()
+        }))(inner)
+    }) {
+        Ok((_, text)) => Ok((rest, text)),
+        Err(_) => Err(not_parsed(i)),
+    }
 }
 
-fn parse_ordered_list_tag(i: &str) -> IResult<&str, &str> {
-    terminated(
-        terminated(take_while1(|d| is_digit(d as u8)), tag(".")),
-        tag(" "),
+fn parse_boldtext(i: &str) -> IResult<&str, MarkdownText> {
+    alt((|i| parse_emphasis(i, "**"), |i| parse_emphasis(i, "__")))(i)
+}
+
+fn parse_italics(i: &str) -> IResult<&str, MarkdownText> {
+    alt((
+        |i| parse_bold_italics(i, "*"),
+        |i| parse_bold_italics(i, "_"),
+        |i| parse_emphasis(i, "*"),
+        |i| parse_emphasis(i, "_"),
+    ))(i)
+}
+
+// `***text***`, the bold text in italics
+fn parse_bold_italics<'a>(i: &'a str, c: &str) -> IResult<&'a str, MarkdownText> {
+    let (rest, _) = tag(c)(i)?;
+    let (rest, text) = parse_emphasis(rest, &c.repeat(2))?;
+    let (rest, _) = tag(c)(rest)?;
+    Ok((rest, vec![MarkdownInline::Bold(text)]))
+}
+
+fn parse_strike(i: &str) -> IResult<&str, &str> {
+    extension(i, Extension::Strikethrough)?;
+    let mut double = delimited(
+        tag("~~"),
+        verify(take_until("~~"), |s: &str| {
+            !s.is_empty() && !s.contains('\n')
+        }),
+        tag("~~"),
+    );
+    // as in GFM, where `~` is never subscript
+    let options = options();
+    if options.single_tilde_strike || !options.has(Extension::Scripts) {
+        alt((double, delimited(tag("~"), is_not("~\n"), tag("~"))))(i)
+    } else {
+        double(i)
+    }
+}
+
+// the text up to the `==` which closes the mark on the same line.
+// a `==` after a space and before a word opens a nested mark, as in `==a ==b== c==`
+fn take_mark(i: &str) -> IResult<&str, &str> {
+    let mut depth = 0;
+    let mut k = 0;
+    while k < i.len() {
+        let tail = &i[k..];
+        if tail.starts_with('\n') {
+            break;
+        } else if tail.starts_with('\\') {
+            k += tail.chars().take(2).map(char::len_utf8).sum::<usize>();
+        } else if let Some(after) = tail.strip_prefix("==") {
+            let opens = i[..k].ends_with(char::is_whitespace)
+                && after.starts_with(|c: char| !c.is_whitespace());
+            if opens {
+                depth += 1;
+            } else if depth == 0 {
+                return Ok((tail, &i[..k]));
+            } else {
+                depth -= 1;
+            }
+            k += 2;
+        } else {
+            k += tail.chars().next().map_or(1, char::len_utf8);
+        }
+    }
+    Err(nom::Err::Error(Error::new(i, ErrorKind::TakeUntil)))
+}
+
+// `==text==`, whose text can neither start nor end with a space
+fn parse_mark(i: &str) -> IResult<&str, MarkdownText> {
+    extension(i, Extension::Mark)?;
+    map(
+        delimited(
+            tag("=="),
+            verify(take_mark, |s: &str| {
+                !s.is_empty()
+                    && !s.contains('\n')
+                    && !s.starts_with(char::is_whitespace)
+                    && !s.ends_with(char::is_whitespace)
+            }),
+            tag("=="),
+        ),
+        parse_inner_text,
     )(i)
 }
 
-fn parse_ordered_list_element(i: &str) -> IResult<&str, MarkdownText> {
-    preceded(parse_ordered_list_tag, parse_markdown_text)(i)
+// `^2^` or `~2~`, without spaces inside as in Pandoc
+fn parse_script<'a>(i: &'a str, delim: &str) -> IResult<&'a str, &'a str> {
+    delimited(
+        tag(delim),
+        take_while1(|c: char| !c.is_whitespace() && !delim.contains(c)),
+        tag(delim),
+    )(i)
 }
 
-fn parse_ordered_list(i: &str) -> IResult<&str, Vec<MarkdownText>> {
-    many1(parse_ordered_list_element)(i)
+fn parse_superscript(i: &str) -> IResult<&str, &str> {
+    extension(i, Extension::Scripts)?;
+    parse_script(i, "^")
 }
 
-fn parse_code_block(i: &str) -> IResult<&str, (&str, &str)> {
-    let f = tuple((
-        tag("```"),
-        alphanumeric0,
-        line_ending,
-        take_until("```"),
-        tag("```"),
-    ));
-    map(f, |(_, language, _, code, _)| (language, code))(i)
+// `~text~` is strikethrough instead with `single_tilde_strike`, which is tried first
+fn parse_subscript(i: &str) -> IResult<&str, &str> {
+    extension(i, Extension::Scripts)?;
+    parse_script(i, "~")
 }
 
-#[cfg(test)]
-mod tests {
-    use crate::parser::*;
-    use nom::error::ErrorKind;
+// `++Ctrl+Alt+Del++`, the keys separated by `+`
+fn parse_kbd(i: &str) -> IResult<&str, Vec<&str>> {
+    extension(i, Extension::Kbd)?;
+    map(
+        delimited(
+            tag("++"),
+            verify(take_until("++"), |s: &str| {
+                !s.contains('\n')
+                    && !s.starts_with(char::is_whitespace)
+                    && !s.ends_with(char::is_whitespace)
+                    && s.split('+').all(|key| !key.trim().is_empty())
+            }),
+            tag("++"),
+        ),
+        |keys: &str| keys.split('+').map(str::trim).collect(),
+    )(i)
+}
 
-    macro_rules! err {
-        ($x:expr, $y:expr) => {
-            Err(nom::Err::Error(nom::error::Error::new($x, $y)))
-        };
-    }
+// `{base|reading}`, or one reading for each character of the base as in `{漢字|かん|じ}`
+fn parse_ruby(i: &str) -> IResult<&str, (&str, Vec<&str>)> {
+    extension(i, Extension::Ruby)?;
+    map(
+        verify(
+            delimited(
+                pair(tag("{"), not(tag("{"))),
+                separated_pair(
+                    is_not("|{}\n"),
+                    tag("|"),
+                    map(is_not("{}\n"), |readings: &str| {
+                        readings.split('|').collect::<Vec<&str>>()
+                    }),
+                ),
+                tag("}"),
+            ),
+            |(base, readings): &(&str, Vec<&str>)| {
+                !base.trim().is_empty()
+                    && readings.iter().all(|reading| !reading.trim().is_empty())
+                    && (readings.len() == 1 || readings.len() == base.chars().count())
+            },
+        ),
+        |(base, readings)| (base, readings.into_iter().map(str::trim).collect()),
+    )(i)
+}
 
-    #[test]
-    fn test_parse_italics() {
-        assert_eq!(
-            parse_italics("*here is italic*"),
-            Ok(("", "here is italic"))
-        );
-        assert_eq!(parse_italics("*here is italic"), err!("", ErrorKind::Tag));
-        assert_eq!(
-            parse_italics("here is italic*"),
-            err!("here is italic*", ErrorKind::Tag)
-        );
-        assert_eq!(
-            parse_italics("here is italic"),
-            err!("here is italic", ErrorKind::Tag)
-        );
-        assert_eq!(parse_italics("*"), err!("", ErrorKind::IsNot));
-        assert_eq!(parse_italics("**"), err!("*", ErrorKind::IsNot));
-        assert_eq!(parse_italics(""), err!("", ErrorKind::Tag));
-        assert_eq!(
-            parse_italics("**we are doing bold**"),
-            err!("*we are doing bold**", ErrorKind::IsNot)
-        );
-    }
+fn parse_inline_code(i: &str) -> IResult<&str, &str> {
+    delimited(tag("`"), is_not("`"), tag("`"))(i)
+}
 
-    #[test]
-    fn test_parse_boldtext() {
-        assert_eq!(parse_boldtext("**here is bold**"), Ok(("", "here is bold")));
-        assert_eq!(parse_boldtext("**here is bold"), err!("", ErrorKind::Tag));
-        assert_eq!(
-            parse_boldtext("here is bold**"),
-            err!("here is bold**", ErrorKind::Tag)
-        );
-        assert_eq!(
-            parse_boldtext("here is bold"),
-            err!("here is bold", ErrorKind::Tag)
-        );
-        assert_eq!(parse_boldtext("****"), err!("**", ErrorKind::IsNot));
-        assert_eq!(parse_boldtext("**"), err!("", ErrorKind::IsNot));
-        assert_eq!(parse_boldtext("*"), err!("*", ErrorKind::Tag));
-        assert_eq!(parse_boldtext(""), err!("", ErrorKind::Tag));
-        assert_eq!(
-            parse_boldtext("*this is italic*"),
-            err!("*this is italic*", ErrorKind::Tag)
-        );
-    }
+// `(url)` or `(url "title")` after a link or an image.
+// the title can be quoted with `"`, `'` or parentheses
+fn parse_destination(i: &str) -> IResult<&str, (&str, Option<&str>)> {
+    let title = alt((
+        delimited(tag("\""), take_until("\""), tag("\"")),
+        delimited(tag("'"), take_until("'"), tag("'")),
+        delimited(tag("("), is_not(")"), tag(")")),
+    ));
+    delimited(
+        tag("("),
+        pair(parse_url, opt(preceded(space1, title))),
+        preceded(space0, tag(")")),
+    )(i)
+}
 
-    #[test]
-    fn test_parse_inline_code() {
-        assert_eq!(
-            parse_boldtext("**here is bold**\n"),
-            Ok(("\n", "here is bold"))
-        );
-        assert_eq!(parse_inline_code("`here is code"), err!("", ErrorKind::Tag));
-        assert_eq!(
-            parse_inline_code("here is code`"),
-            err!("here is code`", ErrorKind::Tag)
-        );
-        assert_eq!(parse_inline_code("``"), err!("`", ErrorKind::IsNot));
-        assert_eq!(parse_inline_code("`"), err!("", ErrorKind::IsNot));
-        assert_eq!(parse_inline_code(""), err!("", ErrorKind::Tag));
+// the url of a destination, up to a space or the `)` which closes it.
+// the parentheses in it have to be balanced, as in `Foo_(bar)`, or escaped with `\`
+fn parse_url(i: &str) -> IResult<&str, &str> {
+    let mut depth = 0;
+    let mut chars = i.char_indices();
+    let mut end = i.len();
+    while let Some((k, c)) = chars.next() {
+        match c {
+            '\\' => {
+                chars.next();
+            }
+            '(' => depth += 1,
+            ')' if depth == 0 => {
+                end = k;
+                break;
+            }
+            ')' => depth -= 1,
+            c if c.is_whitespace() => {
+                end = k;
+                break;
+            }
+            _ => {}
+        }
     }
-
-    #[test]
-    fn test_parse_link() {
-        assert_eq!(
-            parse_link("[title](https://www.example.com)"),
-            Ok(("", ("title", "https://www.example.com")))
-        );
-        assert_eq!(parse_inline_code(""), err!("", ErrorKind::Tag));
+    if end == 0 || depth > 0 {
+        return Err(nom::Err::Error(Error::new(i, ErrorKind::IsNot)));
     }
+    Ok((&i[end..], &i[..end]))
+}
 
-    #[test]
-    fn test_parse_image() {
-        assert_eq!(
-            parse_image("![alt text](image.jpg)"),
-            Ok(("", ("alt text", "image.jpg")))
-        );
-        assert_eq!(parse_inline_code(""), err!("", ErrorKind::Tag));
+// `$x$`. the opening `$` cannot be followed by a space, and the closing one
+// can neither follow a space nor be followed by a digit, so that `$5 and $6` is text
+fn parse_math(i: &str) -> IResult<&str, &str> {
+    extension(i, Extension::Math)?;
+    let (rest, _) = tag("$")(i)?;
+    if rest.starts_with(|c: char| c.is_whitespace() || c == '$') {
+        return Err(nom::Err::Error(Error::new(rest, ErrorKind::Verify)));
     }
+    let mut escaped = false;
+    for (k, c) in rest.char_indices() {
+        match c {
+            '\n' => break,
+            '$' if !escaped
+                && !rest[..k].ends_with(char::is_whitespace)
+                && !rest[k + 1..].starts_with(|c: char| c.is_ascii_digit()) =>
+            {
+                return Ok((&rest[k + 1..], &rest[..k]));
+            }
+            _ => {}
+        }
+        escaped = c == '\\' && !escaped;
+    }
+    Err(nom::Err::Error(Error::new(rest, ErrorKind::TakeUntil)))
+}
 
-    #[test]
-    fn test_parse_plaintext() {
-        assert_eq!(
-            parse_plaintext("1234567890"),
-            Ok(("", String::from("1234567890")))
-        );
+// `:name:` of a known emoji, anything else is plain text
+fn parse_emoji(i: &str) -> IResult<&str, &str> {
+    extension(i, Extension::Emoji)?;
+    verify(
+        delimited(
+            tag(":"),
+            take_while1(|c: char| c.is_ascii_alphanumeric() || "_+-".contains(c)),
+            tag(":"),
+        ),
+        |name: &str| emoji::lookup(name).is_some(),
+    )(i)
+}
+
+fn parse_link(i: &str) -> IResult<&str, (&str, &str, Option<&str>)> {
+    map(
+        pair(
+            delimited(tag("["), is_not("]"), tag("]")),
+            parse_destination,
+        ),
+        |(text, (url, title))| (text, url, title),
+    )(i)
+}
+
+// the target and the label, which are trimmed
+fn parse_wikilink(i: &str) -> IResult<&str, (&str, Option<&str>)> {
+    extension(i, Extension::WikiLinks)?;
+    map(
+        verify(
+            delimited(tag("[["), take_till(|c| c == ']' || c == '\n'), tag("]]")),
+            |inner: &str| !inner.split('|').next().unwrap_or("").trim().is_empty(),
+        ),
+        |inner: &str| match inner.split_once('|') {
+            Some((target, label)) => (target.trim(), Some(label.trim())),
+            None => (inner.trim(), None),
+        },
+    )(i)
+}
+
+fn parse_footnote_ref(i: &str) -> IResult<&str, &str> {
+    extension(i, Extension::Footnotes)?;
+    delimited(tag("[^"), is_not("]"), tag("]"))(i)
+}
+
+// GFM extended autolinks: a url runs until whitespace or `<`,
+// but trailing punctuation and unbalanced closing parens are not part of it
+fn parse_bare_url(i: &str) -> IResult<&str, &str> {
+    extension(i, Extension::Autolinks)?;
+    let (_, scheme) = alt((tag("https://"), tag("http://"), tag("www.")))(i)?;
+    let (_, candidate) = take_till(|c: char| c.is_whitespace() || c == '<')(i)?;
+    let mut url = candidate;
+    loop {
+        let trimmed = url.trim_end_matches(['?', '!', '.', ',', ':', '*', '_', '~', '\'', '"']);
+        let trimmed = if trimmed.ends_with(')')
+            && trimmed.matches(')').count() > trimmed.matches('(').count()
+        {
+            &trimmed[..trimmed.len() - 1]
+        } else {
+            trimmed
+        };
+        if trimmed == url {
+            break;
+        }
+        url = trimmed;
+    }
+    if url.len() <= scheme.len() {
+        return Err(nom::Err::Error(Error::new(i, ErrorKind::Verify)));
+    }
+    Ok((&i[url.len()..], url))
+}
+
+fn complete_url(url: &str) -> String {
+    if url.starts_with("www.") {
+        format!("http://{}", url)
+    } else {
+        url.to_string()
+    }
+}
+
+// `<scheme:...>` and `<user@example.com>`, giving the text and the url of the link
+pub(crate) fn parse_autolink(i: &str) -> IResult<&str, (String, String)> {
+    let uri = map(
+        recognize(tuple((
+            alpha1,
+            take_till(|c: char| c == ':' || c == '>' || c.is_whitespace()),
+            tag(":"),
+            take_till(|c: char| c == '<' || c == '>' || c.is_whitespace()),
+        ))),
+        |uri: &str| (uri.to_string(), uri.to_string()),
+    );
+    let email = map(
+        recognize(tuple((
+            take_while1(|c: char| c.is_alphanumeric() || ".!#$%&'*+/=?^_`{|}~-".contains(c)),
+            tag("@"),
+            take_while1(|c: char| c.is_alphanumeric() || c == '-' || c == '.'),
+        ))),
+        |email: &str| (email.to_string(), format!("mailto:{}", email)),
+    );
+    delimited(tag("<"), alt((uri, email)), tag(">"))(i)
+}
+
+fn parse_comment(i: &str) -> IResult<&str, &str> {
+    delimited(tag("<!--"), take_until("-->"), tag("-->"))(i)
+}
+
+fn parse_inline_comment(i: &str) -> IResult<&str, &str> {
+    verify(parse_comment, |text: &str| !text.contains('\n'))(i)
+}
+
+// a comment alone at the beginning of a line, which can run over several lines
+fn parse_comment_block(i: &str) -> IResult<&str, &str> {
+    terminated(parse_comment, pair(space0, line_ending))(i)
+}
+
+// an opening or closing tag such as `<br>`, `<span class="x">` or `</span>`
+fn parse_inline_html(i: &str) -> IResult<&str, &str> {
+    recognize(tuple((
+        tag("<"),
+        opt(tag("/")),
+        alpha1,
+        take_till(|c| c == '>' || c == '<' || c == '\n'),
+        tag(">"),
+    )))(i)
+}
+
+fn parse_image(i: &str) -> IResult<&str, (&str, &str, Option<&str>)> {
+    // the alt text may be empty, which `prose lint` warns about
+    map(
+        pair(
+            delimited(
+                tag("!["),
+                map(opt(is_not("]")), Option::unwrap_or_default),
+                tag("]"),
+            ),
+            parse_destination,
+        ),
+        |(text, (url, title))| (text, url, title),
+    )(i)
+}
+
+// the characters which can start inline markup, or end the line
+const MARKUP: &str = "*_`~=^+{$:&[!\\<\n\r";
+
+// the text up to the first inline markup.
+// the runs of characters which cannot start any markup are taken at once,
+// then each of the others is read as it is, escaped or as a character reference, if it can be
+fn parse_plaintext(i: &str) -> IResult<&str, String> {
+    // underscores inside a word, as in `snake_case`, are plain text
+    let underscores = |i| -> IResult<&str, &str> { take_while1(|c| c == '_')(i) };
+    let escaped_char =
+        |i| -> IResult<&str, &str> { preceded(tag("\\"), recognize(one_of(ESCAPABLE)))(i) };
+
+    let syntax = SYNTAX.with(|cell| !cell.borrow().inline.is_empty());
+    let mut text = String::new();
+    let mut rest = i;
+    loop {
+        // `h` and `w` can start a bare url, at the beginning, after a space or `*`, `_`, `~`, `(`
+        let k = rest
+            .char_indices()
+            .find(|&(k, c)| {
+                let url_start = || {
+                    let before = &i[..i.len() - rest.len() + k];
+                    before
+                        .chars()
+                        .next_back()
+                        .is_none_or(|c| c.is_whitespace() || "*_~(".contains(c))
+                };
+                MARKUP.contains(c)
+                    || ((c == 'h' || c == 'w') && url_start() && parse_bare_url(&rest[k..]).is_ok())
+                    || (syntax && starts_syntax(&rest[k..], false))
+            })
+            .map_or(rest.len(), |(k, _)| k);
+        text.push_str(&rest[..k]);
+        rest = &rest[k..];
+        let in_word = i[..i.len() - rest.len()].ends_with(char::is_alphanumeric);
+        if let (true, Ok((next, underscores))) = (in_word, underscores(rest)) {
+            text.push_str(underscores);
+            rest = next;
+        } else if let Ok((next, c)) = parse_character_reference(rest) {
+            text.push_str(&c);
+            rest = next;
+        } else if let Ok((next, c)) = parse_literal_char(rest).or_else(|_| escaped_char(rest)) {
+            text.push_str(c);
+            rest = next;
+        } else {
+            break;
+        }
+    }
+    if rest.len() == i.len() {
+        Err(nom::Err::Error(Error::new(i, ErrorKind::Tag)))
+    } else {
+        Ok((rest, text))
+    }
+}
+
+// `&copy;`, `&#169;` or `&#xA9;`, decoded to the character.
+// an unknown name is not a reference and stays as it is written
+fn parse_character_reference(i: &str) -> IResult<&str, String> {
+    let name = take_while1(|c: char| c.is_ascii_alphanumeric());
+    let numeric = preceded(tag("#"), take_while1(|c: char| c.is_ascii_alphanumeric()));
+    delimited(
+        tag("&"),
+        alt((
+            map_opt(numeric, |digits| charref::numeric(digits).map(String::from)),
+            map_opt(name, |name| charref::lookup(name).map(String::from)),
+        )),
+        tag(";"),
+    )(i)
+}
+
+// the ASCII punctuation, which can be escaped with a backslash as in CommonMark
+pub const ESCAPABLE: &str = "!\"#$%&'()*+,-./:;<=>?@[\\]^_`{|}~";
+
+// whether the text starts with a character which is read as it is,
+// the ones which are not need a backslash to be plain text
+pub fn is_literal(i: &str) -> bool {
+    parse_literal_char(i).is_ok()
+}
+
+// a character which does not start any inline markup
+fn parse_literal_char(i: &str) -> IResult<&str, &str> {
+    if starts_syntax(i, false) {
+        return Err(nom::Err::Error(Error::new(i, ErrorKind::Not)));
+    }
+    preceded(
+        not(alt((
+            tag("*"),
+            tag("_"),
+            tag("`"),
+            recognize(parse_strike),
+            recognize(parse_mark),
+            recognize(parse_superscript),
+            recognize(parse_subscript),
+            recognize(parse_kbd),
+            recognize(parse_ruby),
+            recognize(parse_math),
+            recognize(parse_emoji),
+            recognize(parse_character_reference),
+            recognize(parse_inline_comment),
+            tag("["),
+            tag("!["),
+            tag("\\"),
+            parse_inline_html,
+            parse_bare_url,
+            tag("\n"),
+            tag("\r"),
+        ))),
+        take(1u8),
+    )(i)
+}
+
+// a delimiter which opens no markup there, such as the `*` of `5 * 3` or an unclosed `[`,
+// is plain text as in CommonMark. so is a backslash before anything but punctuation,
+// except at the end of a line, where it is a hard line break
+fn parse_delimiter(i: &str) -> IResult<&str, String> {
+    let (rest, c) = verify(take(1u8), |c: &str| c != "\n" && !i.starts_with("\r\n"))(i)?;
+    if c == "\\" && (rest.starts_with('\n') || rest.starts_with("\r\n")) {
+        return Err(nom::Err::Error(Error::new(i, ErrorKind::Verify)));
+    }
+    Ok((rest, c.to_string()))
+}
+
+// inline text, where the plain text read in pieces is joined again
+fn parse_inline_text(i: &str) -> IResult<&str, MarkdownText> {
+    map(many0(parse_markdown_inline), |text| {
+        let mut joined: MarkdownText = vec![];
+        for bit in text {
+            match (joined.last_mut(), bit) {
+                (Some(MarkdownInline::Plaintext(last)), MarkdownInline::Plaintext(s)) => {
+                    last.push_str(&s)
+                }
+                (_, bit) => joined.push(bit),
+            }
+        }
+        joined
+    })(i)
+}
+
+// the text of a link or an image is inline text itself,
+// and stays as it is when it cannot be parsed
+fn parse_inner_text(i: &str) -> MarkdownText {
+    match nested(i, |i| all_consuming(parse_inline_text)(i)) {
+        Ok((_, text)) => text,
+        Err(_) => vec![MarkdownInline::Plaintext(i.to_string())],
+    }
+}
+
+fn parse_markdown_inline(i: &str) -> IResult<&str, MarkdownInline> {
+    // as in `parse_block`
+    if let Ok(parsed) = parse_syntax_inline(i) {
+        return Ok(parsed);
+    }
+    alt((
+        map(parse_boldtext, MarkdownInline::Bold),
+        map(parse_italics, MarkdownInline::Italic),
+        // `alt` takes at most 21 parsers, so the lighter markup is grouped
+        alt((
+            map(parse_strike, |s: &str| {
+                MarkdownInline::Strike(s.to_string())
+            }),
+            map(parse_mark, MarkdownInline::Mark),
+            map(parse_superscript, |s: &str| {
+                MarkdownInline::Superscript(s.to_string())
+            }),
+            map(parse_subscript, |s: &str| {
+                MarkdownInline::Subscript(s.to_string())
+            }),
+            map(parse_kbd, |keys| {
+                MarkdownInline::Kbd(keys.into_iter().map(String::from).collect())
+            }),
+            map(parse_ruby, |(base, readings)| {
+                MarkdownInline::Ruby(
+                    base.to_string(),
+                    readings.into_iter().map(String::from).collect(),
+                )
+            }),
+        )),
+        map(parse_inline_code, |s: &str| {
+            MarkdownInline::InlineCode(s.to_string())
+        }),
+        map(parse_math, |s: &str| MarkdownInline::Math(s.to_string())),
+        map(parse_emoji, |s: &str| MarkdownInline::Emoji(s.to_string())),
+        map(parse_image, |(tag, url, title)| {
+            MarkdownInline::Image(
+                parse_inner_text(tag),
+                url.to_string(),
+                title.map(String::from),
+            )
+        }),
+        map(parse_footnote_ref, |s: &str| {
+            MarkdownInline::FootnoteRef(s.to_string())
+        }),
+        map(parse_wikilink, |(target, label)| {
+            MarkdownInline::WikiLink(target.to_string(), label.map(String::from))
+        }),
+        map(parse_link, |(tag, url, title)| {
+            MarkdownInline::Link(
+                parse_inner_text(tag),
+                url.to_string(),
+                title.map(String::from),
+            )
+        }),
+        map(parse_autolink, |(text, url)| {
+            MarkdownInline::Link(vec![MarkdownInline::Plaintext(text)], url, None)
+        }),
+        map(parse_bare_url, |url: &str| {
+            MarkdownInline::Link(
+                vec![MarkdownInline::Plaintext(url.to_string())],
+                complete_url(url),
+                None,
+            )
+        }),
+        map(parse_inline_comment, |s: &str| {
+            MarkdownInline::Comment(s.to_string())
+        }),
+        map(parse_inline_html, |s: &str| {
+            MarkdownInline::Html(s.to_string())
+        }),
+        map(parse_plaintext, MarkdownInline::Plaintext),
+        map(parse_delimiter, MarkdownInline::Plaintext),
+    ))(i)
+}
+
+// the spaces and tabs at the end of a line, which are not a part of its text
+fn trim_end_text(text: &mut MarkdownText) {
+    if let Some(MarkdownInline::Plaintext(last)) = text.last_mut() {
+        last.truncate(last.trim_end_matches([' ', '\t']).len());
+        if last.is_empty() {
+            text.pop();
+        }
+    }
+}
+
+// a backslash at the end of a heading or an item is kept as it is
+fn parse_markdown_text(i: &str) -> IResult<&str, MarkdownText> {
+    map(
+        terminated(pair(parse_inline_text, opt(tag("\\"))), line_ending),
+        |(mut text, backslash)| {
+            if backslash.is_none() {
+                trim_end_text(&mut text);
+            } else {
+                match text.last_mut() {
+                    Some(MarkdownInline::Plaintext(last)) => last.push('\\'),
+                    _ => text.push(MarkdownInline::Plaintext(String::from("\\"))),
+                }
+            }
+            text
+        },
+    )(i)
+}
+
+// the rest of the line without its `\n` or `\r\n`
+fn parse_line(i: &str) -> IResult<&str, &str> {
+    terminated(take_till(|c| c == '\n' || c == '\r'), line_ending)(i)
+}
+
+// a line of a paragraph, which can end with a hard line break:
+// a backslash, or two or more spaces left at the end of the text
+fn parse_paragraph_line(i: &str) -> IResult<&str, MarkdownText> {
+    map(
+        terminated(
+            preceded(space0, pair(parse_inline_text, opt(tag("\\")))),
+            line_ending,
+        ),
+        |(mut text, backslash)| {
+            if backslash.is_some() {
+                text.push(MarkdownInline::LineBreak);
+            } else if let Some(MarkdownInline::Plaintext(last)) = text.last() {
+                // a line of spaces only is blank, not a break
+                let trimmed = last.trim_end_matches(' ');
+                let break_ =
+                    last.len() - trimmed.len() >= 2 && (text.len() > 1 || !trimmed.is_empty());
+                trim_end_text(&mut text);
+                if break_ {
+                    text.push(MarkdownInline::LineBreak);
+                }
+            }
+            text
+        },
+    )(i)
+}
+
+// the inline text which can be read from the beginning of a line,
+// used to point errors at the character where the line stops parsing
+pub fn parse_inline_prefix(i: &str) -> IResult<&str, MarkdownText> {
+    parse_inline_text(i)
+}
+
+// this guy matches the literal character #
+fn parse_header_tag(i: &str) -> IResult<&str, usize> {
+    map(
+        terminated(take_while1(|c| c == '#'), tag(" ")),
+        |s: &str| s.len(),
+    )(i)
+}
+
+// this combines a tuple of the header tag and the rest of the line
+fn parse_header(i: &str) -> IResult<&str, (usize, MarkdownText)> {
+    tuple((parse_header_tag, parse_markdown_text))(i)
+}
+
+// a line of text underlined with `=` (level 1) or `-` (level 2)
+fn parse_setext_header(i: &str) -> IResult<&str, (usize, MarkdownText)> {
+    let underline = alt((
+        map(take_while1(|c| c == '='), |_| 1),
+        map(take_while1(|c| c == '-'), |_| 2),
+    ));
+    map(
+        pair(
+            verify(parse_markdown_text, |text: &MarkdownText| !is_blank(text)),
+            terminated(underline, pair(space0, line_ending)),
+        ),
+        |(mut text, size)| {
+            if let Some(MarkdownInline::Plaintext(last)) = text.last_mut() {
+                *last = last.trim_end().to_string();
+            }
+            (size, text)
+        },
+    )(i)
+}
+
+fn parse_unordered_list_tag(i: &str) -> IResult<&str, &str> {
+    terminated(alt((tag("-"), tag("*"), tag("+"))), tag(" "))(i)
+}
+
+fn parse_unordered_list_element(i: &str) -> IResult<&str, ListItem> {
+    pair(
+        preceded(parse_unordered_list_tag, parse_markdown_text),
+        parse_list_item_children,
+    )(i)
+}
+
+// a list keeps the marker of its first item, another marker starts a new list
+fn parse_unordered_list(i: &str) -> IResult<&str, Vec<ListItem>> {
+    let (_, marker) = peek(parse_unordered_list_tag)(i)?;
+    many1(preceded(
+        peek(verify(parse_unordered_list_tag, move |m: &str| m == marker)),
+        parse_unordered_list_element,
+    ))(i)
+}
+
+// the number and the delimiter, `1.` or `1)`
+fn parse_ordered_list_tag(i: &str) -> IResult<&str, (&str, char)> {
+    terminated(
+        pair(take_while1(|d| is_digit(d as u8)), one_of(".)")),
+        tag(" "),
+    )(i)
+}
+
+fn parse_ordered_list_element(i: &str) -> IResult<&str, ListItem> {
+    pair(
+        preceded(parse_ordered_list_tag, parse_markdown_text),
+        parse_list_item_children,
+    )(i)
+}
+
+// the items of a list share the delimiter of the first one, which also gives the start number
+fn parse_ordered_list(i: &str) -> IResult<&str, (usize, Vec<ListItem>)> {
+    let (_, (start, delimiter)) = peek(parse_ordered_list_tag)(i)?;
+    let start = start.parse().unwrap_or(1);
+    let same_delimiter = peek(verify(parse_ordered_list_tag, move |(_, d)| {
+        *d == delimiter
+    }));
+    map(
+        many1(preceded(same_delimiter, parse_ordered_list_element)),
+        move |items| (start, items),
+    )(i)
+}
+
+fn parse_indented_line(i: &str) -> IResult<&str, &str> {
+    preceded(peek(tag("  ")), parse_line)(i)
+}
+
+// the lines indented by two or more spaces after a list item belong to that item.
+// they are dedented and parsed again as a document, which gives us nested lists
+fn parse_list_item_children(i: &str) -> IResult<&str, Vec<Markdown>> {
+    let (rest, lines) = many0(parse_indented_line)(i)?;
+    if lines.is_empty() {
+        return Ok((rest, vec![]));
+    }
+    let indent = lines
+        .iter()
+        .map(|line| line.len() - line.trim_start_matches(' ').len())
+        .min()
+        .unwrap_or(0);
+    let inner: String = lines
+        .iter()
+        .map(|line| format!("{}\n", &line[indent..]))
+        .collect();
+    match parse_blocks(&inner) {
+        Ok(("", markdown)) => Ok((rest, markdown)),
+        _ => Err(not_parsed(i)),
+    }
+}
+
+fn parse_indented_code_line(i: &str) -> IResult<&str, &str> {
+    preceded(
+        alt((tag("    "), tag("\t"))),
+        verify(parse_line, |s: &str| !s.trim().is_empty()),
+    )(i)
+}
+
+// lines indented by four spaces or a tab, without the indentation.
+// blank lines between them are kept, the ones after them are not part of the block
+fn parse_indented_code_block(i: &str) -> IResult<&str, String> {
+    let blank_line = terminated(space0, line_ending);
+    map(
+        pair(
+            parse_indented_code_line,
+            many0(pair(many0(blank_line), parse_indented_code_line)),
+        ),
+        |(first, lines)| {
+            let mut code = format!("{}\n", first);
+            for (blanks, line) in lines {
+                code += &"\n".repeat(blanks.len());
+                code += &format!("{}\n", line);
+            }
+            code
+        },
+    )(i)
+}
+
+// a fence of three or more backticks or tildes opens the block.
+// it is closed by a line of the same character, at least as long as the opening fence,
+// or else runs to the end of the document
+// the language is the first word of the info string, the rest is kept as the attributes.
+// `rust,ignore` and `python {linenos=true}` give `ignore` and `{linenos=true}`
+fn parse_info_string(info: &str) -> (&str, &str) {
+    let info = info.trim();
+    let end = info
+        .find(|c: char| c.is_whitespace() || c == ',' || c == '{')
+        .unwrap_or(info.len());
+    let (language, attributes) = info.split_at(end);
+    (language, attributes.trim_start_matches(',').trim())
+}
+
+// a formula between `$$`, either in one line or with the `$$` on their own lines
+fn parse_math_block(i: &str) -> IResult<&str, &str> {
+    extension(i, Extension::Math)?;
+    map(
+        terminated(
+            delimited(
+                tag("$$"),
+                verify(take_until("$$"), |tex: &str| !tex.trim().is_empty()),
+                tag("$$"),
+            ),
+            pair(space0, line_ending),
+        ),
+        |tex: &str| tex.trim(),
+    )(i)
+}
+
+fn parse_code_block(i: &str) -> IResult<&str, ((&str, &str), &str)> {
+    let mut fence = verify(
+        alt((take_while1(|c| c == '`'), take_while1(|c| c == '~'))),
+        |fence: &str| fence.len() >= 3,
+    );
+    let (rest, fence) = fence(i)?;
+    let (rest, info) = terminated(take_till(|c| c == '\n' || c == '\r'), line_ending)(rest)?;
+    if fence.starts_with('`') && info.contains('`') {
+        return Err(nom::Err::Error(Error::new(rest, ErrorKind::Verify)));
+    }
+    let info = parse_info_string(info);
+    let closes = |line: &str| {
+        let trimmed = line.trim_start_matches(' ');
+        let mark = trimmed.trim_end();
+        line.len() - trimmed.len() < 4
+            && mark.len() >= fence.len()
+            && mark.chars().all(|c| fence.starts_with(c))
+    };
+    let mut offset = 0;
+    while offset < rest.len() {
+        let end = rest[offset..]
+            .find('\n')
+            .map_or(rest.len(), |k| offset + k + 1);
+        if closes(&rest[offset..end]) {
+            return Ok((&rest[end..], (info, &rest[..offset])));
+        }
+        offset = end;
+    }
+    Ok(("", (info, rest)))
+}
+
+// a cell is everything between two pipes, parsed as inline text after trimming.
+// an escaped pipe `\|` does not end the cell
+fn parse_table_cell(i: &str) -> IResult<&str, MarkdownText> {
+    let (rest, cell) = recognize(many0(alt((
+        recognize(pair(tag("\\"), none_of("\n\r"))),
+        recognize(none_of("|\n\r")),
+    ))))(i)?;
+    let (_, text) = all_consuming(parse_inline_text)(cell.trim())?;
+    Ok((rest, text))
+}
+
+fn parse_table_row(i: &str) -> IResult<&str, Vec<MarkdownText>> {
+    delimited(
+        tag("|"),
+        many1(terminated(parse_table_cell, tag("|"))),
+        pair(space0, line_ending),
+    )(i)
+}
+
+// `---`, `:--`, `--:` or `:-:`, padded with any number of spaces
+fn parse_table_align(i: &str) -> IResult<&str, Align> {
+    map(
+        delimited(
+            space0,
+            tuple((opt(tag(":")), take_while1(|c| c == '-'), opt(tag(":")))),
+            space0,
+        ),
+        |(left, _, right)| match (left, right) {
+            (Some(_), Some(_)) => Align::Center,
+            (Some(_), None) => Align::Left,
+            (None, Some(_)) => Align::Right,
+            (None, None) => Align::None,
+        },
+    )(i)
+}
+
+fn parse_table_delimiter_row(i: &str) -> IResult<&str, Vec<Align>> {
+    delimited(
+        tag("|"),
+        many1(terminated(parse_table_align, tag("|"))),
+        pair(space0, line_ending),
+    )(i)
+}
+
+// the header row and the delimiter row must have the same number of columns
+#[allow(clippy::type_complexity)]
+fn parse_table(i: &str) -> IResult<&str, (Vec<MarkdownText>, Vec<Align>, Vec<Vec<MarkdownText>>)> {
+    extension(i, Extension::Tables)?;
+    verify(
+        tuple((
+            parse_table_row,
+            parse_table_delimiter_row,
+            many0(parse_table_row),
+        )),
+        |(header, aligns, _)| header.len() == aligns.len(),
+    )(i)
+}
+
+fn parse_footnote_definition(i: &str) -> IResult<&str, (&str, MarkdownText)> {
+    pair(
+        terminated(parse_footnote_ref, pair(tag(":"), space0)),
+        parse_markdown_text,
+    )(i)
+}
+
+// a term on its own line followed by one or more `: definition` lines
+fn parse_definition(i: &str) -> IResult<&str, Definition> {
+    pair(
+        verify(parse_markdown_text, |term: &MarkdownText| !is_blank(term)),
+        many1(preceded(pair(tag(":"), space1), parse_markdown_text)),
+    )(i)
+}
+
+// the terms may be separated by a blank line
+fn parse_definition_list(i: &str) -> IResult<&str, Vec<Definition>> {
+    extension(i, Extension::DefinitionLists)?;
+    many1(terminated(parse_definition, opt(pair(space0, line_ending))))(i)
+}
+
+fn parse_footnotes_marker(i: &str) -> IResult<&str, &str> {
+    extension(i, Extension::Footnotes)?;
+    terminated(tag("[FOOTNOTES]"), pair(space0, line_ending))(i)
+}
+
+fn parse_blockquote_line(i: &str) -> IResult<&str, &str> {
+    preceded(pair(tag(">"), opt(tag(" "))), parse_line)(i)
+}
+
+// the quote markers are stripped and the rest is parsed again as a whole document,
+// so quotes can contain any block, including another quote (`>>`)
+// `{#id .class key=value key="quoted value"}`, the items separated by spaces
+fn parse_attributes(i: &str) -> IResult<&str, Attributes> {
+    let name = |i| take_while1(|c: char| c.is_alphanumeric() || "-_:".contains(c))(i);
+    let value = alt((
+        delimited(tag("\""), take_till(|c| c == '"'), tag("\"")),
+        take_till(|c: char| c.is_whitespace() || c == '}'),
+    ));
+    let item = alt((
+        map(preceded(tag("#"), name), |id: &str| (Some(id), None, None)),
+        map(preceded(tag("."), name), |class: &str| {
+            (None, Some(class), None)
+        }),
+        map(separated_pair(name, tag("="), value), |pair| {
+            (None, None, Some(pair))
+        }),
+    ));
+    map(
+        delimited(
+            pair(tag("{"), space0),
+            many0(terminated(item, space0)),
+            tag("}"),
+        ),
+        |items| {
+            let mut attributes = Attributes::default();
+            for (id, class, pair) in items {
+                if let Some(id) = id {
+                    attributes.id = Some(id.to_string());
+                }
+                attributes.classes.extend(class.map(String::from));
+                attributes.pairs.extend(
+                    pair.map(|(key, value): (&str, &str)| (key.to_string(), value.to_string())),
+                );
+            }
+            attributes
+        },
+    )(i)
+}
+
+// takes the trailing `{#id .class}` off the text of a heading
+fn split_attributes(mut text: MarkdownText) -> (MarkdownText, Attributes) {
+    if !options().has(Extension::Attributes) {
+        return (text, Attributes::default());
+    }
+    if let Some(MarkdownInline::Plaintext(last)) = text.last_mut() {
+        let trimmed = last.trim_end();
+        if let Some(start) = trimmed.rfind('{') {
+            if let Ok(("", attributes)) = parse_attributes(&trimmed[start..]) {
+                *last = trimmed[..start].trim_end().to_string();
+                if last.is_empty() {
+                    text.pop();
+                }
+                return (text, attributes);
+            }
+        }
+    }
+    (text, Attributes::default())
+}
+
+fn parse_div_fence(i: &str) -> IResult<&str, &str> {
+    verify(take_while1(|c| c == ':'), |fence: &str| fence.len() >= 3)(i)
+}
+
+// `::: note` and the lines up to the closing `:::`, whose content is parsed again as blocks.
+// the containers can be nested, so each opening fence needs its own closing one
+fn parse_fenced_div(i: &str) -> IResult<&str, (&str, Vec<Markdown>)> {
+    extension(i, Extension::FencedDivs)?;
+    let (rest, info) = delimited(
+        pair(parse_div_fence, space0),
+        verify(take_till(|c| c == '\n' || c == '\r'), |info: &str| {
+            !info.trim().is_empty()
+        }),
+        line_ending,
+    )(i)?;
+    let (rest, blocks) = parse_fenced_blocks(i, rest, parse_div_fence)?;
+    Ok((rest, (info.trim(), blocks)))
+}
+
+// the blocks up to the fence closing the one opened before `rest`.
+// a fence followed by some text opens a nested container, a bare one closes it
+fn parse_fenced_blocks<'a, F>(
+    i: &'a str,
+    rest: &'a str,
+    fence: F,
+) -> IResult<&'a str, Vec<Markdown>>
+where
+    F: Fn(&str) -> IResult<&str, &str>,
+{
+    let mut depth = 1;
+    let mut offset = 0;
+    while offset < rest.len() {
+        let end = rest[offset..]
+            .find('\n')
+            .map_or(rest.len(), |k| offset + k + 1);
+        let line = rest[offset..end].trim();
+        if let Ok((info, _)) = fence(line) {
+            depth = if info.trim().is_empty() {
+                depth - 1
+            } else {
+                depth + 1
+            };
+        }
+        if depth == 0 {
+            let inner = &rest[..offset];
+            let blocks = match parse_blocks(inner) {
+                _ if inner.is_empty() => vec![],
+                Ok(("", blocks)) => blocks,
+                _ => break,
+            };
+            return Ok((&rest[end..], blocks));
+        }
+        offset = end;
+    }
+    Err(not_parsed(i))
+}
+
+fn parse_details_fence(i: &str) -> IResult<&str, &str> {
+    terminated(tag("???"), opt(tag("+")))(i)
+}
+
+// `??? summary` and the lines up to the closing `???`, as `::: note` is closed by `:::`.
+// `???+` opens the section by default
+#[allow(clippy::type_complexity)]
+fn parse_details(i: &str) -> IResult<&str, ((bool, &str), Vec<Markdown>)> {
+    extension(i, Extension::Details)?;
+    let (rest, (open, summary)) = terminated(
+        pair(
+            preceded(tag("???"), map(opt(tag("+")), |plus| plus.is_some())),
+            preceded(
+                space1,
+                verify(take_till(|c| c == '\n' || c == '\r'), |summary: &str| {
+                    !summary.trim().is_empty()
+                }),
+            ),
+        ),
+        line_ending,
+    )(i)?;
+    let (rest, blocks) = parse_fenced_blocks(i, rest, parse_details_fence)?;
+    Ok((rest, ((open, summary.trim()), blocks)))
+}
+
+fn parse_blockquote(i: &str) -> IResult<&str, Vec<Markdown>> {
+    let (rest, lines) = many1(parse_blockquote_line)(i)?;
+    let inner: String = lines.iter().map(|line| format!("{}\n", line)).collect();
+    match parse_blocks(&inner) {
+        Ok(("", markdown)) => Ok((rest, markdown)),
+        _ => Err(not_parsed(i)),
+    }
+}
+
+const HTML_BLOCK_TAGS: &[&str] = &[
+    "address",
+    "article",
+    "aside",
+    "blockquote",
+    "center",
+    "details",
+    "dialog",
+    "dd",
+    "div",
+    "dl",
+    "dt",
+    "fieldset",
+    "figcaption",
+    "figure",
+    "footer",
+    "form",
+    "h1",
+    "h2",
+    "h3",
+    "h4",
+    "h5",
+    "h6",
+    "header",
+    "hr",
+    "iframe",
+    "li",
+    "main",
+    "nav",
+    "ol",
+    "p",
+    "pre",
+    "section",
+    "summary",
+    "table",
+    "tbody",
+    "td",
+    "tfoot",
+    "th",
+    "thead",
+    "tr",
+    "ul",
+];
+
+fn parse_html_block_tag(i: &str) -> IResult<&str, &str> {
+    terminated(
+        preceded(
+            pair(tag("<"), opt(tag("/"))),
+            verify(alphanumeric1, |name: &str| {
+                HTML_BLOCK_TAGS.contains(&name.to_lowercase().as_str())
+            }),
+        ),
+        peek(alt((tag(" "), tag(">"), tag("/>"), line_ending))),
+    )(i)
+}
+
+fn parse_non_blank_line(i: &str) -> IResult<&str, &str> {
+    verify(parse_line, |line: &str| !line.trim().is_empty())(i)
+}
+
+// a line starting with a block-level tag opens a html block,
+// which is passed through verbatim until the next blank line
+fn parse_html_block(i: &str) -> IResult<&str, &str> {
+    map(
+        recognize(pair(
+            preceded(peek(parse_html_block_tag), parse_non_blank_line),
+            many0(parse_non_blank_line),
+        )),
+        |html: &str| html.trim_end_matches(['\r', '\n']),
+    )(i)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::parser::*;
+
+    macro_rules! err {
+        ($x:expr, $y:expr) => {
+            Err(nom::Err::Error(nom::error::Error::new($x, $y)))
+        };
+    }
+
+    fn plain(s: &str) -> MarkdownInline {
+        MarkdownInline::Plaintext(String::from(s))
+    }
+
+    // `:name:` in capitals, and `!!` as a rule unless the line says `no`
+    struct Shout;
+
+    impl Syntax for Shout {
+        fn inline_prefixes(&self) -> Vec<String> {
+            vec![String::from(":")]
+        }
+
+        fn block_prefixes(&self) -> Vec<String> {
+            vec![String::from("!!")]
+        }
+
+        fn inline(&mut self, text: &str) -> Result<Option<(usize, String)>, String> {
+            Ok(text[1..]
+                .find(':')
+                .map(|n| (n + 2, text[1..n + 1].to_uppercase())))
+        }
+
+        fn block(&mut self, text: &str) -> Result<Option<(usize, String)>, String> {
+            match text.starts_with("!!no") {
+                true => Err(String::from("no")),
+                false => Ok(Some((2, String::from("<hr>")))),
+            }
+        }
+    }
+
+    #[test]
+    fn test_with_syntax() {
+        let parse = |i| with_syntax(vec![Rc::new(RefCell::new(Shout))], || parse_markdown(i));
+        assert_eq!(
+            parse("a:b: c :\n!! d\ne\n"),
+            Ok(Ok((
+                "",
+                vec![
+                    Markdown::Line(vec![
+                        plain("a"),
+                        MarkdownInline::Html(String::from("B")),
+                        plain(" c :")
+                    ]),
+                    Markdown::Html(String::from("<hr>")),
+                    Markdown::Line(vec![plain("e")]),
+                ]
+            )))
+        );
+        assert_eq!(parse("!!no\n").err(), Some(String::from("no")));
+        assert_eq!(
+            parse_markdown("a:b:\n"),
+            Ok(("", vec![Markdown::Line(vec![plain("a:b:")])]))
+        );
+    }
+
+    #[test]
+    fn test_parse_italics() {
+        assert_eq!(
+            parse_italics("*here is italic*"),
+            Ok(("", vec![plain("here is italic")]))
+        );
+        assert_eq!(
+            parse_italics("_here is italic_ too"),
+            Ok((" too", vec![plain("here is italic")]))
+        );
+        assert_eq!(
+            parse_italics("*here is italic"),
+            err!("*here is italic", ErrorKind::Tag)
+        );
+        assert_eq!(
+            parse_italics("here is italic*"),
+            err!("here is italic*", ErrorKind::Tag)
+        );
+        assert_eq!(
+            parse_italics("***bold italic*** c"),
+            Ok((" c", vec![MarkdownInline::Bold(vec![plain("bold italic")])]))
+        );
+        assert_eq!(
+            parse_italics("___bold italic___"),
+            Ok(("", vec![MarkdownInline::Bold(vec![plain("bold italic")])]))
+        );
+        assert_eq!(parse_italics("*"), err!("*", ErrorKind::Tag));
+        assert_eq!(parse_italics("**"), err!("**", ErrorKind::Tag));
+        assert_eq!(parse_italics(""), err!("", ErrorKind::Tag));
+        assert_eq!(
+            parse_italics("**we are doing bold**"),
+            err!("**we are doing bold**", ErrorKind::Tag)
+        );
+        assert_eq!(
+            parse_italics("*a **b** c*"),
+            Ok((
+                "",
+                vec![
+                    plain("a "),
+                    MarkdownInline::Bold(vec![plain("b")]),
+                    plain(" c")
+                ]
+            ))
+        );
+        assert_eq!(parse_italics("_a_b_"), Ok(("", vec![plain("a_b")])));
+        assert_eq!(parse_italics("*a\nb*"), err!("*a\nb*", ErrorKind::Tag));
+    }
+
+    #[test]
+    fn test_parse_boldtext() {
+        assert_eq!(
+            parse_boldtext("**here is bold**"),
+            Ok(("", vec![plain("here is bold")]))
+        );
+        assert_eq!(
+            parse_boldtext("__here is bold__"),
+            Ok(("", vec![plain("here is bold")]))
+        );
+        assert_eq!(
+            parse_boldtext("**here is bold"),
+            err!("**here is bold", ErrorKind::Tag)
+        );
+        assert_eq!(
+            parse_boldtext("here is bold**"),
+            err!("here is bold**", ErrorKind::Tag)
+        );
+        assert_eq!(parse_boldtext("****"), err!("****", ErrorKind::Tag));
+        assert_eq!(parse_boldtext("*"), err!("*", ErrorKind::Tag));
+        assert_eq!(parse_boldtext(""), err!("", ErrorKind::Tag));
+        assert_eq!(
+            parse_boldtext("**bold with *italic* inside**"),
+            Ok((
+                "",
+                vec![
+                    plain("bold with "),
+                    MarkdownInline::Italic(vec![plain("italic")]),
+                    plain(" inside")
+                ]
+            ))
+        );
+        assert_eq!(
+            parse_boldtext("**`**` is bold**"),
+            Ok((
+                "",
+                vec![
+                    MarkdownInline::InlineCode(String::from("**")),
+                    plain(" is bold")
+                ]
+            ))
+        );
+        assert_eq!(
+            parse_boldtext("**an *escaped \\* star***"),
+            Ok((
+                "",
+                vec![
+                    plain("an "),
+                    MarkdownInline::Italic(vec![plain("escaped * star")])
+                ]
+            ))
+        );
+    }
+
+    #[test]
+    fn test_parse_mark() {
+        assert_eq!(
+            parse_mark("==very *big*== x"),
+            Ok((
+                " x",
+                vec![
+                    MarkdownInline::Plaintext(String::from("very ")),
+                    MarkdownInline::Italic(vec![MarkdownInline::Plaintext(String::from("big"))]),
+                ]
+            ))
+        );
+        assert_eq!(parse_mark("== a =="), err!(" a ==", ErrorKind::Verify));
+        assert_eq!(parse_mark("===="), err!("==", ErrorKind::Verify));
+        assert_eq!(
+            parse_mark("==a ==b== c== d"),
+            Ok((
+                " d",
+                vec![
+                    MarkdownInline::Plaintext(String::from("a ")),
+                    MarkdownInline::Mark(vec![MarkdownInline::Plaintext(String::from("b"))]),
+                    MarkdownInline::Plaintext(String::from(" c")),
+                ]
+            ))
+        );
+    }
+
+    #[test]
+    fn test_parse_script() {
+        assert_eq!(parse_superscript("^2^nd"), Ok(("nd", "2")));
+        assert_eq!(parse_subscript("~i~ x"), Ok((" x", "i")));
+        assert_eq!(parse_superscript("^a b^"), err!(" b^", ErrorKind::Tag));
+        assert_eq!(
+            parse_plaintext("2^10 is big"),
+            Ok(("", String::from("2^10 is big")))
+        );
+    }
+
+    #[test]
+    fn test_parse_kbd() {
+        assert_eq!(
+            parse_kbd("++Ctrl + Alt+Del++"),
+            Ok(("", vec!["Ctrl", "Alt", "Del"]))
+        );
+        assert_eq!(parse_kbd("++ a++"), err!(" a++", ErrorKind::Verify));
+        assert_eq!(
+            parse_plaintext("C++ and D++"),
+            Ok(("", String::from("C++ and D++")))
+        );
+    }
+
+    #[test]
+    fn test_parse_ruby() {
+        assert_eq!(
+            parse_ruby("{漢字|かんじ}です"),
+            Ok(("です", ("漢字", vec!["かんじ"])))
+        );
+        assert_eq!(
+            parse_ruby("{漢字|かん|じ}"),
+            Ok(("", ("漢字", vec!["かん", "じ"])))
+        );
+        assert_eq!(
+            parse_ruby("{漢字|か|ん|じ}"),
+            err!("{漢字|か|ん|じ}", ErrorKind::Verify)
+        );
+        assert_eq!(
+            parse_ruby("{{ title }}"),
+            err!("{ title }}", ErrorKind::Not)
+        );
+        assert_eq!(parse_ruby("{#id}"), err!("}", ErrorKind::Tag));
+    }
+
+    #[test]
+    fn test_parse_inline_code() {
+        assert_eq!(parse_inline_code("`here is code"), err!("", ErrorKind::Tag));
+        assert_eq!(
+            parse_inline_code("here is code`"),
+            err!("here is code`", ErrorKind::Tag)
+        );
+        assert_eq!(parse_inline_code("``"), err!("`", ErrorKind::IsNot));
+        assert_eq!(parse_inline_code("`"), err!("", ErrorKind::IsNot));
+        assert_eq!(parse_inline_code(""), err!("", ErrorKind::Tag));
+    }
+
+    #[test]
+    fn test_parse_strike() {
+        assert_eq!(parse_strike("~~gone~~ x"), Ok((" x", "gone")));
+        assert_eq!(parse_strike("~~~~"), err!("~~", ErrorKind::Verify));
+        assert_eq!(parse_strike("~gone~"), err!("~gone~", ErrorKind::Tag));
+        // a single `~` is subscript unless the option is on
+        assert_eq!(
+            parse_plaintext("see ~/.bashrc and ~user~"),
+            Ok(("~user~", String::from("see ~/.bashrc and ")))
+        );
+        let options = ParseOptions {
+            single_tilde_strike: true,
+            ..ParseOptions::default()
+        };
+        assert_eq!(
+            with_options(options, || parse_strike("~gone~")),
+            Ok(("", "gone"))
+        );
+        assert_eq!(
+            with_options(options, || parse_strike("~~gone~~")),
+            Ok(("", "gone"))
+        );
+        // the option does not leak out of `with_options`
+        assert!(parse_strike("~gone~").is_err());
+    }
+
+    #[test]
+    fn test_dialect() {
+        let dialect = |dialect, i| {
+            let options = ParseOptions {
+                dialect,
+                ..ParseOptions::default()
+            };
+            with_options(options, || parse_markdown(i))
+        };
+        let i = "~~a~~ ~b~ ==c== :smile: www.x.org\n";
+        assert_eq!(
+            dialect(Dialect::CommonMark, i),
+            Ok((
+                "",
+                vec![Markdown::Line(vec![MarkdownInline::Plaintext(
+                    i.trim_end().to_string()
+                )])]
+            ))
+        );
+        assert_eq!(
+            dialect(Dialect::Gfm, i),
+            Ok((
+                "",
+                vec![Markdown::Line(vec![
+                    MarkdownInline::Strike(String::from("a")),
+                    MarkdownInline::Plaintext(String::from(" ")),
+                    MarkdownInline::Strike(String::from("b")),
+                    MarkdownInline::Plaintext(String::from(" ==c== :smile: ")),
+                    MarkdownInline::Link(
+                        vec![MarkdownInline::Plaintext(String::from("www.x.org"))],
+                        String::from("http://www.x.org"),
+                        None
+                    ),
+                ])]
+            ))
+        );
+        assert_eq!(dialect(Dialect::Prose, i), parse_markdown(i));
+        // a table is a paragraph in CommonMark, and the attributes stay in the heading
+        assert_eq!(
+            dialect(Dialect::CommonMark, "| a |\n|---|\n"),
+            Ok((
+                "",
+                vec![
+                    Markdown::Line(vec![MarkdownInline::Plaintext(String::from("| a |"))]),
+                    Markdown::Line(vec![MarkdownInline::Plaintext(String::from("|---|"))]),
+                ]
+            ))
+        );
+        assert_eq!(
+            dialect(Dialect::CommonMark, "# a {#b}\n"),
+            Ok((
+                "",
+                vec![Markdown::Heading(
+                    1,
+                    vec![MarkdownInline::Plaintext(String::from("a {#b}"))],
+                    Attributes::default()
+                )]
+            ))
+        );
+        assert_eq!("gfm".parse(), Ok(Dialect::Gfm));
+        assert!("markdown".parse::<Dialect>().is_err());
+    }
+
+    #[test]
+    fn test_extensions() {
+        let parse = |options, i| with_options(options, || parse_markdown(i));
+        let options = ParseOptions {
+            dialect: Dialect::CommonMark,
+            enabled: [Extension::Mark].into_iter().collect(),
+            ..ParseOptions::default()
+        };
+        assert_eq!(
+            parse(options, "==a== ~~b~~\n"),
+            Ok((
+                "",
+                vec![Markdown::Line(vec![
+                    MarkdownInline::Mark(vec![MarkdownInline::Plaintext(String::from("a"))]),
+                    MarkdownInline::Plaintext(String::from(" ~~b~~")),
+                ])]
+            ))
+        );
+        let options = ParseOptions {
+            enabled: [Extension::Math].into_iter().collect(),
+            disabled: [Extension::Math, Extension::Tables].into_iter().collect(),
+            ..ParseOptions::default()
+        };
+        assert!(!options.has(Extension::Math));
+        assert!(!options.has(Extension::Tables));
+        assert!(options.has(Extension::Ruby));
+        assert_eq!(
+            parse(options, "$x$\n"),
+            Ok((
+                "",
+                vec![Markdown::Line(vec![MarkdownInline::Plaintext(
+                    String::from("$x$")
+                )])]
+            ))
+        );
+        assert_eq!("fenced-divs".parse(), Ok(Extension::FencedDivs));
+        assert!("smart".parse::<Extension>().is_err());
+        assert!(Extension::ALL
+            .iter()
+            .all(|extension| extension.name().parse() == Ok(*extension)));
+    }
+
+    #[test]
+    fn test_parse_math() {
+        assert_eq!(parse_math("$x^2$ y"), Ok((" y", "x^2")));
+        assert_eq!(parse_math("$a \\$ b$"), Ok(("", "a \\$ b")));
+        assert_eq!(
+            parse_math("$5 and $6"),
+            err!("5 and $6", ErrorKind::TakeUntil)
+        );
+        assert_eq!(parse_math("$ x$"), err!(" x$", ErrorKind::Verify));
+        assert_eq!(parse_math("$x $"), err!("x $", ErrorKind::TakeUntil));
+        assert_eq!(
+            parse_plaintext("costs $5 or $6"),
+            Ok(("", String::from("costs $5 or $6")))
+        );
+        assert_eq!(
+            parse_markdown_text("*a* $a*b*c$\n"),
+            Ok((
+                "",
+                vec![
+                    MarkdownInline::Italic(vec![MarkdownInline::Plaintext(String::from("a"))]),
+                    MarkdownInline::Plaintext(String::from(" ")),
+                    MarkdownInline::Math(String::from("a*b*c")),
+                ]
+            ))
+        );
+    }
+
+    #[test]
+    fn test_parse_math_block() {
+        assert_eq!(
+            parse_math_block("$$\n\\int_0^1 f\n$$\nafter"),
+            Ok(("after", "\\int_0^1 f"))
+        );
+        assert_eq!(parse_math_block("$$ e^{i\\pi} $$\n"), Ok(("", "e^{i\\pi}")));
+        assert_eq!(
+            parse_math_block("$$ $$\n"),
+            err!(" $$\n", ErrorKind::Verify)
+        );
+    }
+
+    #[test]
+    fn test_parse_link() {
+        assert_eq!(
+            parse_link("[title](https://www.example.com)"),
+            Ok(("", ("title", "https://www.example.com", None)))
+        );
+        assert_eq!(
+            parse_link("[a](u \"the (title)\")"),
+            Ok(("", ("a", "u", Some("the (title)"))))
+        );
+        assert_eq!(parse_link("[a](u 'it')"), Ok(("", ("a", "u", Some("it")))));
+        assert_eq!(parse_link("[a](u (it) )"), Ok(("", ("a", "u", Some("it")))));
+        assert_eq!(parse_link("[a](u \"open)"), err!("\"open)", ErrorKind::Tag));
+        assert_eq!(
+            parse_link("[Foo](https://en.wikipedia.org/wiki/Foo_(bar))"),
+            Ok(("", ("Foo", "https://en.wikipedia.org/wiki/Foo_(bar)", None)))
+        );
+        assert_eq!(
+            parse_link("[a](u(1)(2) \"t\")."),
+            Ok((".", ("a", "u(1)(2)", Some("t"))))
+        );
+        assert_eq!(parse_link("[a](u\\))"), Ok(("", ("a", "u\\)", None))));
+        assert_eq!(parse_link("[a](u(b)"), err!("", ErrorKind::Tag));
+        assert_eq!(parse_link("[a](u(b c)"), err!("u(b c)", ErrorKind::IsNot));
+        assert_eq!(parse_inline_code(""), err!("", ErrorKind::Tag));
+    }
+
+    #[test]
+    fn test_parse_image() {
+        assert_eq!(
+            parse_image("![alt text](image.jpg)"),
+            Ok(("", ("alt text", "image.jpg", None)))
+        );
+        assert_eq!(
+            parse_image("![a](i.png \"hint\")"),
+            Ok(("", ("a", "i.png", Some("hint"))))
+        );
+        assert_eq!(parse_image("![](i.png)"), Ok(("", ("", "i.png", None))));
+        assert_eq!(parse_inline_code(""), err!("", ErrorKind::Tag));
+    }
+
+    #[test]
+    fn test_parse_footnote_ref() {
+        assert_eq!(parse_footnote_ref("[^1]"), Ok(("", "1")));
+        assert_eq!(parse_footnote_ref("[^note]: x"), Ok((": x", "note")));
+        assert_eq!(parse_footnote_ref("[1]"), err!("[1]", ErrorKind::Tag));
+        assert_eq!(parse_footnote_ref("[^]"), err!("]", ErrorKind::IsNot));
+    }
+
+    #[test]
+    fn test_parse_plaintext() {
+        assert_eq!(
+            parse_plaintext("1234567890"),
+            Ok(("", String::from("1234567890")))
+        );
+        assert_eq!(
+            parse_plaintext("oh my gosh!"),
+            Ok(("", String::from("oh my gosh!")))
+        );
+        assert_eq!(
+            parse_plaintext("oh my gosh!["),
+            Ok(("![", String::from("oh my gosh")))
+        );
+        assert_eq!(
+            parse_plaintext("oh my gosh!*"),
+            Ok(("*", String::from("oh my gosh!")))
+        );
+        assert_eq!(
+            parse_plaintext("*bold babey bold*"),
+            err!("*bold babey bold*", ErrorKind::Tag)
+        );
+        assert_eq!(
+            parse_plaintext("[link babey](and then somewhat)"),
+            err!("[link babey](and then somewhat)", ErrorKind::Tag)
+        );
+        assert_eq!(
+            parse_plaintext("`codeblock for bums`"),
+            err!("`codeblock for bums`", ErrorKind::Tag)
+        );
+        assert_eq!(
+            parse_plaintext("![ but wait theres more](jk)"),
+            err!("![ but wait theres more](jk)", ErrorKind::Tag)
+        );
+        assert_eq!(
+            parse_plaintext("see https://example.com"),
+            Ok(("https://example.com", String::from("see ")))
+        );
+        assert_eq!(
+            parse_plaintext("a < b <br>"),
+            Ok(("<br>", String::from("a < b ")))
+        );
+        assert_eq!(
+            parse_plaintext("here is plaintext"),
+            Ok(("", String::from("here is plaintext")))
+        );
+        assert_eq!(
+            parse_plaintext("here is plaintext!"),
+            Ok(("", String::from("here is plaintext!")))
+        );
+        assert_eq!(
+            parse_plaintext("here is plaintext![image starting"),
+            Ok(("![image starting", String::from("here is plaintext")))
+        );
+        assert_eq!(
+            parse_plaintext("here is plaintext\n"),
+            Ok(("\n", String::from("here is plaintext")))
+        );
+        assert_eq!(
+            parse_plaintext("*here is italic*"),
+            err!("*here is italic*", ErrorKind::Tag)
+        );
+        assert_eq!(
+            parse_plaintext("**here is bold**"),
+            err!("**here is bold**", ErrorKind::Tag)
+        );
+        assert_eq!(
+            parse_plaintext("`here is code`"),
+            err!("`here is code`", ErrorKind::Tag)
+        );
+        assert_eq!(
+            parse_plaintext("[title](https://www.example.com)"),
+            err!("[title](https://www.example.com)", ErrorKind::Tag)
+        );
+        assert_eq!(
+            parse_plaintext("![alt text](image.jpg)"),
+            err!("![alt text](image.jpg)", ErrorKind::Tag)
+        );
+        assert_eq!(parse_plaintext(""), err!("", ErrorKind::Tag));
+        assert_eq!(parse_plaintext("\\*\\[\\]"), Ok(("", String::from("*[]"))));
+        assert_eq!(
+            parse_plaintext("\\# \\- \\( \\) \\> \\\\ \\: \\<b>"),
+            Ok(("", String::from("# - ( ) > \\ : <b>")))
+        );
+        assert_eq!(parse_plaintext("a \\x"), Ok(("\\x", String::from("a "))));
+        assert_eq!(
+            parse_plaintext("snake_case, not __init__"),
+            Ok(("__init__", String::from("snake_case, not ")))
+        );
+        assert_eq!(
+            parse_plaintext("1_~ hwww.x"),
+            Ok(("", String::from("1_~ hwww.x")))
+        );
+        assert_eq!(
+            parse_plaintext("see (www.x) or http://y"),
+            Ok(("www.x) or http://y", String::from("see (")))
+        );
+        assert_eq!(
+            parse_plaintext("&copy; &#35;&#x1F600; &amp;lt; &nope; \\&amp;"),
+            Ok(("", String::from("© #😀 &lt; &nope; &amp;")))
+        );
+    }
+
+    #[test]
+    fn test_parse_markdown_inline() {
+        assert_eq!(
+            parse_markdown_inline("*here is italic*"),
+            Ok((
+                "",
+                MarkdownInline::Italic(vec![MarkdownInline::Plaintext(String::from(
+                    "here is italic"
+                ))])
+            ))
+        );
+        assert_eq!(
+            parse_markdown_inline("**here is bold**"),
+            Ok((
+                "",
+                MarkdownInline::Bold(vec![MarkdownInline::Plaintext(String::from(
+                    "here is bold"
+                ))])
+            ))
+        );
+        assert_eq!(
+            parse_markdown_inline("`here is code`"),
+            Ok(("", MarkdownInline::InlineCode(String::from("here is code"))))
+        );
+        assert_eq!(
+            parse_markdown_inline("[title](https://www.example.com)"),
+            Ok((
+                "",
+                (MarkdownInline::Link(
+                    vec![MarkdownInline::Plaintext(String::from("title"))],
+                    String::from("https://www.example.com"),
+                    None
+                ))
+            ))
+        );
+        assert_eq!(
+            parse_markdown_inline("![alt text](image.jpg)"),
+            Ok((
+                "",
+                (MarkdownInline::Image(
+                    vec![MarkdownInline::Plaintext(String::from("alt text"))],
+                    String::from("image.jpg"),
+                    None
+                ))
+            ))
+        );
+        assert_eq!(
+            parse_markdown_inline("[^1]"),
+            Ok(("", MarkdownInline::FootnoteRef(String::from("1"))))
+        );
+        assert_eq!(
+            parse_markdown_inline("here is plaintext!"),
+            Ok((
+                "",
+                MarkdownInline::Plaintext(String::from("here is plaintext!"))
+            ))
+        );
+        assert_eq!(
+            parse_markdown_inline("here is some plaintext *but what if we italicize?"),
+            Ok((
+                "*but what if we italicize?",
+                MarkdownInline::Plaintext(String::from("here is some plaintext "))
+            ))
+        );
+        assert_eq!(
+            parse_markdown_inline("here is some plaintext \n*but what if we italicize?"),
+            Ok((
+                "\n*but what if we italicize?",
+                MarkdownInline::Plaintext(String::from("here is some plaintext "))
+            ))
+        );
+        assert_eq!(parse_markdown_inline("\n"), err!("\n", ErrorKind::Verify));
+        assert_eq!(parse_markdown_inline(""), err!("", ErrorKind::Eof));
+    }
+
+    #[test]
+    fn test_parse_markdown_text() {
+        assert_eq!(parse_markdown_text("\n"), Ok(("", vec![])));
+        assert_eq!(
+            parse_markdown_text("here is some plaintext\n"),
+            Ok((
+                "",
+                vec![MarkdownInline::Plaintext(String::from(
+                    "here is some plaintext"
+                ))]
+            ))
+        );
+        assert_eq!(
+            parse_markdown_text("here is some plaintext *but what if we italicize?*\n"),
+            Ok((
+                "",
+                vec![
+                    MarkdownInline::Plaintext(String::from("here is some plaintext ")),
+                    MarkdownInline::Italic(vec![MarkdownInline::Plaintext(String::from(
+                        "but what if we italicize?"
+                    ))]),
+                ]
+            ))
+        );
+        assert_eq!(
+            parse_markdown_text("here is some plaintext *but what if we italicize?* I guess it doesnt **matter** in my `code`\n"),
+            Ok(("", vec![
+                MarkdownInline::Plaintext(String::from("here is some plaintext ")),
+                MarkdownInline::Italic(vec![MarkdownInline::Plaintext(String::from("but what if we italicize?"))]),
+                MarkdownInline::Plaintext(String::from(" I guess it doesnt ")),
+                MarkdownInline::Bold(vec![MarkdownInline::Plaintext(String::from("matter"))]),
+                MarkdownInline::Plaintext(String::from(" in my ")),
+                MarkdownInline::InlineCode(String::from("code")),
+            ]))
+        );
+        assert_eq!(
+            parse_markdown_text("here is some plaintext *but what if we italicize?*\n"),
+            Ok((
+                "",
+                vec![
+                    MarkdownInline::Plaintext(String::from("here is some plaintext ")),
+                    MarkdownInline::Italic(vec![MarkdownInline::Plaintext(String::from(
+                        "but what if we italicize?"
+                    ))]),
+                ]
+            ))
+        );
         assert_eq!(
-            parse_plaintext("oh my gosh!"),
-            Ok(("", String::from("oh my gosh!")))
+            parse_markdown_text("here is some plaintext *but what if we italicize?"),
+            err!("", ErrorKind::CrLf)
         );
+    }
+
+    #[test]
+    fn test_parse_header_tag() {
+        assert_eq!(parse_header_tag("# "), Ok(("", 1)));
+        assert_eq!(parse_header_tag("### "), Ok(("", 3)));
+        assert_eq!(parse_header_tag("# h1"), Ok(("h1", 1)));
+        assert_eq!(parse_header_tag("# h1"), Ok(("h1", 1)));
+        assert_eq!(parse_header_tag(" "), err!(" ", ErrorKind::TakeWhile1));
+        assert_eq!(parse_header_tag("#"), err!("", ErrorKind::Tag));
+    }
+
+    #[test]
+    fn test_parse_header() {
         assert_eq!(
-            parse_plaintext("oh my gosh!["),
-            Ok(("![", String::from("oh my gosh")))
+            parse_header("# h1\n"),
+            Ok(("", (1, vec![MarkdownInline::Plaintext(String::from("h1"))])))
         );
         assert_eq!(
-            parse_plaintext("oh my gosh!*"),
-            Ok(("*", String::from("oh my gosh!")))
+            parse_header("## h2\n"),
+            Ok(("", (2, vec![MarkdownInline::Plaintext(String::from("h2"))])))
         );
         assert_eq!(
-            parse_plaintext("*bold babey bold*"),
-            err!("*bold babey bold*", ErrorKind::Tag)
+            parse_header("###  h3\n"),
+            Ok((
+                "",
+                (3, vec![MarkdownInline::Plaintext(String::from(" h3"))])
+            ))
         );
+        assert_eq!(parse_header("###h3"), err!("h3", ErrorKind::Tag));
+        assert_eq!(parse_header("###"), err!("", ErrorKind::Tag));
+        assert_eq!(parse_header(""), err!("", ErrorKind::TakeWhile1));
+        assert_eq!(parse_header("#"), err!("", ErrorKind::Tag));
+        assert_eq!(parse_header("# \n"), Ok(("", (1, vec![]))));
+        assert_eq!(parse_header("# test"), err!("", ErrorKind::CrLf));
+    }
+
+    #[test]
+    fn test_parse_horizontal_rule() {
+        assert_eq!(parse_horizontal_rule("---\nx"), Ok(("x", "---")));
+        assert_eq!(parse_horizontal_rule("***\n"), Ok(("", "***")));
+        assert_eq!(parse_horizontal_rule("_____\n"), Ok(("", "_____")));
+        assert_eq!(parse_horizontal_rule(" - - -\n"), Ok(("", " - - -")));
+        assert_eq!(parse_horizontal_rule("--- \n"), Ok(("", "--- ")));
         assert_eq!(
-            parse_plaintext("[link babey](and then somewhat)"),
-            err!("[link babey](and then somewhat)", ErrorKind::Tag)
+            parse_horizontal_rule("--\n"),
+            err!("--\n", ErrorKind::Verify)
+        );
+        assert_eq!(
+            parse_horizontal_rule("-*-\n"),
+            err!("-*-\n", ErrorKind::Verify)
+        );
+        assert_eq!(
+            parse_horizontal_rule("    ---\n"),
+            err!("    ---\n", ErrorKind::Verify)
+        );
+        assert_eq!(
+            parse_horizontal_rule("--- a\n"),
+            err!("--- a\n", ErrorKind::Verify)
+        );
+    }
+
+    #[test]
+    fn test_parse_setext_header() {
+        assert_eq!(
+            parse_setext_header("Title \n=====\n"),
+            Ok((
+                "",
+                (1, vec![MarkdownInline::Plaintext(String::from("Title"))])
+            ))
+        );
+        assert_eq!(
+            parse_setext_header("*sub*\n-  \nrest\n"),
+            Ok((
+                "rest\n",
+                (
+                    2,
+                    vec![MarkdownInline::Italic(vec![MarkdownInline::Plaintext(
+                        String::from("sub")
+                    )])]
+                )
+            ))
+        );
+        assert!(parse_setext_header("\n---\n").is_err());
+        assert!(parse_setext_header("a\n-=-\n").is_err());
+        assert!(parse_setext_header("a\nb\n").is_err());
+    }
+
+    #[test]
+    fn test_parse_unordered_list_tag() {
+        assert_eq!(parse_unordered_list_tag("- "), Ok(("", "-")));
+        assert_eq!(
+            parse_unordered_list_tag("- and some more"),
+            Ok(("and some more", "-"))
+        );
+        assert_eq!(parse_unordered_list_tag("-"), err!("", ErrorKind::Tag));
+        assert_eq!(
+            parse_unordered_list_tag("-and some more"),
+            err!("and some more", ErrorKind::Tag)
+        );
+        assert_eq!(parse_unordered_list_tag("--"), err!("-", ErrorKind::Tag));
+        assert_eq!(parse_unordered_list_tag("* a"), Ok(("a", "*")));
+        assert_eq!(parse_unordered_list_tag("+ a"), Ok(("a", "+")));
+        assert_eq!(parse_unordered_list_tag("*a*"), err!("a*", ErrorKind::Tag));
+        assert_eq!(parse_unordered_list_tag(""), err!("", ErrorKind::Tag));
+    }
+
+    #[test]
+    fn test_parse_unordered_list_element() {
+        assert_eq!(
+            parse_unordered_list_element("- this is an element\n"),
+            Ok((
+                "",
+                (
+                    vec![MarkdownInline::Plaintext(String::from(
+                        "this is an element"
+                    ))],
+                    vec![]
+                )
+            ))
+        );
+        assert_eq!(
+            parse_unordered_list_element("- this is an element\n- this is another element\n"),
+            Ok((
+                "- this is another element\n",
+                (
+                    vec![MarkdownInline::Plaintext(String::from(
+                        "this is an element"
+                    ))],
+                    vec![]
+                )
+            ))
+        );
+        assert_eq!(parse_unordered_list_element(""), err!("", ErrorKind::Tag));
+        assert_eq!(
+            parse_unordered_list_element("- \n"),
+            Ok(("", (vec![], vec![])))
+        );
+        assert_eq!(
+            parse_unordered_list_element("- "),
+            err!("", ErrorKind::CrLf)
+        );
+        assert_eq!(
+            parse_unordered_list_element("- test"),
+            err!("", ErrorKind::CrLf)
+        );
+        assert_eq!(parse_unordered_list_element("-"), err!("", ErrorKind::Tag));
+    }
+
+    #[test]
+    fn test_parse_unordered_list() {
+        assert_eq!(
+            parse_unordered_list("- this is an element"),
+            err!("", ErrorKind::CrLf)
+        );
+        assert_eq!(
+            parse_unordered_list("- this is an element\n"),
+            Ok((
+                "",
+                vec![(
+                    vec![MarkdownInline::Plaintext(String::from(
+                        "this is an element"
+                    ))],
+                    vec![]
+                )]
+            ))
+        );
+        assert_eq!(
+            parse_unordered_list("- this is an element\n- here is another\n"),
+            Ok((
+                "",
+                vec![
+                    (
+                        vec![MarkdownInline::Plaintext(String::from(
+                            "this is an element"
+                        ))],
+                        vec![]
+                    ),
+                    (
+                        vec![MarkdownInline::Plaintext(String::from("here is another"))],
+                        vec![]
+                    )
+                ]
+            ))
+        );
+        assert_eq!(
+            parse_unordered_list("* a\n* b\n- c\n"),
+            Ok((
+                "- c\n",
+                vec![
+                    (vec![MarkdownInline::Plaintext(String::from("a"))], vec![]),
+                    (vec![MarkdownInline::Plaintext(String::from("b"))], vec![])
+                ]
+            ))
+        );
+    }
+
+    #[test]
+    fn test_parse_list_item_children() {
+        assert_eq!(parse_list_item_children("- a\n"), Ok(("- a\n", vec![])));
+        assert_eq!(
+            parse_list_item_children("  - a\n    1. b\n- c\n"),
+            Ok((
+                "- c\n",
+                vec![Markdown::UnorderedList(vec![(
+                    vec![MarkdownInline::Plaintext(String::from("a"))],
+                    vec![Markdown::OrderedList(
+                        1,
+                        vec![(vec![MarkdownInline::Plaintext(String::from("b"))], vec![])]
+                    )]
+                )])]
+            ))
+        );
+        assert_eq!(
+            parse_list_item_children("  \\x\n"),
+            Ok((
+                "",
+                vec![Markdown::Line(vec![MarkdownInline::Plaintext(
+                    String::from("\\x")
+                )])]
+            ))
+        );
+    }
+
+    #[test]
+    fn test_parse_ordered_list_tag() {
+        assert_eq!(parse_ordered_list_tag("1. "), Ok(("", ("1", '.'))));
+        assert_eq!(
+            parse_ordered_list_tag("1234567. "),
+            Ok(("", ("1234567", '.')))
+        );
+        assert_eq!(
+            parse_ordered_list_tag("3) and some more"),
+            Ok(("and some more", ("3", ')')))
+        );
+        assert_eq!(parse_ordered_list_tag("1"), err!("", ErrorKind::OneOf));
+        assert_eq!(
+            parse_ordered_list_tag("1.and some more"),
+            err!("and some more", ErrorKind::Tag)
+        );
+        assert_eq!(parse_ordered_list_tag("1111."), err!("", ErrorKind::Tag));
+        assert_eq!(parse_ordered_list_tag(""), err!("", ErrorKind::TakeWhile1));
+    }
+
+    #[test]
+    fn test_parse_ordered_list_element() {
+        assert_eq!(
+            parse_ordered_list_element("1. this is an element\n"),
+            Ok((
+                "",
+                (
+                    vec![MarkdownInline::Plaintext(String::from(
+                        "this is an element"
+                    ))],
+                    vec![]
+                )
+            ))
+        );
+        assert_eq!(
+            parse_ordered_list_element("1. this is an element\n1. here is another\n"),
+            Ok((
+                "1. here is another\n",
+                (
+                    vec![MarkdownInline::Plaintext(String::from(
+                        "this is an element"
+                    ))],
+                    vec![]
+                )
+            ))
+        );
+        assert_eq!(
+            parse_ordered_list_element(""),
+            err!("", ErrorKind::TakeWhile1)
+        );
+        assert_eq!(
+            parse_ordered_list_element(""),
+            err!("", ErrorKind::TakeWhile1)
+        );
+        assert_eq!(
+            parse_ordered_list_element("1. \n"),
+            Ok(("", (vec![], vec![])))
+        );
+        assert_eq!(
+            parse_ordered_list_element("1. test"),
+            err!("", ErrorKind::CrLf)
+        );
+        assert_eq!(parse_ordered_list_element("1. "), err!("", ErrorKind::CrLf));
+        assert_eq!(parse_ordered_list_element("1."), err!("", ErrorKind::Tag));
+    }
+
+    #[test]
+    fn test_parse_ordered_list() {
+        assert_eq!(
+            parse_ordered_list("1. this is an element\n"),
+            Ok((
+                "",
+                (
+                    1,
+                    vec![(
+                        vec![MarkdownInline::Plaintext(String::from(
+                            "this is an element"
+                        ))],
+                        vec![]
+                    )]
+                )
+            ))
+        );
+        assert_eq!(parse_ordered_list("1. test"), err!("", ErrorKind::CrLf));
+        assert_eq!(
+            parse_ordered_list("1. this is an element\n2. here is another\n"),
+            Ok((
+                "",
+                (
+                    1,
+                    vec![
+                        (
+                            vec!(MarkdownInline::Plaintext(String::from(
+                                "this is an element"
+                            ))),
+                            vec![]
+                        ),
+                        (
+                            vec![MarkdownInline::Plaintext(String::from("here is another"))],
+                            vec![]
+                        )
+                    ]
+                )
+            ))
+        );
+        assert_eq!(
+            parse_ordered_list("3) a\n4) b\n5. c\n"),
+            Ok((
+                "5. c\n",
+                (
+                    3,
+                    vec![
+                        (vec![MarkdownInline::Plaintext(String::from("a"))], vec![]),
+                        (vec![MarkdownInline::Plaintext(String::from("b"))], vec![])
+                    ]
+                )
+            ))
+        );
+    }
+
+    #[test]
+    fn test_parse_indented_code_block() {
+        assert_eq!(
+            parse_indented_code_block("    fn main() {\n\n\t  x\n    }\n\nafter\n"),
+            Ok(("\nafter\n", String::from("fn main() {\n\n  x\n}\n")))
+        );
+        assert_eq!(
+            parse_indented_code_block("   x\n"),
+            err!("   x\n", ErrorKind::Tag)
+        );
+        assert_eq!(
+            parse_indented_code_block("    \n"),
+            err!("\n", ErrorKind::Verify)
+        );
+    }
+
+    #[test]
+    fn test_parse_blocks_indented_code() {
+        let code = |s: &str| Markdown::Codeblock(String::new(), String::from(s), String::new());
+        let line = |s: &str| Markdown::Line(vec![MarkdownInline::Plaintext(String::from(s))]);
+        assert_eq!(
+            parse_blocks("    a\ntext\n    b\n\n    c\n"),
+            Ok((
+                "",
+                vec![
+                    code("a\n"),
+                    line("text"),
+                    line("b"),
+                    Markdown::Line(vec![]),
+                    code("c\n"),
+                ]
+            ))
+        );
+        assert_eq!(
+            parse_blocks("- a\n    - b\n"),
+            Ok((
+                "",
+                vec![Markdown::UnorderedList(vec![(
+                    vec![MarkdownInline::Plaintext(String::from("a"))],
+                    vec![Markdown::UnorderedList(vec![(
+                        vec![MarkdownInline::Plaintext(String::from("b"))],
+                        vec![]
+                    )])]
+                )])]
+            ))
+        );
+    }
+
+    #[test]
+    fn test_parse_codeblock() {
+        assert_eq!(
+            parse_code_block("```bash\npip install foobar\n```"),
+            Ok(("", (("bash", ""), "pip install foobar\n")))
         );
         assert_eq!(
-            parse_plaintext("`codeblock for bums`"),
-            err!("`codeblock for bums`", ErrorKind::Tag)
+            parse_code_block("```\nimport foobar\n\n```"),
+            Ok(("", (("", ""), "import foobar\n\n")))
         );
         assert_eq!(
-            parse_plaintext("![ but wait theres more](jk)"),
-            err!("![ but wait theres more](jk)", ErrorKind::Tag)
+            parse_code_block("```python\nimport foobar\n\n```"),
+            Ok(("", (("python", ""), "import foobar\n\n")))
         );
         assert_eq!(
-            parse_plaintext("here is plaintext"),
-            Ok(("", String::from("here is plaintext")))
+            parse_code_block("```\npip `install` foobar\n```"),
+            Ok(("", (("", ""), "pip `install` foobar\n")))
         );
         assert_eq!(
-            parse_plaintext("here is plaintext!"),
-            Ok(("", String::from("here is plaintext!")))
+            parse_code_block("~~~\n```\n~~\n  ~~~~ \nafter\n"),
+            Ok(("after\n", (("", ""), "```\n~~\n")))
         );
         assert_eq!(
-            parse_plaintext("here is plaintext![image starting"),
-            Ok(("![image starting", String::from("here is plaintext")))
+            parse_code_block("````md\n```rust\n```\n````\n"),
+            Ok(("", (("md", ""), "```rust\n```\n")))
         );
         assert_eq!(
-            parse_plaintext("here is plaintext\n"),
-            Ok(("\n", String::from("here is plaintext")))
+            parse_code_block("```\nunclosed\n"),
+            Ok(("", (("", ""), "unclosed\n")))
         );
         assert_eq!(
-            parse_plaintext("*here is italic*"),
-            err!("*here is italic*", ErrorKind::Tag)
+            parse_code_block("``\ncode\n``\n"),
+            err!("``\ncode\n``\n", ErrorKind::Verify)
         );
         assert_eq!(
-            parse_plaintext("**here is bold**"),
-            err!("**here is bold**", ErrorKind::Tag)
+            parse_code_block("```c++\nx\n```\n"),
+            Ok(("", (("c++", ""), "x\n")))
         );
         assert_eq!(
-            parse_plaintext("`here is code`"),
-            err!("`here is code`", ErrorKind::Tag)
+            parse_code_block("``` rust,ignore\nx\n```\n"),
+            Ok(("", (("rust", "ignore"), "x\n")))
         );
         assert_eq!(
-            parse_plaintext("[title](https://www.example.com)"),
-            err!("[title](https://www.example.com)", ErrorKind::Tag)
+            parse_code_block("~~~python {linenos=true}\nx\n~~~\n"),
+            Ok(("", (("python", "{linenos=true}"), "x\n")))
         );
         assert_eq!(
-            parse_plaintext("![alt text](image.jpg)"),
-            err!("![alt text](image.jpg)", ErrorKind::Tag)
+            parse_code_block("```a`b\nx\n```\n"),
+            err!("x\n```\n", ErrorKind::Verify)
         );
-        assert_eq!(parse_plaintext(""), err!("", ErrorKind::Tag));
-        assert_eq!(parse_plaintext("\\*\\[\\]"), Ok(("", String::from("*[]"))));
     }
 
     #[test]
-    fn test_parse_markdown_inline() {
-        assert_eq!(
-            parse_markdown_inline("*here is italic*"),
-            Ok(("", MarkdownInline::Italic(String::from("here is italic"))))
-        );
-        assert_eq!(
-            parse_markdown_inline("**here is bold**"),
-            Ok(("", MarkdownInline::Bold(String::from("here is bold"))))
-        );
-        assert_eq!(
-            parse_markdown_inline("`here is code`"),
-            Ok(("", MarkdownInline::InlineCode(String::from("here is code"))))
-        );
+    fn test_parse_table_align() {
+        assert_eq!(parse_table_align("---"), Ok(("", Align::None)));
+        assert_eq!(parse_table_align(" :-- "), Ok(("", Align::Left)));
+        assert_eq!(parse_table_align("--:"), Ok(("", Align::Right)));
+        assert_eq!(parse_table_align(":-:"), Ok(("", Align::Center)));
+        assert_eq!(parse_table_align("::"), err!(":", ErrorKind::TakeWhile1));
+    }
+
+    #[test]
+    fn test_parse_table_row() {
         assert_eq!(
-            parse_markdown_inline("[title](https://www.example.com)"),
+            parse_table_row("| a | **b** |\n"),
             Ok((
                 "",
-                (MarkdownInline::Link(
-                    String::from("title"),
-                    String::from("https://www.example.com")
-                ))
+                vec![
+                    vec![MarkdownInline::Plaintext(String::from("a"))],
+                    vec![MarkdownInline::Bold(vec![MarkdownInline::Plaintext(
+                        String::from("b")
+                    )])],
+                ]
             ))
         );
+        assert_eq!(parse_table_row("||\n"), Ok(("", vec![vec![]])));
         assert_eq!(
-            parse_markdown_inline("![alt text](image.jpg)"),
+            parse_table_row("| a \\| b |\n"),
             Ok((
                 "",
-                (MarkdownInline::Image(String::from("alt text"), String::from("image.jpg")))
+                vec![vec![MarkdownInline::Plaintext(String::from("a | b"))]]
             ))
         );
+        assert_eq!(parse_table_row("| a\n"), err!("\n", ErrorKind::Tag));
+    }
+
+    #[test]
+    fn test_parse_table() {
         assert_eq!(
-            parse_markdown_inline("here is plaintext!"),
+            parse_table("| a | b |\n|:--|--:|\n| 1 | 2 |\n"),
             Ok((
                 "",
-                MarkdownInline::Plaintext(String::from("here is plaintext!"))
+                (
+                    vec![
+                        vec![MarkdownInline::Plaintext(String::from("a"))],
+                        vec![MarkdownInline::Plaintext(String::from("b"))],
+                    ],
+                    vec![Align::Left, Align::Right],
+                    vec![vec![
+                        vec![MarkdownInline::Plaintext(String::from("1"))],
+                        vec![MarkdownInline::Plaintext(String::from("2"))],
+                    ]],
+                )
             ))
         );
         assert_eq!(
-            parse_markdown_inline("here is some plaintext *but what if we italicize?"),
-            Ok((
-                "*but what if we italicize?",
-                MarkdownInline::Plaintext(String::from("here is some plaintext "))
-            ))
+            parse_table("| a | b |\n|---|\n"),
+            err!("| a | b |\n|---|\n", ErrorKind::Verify)
+        );
+        assert_eq!(parse_table("| a | b |\n"), err!("", ErrorKind::Tag));
+    }
+
+    #[test]
+    fn test_parse_comment() {
+        assert_eq!(
+            parse_comment_block("<!-- a\nb --> \nrest"),
+            Ok(("rest", " a\nb "))
         );
         assert_eq!(
-            parse_markdown_inline("here is some plaintext \n*but what if we italicize?"),
+            parse_comment_block("<!-- a --> b\n"),
+            err!("b\n", ErrorKind::CrLf)
+        );
+        assert_eq!(
+            parse_inline_comment("<!-- a\nb -->"),
+            err!("<!-- a\nb -->", ErrorKind::Verify)
+        );
+        assert_eq!(
+            parse_markdown_text("a<!--x-->b\n"),
             Ok((
-                "\n*but what if we italicize?",
-                MarkdownInline::Plaintext(String::from("here is some plaintext "))
+                "",
+                vec![
+                    MarkdownInline::Plaintext(String::from("a")),
+                    MarkdownInline::Comment(String::from("x")),
+                    MarkdownInline::Plaintext(String::from("b")),
+                ]
             ))
         );
-        assert_eq!(parse_markdown_inline("\n"), err!("\n", ErrorKind::Tag));
-        assert_eq!(parse_markdown_inline(""), err!("", ErrorKind::Tag));
     }
 
     #[test]
-    fn test_parse_markdown_text() {
-        assert_eq!(parse_markdown_text("\n"), Ok(("", vec![])));
+    fn test_parse_wikilink() {
         assert_eq!(
-            parse_markdown_text("here is some plaintext\n"),
-            Ok((
-                "",
-                vec![MarkdownInline::Plaintext(String::from(
-                    "here is some plaintext"
-                ))]
-            ))
+            parse_wikilink("[[Page Name]] x"),
+            Ok((" x", ("Page Name", None)))
         );
         assert_eq!(
-            parse_markdown_text("here is some plaintext *but what if we italicize?*\n"),
+            parse_wikilink("[[ Page#Usage | the usage ]]"),
+            Ok(("", ("Page#Usage", Some("the usage"))))
+        );
+        assert_eq!(
+            parse_wikilink("[[ |x]]"),
+            err!("[[ |x]]", ErrorKind::Verify)
+        );
+        assert_eq!(parse_wikilink("[[a]"), err!("]", ErrorKind::Tag));
+    }
+
+    #[test]
+    fn test_parse_emoji() {
+        assert_eq!(parse_emoji(":tada: yay"), Ok((" yay", "tada")));
+        assert_eq!(parse_emoji(":+1:"), Ok(("", "+1")));
+        assert_eq!(parse_emoji(":nope:"), err!(":nope:", ErrorKind::Verify));
+        assert_eq!(parse_emoji(":ta da:"), err!(" da:", ErrorKind::Tag));
+        assert_eq!(
+            parse_markdown_text("at 10:30: :tada:\n"),
             Ok((
                 "",
                 vec![
-                    MarkdownInline::Plaintext(String::from("here is some plaintext ")),
-                    MarkdownInline::Italic(String::from("but what if we italicize?")),
+                    MarkdownInline::Plaintext(String::from("at 10:30: ")),
+                    MarkdownInline::Emoji(String::from("tada")),
                 ]
             ))
         );
+    }
+
+    #[test]
+    fn test_parse_definition_list() {
+        let plain = |s: &str| vec![MarkdownInline::Plaintext(String::from(s))];
         assert_eq!(
-            parse_markdown_text("here is some plaintext *but what if we italicize?* I guess it doesnt **matter** in my `code`\n"),
-            Ok(("", vec![
-                MarkdownInline::Plaintext(String::from("here is some plaintext ")),
-                MarkdownInline::Italic(String::from("but what if we italicize?")),
-                MarkdownInline::Plaintext(String::from(" I guess it doesnt ")),
-                MarkdownInline::Bold(String::from("matter")),
-                MarkdownInline::Plaintext(String::from(" in my ")),
-                MarkdownInline::InlineCode(String::from("code")),
-            ]))
-        );
-        assert_eq!(
-            parse_markdown_text("here is some plaintext *but what if we italicize?*\n"),
+            parse_definition_list("apple\n: a fruit\n: a company\n\npear\n:   a fruit\nrest\n"),
             Ok((
-                "",
+                "rest\n",
                 vec![
-                    MarkdownInline::Plaintext(String::from("here is some plaintext ")),
-                    MarkdownInline::Italic(String::from("but what if we italicize?")),
+                    (plain("apple"), vec![plain("a fruit"), plain("a company")]),
+                    (plain("pear"), vec![plain("a fruit")]),
                 ]
             ))
         );
         assert_eq!(
-            parse_markdown_text("here is some plaintext *but what if we italicize?"),
-            err!("*but what if we italicize?", ErrorKind::Tag)
+            parse_definition_list("term\n:no space\n"),
+            err!("no space\n", ErrorKind::Space)
+        );
+        assert_eq!(
+            parse_definition_list("\n: x\n"),
+            err!("\n: x\n", ErrorKind::Verify)
         );
     }
 
     #[test]
-    fn test_parse_header_tag() {
-        assert_eq!(parse_header_tag("# "), Ok(("", 1)));
-        assert_eq!(parse_header_tag("### "), Ok(("", 3)));
-        assert_eq!(parse_header_tag("# h1"), Ok(("h1", 1)));
-        assert_eq!(parse_header_tag("# h1"), Ok(("h1", 1)));
-        assert_eq!(parse_header_tag(" "), err!(" ", ErrorKind::TakeWhile1));
-        assert_eq!(parse_header_tag("#"), err!("", ErrorKind::Tag));
+    fn test_parse_line() {
+        assert_eq!(parse_line("a b\r\nc"), Ok(("c", "a b")));
+        assert_eq!(parse_line("a b\nc"), Ok(("c", "a b")));
+        assert_eq!(parse_line("a\rb\n"), err!("\rb\n", ErrorKind::CrLf));
+        assert_eq!(
+            parse_blockquote("> a\r\n> b\r\n"),
+            parse_blockquote("> a\n> b\n")
+        );
+        assert_eq!(
+            parse_indented_code_block("    a\r\n\r\n    b\r\n"),
+            Ok(("", String::from("a\n\nb\n")))
+        );
     }
 
     #[test]
-    fn test_parse_header() {
+    fn test_parse_footnotes_marker() {
         assert_eq!(
-            parse_header("# h1\n"),
-            Ok(("", (1, vec![MarkdownInline::Plaintext(String::from("h1"))])))
+            parse_footnotes_marker("[FOOTNOTES] \nrest"),
+            Ok(("rest", "[FOOTNOTES]"))
         );
         assert_eq!(
-            parse_header("## h2\n"),
-            Ok(("", (2, vec![MarkdownInline::Plaintext(String::from("h2"))])))
+            parse_footnotes_marker("[FOOTNOTES] here\n"),
+            err!("here\n", ErrorKind::CrLf)
         );
+    }
+
+    #[test]
+    fn test_parse_footnote_definition() {
         assert_eq!(
-            parse_header("###  h3\n"),
+            parse_footnote_definition("[^1]: the *note*\n"),
             Ok((
                 "",
-                (3, vec![MarkdownInline::Plaintext(String::from(" h3"))])
+                (
+                    "1",
+                    vec![
+                        MarkdownInline::Plaintext(String::from("the ")),
+                        MarkdownInline::Italic(vec![MarkdownInline::Plaintext(String::from(
+                            "note"
+                        ))]),
+                    ]
+                )
             ))
         );
-        assert_eq!(parse_header("###h3"), err!("h3", ErrorKind::Tag));
-        assert_eq!(parse_header("###"), err!("", ErrorKind::Tag));
-        assert_eq!(parse_header(""), err!("", ErrorKind::TakeWhile1));
-        assert_eq!(parse_header("#"), err!("", ErrorKind::Tag));
-        assert_eq!(parse_header("# \n"), Ok(("", (1, vec![]))));
-        assert_eq!(parse_header("# test"), err!("", ErrorKind::Tag));
+        assert_eq!(
+            parse_footnote_definition("[^1] text\n"),
+            err!(" text\n", ErrorKind::Tag)
+        );
     }
 
     #[test]
-    fn test_parse_unordered_list_tag() {
-        assert_eq!(parse_unordered_list_tag("- "), Ok(("", "-")));
+    fn test_parse_bare_url() {
         assert_eq!(
-            parse_unordered_list_tag("- and some more"),
-            Ok(("and some more", "-"))
+            parse_bare_url("https://example.com rest"),
+            Ok((" rest", "https://example.com"))
         );
-        assert_eq!(parse_unordered_list_tag("-"), err!("", ErrorKind::Tag));
         assert_eq!(
-            parse_unordered_list_tag("-and some more"),
-            err!("and some more", ErrorKind::Tag)
+            parse_bare_url("www.example.com/a_(b)."),
+            Ok((".", "www.example.com/a_(b)"))
+        );
+        assert_eq!(
+            parse_bare_url("http://example.com)!"),
+            Ok((")!", "http://example.com"))
+        );
+        assert_eq!(
+            parse_bare_url("https://"),
+            err!("https://", ErrorKind::Verify)
+        );
+        assert_eq!(
+            parse_bare_url("example.com"),
+            err!("example.com", ErrorKind::Tag)
         );
-        assert_eq!(parse_unordered_list_tag("--"), err!("-", ErrorKind::Tag));
-        assert_eq!(parse_unordered_list_tag(""), err!("", ErrorKind::Tag));
     }
 
     #[test]
-    fn test_parse_unordered_list_element() {
+    fn test_parse_autolink() {
         assert_eq!(
-            parse_unordered_list_element("- this is an element\n"),
+            parse_autolink("<https://example.com/?a=b>"),
             Ok((
                 "",
-                vec![MarkdownInline::Plaintext(String::from(
-                    "this is an element"
-                ))]
+                (
+                    String::from("https://example.com/?a=b"),
+                    String::from("https://example.com/?a=b")
+                )
             ))
         );
         assert_eq!(
-            parse_unordered_list_element("- this is an element\n- this is another element\n"),
+            parse_autolink("<foo@example.com>"),
             Ok((
-                "- this is another element\n",
-                vec![MarkdownInline::Plaintext(String::from(
-                    "this is an element"
-                ))]
+                "",
+                (
+                    String::from("foo@example.com"),
+                    String::from("mailto:foo@example.com")
+                )
             ))
         );
-        assert_eq!(parse_unordered_list_element(""), err!("", ErrorKind::Tag));
-        assert_eq!(parse_unordered_list_element("- \n"), Ok(("", vec![])));
-        assert_eq!(parse_unordered_list_element("- "), err!("", ErrorKind::Tag));
+        assert_eq!(parse_autolink("<a b>"), err!(" b>", ErrorKind::Tag));
+    }
+
+    #[test]
+    fn test_parse_inline_html() {
+        assert_eq!(parse_inline_html("<br>"), Ok(("", "<br>")));
         assert_eq!(
-            parse_unordered_list_element("- test"),
-            err!("", ErrorKind::Tag)
+            parse_inline_html("<span class=\"x\">a</span>"),
+            Ok(("a</span>", "<span class=\"x\">"))
         );
-        assert_eq!(parse_unordered_list_element("-"), err!("", ErrorKind::Tag));
+        assert_eq!(parse_inline_html("</span>!"), Ok(("!", "</span>")));
+        assert_eq!(parse_inline_html("< b"), err!(" b", ErrorKind::Alpha));
+        assert_eq!(parse_inline_html("<b\n>"), err!("\n>", ErrorKind::Tag));
     }
 
     #[test]
-    fn test_parse_unordered_list() {
+    fn test_parse_html_block() {
         assert_eq!(
-            parse_unordered_list("- this is an element"),
-            err!("", ErrorKind::Tag)
+            parse_html_block("<div class=\"x\">\n*a*\n</div>\n\nb\n"),
+            Ok(("\nb\n", "<div class=\"x\">\n*a*\n</div>"))
         );
+        assert_eq!(parse_html_block("<DETAILS>\n"), Ok(("", "<DETAILS>")));
         assert_eq!(
-            parse_unordered_list("- this is an element\n"),
+            parse_html_block("<span>a</span>\n"),
+            err!("span>a</span>\n", ErrorKind::Verify)
+        );
+        assert_eq!(
+            parse_html_block("<divx>\n"),
+            err!("divx>\n", ErrorKind::Verify)
+        );
+    }
+
+    #[test]
+    fn test_parse_blockquote_line() {
+        assert_eq!(parse_blockquote_line("> quote\n"), Ok(("", "quote")));
+        assert_eq!(parse_blockquote_line(">quote\n"), Ok(("", "quote")));
+        assert_eq!(parse_blockquote_line(">\n"), Ok(("", "")));
+        assert_eq!(parse_blockquote_line("> quote"), err!("", ErrorKind::CrLf));
+        assert_eq!(
+            parse_blockquote_line("quote\n"),
+            err!("quote\n", ErrorKind::Tag)
+        );
+    }
+
+    #[test]
+    fn test_parse_paragraph_line() {
+        assert_eq!(
+            parse_paragraph_line("a  \n"),
             Ok((
                 "",
-                vec![vec![MarkdownInline::Plaintext(String::from(
-                    "this is an element"
-                ))]]
+                vec![
+                    MarkdownInline::Plaintext(String::from("a")),
+                    MarkdownInline::LineBreak
+                ]
             ))
         );
         assert_eq!(
-            parse_unordered_list("- this is an element\n- here is another\n"),
+            parse_paragraph_line("*a*\\\n"),
             Ok((
                 "",
                 vec![
-                    vec![MarkdownInline::Plaintext(String::from(
-                        "this is an element"
-                    ))],
-                    vec![MarkdownInline::Plaintext(String::from("here is another"))]
+                    MarkdownInline::Italic(vec![MarkdownInline::Plaintext(String::from("a"))]),
+                    MarkdownInline::LineBreak
                 ]
             ))
         );
+        assert_eq!(
+            parse_paragraph_line("a \t\n"),
+            Ok(("", vec![MarkdownInline::Plaintext(String::from("a"))]))
+        );
+        assert_eq!(parse_paragraph_line("   \n"), Ok(("", vec![])));
+        assert_eq!(
+            parse_paragraph_line("  a\n"),
+            Ok(("", vec![MarkdownInline::Plaintext(String::from("a"))]))
+        );
     }
 
     #[test]
-    fn test_parse_ordered_list_tag() {
-        assert_eq!(parse_ordered_list_tag("1. "), Ok(("", "1")));
-        assert_eq!(parse_ordered_list_tag("1234567. "), Ok(("", "1234567")));
+    fn test_parse_attributes() {
         assert_eq!(
-            parse_ordered_list_tag("3. and some more"),
-            Ok(("and some more", "3"))
+            parse_attributes("{#top .wide .dark data-x=1 title=\"a b\"} rest"),
+            Ok((
+                " rest",
+                Attributes {
+                    id: Some(String::from("top")),
+                    classes: vec![String::from("wide"), String::from("dark")],
+                    pairs: vec![
+                        (String::from("data-x"), String::from("1")),
+                        (String::from("title"), String::from("a b")),
+                    ],
+                }
+            ))
         );
-        assert_eq!(parse_ordered_list_tag("1"), err!("", ErrorKind::Tag));
-        assert_eq!(
-            parse_ordered_list_tag("1.and some more"),
-            err!("and some more", ErrorKind::Tag)
+        assert_eq!(parse_attributes("{}"), Ok(("", Attributes::default())));
+        assert_eq!(parse_attributes("{ what }"), err!("what }", ErrorKind::Tag));
+    }
+
+    #[test]
+    fn test_split_attributes() {
+        let plain = |s: &str| MarkdownInline::Plaintext(String::from(s));
+        assert_eq!(
+            split_attributes(vec![plain("Title {#top .wide} ")]),
+            (
+                vec![plain("Title")],
+                Attributes {
+                    id: Some(String::from("top")),
+                    classes: vec![String::from("wide")],
+                    pairs: vec![],
+                }
+            )
+        );
+        assert_eq!(
+            split_attributes(vec![
+                MarkdownInline::InlineCode(String::from("x")),
+                plain(" {#x}")
+            ]),
+            (
+                vec![MarkdownInline::InlineCode(String::from("x"))],
+                Attributes {
+                    id: Some(String::from("x")),
+                    ..Attributes::default()
+                }
+            )
+        );
+        assert_eq!(
+            split_attributes(vec![plain("set {a, b}")]),
+            (vec![plain("set {a, b}")], Attributes::default())
         );
-        assert_eq!(parse_ordered_list_tag("1111."), err!("", ErrorKind::Tag));
-        assert_eq!(parse_ordered_list_tag(""), err!("", ErrorKind::TakeWhile1));
     }
 
     #[test]
-    fn test_parse_ordered_list_element() {
+    fn test_parse_fenced_div() {
         assert_eq!(
-            parse_ordered_list_element("1. this is an element\n"),
+            parse_fenced_div(
+                "::: warning Mind the gap\ntext\n\n:::: tip\ninner\n::::\n:::\nafter\n"
+            ),
             Ok((
-                "",
-                vec![MarkdownInline::Plaintext(String::from(
-                    "this is an element"
-                ))]
+                "after\n",
+                (
+                    "warning Mind the gap",
+                    vec![
+                        Markdown::Line(vec![MarkdownInline::Plaintext(String::from("text"))]),
+                        Markdown::Line(vec![]),
+                        Markdown::Admonition(
+                            String::from("tip"),
+                            None,
+                            vec![Markdown::Line(vec![MarkdownInline::Plaintext(
+                                String::from("inner")
+                            )])]
+                        ),
+                    ]
+                )
             ))
         );
         assert_eq!(
-            parse_ordered_list_element("1. this is an element\n1. here is another\n"),
+            parse_fenced_div("::: note\n:::\n"),
+            Ok(("", ("note", vec![])))
+        );
+        assert_eq!(
+            parse_blocks("::: {.cols}\n::: {#left}\nx\n:::\n:::\n"),
             Ok((
-                "1. here is another\n",
-                vec![MarkdownInline::Plaintext(String::from(
-                    "this is an element"
-                ))]
+                "",
+                vec![Markdown::Div(
+                    Attributes {
+                        classes: vec![String::from("cols")],
+                        ..Attributes::default()
+                    },
+                    vec![Markdown::Div(
+                        Attributes {
+                            id: Some(String::from("left")),
+                            ..Attributes::default()
+                        },
+                        vec![Markdown::Line(vec![MarkdownInline::Plaintext(
+                            String::from("x")
+                        )])]
+                    )]
+                )]
             ))
         );
         assert_eq!(
-            parse_ordered_list_element(""),
-            err!("", ErrorKind::TakeWhile1)
+            parse_fenced_div("::: note\nunclosed\n"),
+            err!("::: note\nunclosed\n", ErrorKind::Verify)
         );
         assert_eq!(
-            parse_ordered_list_element(""),
-            err!("", ErrorKind::TakeWhile1)
+            parse_fenced_div(":::\nx\n:::\n"),
+            err!("\nx\n:::\n", ErrorKind::Verify)
         );
-        assert_eq!(parse_ordered_list_element("1. \n"), Ok(("", vec![])));
+    }
+
+    #[test]
+    fn test_parse_details() {
         assert_eq!(
-            parse_ordered_list_element("1. test"),
-            err!("", ErrorKind::Tag)
+            parse_details("??? *Spoiler*\n???+ inner\nx\n???\n???\nafter\n"),
+            Ok((
+                "after\n",
+                (
+                    (false, "*Spoiler*"),
+                    vec![Markdown::Details(
+                        vec![MarkdownInline::Plaintext(String::from("inner"))],
+                        true,
+                        vec![Markdown::Line(vec![MarkdownInline::Plaintext(
+                            String::from("x")
+                        )])]
+                    )]
+                )
+            ))
+        );
+        assert_eq!(
+            parse_details("???\nx\n???\n"),
+            err!("\nx\n???\n", ErrorKind::Space)
+        );
+        assert_eq!(
+            parse_details("??? open\nunclosed\n"),
+            err!("??? open\nunclosed\n", ErrorKind::Verify)
         );
-        assert_eq!(parse_ordered_list_element("1. "), err!("", ErrorKind::Tag));
-        assert_eq!(parse_ordered_list_element("1."), err!("", ErrorKind::Tag));
     }
 
     #[test]
-    fn test_parse_ordered_list() {
+    fn test_parse_blockquote() {
         assert_eq!(
-            parse_ordered_list("1. this is an element\n"),
+            parse_blockquote("> a\n> b\nc\n"),
             Ok((
-                "",
-                vec![vec![MarkdownInline::Plaintext(String::from(
-                    "this is an element"
-                ))]]
+                "c\n",
+                vec![
+                    Markdown::Line(vec![MarkdownInline::Plaintext(String::from("a"))]),
+                    Markdown::Line(vec![MarkdownInline::Plaintext(String::from("b"))]),
+                ]
             ))
         );
-        assert_eq!(parse_ordered_list("1. test"), err!("", ErrorKind::Tag));
         assert_eq!(
-            parse_ordered_list("1. this is an element\n2. here is another\n"),
+            parse_blockquote("> # h1\n>> nested\n"),
             Ok((
                 "",
                 vec![
-                    vec!(MarkdownInline::Plaintext(String::from(
-                        "this is an element"
-                    ))),
-                    vec![MarkdownInline::Plaintext(String::from("here is another"))]
+                    Markdown::Heading(
+                        1,
+                        vec![MarkdownInline::Plaintext(String::from("h1"))],
+                        Attributes::default()
+                    ),
+                    Markdown::Blockquote(vec![Markdown::Line(vec![MarkdownInline::Plaintext(
+                        String::from("nested")
+                    )])]),
                 ]
             ))
         );
+        assert_eq!(parse_blockquote("quote\n"), err!("quote\n", ErrorKind::Tag));
     }
 
     #[test]
-    fn test_parse_codeblock() {
+    fn test_parse_front_matter() {
+        let mut expected = BTreeMap::new();
+        expected.insert(String::from("title"), String::from("Hello: World"));
+        expected.insert(String::from("author"), String::from("cympfh"));
+        expected.insert(String::from("tags"), String::new());
         assert_eq!(
-            parse_code_block("```bash\npip install foobar\n```"),
-            Ok(("", ("bash", "pip install foobar\n")))
+            parse_front_matter(
+                "---\ntitle: \"Hello: World\"\nauthor: 'cympfh'\n# comment\ntags:\n  - a\n---\nbody\n"
+            ),
+            Ok(("body\n", expected))
         );
+        assert_eq!(parse_front_matter("---\n---\n"), Ok(("", BTreeMap::new())));
         assert_eq!(
-            parse_code_block("```\nimport foobar\n\n```"),
-            Ok(("", ("", "import foobar\n\n")))
+            parse_front_matter("---\ntitle: x\n"),
+            err!("", ErrorKind::Tag)
         );
+    }
+
+    #[test]
+    fn test_parse_markdown_front_matter() {
+        let mut front_matter = BTreeMap::new();
+        front_matter.insert(String::from("title"), String::from("x"));
         assert_eq!(
-            parse_code_block("```python\nimport foobar\n\n```"),
-            Ok(("", ("python", "import foobar\n\n")))
+            parse_markdown("---\ntitle: x\n---\n---\n"),
+            Ok((
+                "",
+                vec![
+                    Markdown::FrontMatter(front_matter.clone()),
+                    Markdown::HorizontalRule
+                ]
+            ))
         );
         assert_eq!(
-            parse_code_block("```\npip `install` foobar\n```"),
-            Ok(("", ("", "pip `install` foobar\n")))
+            parse_markdown("---\ntitle: x\n---\n"),
+            Ok(("", vec![Markdown::FrontMatter(front_matter)]))
+        );
+        assert_eq!(
+            parse_markdown("---\n"),
+            Ok(("", vec![Markdown::HorizontalRule]))
         );
     }
 
@@ -659,20 +3589,19 @@ mod tests {
         assert_eq!(
             parse_markdown("# Foobar\n\nFoobar is a Python library for dealing with word pluralization.\n\n```bash\n pip install foobar\n```\n## Installation\n\nUse the package manager [pip](https://pip.pypa.io/en/stable/) to install foobar.\n```python\nimport foobar\n\nfoobar.pluralize('word') # returns 'words'\nfoobar.pluralize('goose') # returns 'geese'\nfoobar.singularize('phenomena') # returns 'phenomenon'\n```"),
             Ok(("", vec![
-                Markdown::Heading(1, vec![MarkdownInline::Plaintext(String::from("Foobar"))]),
+                Markdown::Heading(1, vec![MarkdownInline::Plaintext(String::from("Foobar"))], Attributes::default()),
                 Markdown::Line(vec![]),
                 Markdown::Line(vec![MarkdownInline::Plaintext(String::from("Foobar is a Python library for dealing with word pluralization."))]),
                 Markdown::Line(vec![]),
-                Markdown::Codeblock(String::from("bash"), String::from(" pip install foobar\n")),
-                Markdown::Line(vec![]),
-                Markdown::Heading(2, vec![MarkdownInline::Plaintext(String::from("Installation"))]),
+                Markdown::Codeblock(String::from("bash"), String::from(" pip install foobar\n"), String::new()),
+                Markdown::Heading(2, vec![MarkdownInline::Plaintext(String::from("Installation"))], Attributes::default()),
                 Markdown::Line(vec![]),
                 Markdown::Line(vec![
                     MarkdownInline::Plaintext(String::from("Use the package manager ")),
-                    MarkdownInline::Link(String::from("pip"), String::from("https://pip.pypa.io/en/stable/")),
+                    MarkdownInline::Link(vec![MarkdownInline::Plaintext(String::from("pip"))], String::from("https://pip.pypa.io/en/stable/"), None),
                     MarkdownInline::Plaintext(String::from(" to install foobar.")),
                 ]),
-                Markdown::Codeblock(String::from("python"), String::from("import foobar\n\nfoobar.pluralize('word') # returns 'words'\nfoobar.pluralize('goose') # returns 'geese'\nfoobar.singularize('phenomena') # returns 'phenomenon'\n")),
+                Markdown::Codeblock(String::from("python"), String::from("import foobar\n\nfoobar.pluralize('word') # returns 'words'\nfoobar.pluralize('goose') # returns 'geese'\nfoobar.singularize('phenomena') # returns 'phenomenon'\n"), String::new()),
             ]))
         )
     }
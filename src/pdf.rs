@@ -0,0 +1,126 @@
+use crate::entity::Markdown;
+use crate::translator::{self, latex};
+
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+
+// how the PDF is typeset
+#[derive(Clone, Debug)]
+pub struct Options {
+    // the TeX engine, such as `pdflatex`, `xelatex` or `lualatex`
+    pub engine: String,
+    // the paper of the `geometry` package without `paper`, such as `a4` or `letter`
+    pub paper: String,
+    // the margins of the pages, such as `2cm` or `1in`
+    pub margin: String,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Options {
+            engine: String::from("pdflatex"),
+            paper: String::from("a4"),
+            margin: String::from("2cm"),
+        }
+    }
+}
+
+// the standalone LaTeX of `--to latex` on the paper of the options
+pub fn source(md: Vec<Markdown>, options: &translator::Options, pdf: &Options) -> String {
+    let options = translator::Options {
+        standalone: true,
+        ..options.clone()
+    };
+    latex::translate(md, &options).replacen(
+        "\\begin{document}",
+        &format!(
+            "\\usepackage[{}paper,margin={}]{{geometry}}\n\\begin{{document}}",
+            pdf.paper, pdf.margin
+        ),
+        1,
+    )
+}
+
+// the PDF of the document, typeset by the engine of the options in a temporary directory.
+// the engine is run in the `base_dir` of the options, which the local images are read from
+pub fn pdf(
+    md: Vec<Markdown>,
+    options: &translator::Options,
+    pdf: &Options,
+) -> Result<Vec<u8>, String> {
+    let dir = std::env::temp_dir().join(format!("prose-pdf-{}", std::process::id()));
+    fs::create_dir_all(&dir).map_err(|err| format!("Cannot create {}: {}", dir.display(), err))?;
+    let typeset = typeset(
+        &source(md, options, pdf),
+        &dir,
+        options.base_dir.as_deref(),
+        pdf,
+    );
+    let _ = fs::remove_dir_all(&dir);
+    typeset
+}
+
+fn typeset(
+    source: &str,
+    dir: &Path,
+    base_dir: Option<&Path>,
+    pdf: &Options,
+) -> Result<Vec<u8>, String> {
+    let tex = dir.join("prose.tex");
+    fs::write(&tex, source).map_err(|err| format!("Cannot write {}: {}", tex.display(), err))?;
+    let mut command = Command::new(&pdf.engine);
+    command
+        .arg("-interaction=nonstopmode")
+        .arg("-halt-on-error")
+        .arg(format!("-output-directory={}", dir.display()))
+        .arg(&tex);
+    if let Some(base_dir) = base_dir.filter(|base_dir| !base_dir.as_os_str().is_empty()) {
+        command.current_dir(base_dir);
+    }
+    let output = command
+        .output()
+        .map_err(|err| format!("Cannot run the PDF engine {}: {}", pdf.engine, err))?;
+    if !output.status.success() {
+        // TeX tells what is wrong in a line starting with `!`
+        let log = String::from_utf8_lossy(&output.stdout);
+        let error = log
+            .lines()
+            .find(|line| line.starts_with('!'))
+            .map(|line| format!(": {}", line))
+            .unwrap_or_default();
+        return Err(format!(
+            "The PDF engine {} failed: {}{}",
+            pdf.engine, output.status, error
+        ));
+    }
+    let path = dir.join("prose.pdf");
+    fs::read(&path).map_err(|err| format!("Cannot read {}: {}", path.display(), err))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pdf() {
+        let md = crate::parse("# A\n\ntext\n").unwrap();
+        let options = Options {
+            paper: String::from("letter"),
+            margin: String::from("1in"),
+            ..Options::default()
+        };
+        let source = source(md.clone(), &translator::Options::default(), &options);
+        assert!(source.starts_with("\\documentclass{article}\n"));
+        assert!(
+            source.contains("\\usepackage[letterpaper,margin=1in]{geometry}\n\\begin{document}\n")
+        );
+        let options = Options {
+            engine: String::from("prose-no-such-engine"),
+            ..Options::default()
+        };
+        assert!(pdf(md, &translator::Options::default(), &options)
+            .unwrap_err()
+            .starts_with("Cannot run the PDF engine prose-no-such-engine"));
+    }
+}
@@ -0,0 +1,404 @@
+use crate::entity::{ListItem, Markdown};
+use crate::json;
+use crate::parser::Syntax;
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::path::Path;
+use wasmtime::{
+    Config, Engine, Instance, Memory, Module, Store, StoreLimits, StoreLimitsBuilder, Trap,
+    TypedFunc,
+};
+
+// a WebAssembly module which extends prose. it exports its `memory` and
+// `alloc(len: i32) -> i32`, which gives prose the place to write its input to,
+// and any of these, each of which returns where its output is as `ptr << 32 | len`:
+//
+// - `languages() -> i64`: the languages of the code blocks it renders, separated by commas
+// - `render(ptr: i32, len: i32) -> i64`: the html of such a code block, given as `lang\ncode`
+// - `inlines() -> i64` and `blocks() -> i64`: the prefixes of its own syntax, separated by spaces
+// - `inline(ptr: i32, len: i32) -> i64`: given the rest of a line which starts with a prefix,
+//   the number of bytes it reads and their html, as `len\nhtml`, or an empty string
+// - `block(ptr: i32, len: i32) -> i64`: the same with the rest of the document at a line
+//   which starts with a prefix, where the block runs to the end of the last line read
+// - `transform(ptr: i32, len: i32) -> i64`: the AST as the JSON of `--to json`, changed
+//
+// the strings are UTF-8 both ways.
+// every call runs on at most `FUEL` instructions, roughly, and the memory is at most `MEMORY`,
+// so a plugin which loops forever or grows without end fails rather than hangs prose
+pub struct Plugin {
+    name: String,
+    store: Store<StoreLimits>,
+    instance: Instance,
+    memory: Memory,
+    alloc: TypedFunc<i32, i32>,
+    languages: Vec<String>,
+    inlines: Vec<String>,
+    blocks: Vec<String>,
+    fuel: u64,
+}
+
+const FUEL: u64 = 1 << 30;
+const MEMORY: usize = 64 << 20;
+
+thread_local! {
+    // the modules compiled on this thread by their bytes,
+    // as the plugins of a document are loaded to parse it and again to transform it
+    static MODULES: RefCell<HashMap<Vec<u8>, Module>> = RefCell::new(HashMap::new());
+}
+
+impl Plugin {
+    pub fn load(path: &Path) -> Result<Plugin, String> {
+        let wasm = std::fs::read(path)
+            .map_err(|err| format!("Cannot read the plugin {}: {}", path.display(), err))?;
+        Plugin::new(&path.display().to_string(), &wasm)
+    }
+
+    // the module in the binary format, or in the text format
+    pub fn new(name: &str, wasm: &[u8]) -> Result<Plugin, String> {
+        let error = |err: wasmtime::Error| format!("Cannot load the plugin {}: {}", name, err);
+        let module = match MODULES.with(|modules| modules.borrow().get(wasm).cloned()) {
+            Some(module) => module,
+            None => {
+                let engine = Engine::new(Config::new().consume_fuel(true)).map_err(error)?;
+                let module = Module::new(&engine, wasm).map_err(error)?;
+                MODULES.with(|modules| modules.borrow_mut().insert(wasm.to_vec(), module.clone()));
+                module
+            }
+        };
+        let limits = StoreLimitsBuilder::new().memory_size(MEMORY).build();
+        let mut store = Store::new(module.engine(), limits);
+        store.limiter(|limits| limits);
+        // for the start function
+        store.set_fuel(FUEL).map_err(error)?;
+        let instance = Instance::new(&mut store, &module, &[]).map_err(error)?;
+        let memory = instance
+            .get_memory(&mut store, "memory")
+            .ok_or_else(|| format!("The plugin {} does not export its memory", name))?;
+        let alloc = instance
+            .get_typed_func(&mut store, "alloc")
+            .map_err(error)?;
+        let mut plugin = Plugin {
+            name: name.to_string(),
+            store,
+            instance,
+            memory,
+            alloc,
+            languages: vec![],
+            inlines: vec![],
+            blocks: vec![],
+            fuel: FUEL,
+        };
+        if let Some(languages) = plugin.call("languages", None)? {
+            plugin.languages = languages
+                .split(',')
+                .map(|lang| lang.trim().to_string())
+                .filter(|lang| !lang.is_empty())
+                .collect();
+        }
+        let prefixes = |list: Option<String>| -> Vec<String> {
+            list.iter()
+                .flat_map(|list| list.split_whitespace())
+                .map(String::from)
+                .collect()
+        };
+        plugin.inlines = prefixes(plugin.call("inlines", None)?);
+        plugin.blocks = prefixes(plugin.call("blocks", None)?);
+        Ok(plugin)
+    }
+
+    pub fn languages(&self) -> &[String] {
+        &self.languages
+    }
+
+    // the code blocks of its languages rendered, and then the document transformed
+    pub fn apply(&mut self, md: Vec<Markdown>) -> Result<Vec<Markdown>, String> {
+        let md = if self.languages.is_empty() {
+            md
+        } else {
+            self.render_blocks(md)?
+        };
+        match self.call("transform", Some(&json::to_json(&md)))? {
+            Some(output) => json::from_json(&output).map_err(|err| {
+                format!(
+                    "The plugin {} returned an invalid JSON AST: {}",
+                    self.name, err
+                )
+            }),
+            None => Ok(md),
+        }
+    }
+
+    fn render_blocks(&mut self, md: Vec<Markdown>) -> Result<Vec<Markdown>, String> {
+        md.into_iter()
+            .map(|block| self.render_block(block))
+            .collect()
+    }
+
+    fn render_list(&mut self, items: Vec<ListItem>) -> Result<Vec<ListItem>, String> {
+        items
+            .into_iter()
+            .map(|(line, children)| Ok((line, self.render_blocks(children)?)))
+            .collect()
+    }
+
+    fn render_block(&mut self, block: Markdown) -> Result<Markdown, String> {
+        Ok(match block {
+            Markdown::Codeblock(lang, code, _) if self.languages.contains(&lang) => {
+                let input = format!("{}\n{}", lang, code);
+                match self.call("render", Some(&input))? {
+                    Some(html) => Markdown::Html(html),
+                    None => {
+                        return Err(format!(
+                            "The plugin {} renders {} but does not export render",
+                            self.name, lang
+                        ))
+                    }
+                }
+            }
+            Markdown::Blockquote(inner) => Markdown::Blockquote(self.render_blocks(inner)?),
+            Markdown::Admonition(kind, title, inner) => {
+                Markdown::Admonition(kind, title, self.render_blocks(inner)?)
+            }
+            Markdown::Div(attributes, inner) => {
+                Markdown::Div(attributes, self.render_blocks(inner)?)
+            }
+            Markdown::Details(summary, open, inner) => {
+                Markdown::Details(summary, open, self.render_blocks(inner)?)
+            }
+            Markdown::UnorderedList(items) => Markdown::UnorderedList(self.render_list(items)?),
+            Markdown::OrderedList(start, items) => {
+                Markdown::OrderedList(start, self.render_list(items)?)
+            }
+            block => block,
+        })
+    }
+
+    // what `inline` or `block` reads of the text
+    fn read(&mut self, export: &str, text: &str) -> Result<Option<(usize, String)>, String> {
+        let output = self.call(export, Some(text))?.ok_or_else(|| {
+            format!(
+                "The plugin {} has prefixes for {} but does not export it",
+                self.name, export
+            )
+        })?;
+        if output.is_empty() {
+            return Ok(None);
+        }
+        let (len, html) = output.split_once('\n').unwrap_or((&output, ""));
+        match len.parse::<usize>() {
+            Ok(len) if len > 0 && text.is_char_boundary(len) => Ok(Some((len, html.to_string()))),
+            _ => Err(format!(
+                "The plugin {} returned an invalid {}: {}",
+                self.name, export, len
+            )),
+        }
+    }
+
+    // the output of the export, or none when the plugin does not have it.
+    // the lists such as `languages` are the ones which take no input
+    fn call(&mut self, export: &str, input: Option<&str>) -> Result<Option<String>, String> {
+        let name = self.name.clone();
+        let error = |err: wasmtime::Error| match err.downcast_ref::<Trap>() {
+            Some(Trap::OutOfFuel) => format!("The plugin {} ran out of fuel in {}", name, export),
+            _ => format!("The plugin {} failed in {}: {}", name, export, err),
+        };
+        if self.instance.get_func(&mut self.store, export).is_none() {
+            return Ok(None);
+        }
+        self.store.set_fuel(self.fuel).map_err(error)?;
+        let packed = match input {
+            Some(input) => {
+                let f: TypedFunc<(i32, i32), i64> = self
+                    .instance
+                    .get_typed_func(&mut self.store, export)
+                    .map_err(error)?;
+                let len = i32::try_from(input.len())
+                    .map_err(|_| format!("The input of the plugin {} is too long", name))?;
+                let ptr = self.alloc.call(&mut self.store, len).map_err(error)?;
+                self.memory
+                    .write(&mut self.store, ptr as u32 as usize, input.as_bytes())
+                    .map_err(|err| error(err.into()))?;
+                f.call(&mut self.store, (ptr, len)).map_err(error)?
+            }
+            None => {
+                let f: TypedFunc<(), i64> = self
+                    .instance
+                    .get_typed_func(&mut self.store, export)
+                    .map_err(error)?;
+                f.call(&mut self.store, ()).map_err(error)?
+            }
+        };
+        let (ptr, len) = ((packed as u64 >> 32) as usize, packed as u32 as usize);
+        let bytes = self
+            .memory
+            .data(&self.store)
+            .get(ptr..ptr + len)
+            .ok_or_else(|| format!("The plugin {} returned {} out of its memory", name, export))?;
+        String::from_utf8(bytes.to_vec())
+            .map(Some)
+            .map_err(|_| format!("The plugin {} returned invalid UTF-8 in {}", name, export))
+    }
+}
+
+impl Syntax for Plugin {
+    fn inline_prefixes(&self) -> Vec<String> {
+        self.inlines.clone()
+    }
+
+    fn block_prefixes(&self) -> Vec<String> {
+        self.blocks.clone()
+    }
+
+    fn inline(&mut self, text: &str) -> Result<Option<(usize, String)>, String> {
+        self.read("inline", text)
+    }
+
+    fn block(&mut self, text: &str) -> Result<Option<(usize, String)>, String> {
+        self.read("block", text)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::entity::MarkdownInline;
+
+    // renders `shout` code blocks as `<b>!</b>`, and transforms the AST into itself
+    const PLUGIN: &str = r#"
+(module
+  (memory (export "memory") 1)
+  (global $next (mut i32) (i32.const 1024))
+  (data (i32.const 0) "shout, ")
+  (data (i32.const 16) "<b>!</b>")
+  (func (export "alloc") (param $len i32) (result i32)
+    (local $ptr i32)
+    (local.set $ptr (global.get $next))
+    (global.set $next (i32.add (global.get $next) (local.get $len)))
+    (local.get $ptr))
+  (func (export "languages") (result i64)
+    (i64.const 7))
+  (func (export "render") (param i32 i32) (result i64)
+    (i64.or (i64.shl (i64.const 16) (i64.const 32)) (i64.const 8)))
+  (func (export "transform") (param $ptr i32) (param $len i32) (result i64)
+    (i64.or
+      (i64.shl (i64.extend_i32_u (local.get $ptr)) (i64.const 32))
+      (i64.extend_i32_u (local.get $len)))))
+"#;
+
+    #[test]
+    fn test_plugin() {
+        let mut plugin = Plugin::new("test", PLUGIN.as_bytes()).unwrap();
+        assert_eq!(plugin.languages(), ["shout"]);
+        let md = crate::parse("# a\n\n> ```shout\n> hey\n> ```\n\n```rust\nx\n```\n").unwrap();
+        let mut expected = md.clone();
+        expected[1] = Markdown::Blockquote(vec![Markdown::Html(String::from("<b>!</b>"))]);
+        assert_eq!(plugin.apply(md), Ok(expected));
+    }
+
+    // reads `@@` as `<i>@</i>`, and a line of `%%%` as a rule
+    const SYNTAX: &str = r#"
+(module
+  (memory (export "memory") 1)
+  (data (i32.const 0) "@@")
+  (data (i32.const 16) "%%%")
+  (data (i32.const 32) "2\0a<i>@</i>")
+  (data (i32.const 64) "3\0a<hr class=\22x\22>")
+  (func (export "alloc") (param i32) (result i32) (i32.const 1024))
+  (func (export "inlines") (result i64) (i64.const 2))
+  (func (export "blocks") (result i64) (i64.const 0x1000000003))
+  (func (export "inline") (param i32 i32) (result i64) (i64.const 0x200000000a))
+  (func (export "block") (param i32 i32) (result i64) (i64.const 0x4000000010)))
+"#;
+
+    #[test]
+    fn test_plugin_syntax() {
+        use std::rc::Rc;
+        let plugin = Plugin::new("syntax", SYNTAX.as_bytes()).unwrap();
+        let md = crate::parser::with_syntax(vec![Rc::new(RefCell::new(plugin))], || {
+            crate::parse("a@@b\n%%% c\n\n> %%%\n").unwrap()
+        });
+        assert_eq!(
+            md,
+            Ok(vec![
+                Markdown::Paragraph(vec![vec![
+                    MarkdownInline::Plaintext(String::from("a")),
+                    MarkdownInline::Html(String::from("<i>@</i>")),
+                    MarkdownInline::Plaintext(String::from("b")),
+                ]]),
+                Markdown::Html(String::from("<hr class=\"x\">")),
+                Markdown::Blockquote(vec![Markdown::Html(String::from("<hr class=\"x\">"))]),
+            ])
+        );
+        // without the plugin, they are text
+        assert_eq!(
+            crate::parse("a@@b\n").unwrap(),
+            vec![Markdown::Paragraph(vec![vec![MarkdownInline::Plaintext(
+                String::from("a@@b")
+            )]])]
+        );
+    }
+
+    #[test]
+    fn test_plugin_errors() {
+        let trap = r#"(module (memory (export "memory") 1)
+            (func (export "alloc") (param i32) (result i32) (i32.const 0))
+            (func (export "transform") (param i32 i32) (result i64) unreachable))"#;
+        let mut plugin = Plugin::new("trap", trap.as_bytes()).unwrap();
+        assert!(plugin
+            .apply(vec![])
+            .unwrap_err()
+            .starts_with("The plugin trap failed in transform"));
+        let out_of_memory = r#"(module (memory (export "memory") 1)
+            (func (export "alloc") (param i32) (result i32) (i32.const 0))
+            (func (export "transform") (param i32 i32) (result i64) (i64.const -1)))"#;
+        let mut plugin = Plugin::new("oom", out_of_memory.as_bytes()).unwrap();
+        assert_eq!(
+            plugin.apply(vec![]),
+            Err(String::from(
+                "The plugin oom returned transform out of its memory"
+            ))
+        );
+        let looping = r#"(module (memory (export "memory") 1)
+            (func (export "alloc") (param i32) (result i32) (i32.const 0))
+            (func (export "transform") (param i32 i32) (result i64) (loop (br 0)) (i64.const 0)))"#;
+        let mut plugin = Plugin::new("loop", looping.as_bytes()).unwrap();
+        plugin.fuel = 1 << 20;
+        assert_eq!(
+            plugin.apply(vec![]),
+            Err(String::from("The plugin loop ran out of fuel in transform"))
+        );
+        // the start function runs on the fuel too
+        let start = r#"(module (memory (export "memory") 1) (func $f (loop (br 0))) (start $f))"#;
+        assert!(Plugin::new("start", start.as_bytes())
+            .err()
+            .unwrap()
+            .starts_with("Cannot load the plugin start"));
+        // the memory is limited
+        let big = r#"(module (memory (export "memory") 2048)
+            (func (export "alloc") (param i32) (result i32) (i32.const 0)))"#;
+        assert!(Plugin::new("big", big.as_bytes())
+            .err()
+            .unwrap()
+            .starts_with("Cannot load the plugin big"));
+        let invalid = r#"(module (memory (export "memory") 1)
+            (data (i32.const 0) "@ x")
+            (func (export "alloc") (param i32) (result i32) (i32.const 16))
+            (func (export "inlines") (result i64) (i64.const 1))
+            (func (export "inline") (param i32 i32) (result i64) (i64.const 3)))"#;
+        let plugin = Plugin::new("invalid", invalid.as_bytes()).unwrap();
+        assert_eq!(
+            crate::parser::with_syntax(vec![std::rc::Rc::new(RefCell::new(plugin))], || {
+                crate::parse("a @\n")
+            })
+            .err(),
+            Some(String::from(
+                "The plugin invalid returned an invalid inline: @ x"
+            ))
+        );
+        assert_eq!(
+            Plugin::new("bare", b"(module)").err(),
+            Some(String::from("The plugin bare does not export its memory"))
+        );
+    }
+}
@@ -0,0 +1,202 @@
+// the block elements, which start a line of their own. sorted for the binary search
+const BLOCKS: [&str; 39] = [
+    "address",
+    "article",
+    "aside",
+    "blockquote",
+    "caption",
+    "dd",
+    "details",
+    "div",
+    "dl",
+    "dt",
+    "fieldset",
+    "figcaption",
+    "figure",
+    "footer",
+    "form",
+    "h1",
+    "h2",
+    "h3",
+    "h4",
+    "h5",
+    "h6",
+    "header",
+    "hr",
+    "li",
+    "main",
+    "nav",
+    "ol",
+    "p",
+    "pre",
+    "section",
+    "summary",
+    "table",
+    "tbody",
+    "td",
+    "tfoot",
+    "th",
+    "thead",
+    "tr",
+    "ul",
+];
+
+// the elements whose content is written as it is, up to their closing tag
+const VERBATIM: [&str; 4] = ["pre", "script", "style", "textarea"];
+
+const INDENT: &str = "  ";
+
+// what was written last, which decides where the text after it goes
+#[derive(PartialEq)]
+enum Last {
+    Open,
+    Close,
+    Inline,
+}
+
+// the html with every block element on a line of its own, indented by how deep it is.
+// the text and the inline elements are kept on the line of their block,
+// and the content of `<pre>` and the like is not touched
+pub fn pretty(html: &str) -> String {
+    let mut out = String::with_capacity(html.len() + html.len() / 4);
+    // whether each open block has a block inside it, which puts its closing tag on a line of its own
+    let mut open: Vec<bool> = vec![];
+    let mut last = Last::Open;
+    let mut rest = html;
+    while !rest.is_empty() {
+        let k = rest.find('<').unwrap_or(rest.len());
+        let text = &rest[..k];
+        if !text.is_empty() {
+            rest = &rest[k..];
+            // the whitespace between blocks is replaced with the newlines and the indents
+            if last != Last::Inline && text.trim().is_empty() {
+                continue;
+            }
+            if last == Last::Close {
+                newline(&mut out, open.len());
+                out.push_str(text.trim_start());
+            } else {
+                out.push_str(text);
+            }
+            last = Last::Inline;
+            continue;
+        }
+        if let Some(comment) = rest.strip_prefix("<!--") {
+            let end = comment.find("-->").map_or(rest.len(), |n| n + 7);
+            out.push_str(&rest[..end]);
+            rest = &rest[end..];
+            last = Last::Inline;
+            continue;
+        }
+        let end = tag_end(rest);
+        let (tag, after) = rest.split_at(end);
+        rest = after;
+        let closing = tag.starts_with("</");
+        let name = tag_name(tag);
+        let block = BLOCKS.binary_search(&name.as_str()).is_ok();
+        if block && closing {
+            if open.pop().unwrap_or(false) {
+                newline(&mut out, open.len());
+            }
+            out.push_str(tag);
+            last = Last::Close;
+            continue;
+        }
+        if block {
+            if let Some(parent) = open.last_mut() {
+                *parent = true;
+            }
+            if !out.is_empty() {
+                newline(&mut out, open.len());
+            }
+        }
+        out.push_str(tag);
+        last = if block { Last::Open } else { Last::Inline };
+        if closing || tag.ends_with("/>") {
+            if block {
+                last = Last::Close;
+            }
+        } else if name == "hr" {
+            last = Last::Close;
+        } else {
+            if VERBATIM.contains(&name.as_str()) {
+                let end = closing_tag(rest, &name);
+                out.push_str(&rest[..end]);
+                rest = &rest[end..];
+            }
+            if block {
+                open.push(false);
+            }
+        }
+    }
+    out
+}
+
+fn newline(out: &mut String, depth: usize) {
+    out.push('\n');
+    for _ in 0..depth {
+        out.push_str(INDENT);
+    }
+}
+
+// the length of the tag at the start of `i`, up to its `>` outside the quotes
+fn tag_end(i: &str) -> usize {
+    let mut quote = None;
+    for (k, c) in i.char_indices() {
+        match (quote, c) {
+            (None, '"' | '\'') => quote = Some(c),
+            (Some(q), _) if q == c => quote = None,
+            (None, '>') => return k + 1,
+            _ => {}
+        }
+    }
+    i.len()
+}
+
+// the name of `<name ...>` or `</name>` in lowercase, empty for `<!DOCTYPE>` and the like
+fn tag_name(tag: &str) -> String {
+    let name = tag.trim_start_matches(['<', '/']);
+    let end = name
+        .find(|c: char| !c.is_ascii_alphanumeric())
+        .unwrap_or(name.len());
+    name[..end].to_ascii_lowercase()
+}
+
+// where the closing tag of `name` starts, or the end of `i` without one
+fn closing_tag(i: &str, name: &str) -> usize {
+    i.match_indices("</")
+        .map(|(k, _)| k)
+        .find(|k| tag_name(&i[*k..]) == name)
+        .unwrap_or(i.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_blocks() {
+        assert!(BLOCKS.windows(2).all(|w| w[0] < w[1]));
+    }
+
+    #[test]
+    fn test_pretty() {
+        assert_eq!(
+            pretty("<h1 id=\"a\">A <b>b</b></h1><p>x<br />y</p><hr />"),
+            "<h1 id=\"a\">A <b>b</b></h1>\n<p>x<br />y</p>\n<hr />"
+        );
+        assert_eq!(
+            pretty("<ul><li>a<ul><li>b</li></ul>c</li><li><p>d</p></li></ul>"),
+            "<ul>\n  <li>a\n    <ul>\n      <li>b</li>\n    </ul>\n    c\n  </li>\n  <li>\n    <p>d</p>\n  </li>\n</ul>"
+        );
+        assert_eq!(
+            pretty("<blockquote>\n<p>a</p>\n</blockquote>\n<pre><code>x\n  <p>y</p>\n</code></pre><p>z</p>"),
+            "<blockquote>\n  <p>a</p>\n</blockquote>\n<pre><code>x\n  <p>y</p>\n</code></pre>\n<p>z</p>"
+        );
+        assert_eq!(
+            pretty("<div title=\"a>b\"><!-- <p> --><p>a</p></div>"),
+            "<div title=\"a>b\"><!-- <p> -->\n  <p>a</p>\n</div>"
+        );
+        assert_eq!(pretty("a</p></div>b<p"), "a</p></div>\nb\n<p");
+    }
+}
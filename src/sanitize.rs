@@ -0,0 +1,476 @@
+use crate::charref;
+use crate::url;
+
+// the elements kept in the raw html, sorted for the binary search.
+// the tags of any other element are dropped, but the text inside them is kept
+const ELEMENTS: [&str; 59] = [
+    "a",
+    "abbr",
+    "b",
+    "bdi",
+    "bdo",
+    "blockquote",
+    "br",
+    "caption",
+    "center",
+    "cite",
+    "code",
+    "col",
+    "colgroup",
+    "dd",
+    "del",
+    "details",
+    "dfn",
+    "div",
+    "dl",
+    "dt",
+    "em",
+    "figcaption",
+    "figure",
+    "h1",
+    "h2",
+    "h3",
+    "h4",
+    "h5",
+    "h6",
+    "hr",
+    "i",
+    "img",
+    "ins",
+    "kbd",
+    "li",
+    "mark",
+    "ol",
+    "p",
+    "pre",
+    "q",
+    "rp",
+    "rt",
+    "ruby",
+    "s",
+    "samp",
+    "small",
+    "span",
+    "strong",
+    "sub",
+    "summary",
+    "sup",
+    "table",
+    "tbody",
+    "td",
+    "tfoot",
+    "th",
+    "thead",
+    "tr",
+    "u",
+];
+
+// the elements which are dropped together with everything up to their closing tag,
+// which can be in a later piece of the html with `sanitize_pieces`
+const DROPPED: [&str; 10] = [
+    "iframe", "noembed", "noframes", "noscript", "object", "script", "style", "template",
+    "textarea", "xmp",
+];
+
+// the elements without content, which are closed as `<br />` in XML
+const VOID: [&str; 14] = [
+    "area", "base", "br", "col", "embed", "hr", "img", "input", "link", "meta", "param", "source",
+    "track", "wbr",
+];
+
+// the attributes kept on any element, along with `data-*`. sorted for the binary search
+const ATTRIBUTES: [&str; 22] = [
+    "abbr", "align", "alt", "cite", "class", "colspan", "datetime", "dir", "headers", "height",
+    "href", "hreflang", "id", "lang", "open", "reversed", "rowspan", "scope", "span", "src",
+    "start", "title",
+];
+
+// the attributes whose value is a url, which is kept only when it is safe
+const URLS: [&str; 3] = ["cite", "href", "src"];
+
+// the schemes a url may have; the ones without a scheme are relative to the page
+const SCHEMES: [&str; 3] = ["http", "https", "mailto"];
+
+// whether following the url cannot run a script, as `javascript:` does
+pub fn is_safe_url(url: &str) -> bool {
+    match url::scheme(url) {
+        Some(scheme) => SCHEMES.contains(&scheme.as_str()),
+        None => true,
+    }
+}
+
+// whether the attribute can be kept: not an event handler such as `onclick` nor `style`,
+// and not a url which could run a script
+pub fn is_safe_attribute(name: &str, value: &str) -> bool {
+    let name = name.to_ascii_lowercase();
+    let allowed = name.starts_with("data-") || ATTRIBUTES.binary_search(&name.as_str()).is_ok();
+    allowed && (!URLS.contains(&name.as_str()) || is_safe_url(value))
+}
+
+// the raw html with only the elements and the attributes of the allowlists,
+// where the values of the attributes are escaped again.
+// comments and anything which is not a complete tag are dropped or escaped
+pub fn sanitize(html: &str) -> String {
+    sanitize_pieces(html, &mut None)
+}
+
+// `sanitize` of a piece of the html, such as one raw html node of a line, where `dropped`
+// is the element of `DROPPED` left open by the pieces before, whose content is still dropped
+pub fn sanitize_pieces(html: &str, dropped: &mut Option<String>) -> String {
+    let mut out = String::with_capacity(html.len());
+    let mut rest = html;
+    if let Some(name) = dropped.take() {
+        match skip_element(rest, &name) {
+            Some(after) => rest = after,
+            None => {
+                *dropped = Some(name);
+                return out;
+            }
+        }
+    }
+    while let Some(k) = rest.find('<') {
+        out.push_str(&rest[..k]);
+        rest = &rest[k..];
+        if let Some(comment) = rest.strip_prefix("<!--") {
+            rest = comment.find("-->").map_or("", |k| &comment[k + 3..]);
+        } else if rest.starts_with("<!") || rest.starts_with("<?") {
+            rest = rest.find('>').map_or("", |k| &rest[k + 1..]);
+        } else if let Some((tag, after)) = parse_tag(rest) {
+            rest = after;
+            if DROPPED.contains(&tag.name.as_str()) {
+                if !tag.closing {
+                    rest = skip_element(rest, &tag.name).unwrap_or_else(|| {
+                        *dropped = Some(tag.name.clone());
+                        ""
+                    });
+                }
+            } else if ELEMENTS.binary_search(&tag.name.as_str()).is_ok() {
+                write_tag(&mut out, &tag);
+            }
+        } else {
+            out.push_str("&lt;");
+            rest = &rest[1..];
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+// the html written again as well-formed XML, for XHTML: the void elements closed,
+// the other elements closed in order, the stray closing tags dropped, the attributes quoted,
+// and the text escaped with the character references decoded, which XML does not know
+pub fn xhtml(html: &str) -> String {
+    let mut out = String::with_capacity(html.len());
+    let mut open: Vec<String> = vec![];
+    let mut rest = html;
+    while let Some(k) = rest.find('<') {
+        escape_into(&mut out, &decode(&rest[..k]));
+        rest = &rest[k..];
+        if let Some(comment) = rest.strip_prefix("<!--") {
+            let end = comment.find("-->").unwrap_or(comment.len());
+            if !comment[..end].contains("--") && !comment[..end].ends_with('-') {
+                out.push_str(&rest[..end + 4]);
+                out.push_str("-->");
+            }
+            rest = comment.get(end + 3..).unwrap_or("");
+        } else if rest.starts_with("<!") || rest.starts_with("<?") {
+            rest = rest.find('>').map_or("", |k| &rest[k + 1..]);
+        } else if let Some((mut tag, after)) = parse_tag(rest) {
+            rest = after;
+            let void = VOID.contains(&tag.name.as_str());
+            if tag.closing {
+                if let Some(k) = open.iter().rposition(|name| *name == tag.name) {
+                    for name in open.drain(k..).rev() {
+                        out.push_str(&format!("</{}>", name));
+                    }
+                }
+                continue;
+            }
+            let mut names = vec![];
+            tag.attributes.retain(|(name, _)| {
+                let valid = name.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+                    && name
+                        .chars()
+                        .all(|c| c.is_ascii_alphanumeric() || "-_.".contains(c))
+                    || name == "xml:lang"
+                    || name == "epub:type";
+                valid && !names.contains(name) && {
+                    names.push(name.clone());
+                    true
+                }
+            });
+            out.push('<');
+            out.push_str(&tag.name);
+            for (name, value) in &tag.attributes {
+                out.push_str(&format!(" {}=\"", name));
+                escape_into(&mut out, value.as_deref().unwrap_or(name));
+                out.push('"');
+            }
+            if void || tag.self_closing {
+                out.push_str(" />");
+            } else {
+                out.push('>');
+                open.push(tag.name);
+            }
+        } else {
+            out.push_str("&lt;");
+            rest = &rest[1..];
+        }
+    }
+    escape_into(&mut out, &decode(rest));
+    for name in open.into_iter().rev() {
+        out.push_str(&format!("</{}>", name));
+    }
+    out
+}
+
+#[derive(Debug)]
+struct Tag {
+    // in lowercase
+    name: String,
+    closing: bool,
+    // the values decoded, and `None` for the ones without `=`
+    attributes: Vec<(String, Option<String>)>,
+    self_closing: bool,
+}
+
+// `<name attr="value" ...>` or `</name>` at the start of `i`, and the rest after it.
+// a `/` between the attributes is taken as a space, as browsers do
+fn parse_tag(i: &str) -> Option<(Tag, &str)> {
+    let rest = i.strip_prefix('<')?;
+    let (closing, rest) = match rest.strip_prefix('/') {
+        Some(rest) => (true, rest),
+        None => (false, rest),
+    };
+    if !rest.starts_with(|c: char| c.is_ascii_alphabetic()) {
+        return None;
+    }
+    let end = rest
+        .find(|c: char| !c.is_ascii_alphanumeric() && c != '-')
+        .unwrap_or(rest.len());
+    let mut tag = Tag {
+        name: rest[..end].to_ascii_lowercase(),
+        closing,
+        attributes: vec![],
+        self_closing: false,
+    };
+    let mut rest = &rest[end..];
+    loop {
+        rest = rest.trim_start_matches(|c: char| c.is_ascii_whitespace());
+        if let Some(after) = rest.strip_prefix("/>") {
+            tag.self_closing = true;
+            return Some((tag, after));
+        } else if let Some(after) = rest.strip_prefix('>') {
+            return Some((tag, after));
+        } else if let Some(after) = rest.strip_prefix('/') {
+            rest = after;
+            continue;
+        }
+        let end = rest
+            .find(|c: char| c.is_ascii_whitespace() || "/>=\"'<".contains(c))
+            .unwrap_or(rest.len());
+        if end == 0 {
+            return None;
+        }
+        let name = rest[..end].to_ascii_lowercase();
+        rest = rest[end..].trim_start_matches(|c: char| c.is_ascii_whitespace());
+        let value = match rest.strip_prefix('=') {
+            Some(after) => {
+                let after = after.trim_start_matches(|c: char| c.is_ascii_whitespace());
+                let (value, after) = match after.chars().next() {
+                    Some(quote @ ('"' | '\'')) => {
+                        let end = after[1..].find(quote)? + 1;
+                        (&after[1..end], &after[end + 1..])
+                    }
+                    _ => {
+                        let end = after
+                            .find(|c: char| c.is_ascii_whitespace() || c == '>')
+                            .unwrap_or(after.len());
+                        (&after[..end], &after[end..])
+                    }
+                };
+                rest = after;
+                Some(decode(value))
+            }
+            None => None,
+        };
+        tag.attributes.push((name, value));
+    }
+}
+
+// the html after the closing tag of `name`, or none without one
+fn skip_element<'a>(rest: &'a str, name: &str) -> Option<&'a str> {
+    for (k, _) in rest.match_indices("</") {
+        let after = &rest[k + 2..];
+        let closes = after
+            .get(..name.len())
+            .is_some_and(|tag| tag.eq_ignore_ascii_case(name))
+            && after[name.len()..]
+                .starts_with(|c: char| c.is_ascii_whitespace() || c == '>' || c == '/');
+        if closes {
+            return Some(after.find('>').map_or("", |k| &after[k + 1..]));
+        }
+    }
+    None
+}
+
+fn write_tag(out: &mut String, tag: &Tag) {
+    out.push('<');
+    if tag.closing {
+        out.push('/');
+    }
+    out.push_str(&tag.name);
+    if !tag.closing {
+        for (name, value) in &tag.attributes {
+            if !is_safe_attribute(name, value.as_deref().unwrap_or("")) {
+                continue;
+            }
+            out.push(' ');
+            out.push_str(name);
+            if let Some(value) = value {
+                out.push_str("=\"");
+                escape_into(out, value);
+                out.push('"');
+            }
+        }
+    }
+    if tag.self_closing {
+        out.push_str(" /");
+    }
+    out.push('>');
+}
+
+fn escape_into(out: &mut String, text: &str) {
+    for c in text.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&#39;"),
+            c => out.push(c),
+        }
+    }
+}
+
+// the character references such as `&colon;` or `&#58;` decoded.
+// the ones which are not known are kept as they are, and escaped when written again
+pub(crate) fn decode(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(k) = rest.find('&') {
+        out.push_str(&rest[..k]);
+        rest = &rest[k + 1..];
+        // no reference is longer than this
+        let reference = rest
+            .char_indices()
+            .take(32)
+            .find(|(_, c)| *c == ';')
+            .map(|(end, _)| (&rest[..end], end));
+        let decoded = reference.and_then(|(name, end)| {
+            let c = match name.strip_prefix('#') {
+                Some(digits) => charref::numeric(digits)?.to_string(),
+                None => charref::lookup(name)?.to_string(),
+            };
+            Some((c, end))
+        });
+        match decoded {
+            Some((c, end)) => {
+                out.push_str(&c);
+                rest = &rest[end + 1..];
+            }
+            None => out.push('&'),
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_allowlists() {
+        assert!(ELEMENTS.windows(2).all(|w| w[0] < w[1]));
+        assert!(ATTRIBUTES.windows(2).all(|w| w[0] < w[1]));
+    }
+
+    #[test]
+    fn test_xhtml() {
+        assert_eq!(
+            xhtml("<div>x<br>y&nbsp;&amp; z"),
+            "<div>x<br />y\u{a0}&amp; z</div>"
+        );
+        assert_eq!(
+            xhtml("<p><b>a</p></i><img src=a.png alt>"),
+            "<p><b>a</b></p><img src=\"a.png\" alt=\"alt\" />"
+        );
+        assert_eq!(
+            xhtml("<details open @click=x id=a id=b><!-- a -- b --><!-- c -->"),
+            "<details open=\"open\" id=\"a\"><!-- c --></details>"
+        );
+        assert_eq!(xhtml("1 < 2 & <!DOCTYPE html>"), "1 &lt; 2 &amp; ");
+    }
+
+    #[test]
+    fn test_is_safe_url() {
+        assert!(is_safe_url("https://example.com/a?b#c"));
+        assert!(is_safe_url("mailto:a@b.c"));
+        assert!(is_safe_url("../page.html"));
+        assert!(is_safe_url("/a:b"));
+        assert!(is_safe_url("#top"));
+        assert!(!is_safe_url("javascript:alert(1)"));
+        assert!(!is_safe_url(" JavaScript:alert(1)"));
+        assert!(!is_safe_url("java\tscript:alert(1)"));
+        assert!(!is_safe_url("javascript&#58;alert(1)"));
+        assert!(!is_safe_url("data:text/html,<script>"));
+        assert!(!is_safe_url("vbscript:x"));
+    }
+
+    #[test]
+    fn test_sanitize() {
+        assert_eq!(
+            sanitize("<p class=\"x\" onclick=\"alert(1)\">hi</p>"),
+            "<p class=\"x\">hi</p>"
+        );
+        assert_eq!(sanitize("a<script>alert('<b>')</script>b"), "ab");
+        assert_eq!(sanitize("<SCRIPT SRC=x></SCRIPT >c"), "c");
+        assert_eq!(sanitize("<style>p {}"), "");
+        assert_eq!(sanitize("<script>"), "");
+        assert_eq!(sanitize("</script>"), "");
+        assert_eq!(
+            sanitize("<a href=\"javascript:alert(1)\" title=x>a</a>"),
+            "<a title=\"x\">a</a>"
+        );
+        assert_eq!(
+            sanitize("<a href='https://a.com/?a=1&amp;b=2'>a</a>"),
+            "<a href=\"https://a.com/?a=1&amp;b=2\">a</a>"
+        );
+        assert_eq!(
+            sanitize("<img/src=\"x.png\"/onerror=alert(1) />"),
+            "<img src=\"x.png\" />"
+        );
+        assert_eq!(
+            sanitize("<form action=x><input value=\"\"></form>text"),
+            "text"
+        );
+        assert_eq!(
+            sanitize("<details open data-id=\"1\"><summary>s</summary></details>"),
+            "<details open data-id=\"1\"><summary>s</summary></details>"
+        );
+        assert_eq!(sanitize("a <!-- <script> --> b"), "a  b");
+        let mut dropped = None;
+        assert_eq!(sanitize_pieces("a<script>x", &mut dropped), "a");
+        assert_eq!(dropped.as_deref(), Some("script"));
+        assert_eq!(sanitize_pieces("y</b>", &mut dropped), "");
+        assert_eq!(sanitize_pieces("</script><b>", &mut dropped), "<b>");
+        assert_eq!(dropped, None);
+        assert_eq!(sanitize("<!DOCTYPE html><?php x ?>a"), "a");
+        assert_eq!(sanitize("1 < 2 <a href=\"x"), "1 &lt; 2 &lt;a href=\"x");
+        assert_eq!(
+            sanitize("<p title=\"&quot;>\">"),
+            "<p title=\"&quot;&gt;\">"
+        );
+    }
+}
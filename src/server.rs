@@ -0,0 +1,133 @@
+use crate::json::Json;
+use crate::lsp::{self, error_reply, object, string};
+
+use std::io::{self, BufRead, Write};
+
+// a long-running process for the editor plugins and the build tools which render many small
+// documents, without starting prose for each. the messages are framed as those of `prose lsp`,
+// each after a `Content-Length` header, and are JSON-RPC:
+//
+//     {"jsonrpc": "2.0", "id": 1, "method": "render", "params": {"text": "# Hello"}}
+//
+// is answered with `{"jsonrpc": "2.0", "id": 1, "result": "<h1 id=\"hello\">Hello</h1>"}`.
+// `version` gives the version of prose, and `exit` or the end of the input stops the server
+pub fn serve<R, W, F>(mut input: R, mut output: W, mut render: F) -> io::Result<()>
+where
+    R: BufRead,
+    W: Write,
+    F: FnMut(&str) -> Result<String, String>,
+{
+    while let Some(message) = lsp::read_message(&mut input)? {
+        let request = match message.and_then(|message| lsp::parse_message(&message)) {
+            Ok(request) => request,
+            Err(reply) => {
+                lsp::write_message(&mut output, &reply)?;
+                continue;
+            }
+        };
+        if let Some(Json::String(method)) = request.get("method") {
+            if method == "exit" {
+                break;
+            }
+        }
+        if let Some(reply) = respond(&request, &mut render) {
+            lsp::write_message(&mut output, &reply)?;
+        }
+    }
+    Ok(())
+}
+
+// the reply to a request, or none for a notification, which has no id
+pub fn respond<F>(request: &Json, render: &mut F) -> Option<Json>
+where
+    F: FnMut(&str) -> Result<String, String>,
+{
+    let id = request.get("id")?.clone();
+    let method = match request.get("method") {
+        Some(Json::String(method)) => method.as_str(),
+        _ => return Some(error_reply(id, -32600, "no method in the request")),
+    };
+    let result = match method {
+        "render" => match request.get("params").and_then(|params| params.get("text")) {
+            Some(Json::String(text)) => match render(text) {
+                Ok(output) => Json::String(output),
+                Err(err) => return Some(error_reply(id, -32000, &err)),
+            },
+            _ => return Some(error_reply(id, -32602, "no params.text in the request")),
+        },
+        "version" => string(env!("CARGO_PKG_VERSION")),
+        _ => {
+            let message = format!("unknown method {}", method);
+            return Some(error_reply(id, -32601, &message));
+        }
+    };
+    Some(object(vec![
+        ("jsonrpc", string("2.0")),
+        ("id", id),
+        ("result", result),
+    ]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn render(text: &str) -> Result<String, String> {
+        let (markdown, warnings) = crate::parse_with_warnings(text);
+        match warnings.first() {
+            Some(warning) => Err(warning.to_string()),
+            None => Ok(crate::to_html(&markdown)),
+        }
+    }
+
+    #[test]
+    fn test_serve() {
+        let body = |s: &str| format!("Content-Length: {}\r\n\r\n{}", s.len(), s);
+        let input = [
+            body(r##"{"jsonrpc":"2.0","id":1,"method":"render","params":{"text":"# Hello"}}"##),
+            body(r#"{"jsonrpc":"2.0","method":"render","params":{"text":"a"}}"#),
+            body(r#"{"jsonrpc":"2.0","id":"b","method":"render","params":{}}"#),
+            body(r#"{"jsonrpc":"2.0","id":2,"method":"nope"}"#),
+            body("{"),
+            body(r#"{"jsonrpc":"2.0","method":"exit"}"#),
+            body(r#"{"jsonrpc":"2.0","id":3,"method":"version"}"#),
+        ]
+        .concat();
+        let mut output = vec![];
+        serve(input.as_bytes(), &mut output, render).unwrap();
+        let expected = [
+            body(r#"{"jsonrpc":"2.0","id":1,"result":"<h1 id=\"hello\">Hello</h1>"}"#),
+            body(r#"{"jsonrpc":"2.0","id":"b","error":{"code":-32602,"message":"no params.text in the request"}}"#),
+            body(r#"{"jsonrpc":"2.0","id":2,"error":{"code":-32601,"message":"unknown method nope"}}"#),
+        ]
+        .concat();
+        let output = String::from_utf8(output).unwrap();
+        assert!(output.starts_with(&expected));
+        assert!(output[expected.len()..].contains(r#""id":null,"error":{"code":-32700"#));
+        assert!(!output.contains(r#""id":3"#));
+    }
+
+    #[test]
+    fn test_serve_untrusted() {
+        let body = |s: &str| format!("Content-Length: {}\r\n\r\n{}", s.len(), s);
+        let deep = format!("{}{}", "[".repeat(100_000), "]".repeat(100_000));
+        let input = [
+            body(&deep),
+            body(r#"{"jsonrpc":"2.0","id":1,"method":"version"}"#),
+            String::from("Content-Length: 999999999999999\r\n\r\n{}"),
+        ]
+        .concat();
+        let mut output = vec![];
+        serve(input.as_bytes(), &mut output, render).unwrap();
+        let output = String::from_utf8(output).unwrap();
+        let replies: Vec<&str> = output.split("Content-Length: ").skip(1).collect();
+        assert_eq!(replies.len(), 3);
+        assert!(
+            replies[0].contains(r#""error":{"code":-32700,"message":"recursion limit exceeded"#)
+        );
+        assert!(replies[1].contains(r#""id":1,"result""#));
+        assert!(
+            replies[2].contains(r#""error":{"code":-32600,"message":"the message is longer than"#)
+        );
+    }
+}
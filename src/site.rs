@@ -0,0 +1,674 @@
+use crate::build::{self, Output, Summary};
+use crate::cache::{self, Cache, Entry};
+use crate::entity::{Markdown, Span};
+use crate::json::{self, Json};
+use crate::template;
+use crate::translator::{self, plain_text, translate_title};
+
+use rayon::prelude::*;
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+// the templates used when the site has none of its own
+const PAGE: &str = r#"<!DOCTYPE html>
+<html>
+<head>
+<meta charset="utf-8">
+<meta name="viewport" content="width=device-width, initial-scale=1">
+<title>{{ title }}</title>
+{{ meta }}</head>
+<body>
+<main>
+{{ body }}
+</main>
+{{ nav }}
+</body>
+</html>"#;
+
+const INDEX: &str = r#"<!DOCTYPE html>
+<html>
+<head>
+<meta charset="utf-8">
+<meta name="viewport" content="width=device-width, initial-scale=1">
+<title>{{ title }}</title>
+</head>
+<body>
+<main>
+<h1>{{ title }}</h1>
+{{ pages }}
+</main>
+</body>
+</html>"#;
+
+// the placeholders of a template by their names
+type Context = BTreeMap<String, String>;
+
+// a page after the filters and the substitutions, with the options of its html
+pub type Document = (Vec<Markdown>, Vec<Span>, translator::Options);
+
+// the options of a site besides those of its pages
+#[derive(Clone, Debug, Default)]
+pub struct Options {
+    // for the index page and `{{ site_title }}`
+    pub title: String,
+    // stands for the options of the pages, so that the cache of other options is not used
+    pub key: String,
+    // write `search-index.json`, the pages for a search on the client such as lunr
+    pub search_index: bool,
+    // the url the site is served at, for `sitemap.xml` and the Atom feed `feed.xml`,
+    // which are written only with it. it ends with `/`, as `url::directory` gives
+    pub base_url: Option<String>,
+}
+
+// the placeholders of a source and its entry of the search index,
+// with the hash of its content and of the files read while rendering it
+struct Source {
+    context: Context,
+    search: Json,
+    hash: u64,
+    reads: BTreeMap<String, u64>,
+}
+
+// a converted page, with its url relative to the root of the site
+struct Page {
+    source: PathBuf,
+    url: String,
+    context: Context,
+    search: Json,
+}
+
+// builds a site from the `.md` files under `dir` into `out_dir`, as `build::build` does,
+// but each page is put in a template: `_templates/<name>.html` for `template: <name>`
+// in its front matter, or `_templates/page.html`, or a plain page when there is none.
+// `convert` is given the path of each file with its content, and returns the document,
+// whose html is the placeholder `body` of its template, with `title` and the fields of
+// its front matter. the pages are in the order of the `date`
+// of their front matter, after the ones without a date in the order of their paths,
+// and each is given
+// - `nav`, the links to the pages before and after it, also as `prev_url`, `prev_title`,
+//   `next_url` and `next_title`
+// - `pages`, the list of the pages with their dates
+// - `root`, the relative url of the root of the site, such as `../`, and `site_title`.
+// `index.md` is the index page, or else one listing the pages is written with
+// `_templates/index.html`. the other files are copied as they are, and the entries starting
+// with `_`, as `_templates`, are neither.
+// `search-index.json` has the url, the title, the headings and the text of each page,
+// and with a base url, `sitemap.xml` lists the pages and `feed.xml` the ones with a date.
+// the placeholders of the pages are kept in `out_dir/.prose-cache` with the hashes of their
+// sources, for the next build with the same `key` to reuse, and a file which would be written
+// as it is already is left alone
+pub fn build<F>(
+    dir: &Path,
+    out_dir: &Path,
+    options: &Options,
+    convert: &F,
+) -> Result<Summary, String>
+where
+    F: Fn(&Path, &str) -> Result<Document, String> + Sync,
+{
+    let title = options.title.as_str();
+    let (sources, assets): (Vec<PathBuf>, Vec<PathBuf>) = build::sources(dir, out_dir)?
+        .into_iter()
+        .filter(|path| {
+            !path
+                .strip_prefix(dir)
+                .unwrap_or(path)
+                .iter()
+                .any(|name| name.to_str().is_some_and(|name| name.starts_with('_')))
+        })
+        .partition(|path| path.extension().and_then(|ext| ext.to_str()) == Some("md"));
+    let mut cache = Cache::load(out_dir, &format!("site\n{}", options.key));
+    let converted: Vec<(PathBuf, Result<Source, String>)> = sources
+        .into_par_iter()
+        .map(|path| {
+            let result = fs::read_to_string(&path)
+                .map_err(|err| format!("Cannot read {}: {}", path.display(), err))
+                .and_then(|content| {
+                    let hash = cache::hash(&[content.as_bytes()]);
+                    let cached =
+                        cache
+                            .get(&cache::source_key(dir, &path), hash)
+                            .and_then(|entry| {
+                                let reads = entry.reads.clone();
+                                Some(Source {
+                                    reads,
+                                    ..from_json(&entry.data, hash)?
+                                })
+                            });
+                    match cached {
+                        Some(source) => Ok(source),
+                        None => {
+                            let (converted, reads) = cache::recording(|| {
+                                convert(&path, &content).map(|document| source(document, hash))
+                            });
+                            converted.map(|source| Source { reads, ..source })
+                        }
+                    }
+                });
+            (path, result)
+        })
+        .collect();
+    let mut summary = Summary::default();
+    let mut pages = vec![];
+    let mut index = None;
+    for (source, result) in converted {
+        let (context, search) = match result {
+            Ok(page) => {
+                let data = to_json(&page);
+                let (hash, reads) = (page.hash, page.reads);
+                cache.insert(cache::source_key(dir, &source), Entry { hash, data, reads });
+                (page.context, page.search)
+            }
+            Err(err) => {
+                summary.failed.push((source, err));
+                continue;
+            }
+        };
+        let url = url(source.strip_prefix(dir).unwrap_or(&source));
+        let page = Page {
+            source,
+            url,
+            context,
+            search,
+        };
+        if page.url == "index.html" {
+            index = Some(page);
+        } else {
+            pages.push(page);
+        }
+    }
+    pages.sort_by(|a, b| (date(a), &a.url).cmp(&(date(b), &b.url)));
+
+    let templates_dir = dir.join("_templates");
+    let mut templates = HashMap::new();
+    // whether the page is written, or was already
+    let mut write = |page: &Page, name: &str, context: &Context| {
+        let target = out_dir.join(&page.url);
+        let result = template(&mut templates, &templates_dir, name).and_then(|template| {
+            let output = format!("{}\n", template::render(template, context)?);
+            write_if_changed(&target, output.as_bytes())
+        });
+        match result {
+            Ok(changed) => {
+                summary.record(target, changed);
+                true
+            }
+            Err(err) => {
+                summary.failed.push((page.source.clone(), err));
+                false
+            }
+        }
+    };
+    // the pages written, or already there, for the search index, the sitemap and the feed
+    let mut done = vec![];
+    for (k, page) in pages.iter().enumerate() {
+        let root = "../".repeat(page.url.matches('/').count());
+        let prev = k.checked_sub(1).map(|k| &pages[k]);
+        let next = pages.get(k + 1);
+        let mut context = page.context.clone();
+        context.insert(String::from("nav"), nav(prev, next, &root));
+        for (name, other) in [("prev", prev), ("next", next)] {
+            if let Some(other) = other {
+                let url = format!("{}{}", root, other.url);
+                context.insert(format!("{}_url", name), escape(&url));
+                context.insert(format!("{}_title", name), title_of(other).to_string());
+            }
+        }
+        context.insert(String::from("pages"), list(&pages, &root));
+        context.insert(String::from("root"), escape(&root));
+        context.insert(String::from("site_title"), escape(title));
+        let name = page.context.get("template").map_or("page", String::as_str);
+        if write(page, name, &context) {
+            done.push(page);
+        }
+    }
+    // the index page is searched only when it is written in `index.md`
+    let (page, name) = match index {
+        Some(page) => {
+            let name = page.context.get("template").cloned();
+            (page, name.unwrap_or_else(|| String::from("page")))
+        }
+        None => {
+            let mut context = Context::new();
+            context.insert(String::from("title"), escape(title));
+            let page = Page {
+                source: dir.join("index.md"),
+                url: String::from("index.html"),
+                context,
+                search: Json::Null,
+            };
+            (page, String::from("index"))
+        }
+    };
+    let mut context = page.context.clone();
+    context.insert(String::from("pages"), list(&pages, ""));
+    context.entry(String::from("nav")).or_default();
+    context.entry(String::from("root")).or_default();
+    context.insert(String::from("site_title"), escape(title));
+    if write(&page, &name, &context) {
+        done.insert(0, &page);
+    }
+    let mut files = vec![];
+    if options.search_index {
+        let searched = done
+            .iter()
+            .filter(|page| page.search != Json::Null)
+            .map(|page| search_entry(page))
+            .collect();
+        files.push(("search-index.json", json::write(&Json::Array(searched))));
+    }
+    if let Some(base_url) = &options.base_url {
+        files.push(("sitemap.xml", sitemap(&done, base_url)));
+        if let Some(feed) = feed(&done, base_url, title) {
+            files.push(("feed.xml", feed));
+        }
+    }
+    for (name, content) in files {
+        let target = out_dir.join(name);
+        match write_if_changed(&target, format!("{}\n", content).as_bytes()) {
+            Ok(changed) => summary.record(target, changed),
+            Err(err) => summary.failed.push((dir.to_path_buf(), err)),
+        }
+    }
+
+    let copied: Vec<(PathBuf, Result<Output, String>)> = assets
+        .into_par_iter()
+        .map(|path| {
+            let target = out_dir.join(path.strip_prefix(dir).unwrap_or(&path));
+            let result = fs::read(&path)
+                .map_err(|err| format!("Cannot read {}: {}", path.display(), err))
+                .and_then(|content| {
+                    let hash = cache::hash(&[&content]);
+                    let source = cache::source_key(dir, &path);
+                    if cache.get(&source, hash).is_some() && target.exists() {
+                        return Ok(Output {
+                            target,
+                            hash,
+                            reads: BTreeMap::new(),
+                            reused: true,
+                        });
+                    }
+                    build::write(&target, &content).map(|_| Output {
+                        target,
+                        hash,
+                        reads: BTreeMap::new(),
+                        reused: false,
+                    })
+                });
+            (path, result)
+        })
+        .collect();
+    for (path, result) in copied {
+        match result {
+            Ok(output) => {
+                let (hash, data, reads) = (output.hash, Json::Null, output.reads);
+                cache.insert(cache::source_key(dir, &path), Entry { hash, data, reads });
+                summary.record(output.target, !output.reused);
+            }
+            Err(err) => summary.failed.push((path, err)),
+        }
+    }
+    cache.save()?;
+    Ok(summary)
+}
+
+// the template of the name, read once
+fn template<'a>(
+    templates: &'a mut HashMap<String, String>,
+    dir: &Path,
+    name: &str,
+) -> Result<&'a str, String> {
+    if !name
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+    {
+        return Err(format!("Invalid template name {}", name));
+    }
+    if !templates.contains_key(name) {
+        let path = dir.join(format!("{}.html", name));
+        let template = match fs::read_to_string(&path) {
+            Ok(template) => template,
+            Err(err) if err.kind() == io::ErrorKind::NotFound && name == "page" => PAGE.to_string(),
+            Err(err) if err.kind() == io::ErrorKind::NotFound && name == "index" => {
+                INDEX.to_string()
+            }
+            Err(err) => {
+                return Err(format!(
+                    "Cannot read the template {}: {}",
+                    path.display(),
+                    err
+                ))
+            }
+        };
+        templates.insert(name.to_string(), template);
+    }
+    Ok(&templates[name])
+}
+
+// the placeholders of the page, and its entry of the search index from the text of
+// the document rather than its html
+fn source((markdown, spans, options): Document, hash: u64) -> Source {
+    let mut headings = vec![];
+    let mut words = vec![];
+    for block in &markdown {
+        block.walk(&mut |block| {
+            if let Markdown::Heading(_, text, _) = block {
+                headings.push(Json::String(plain_text(text)));
+            }
+            for text in block.texts() {
+                words.extend(plain_text(text).split_whitespace().map(String::from));
+            }
+        });
+    }
+    let title = translate_title(&markdown).unwrap_or_default();
+    let body = words.join(" ");
+    let search = Json::Object(
+        [
+            (String::from("title"), Json::String(title)),
+            (String::from("headings"), Json::Array(headings)),
+            (String::from("body"), Json::String(body)),
+        ]
+        .into_iter()
+        .collect(),
+    );
+    let context = translator::translate_context_with_spans(markdown, &spans, &options);
+    Source {
+        context,
+        search,
+        hash,
+        reads: BTreeMap::new(),
+    }
+}
+
+// every page, with the date of its front matter as the time it was modified
+fn sitemap(pages: &[&Page], base_url: &str) -> String {
+    let mut xml = String::from(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+         <urlset xmlns=\"http://www.sitemaps.org/schemas/sitemap/0.9\">\n",
+    );
+    for page in pages {
+        let url = escape(&format!("{}{}", base_url, page.url));
+        xml.push_str(&format!("<url><loc>{}</loc>", url));
+        if let Some(date) = page.context.get("date") {
+            xml.push_str(&format!("<lastmod>{}</lastmod>", date));
+        }
+        xml.push_str("</url>\n");
+    }
+    xml.push_str("</urlset>");
+    xml
+}
+
+// the pages with a date, the newest first, or none when no page has a date
+fn feed(pages: &[&Page], base_url: &str, title: &str) -> Option<String> {
+    let mut dated: Vec<(&Page, &String)> = pages
+        .iter()
+        .filter_map(|page| page.context.get("date").map(|date| (*page, date)))
+        .collect();
+    dated.sort_by(|(a, x), (b, y)| (y, &b.url).cmp(&(x, &a.url)));
+    let (_, updated) = dated.first()?;
+    let base = escape(base_url);
+    let mut xml = format!(
+        "<?xml version=\"1.0\" encoding=\"utf-8\"?>\n\
+         <feed xmlns=\"http://www.w3.org/2005/Atom\">\n\
+         <title>{0}</title>\n\
+         <link href=\"{1}\"/>\n\
+         <link rel=\"self\" href=\"{1}feed.xml\"/>\n\
+         <id>{1}</id>\n\
+         <updated>{2}</updated>\n\
+         <author><name>{0}</name></author>\n",
+        escape(title),
+        base,
+        rfc3339(updated)
+    );
+    for (page, date) in dated {
+        let url = escape(&format!("{}{}", base_url, page.url));
+        xml.push_str(&format!(
+            "<entry>\n<title>{}</title>\n<link href=\"{}\"/>\n<id>{1}</id>\n<updated>{}</updated>\n",
+            title_of(page),
+            url,
+            rfc3339(date)
+        ));
+        if let Some(author) = page.context.get("author") {
+            xml.push_str(&format!("<author><name>{}</name></author>\n", author));
+        }
+        if let Some(body) = page.context.get("body") {
+            xml.push_str(&format!(
+                "<content type=\"html\">{}</content>\n",
+                escape(body)
+            ));
+        }
+        xml.push_str("</entry>\n");
+    }
+    xml.push_str("</feed>");
+    Some(xml)
+}
+
+// Atom wants the time as well as the date, which is midnight in UTC when it is not given
+fn rfc3339(date: &str) -> String {
+    if date.contains('T') {
+        date.to_string()
+    } else {
+        format!("{}T00:00:00Z", date)
+    }
+}
+
+fn search_entry(page: &Page) -> Json {
+    let mut fields = json::Map::new();
+    fields.insert(String::from("url"), Json::String(page.url.clone()));
+    if let Json::Object(entries) = &page.search {
+        fields.extend(entries.clone());
+    }
+    Json::Object(fields)
+}
+
+fn to_json(source: &Source) -> Json {
+    let context = source
+        .context
+        .iter()
+        .map(|(name, value)| (name.clone(), Json::String(value.clone())))
+        .collect();
+    Json::Object(
+        [
+            (String::from("context"), Json::Object(context)),
+            (String::from("search"), source.search.clone()),
+        ]
+        .into_iter()
+        .collect(),
+    )
+}
+
+fn from_json(json: &Json, hash: u64) -> Option<Source> {
+    let context = match json.get("context")? {
+        Json::Object(entries) => entries
+            .iter()
+            .map(|(name, value)| match value {
+                Json::String(value) => Some((name.clone(), value.clone())),
+                _ => None,
+            })
+            .collect::<Option<Context>>()?,
+        _ => return None,
+    };
+    let search = json.get("search")?.clone();
+    Some(Source {
+        context,
+        search,
+        hash,
+        reads: BTreeMap::new(),
+    })
+}
+
+// whether the file is written, which it is not when it has the content already
+fn write_if_changed(path: &Path, content: &[u8]) -> Result<bool, String> {
+    if fs::read(path).is_ok_and(|old| old == content) {
+        return Ok(false);
+    }
+    build::write(path, content).map(|_| true)
+}
+
+// `posts/a.md` is `posts/a.html`, with `/` on every platform
+fn url(relative: &Path) -> String {
+    let path = relative.with_extension("html");
+    let names: Vec<_> = path.iter().map(|name| name.to_string_lossy()).collect();
+    names.join("/")
+}
+
+fn date(page: &Page) -> &str {
+    page.context.get("date").map_or("", String::as_str)
+}
+
+// the title is escaped already, as the other placeholders
+fn title_of(page: &Page) -> &str {
+    match page.context.get("title") {
+        Some(title) if !title.is_empty() => title,
+        _ => &page.url,
+    }
+}
+
+fn nav(prev: Option<&Page>, next: Option<&Page>, root: &str) -> String {
+    if prev.is_none() && next.is_none() {
+        return String::new();
+    }
+    let mut nav = String::from("<nav class=\"pagination\">\n");
+    for (rel, page) in [("prev", prev), ("next", next)] {
+        if let Some(page) = page {
+            nav.push_str(&format!(
+                "<a rel=\"{}\" href=\"{}\">{}</a>\n",
+                rel,
+                escape(&format!("{}{}", root, page.url)),
+                title_of(page)
+            ));
+        }
+    }
+    nav.push_str("</nav>");
+    nav
+}
+
+fn list(pages: &[Page], root: &str) -> String {
+    let mut list = String::from("<ul class=\"pages\">\n");
+    for page in pages {
+        let href = escape(&format!("{}{}", root, page.url));
+        list.push_str(&format!("<li><a href=\"{}\">{}</a>", href, title_of(page)));
+        if let Some(date) = page.context.get("date") {
+            list.push_str(&format!(" <time datetime=\"{0}\">{0}</time>", date));
+        }
+        list.push_str("</li>\n");
+    }
+    list.push_str("</ul>");
+    list
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::translator;
+
+    #[test]
+    fn test_build() {
+        let root = std::env::temp_dir().join(format!("prose-site-{}", std::process::id()));
+        let src = root.join("src");
+        let out = root.join("out");
+        fs::create_dir_all(src.join("posts")).unwrap();
+        fs::create_dir_all(src.join("_templates")).unwrap();
+        fs::write(src.join("about.md"), "# About\n").unwrap();
+        fs::write(
+            src.join("posts/b.md"),
+            "---\ntitle: B & co\ndate: 2024-02-01\n---\nb\n",
+        )
+        .unwrap();
+        fs::write(
+            src.join("posts/a.md"),
+            "---\ntitle: A\ndate: 2024-01-01\ntemplate: post\n---\na\n",
+        )
+        .unwrap();
+        fs::write(src.join("style.css"), "p {}").unwrap();
+        fs::write(
+            src.join("_templates/post.html"),
+            "{{ root }}|{{ title }}|{{ next_url }}|{{ next_title }}|{{ body }}",
+        )
+        .unwrap();
+        let convert = |_: &Path, content: &str| {
+            let md = crate::parse(content).map_err(|err| err.to_string())?;
+            Ok((md, vec![], translator::Options::default()))
+        };
+        let options = Options {
+            title: String::from("Site"),
+            search_index: true,
+            base_url: Some(String::from("https://example.com/blog/")),
+            ..Options::default()
+        };
+
+        let summary = build(&src, &out, &options, &convert).unwrap();
+        assert!(summary.failed.is_empty());
+        assert_eq!(
+            summary.written,
+            vec![
+                out.join("about.html"),
+                out.join("posts/a.html"),
+                out.join("posts/b.html"),
+                out.join("index.html"),
+                out.join("search-index.json"),
+                out.join("sitemap.xml"),
+                out.join("feed.xml"),
+                out.join("style.css"),
+            ]
+        );
+        assert_eq!(
+            fs::read_to_string(out.join("posts/a.html")).unwrap(),
+            "../|A|../posts/b.html|B &amp; co|<p>a</p>\n"
+        );
+        let b = fs::read_to_string(out.join("posts/b.html")).unwrap();
+        assert!(b.contains(
+            "<nav class=\"pagination\">\n<a rel=\"prev\" href=\"../posts/a.html\">A</a>\n</nav>"
+        ));
+        let index = fs::read_to_string(out.join("index.html")).unwrap();
+        assert!(index.contains(
+            "<ul class=\"pages\">\n\
+             <li><a href=\"about.html\">About</a></li>\n\
+             <li><a href=\"posts/a.html\">A</a> <time datetime=\"2024-01-01\">2024-01-01</time></li>\n"
+        ));
+        assert!(!out.join("_templates").exists());
+        assert_eq!(
+            fs::read_to_string(out.join("search-index.json")).unwrap(),
+            "[{\"url\":\"about.html\",\"title\":\"About\",\"headings\":[\"About\"],\"body\":\"About\"},\
+             {\"url\":\"posts/a.html\",\"title\":\"A\",\"headings\":[],\"body\":\"a\"},\
+             {\"url\":\"posts/b.html\",\"title\":\"B & co\",\"headings\":[],\"body\":\"b\"}]\n"
+        );
+
+        let sitemap = fs::read_to_string(out.join("sitemap.xml")).unwrap();
+        assert!(sitemap.contains(
+            "<url><loc>https://example.com/blog/index.html</loc></url>\n\
+             <url><loc>https://example.com/blog/about.html</loc></url>\n\
+             <url><loc>https://example.com/blog/posts/a.html</loc><lastmod>2024-01-01</lastmod></url>\n"
+        ));
+        let feed = fs::read_to_string(out.join("feed.xml")).unwrap();
+        assert!(feed.contains("<updated>2024-02-01T00:00:00Z</updated>\n<author><name>Site</name>"));
+        assert!(feed.contains(
+            "<entry>\n<title>B &amp; co</title>\n\
+             <link href=\"https://example.com/blog/posts/b.html\"/>\n\
+             <id>https://example.com/blog/posts/b.html</id>\n\
+             <updated>2024-02-01T00:00:00Z</updated>\n\
+             <content type=\"html\">&lt;p&gt;b&lt;/p&gt;</content>\n</entry>\n<entry>\n<title>A</title>"
+        ));
+
+        // nothing has changed, so nothing is written again
+        let summary = build(&src, &out, &options, &convert).unwrap();
+        assert!(summary.written.is_empty());
+        assert_eq!(summary.reused.len(), 8);
+
+        // a template which is not there fails its page only
+        fs::write(src.join("c.md"), "---\ntemplate: missing\n---\nc\n").unwrap();
+        let summary = build(&src, &out, &options, &convert).unwrap();
+        assert_eq!(summary.failed.len(), 1);
+        assert_eq!(summary.failed[0].0, src.join("c.md"));
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
@@ -0,0 +1,107 @@
+use std::collections::{HashMap, HashSet};
+
+// ids for headings, in the style of GitHub: `Getting Started` becomes `getting-started`.
+// latin letters lose their accents, other letters such as kana are kept as they are
+pub fn slugify(text: &str) -> String {
+    let mut slug = String::new();
+    for c in text.chars().flat_map(char::to_lowercase) {
+        if c.is_alphanumeric() {
+            match transliterate(c) {
+                Some(latin) => slug.push_str(latin),
+                None => slug.push(c),
+            }
+        } else if (c.is_whitespace() || c == '-' || c == '_') && !slug.ends_with('-') {
+            slug.push(if c == '_' { '_' } else { '-' });
+        }
+    }
+    let slug = slug.trim_matches('-');
+    if slug.is_empty() {
+        String::from("section")
+    } else {
+        slug.to_string()
+    }
+}
+
+// the ids given so far in a document.
+// a slug which is already taken gets `-1`, `-2`, ... appended, counting on from the last one given
+#[derive(Debug, Default)]
+pub(crate) struct Slugs {
+    taken: HashSet<String>,
+    next: HashMap<String, usize>,
+}
+
+impl Slugs {
+    pub(crate) fn insert(&mut self, id: String) {
+        self.taken.insert(id);
+    }
+
+    // an id made from `text` which is not taken yet, and is taken from now on
+    pub(crate) fn unique(&mut self, text: &str) -> String {
+        let slug = slugify(text);
+        let k = self.next.entry(slug.clone()).or_insert(0);
+        let mut id = slug.clone();
+        if *k > 0 {
+            id = format!("{}-{}", slug, k);
+        }
+        while self.taken.contains(&id) {
+            *k += 1;
+            id = format!("{}-{}", slug, k);
+        }
+        self.taken.insert(id.clone());
+        id
+    }
+}
+
+fn transliterate(c: char) -> Option<&'static str> {
+    Some(match c {
+        'à' | 'á' | 'â' | 'ã' | 'ä' | 'å' | 'ā' | 'ă' | 'ą' => "a",
+        'æ' => "ae",
+        'ç' | 'ć' | 'ĉ' | 'ċ' | 'č' => "c",
+        'ď' | 'đ' | 'ð' => "d",
+        'è' | 'é' | 'ê' | 'ë' | 'ē' | 'ĕ' | 'ė' | 'ę' | 'ě' => "e",
+        'ĝ' | 'ğ' | 'ġ' | 'ģ' => "g",
+        'ĥ' | 'ħ' => "h",
+        'ì' | 'í' | 'î' | 'ï' | 'ĩ' | 'ī' | 'ĭ' | 'į' | 'ı' => "i",
+        'ĵ' => "j",
+        'ķ' => "k",
+        'ĺ' | 'ļ' | 'ľ' | 'ŀ' | 'ł' => "l",
+        'ñ' | 'ń' | 'ņ' | 'ň' => "n",
+        'ò' | 'ó' | 'ô' | 'õ' | 'ö' | 'ø' | 'ō' | 'ŏ' | 'ő' => "o",
+        'œ' => "oe",
+        'ŕ' | 'ŗ' | 'ř' => "r",
+        'ś' | 'ŝ' | 'ş' | 'š' => "s",
+        'ß' => "ss",
+        'ţ' | 'ť' | 'ŧ' => "t",
+        'þ' => "th",
+        'ù' | 'ú' | 'û' | 'ü' | 'ũ' | 'ū' | 'ŭ' | 'ů' | 'ű' | 'ų' => "u",
+        'ŵ' => "w",
+        'ý' | 'ÿ' | 'ŷ' => "y",
+        'ź' | 'ż' | 'ž' => "z",
+        _ => return None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_slugify() {
+        assert_eq!(slugify("Getting Started"), "getting-started");
+        assert_eq!(slugify("  What's new in 2.0?  "), "whats-new-in-20");
+        assert_eq!(slugify("snake_case -- and more"), "snake_case-and-more");
+        assert_eq!(slugify("Crème Brûlée, Straße"), "creme-brulee-strasse");
+        assert_eq!(slugify("日本語の見出し"), "日本語の見出し");
+        assert_eq!(slugify("!!!"), "section");
+    }
+
+    #[test]
+    fn test_slugs() {
+        let mut slugs = Slugs::default();
+        slugs.insert(String::from("a-1"));
+        assert_eq!(slugs.unique("A"), "a");
+        assert_eq!(slugs.unique("A"), "a-2");
+        assert_eq!(slugs.unique("a"), "a-3");
+        assert_eq!(slugs.unique("B"), "b");
+    }
+}
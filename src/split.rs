@@ -0,0 +1,163 @@
+use crate::entity::{Markdown, MarkdownInline, MarkdownText};
+use crate::translator::{assign_heading_ids, map_text, plain_text};
+
+use std::collections::HashMap;
+
+// a part of a document which is a page of its own
+#[derive(Clone, Debug, PartialEq)]
+pub struct Page {
+    // the file name, such as `index.html` or `installation.html`
+    pub name: String,
+    pub title: String,
+    pub blocks: Vec<Markdown>,
+}
+
+// splits a document at its top-level headings of `level` and above, such as the chapters of
+// a book. the first page is `index`, with what comes before the first of those headings,
+// and the others are named after the ids of their headings, with `extension`.
+// the links to the anchors of the document are made links to the pages the anchors are on,
+// and each page ends with the links to the pages before and after it
+pub fn split(md: Vec<Markdown>, level: usize, extension: &str) -> Vec<Page> {
+    let pages = chapters(md, level, extension);
+    let count = pages.len();
+    let links: Vec<(String, String)> = pages
+        .iter()
+        .map(|page| (page.name.clone(), page.title.clone()))
+        .collect();
+    pages
+        .into_iter()
+        .enumerate()
+        .map(|(k, mut page)| {
+            if count > 1 {
+                let prev = k.checked_sub(1).map(|k| &links[k]);
+                page.blocks
+                    .push(Markdown::Html(nav(prev, links.get(k + 1))));
+            }
+            page
+        })
+        .collect()
+}
+
+// the pages of `split` without the links between them
+pub fn chapters(md: Vec<Markdown>, level: usize, extension: &str) -> Vec<Page> {
+    let md = assign_heading_ids(md);
+    let mut pages = vec![Page {
+        name: format!("index.{}", extension),
+        title: String::new(),
+        blocks: vec![],
+    }];
+    for bit in md {
+        if let Markdown::Heading(size, text, attributes) = &bit {
+            if *size <= level {
+                if pages.len() == 1 && is_empty(&pages[0].blocks) {
+                    // the document starts with the heading, which is the title of the index
+                    pages[0].title = plain_text(text);
+                } else {
+                    let id = attributes.id.clone().unwrap_or_default();
+                    pages.push(Page {
+                        name: format!("{}.{}", id, extension),
+                        title: plain_text(text),
+                        blocks: vec![],
+                    });
+                }
+            }
+        }
+        pages.last_mut().unwrap().blocks.push(bit);
+    }
+
+    let mut anchors = HashMap::new();
+    for page in &pages {
+        for bit in &page.blocks {
+            bit.walk(&mut |bit| {
+                if let Markdown::Heading(_, _, attributes) = bit {
+                    if let Some(id) = &attributes.id {
+                        anchors.insert(id.clone(), page.name.clone());
+                    }
+                }
+            });
+        }
+    }
+    pages
+        .into_iter()
+        .map(|page| {
+            let blocks = map_text(page.blocks, &mut |text| relink(text, &page.name, &anchors));
+            Page { blocks, ..page }
+        })
+        .collect()
+}
+
+// whether the page has nothing to show yet but its front matter
+fn is_empty(blocks: &[Markdown]) -> bool {
+    blocks
+        .iter()
+        .all(|bit| matches!(bit, Markdown::FrontMatter(_)))
+}
+
+fn relink(text: MarkdownText, name: &str, anchors: &HashMap<String, String>) -> MarkdownText {
+    let relink = |text| relink(text, name, anchors);
+    text.into_iter()
+        .map(|part| match part {
+            MarkdownInline::Link(text, url, title) => {
+                let url = match url.strip_prefix('#').and_then(|id| anchors.get(id)) {
+                    Some(page) if page != name => format!("{}{}", page, url),
+                    _ => url,
+                };
+                MarkdownInline::Link(relink(text), url, title)
+            }
+            MarkdownInline::Bold(text) => MarkdownInline::Bold(relink(text)),
+            MarkdownInline::Italic(text) => MarkdownInline::Italic(relink(text)),
+            MarkdownInline::Mark(text) => MarkdownInline::Mark(relink(text)),
+            part => part,
+        })
+        .collect()
+}
+
+fn nav(prev: Option<&(String, String)>, next: Option<&(String, String)>) -> String {
+    let mut nav = String::from("<nav class=\"pagination\">\n");
+    for (rel, link) in [("prev", prev), ("next", next)] {
+        if let Some((name, title)) = link {
+            nav.push_str(&format!(
+                "<a rel=\"{}\" href=\"{}\">{}</a>\n",
+                rel,
+                escape(name),
+                escape(title)
+            ));
+        }
+    }
+    nav.push_str("</nav>");
+    nav
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split() {
+        let md = crate::parse(
+            "# Book\n\nsee [setup](#setup)\n\n## Setup\n\n### Linux\n\nback to [top](#book)\n\n## Usage\n\n[linux](#linux) [here](#usage)\n",
+        )
+        .unwrap();
+        let pages = split(md, 2, "html");
+        let names: Vec<_> = pages.iter().map(|page| page.name.as_str()).collect();
+        assert_eq!(names, vec!["index.html", "setup.html", "usage.html"]);
+        let titles: Vec<_> = pages.iter().map(|page| page.title.as_str()).collect();
+        assert_eq!(titles, vec!["Book", "Setup", "Usage"]);
+        let html =
+            |page: &Page| crate::translator::translate(page.blocks.clone(), &Default::default());
+        assert!(html(&pages[0]).contains("href=\"setup.html#setup\""));
+        assert!(html(&pages[1]).contains("href=\"index.html#book\""));
+        assert!(html(&pages[1]).contains("<a rel=\"prev\" href=\"index.html\">Book</a>"));
+        assert!(html(&pages[1]).contains("<a rel=\"next\" href=\"usage.html\">Usage</a>"));
+        assert!(html(&pages[2]).contains("href=\"setup.html#linux\""));
+        assert!(html(&pages[2]).contains("href=\"#usage\""));
+        assert!(!html(&pages[2]).contains("rel=\"next\""));
+    }
+}
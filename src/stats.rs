@@ -0,0 +1,196 @@
+use crate::entity::{walk_inlines, Markdown, MarkdownInline};
+use crate::json::Json;
+use crate::translator::plain_text;
+
+use std::collections::BTreeMap;
+use std::fmt;
+
+// the words read in a minute, for the reading time
+const WORDS_PER_MINUTE: usize = 200;
+
+// what a document is made of, as `prose stats` prints it
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Stats {
+    // the words of the text outside the code, where each CJK character is a word
+    pub words: usize,
+    // the characters of that text, without the whitespace
+    pub characters: usize,
+    // in minutes, rounded up
+    pub reading_time: usize,
+    // the level and the text of every heading, in order
+    pub headings: Vec<(usize, String)>,
+    // the code blocks by language, `""` for the ones without
+    pub code_blocks: BTreeMap<String, usize>,
+    // the wiki links and the autolinks are links too
+    pub links: usize,
+    pub images: usize,
+}
+
+pub fn stats(md: &[Markdown]) -> Stats {
+    let mut stats = Stats::default();
+    for bit in md {
+        bit.walk(&mut |block| {
+            match block {
+                Markdown::Heading(level, text, _) => {
+                    stats.headings.push((*level, plain_text(text)));
+                }
+                Markdown::Codeblock(lang, _, _) => {
+                    *stats.code_blocks.entry(lang.clone()).or_insert(0) += 1;
+                }
+                _ => {}
+            }
+            for text in block.texts() {
+                walk_inlines(text, &mut |part| match part {
+                    MarkdownInline::Link(..) | MarkdownInline::WikiLink(..) => stats.links += 1,
+                    MarkdownInline::Image(..) => stats.images += 1,
+                    _ => {}
+                });
+                let text = plain_text(text);
+                stats.words += count_words(&text);
+                stats.characters += text.chars().filter(|c| !c.is_whitespace()).count();
+            }
+        });
+    }
+    stats.reading_time = stats.words.div_ceil(WORDS_PER_MINUTE);
+    stats
+}
+
+// the runs of characters between the whitespace and the punctuation,
+// and each character of the scripts written without spaces, such as kanji and kana
+fn count_words(text: &str) -> usize {
+    let mut words = 0;
+    let mut in_word = false;
+    for c in text.chars() {
+        if is_cjk(c) {
+            words += 1;
+            in_word = false;
+        } else if c.is_alphanumeric() || (in_word && (c == '\'' || c == '’' || c == '-')) {
+            words += !in_word as usize;
+            in_word = true;
+        } else {
+            in_word = false;
+        }
+    }
+    words
+}
+
+fn is_cjk(c: char) -> bool {
+    matches!(c,
+        '\u{3040}'..='\u{30ff}' // hiragana and katakana
+        | '\u{3400}'..='\u{4dbf}' // CJK extension A
+        | '\u{4e00}'..='\u{9fff}' // CJK unified ideographs
+        | '\u{ac00}'..='\u{d7af}' // hangul syllables
+        | '\u{f900}'..='\u{faff}' // CJK compatibility ideographs
+        | '\u{20000}'..='\u{2ffff}' // the supplementary ideographs
+    )
+}
+
+impl Stats {
+    pub fn to_json(&self) -> Json {
+        let number = |n: usize| Json::from(n);
+        let headings = self
+            .headings
+            .iter()
+            .map(|(level, text)| {
+                Json::Object(
+                    [
+                        (String::from("level"), number(*level)),
+                        (String::from("text"), Json::String(text.clone())),
+                    ]
+                    .into_iter()
+                    .collect(),
+                )
+            })
+            .collect();
+        let code_blocks = self
+            .code_blocks
+            .iter()
+            .map(|(lang, n)| (lang.clone(), number(*n)))
+            .collect();
+        Json::Object(
+            [
+                (String::from("words"), number(self.words)),
+                (String::from("characters"), number(self.characters)),
+                (String::from("reading_time"), number(self.reading_time)),
+                (String::from("headings"), Json::Array(headings)),
+                (String::from("code_blocks"), Json::Object(code_blocks)),
+                (String::from("links"), number(self.links)),
+                (String::from("images"), number(self.images)),
+            ]
+            .into_iter()
+            .collect(),
+        )
+    }
+}
+
+impl fmt::Display for Stats {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "words: {}", self.words)?;
+        writeln!(f, "characters: {}", self.characters)?;
+        writeln!(f, "reading time: {} min", self.reading_time)?;
+        writeln!(f, "headings: {}", self.headings.len())?;
+        for (level, text) in &self.headings {
+            writeln!(
+                f,
+                "  {}{} {}",
+                "  ".repeat(level - 1),
+                "#".repeat(*level),
+                text
+            )?;
+        }
+        writeln!(
+            f,
+            "code blocks: {}",
+            self.code_blocks.values().sum::<usize>()
+        )?;
+        for (lang, n) in &self.code_blocks {
+            let lang = if lang.is_empty() { "(none)" } else { lang };
+            writeln!(f, "  {}: {}", lang, n)?;
+        }
+        writeln!(f, "links: {}", self.links)?;
+        write!(f, "images: {}", self.images)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_count_words() {
+        assert_eq!(count_words("Don't stop-me now, 2 times!"), 5);
+        assert_eq!(count_words("日本語のtext"), 5);
+        assert_eq!(count_words(" -- "), 0);
+    }
+
+    #[test]
+    fn test_stats() {
+        let md = crate::parse(
+            "# Title\n\nSome *words* [here](a.md) and [[Wiki]].\n\n## Code\n\n> ![alt](i.png)\n\n```rust\nlet x;\n```\n\n```\nx\n```\n\n> ```rust\n> y\n> ```\n",
+        )
+        .unwrap();
+        let stats = stats(&md);
+        assert_eq!(
+            stats,
+            Stats {
+                words: 8,
+                characters: 33,
+                reading_time: 1,
+                headings: vec![(1, String::from("Title")), (2, String::from("Code"))],
+                code_blocks: [(String::new(), 1), (String::from("rust"), 2)]
+                    .into_iter()
+                    .collect(),
+                links: 2,
+                images: 1,
+            }
+        );
+        assert_eq!(
+            stats.to_string(),
+            "words: 8\ncharacters: 33\nreading time: 1 min\nheadings: 2\n  # Title\n    ## Code\ncode blocks: 3\n  (none): 1\n  rust: 2\nlinks: 2\nimages: 1"
+        );
+        assert_eq!(
+            crate::json::write(&stats.to_json()),
+            "{\"words\":8,\"characters\":33,\"reading_time\":1,\"headings\":[{\"level\":1,\"text\":\"Title\"},{\"level\":2,\"text\":\"Code\"}],\"code_blocks\":{\"\":1,\"rust\":2},\"links\":2,\"images\":1}"
+        );
+    }
+}
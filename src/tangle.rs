@@ -0,0 +1,146 @@
+use crate::entity::Markdown;
+
+use std::path::{Component, Path, PathBuf};
+
+// the code blocks to extract, in the order of the files they go to:
+// the ones with `file=path` in their info string go to that file, one after another,
+// and with `lang` the others of that language too, to `None`, which is stdout.
+// the paths are relative, and cannot go above the directory they are written in
+pub fn tangle(
+    md: &[Markdown],
+    lang: Option<&str>,
+) -> Result<Vec<(Option<PathBuf>, String)>, String> {
+    let mut files: Vec<(Option<PathBuf>, String)> = vec![];
+    let mut error = None;
+    for bit in md {
+        bit.walk(&mut |block| {
+            let Markdown::Codeblock(language, code, info) = block else {
+                return;
+            };
+            if lang.is_some_and(|lang| lang != language) {
+                return;
+            }
+            let path = match info_attribute(info, "file") {
+                Some(path) => match relative_path(&path) {
+                    Some(path) => Some(path),
+                    None => {
+                        error.get_or_insert_with(|| {
+                            format!("Cannot write outside the directory: {}", path)
+                        });
+                        return;
+                    }
+                },
+                None if lang.is_some() => None,
+                None => return,
+            };
+            match files.iter_mut().find(|(file, _)| *file == path) {
+                Some((_, content)) => content.push_str(code),
+                None => files.push((path, code.clone())),
+            }
+        });
+    }
+    match error {
+        Some(error) => Err(error),
+        None => Ok(files),
+    }
+}
+
+// the value of `key=value` in the attributes of an info string,
+// such as `file=src/main.rs` or `{file="src/main.rs" linenos=true}`
+pub fn info_attribute(info: &str, key: &str) -> Option<String> {
+    let separator = |c: char| c.is_whitespace() || matches!(c, ',' | '{' | '}');
+    let mut rest = info;
+    loop {
+        rest = rest.trim_start_matches(separator);
+        if rest.is_empty() {
+            return None;
+        }
+        let end = rest
+            .find(|c: char| separator(c) || c == '=')
+            .unwrap_or(rest.len());
+        let (name, after) = rest.split_at(end);
+        let Some(after) = after.strip_prefix('=') else {
+            rest = after;
+            continue;
+        };
+        let (value, after) = match after.chars().next() {
+            Some(quote @ ('"' | '\'')) => {
+                let inner = &after[1..];
+                let end = inner.find(quote).unwrap_or(inner.len());
+                (&inner[..end], inner.get(end + 1..).unwrap_or(""))
+            }
+            _ => after.split_at(after.find(separator).unwrap_or(after.len())),
+        };
+        if name == key {
+            return Some(value.to_string());
+        }
+        rest = after;
+    }
+}
+
+// the path without `.`, so that `./a.rs` and `a.rs` are one file.
+// none with `..` or the root, which would write somewhere else
+fn relative_path(path: &str) -> Option<PathBuf> {
+    let path = Path::new(path);
+    let normal = path
+        .components()
+        .all(|component| matches!(component, Component::Normal(_) | Component::CurDir));
+    (normal && path.file_name().is_some()).then(|| {
+        path.components()
+            .filter(|component| *component != Component::CurDir)
+            .collect()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_info_attribute() {
+        assert_eq!(
+            info_attribute("file=a.rs", "file"),
+            Some(String::from("a.rs"))
+        );
+        assert_eq!(
+            info_attribute("{linenos=true file=\"src/a b.rs\"}", "file"),
+            Some(String::from("src/a b.rs"))
+        );
+        assert_eq!(
+            info_attribute("ignore, file='x'", "file"),
+            Some(String::from("x"))
+        );
+        assert_eq!(info_attribute("{.class #id}", "file"), None);
+        assert_eq!(info_attribute("profile=a", "file"), None);
+    }
+
+    #[test]
+    fn test_tangle() {
+        let md = crate::parse(
+            "```rust file=src/main.rs\nfn main() {\n```\n\n```sh\nls\n```\n\n> ```rust\n> let x = 1;\n> ```\n\n```rust {file=src/main.rs}\n}\n```\n\n```toml file=Cargo.toml\n[package]\n```\n",
+        )
+        .unwrap();
+        let path = |s: &str| Some(PathBuf::from(s));
+        assert_eq!(
+            tangle(&md, None),
+            Ok(vec![
+                (path("src/main.rs"), String::from("fn main() {\n}\n")),
+                (path("Cargo.toml"), String::from("[package]\n")),
+            ])
+        );
+        assert_eq!(
+            tangle(&md, Some("rust")),
+            Ok(vec![
+                (path("src/main.rs"), String::from("fn main() {\n}\n")),
+                (None, String::from("let x = 1;\n")),
+            ])
+        );
+        let md = crate::parse("```sh file=../x.sh\nrm\n```\n").unwrap();
+        assert_eq!(
+            tangle(&md, None),
+            Err(String::from("Cannot write outside the directory: ../x.sh"))
+        );
+        assert!(relative_path("/etc/passwd").is_none());
+        assert_eq!(relative_path("./a/b.rs"), Some(PathBuf::from("a/b.rs")));
+    }
+}
@@ -0,0 +1,178 @@
+use crate::entity::{Markdown, MarkdownInline, MarkdownText};
+use crate::translator::map_text;
+use std::collections::BTreeMap;
+
+use nom::{
+    bytes::complete::{tag, take_while1},
+    character::complete::space0,
+    sequence::{delimited, pair},
+    IResult,
+};
+
+// `{{ name }}` in the text of a document, the placeholder syntax of the Tera templates
+fn parse_placeholder(i: &str) -> IResult<&str, &str> {
+    delimited(
+        pair(tag("{{"), space0),
+        take_while1(|c: char| c.is_alphanumeric() || c == '_' || c == '-' || c == '.'),
+        pair(space0, tag("}}")),
+    )(i)
+}
+
+// a Tera template filled in with the context: `{{ body }}`, `{{ title }}` and the others as they are,
+// without escaping them again, and `{% if %}`, `{% for %}` and the filters of Tera.
+// an unknown name is an error, unless it is given a `default`
+pub fn render(template: &str, context: &BTreeMap<String, String>) -> Result<String, String> {
+    let mut values = tera::Context::new();
+    for (name, value) in context {
+        values.insert(name, value);
+    }
+    tera::Tera::one_off(template, &values, false).map_err(|err| {
+        // the cause is in the sources, below "Failed to render"
+        let mut message = err.to_string();
+        let mut source = std::error::Error::source(&err);
+        while let Some(err) = source {
+            message = format!("{}: {}", message, err);
+            source = err.source();
+        }
+        message
+    })
+}
+
+// the placeholders in the text of the document, with the values of its front matter
+// and then `defines`, which win over the front matter.
+// the values are plain text, not markdown, and unknown names are left as they are written.
+// code is not touched
+pub fn substitute(md: Vec<Markdown>, defines: &BTreeMap<String, String>) -> Vec<Markdown> {
+    let mut context: BTreeMap<String, String> = md
+        .iter()
+        .filter_map(|bit| match bit {
+            Markdown::FrontMatter(front_matter) => Some(front_matter.clone()),
+            _ => None,
+        })
+        .flatten()
+        .collect();
+    context.extend(defines.clone());
+    map_text(md, &mut |text| substitute_text(text, &context))
+}
+
+fn substitute_text(text: MarkdownText, context: &BTreeMap<String, String>) -> MarkdownText {
+    let expand = |s: String| expand(&s, context);
+    text.into_iter()
+        .map(|part| match part {
+            MarkdownInline::Plaintext(s) => MarkdownInline::Plaintext(expand(s)),
+            MarkdownInline::Bold(text) => MarkdownInline::Bold(substitute_text(text, context)),
+            MarkdownInline::Italic(text) => MarkdownInline::Italic(substitute_text(text, context)),
+            MarkdownInline::Mark(text) => MarkdownInline::Mark(substitute_text(text, context)),
+            MarkdownInline::Link(text, url, title) => MarkdownInline::Link(
+                substitute_text(text, context),
+                expand(url),
+                title.map(expand),
+            ),
+            MarkdownInline::Image(text, url, title) => MarkdownInline::Image(
+                substitute_text(text, context),
+                expand(url),
+                title.map(expand),
+            ),
+            part => part,
+        })
+        .collect()
+}
+
+// the placeholders of the text replaced, and the unknown ones left as they are written
+fn expand(template: &str, context: &BTreeMap<String, String>) -> String {
+    let mut output = String::new();
+    let mut rest = template;
+    while let Some(pos) = rest.find("{{") {
+        output.push_str(&rest[..pos]);
+        rest = &rest[pos..];
+        match parse_placeholder(rest) {
+            Ok((next, name)) => {
+                match context.get(name) {
+                    Some(value) => output.push_str(value),
+                    None => output.push_str(&rest[..rest.len() - next.len()]),
+                }
+                rest = next;
+            }
+            Err(_) => {
+                output.push_str("{{");
+                rest = &rest[2..];
+            }
+        }
+    }
+    output.push_str(rest);
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_placeholder() {
+        assert_eq!(parse_placeholder("{{title}}"), Ok(("", "title")));
+        assert_eq!(parse_placeholder("{{  body }}<"), Ok(("<", "body")));
+        assert!(parse_placeholder("{{ a b }}").is_err());
+        assert!(parse_placeholder("{{}}").is_err());
+    }
+
+    #[test]
+    fn test_render() {
+        let mut context = BTreeMap::new();
+        context.insert(String::from("title"), String::from("Hello"));
+        context.insert(String::from("body"), String::from("<p>x</p>"));
+        assert_eq!(
+            render(
+                "<title>{{ title }}</title>{{body}}{% if missing %}!{% endif %}{{ missing | default(value=\"-\") }}",
+                &context
+            ),
+            Ok(String::from("<title>Hello</title><p>x</p>-"))
+        );
+        assert_eq!(
+            render("no placeholders", &context),
+            Ok(String::from("no placeholders"))
+        );
+        assert!(render("{{ missing }}", &context)
+            .unwrap_err()
+            .contains("Variable `missing` not found"));
+        assert!(render("{{ not closed", &context).is_err());
+    }
+
+    #[test]
+    fn test_substitute() {
+        let text = |s: &str| vec![MarkdownInline::Plaintext(String::from(s))];
+        let mut front_matter = BTreeMap::new();
+        front_matter.insert(String::from("version"), String::from("1.0"));
+        front_matter.insert(String::from("name"), String::from("prose"));
+        let mut defines = BTreeMap::new();
+        defines.insert(String::from("version"), String::from("*2.0*"));
+        assert_eq!(
+            substitute(
+                vec![
+                    Markdown::FrontMatter(front_matter.clone()),
+                    Markdown::Paragraph(vec![
+                        text("{{ name }} {{version}} {{ missing }}"),
+                        vec![MarkdownInline::Link(
+                            text("{{name}}"),
+                            String::from("https://x.com/{{ name }}"),
+                            None
+                        )],
+                    ]),
+                    Markdown::Codeblock(String::new(), String::from("{{ name }}"), String::new()),
+                ],
+                &defines
+            ),
+            vec![
+                Markdown::FrontMatter(front_matter),
+                Markdown::Paragraph(vec![
+                    text("prose *2.0* {{ missing }}"),
+                    vec![MarkdownInline::Link(
+                        text("prose"),
+                        String::from("https://x.com/prose"),
+                        None
+                    )],
+                ]),
+                Markdown::Codeblock(String::new(), String::from("{{ name }}"), String::new()),
+            ]
+        );
+    }
+}
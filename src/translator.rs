@@ -1,111 +1,1741 @@
+use crate::cache;
+use crate::emoji;
+use crate::entity::Align;
+use crate::entity::Attributes;
+use crate::entity::ListItem;
 use crate::entity::Markdown;
 use crate::entity::MarkdownInline;
 use crate::entity::MarkdownText;
+use crate::entity::{Span, Spanned};
+use crate::excerpt;
+#[cfg(feature = "highlight")]
+use crate::highlight;
+use crate::pretty;
+use crate::sanitize;
+use crate::slug::{slugify, Slugs};
+use crate::url;
+use render::Render;
 
-pub fn translate(md: Vec<Markdown>) -> String {
-    md.iter()
-        .map(|bit| match bit {
-            Markdown::Heading(size, line) => translate_header(*size, line.to_vec()),
-            Markdown::UnorderedList(lines) => translate_unordered_list(lines.to_vec()),
-            Markdown::OrderedList(lines) => translate_ordered_list(lines.to_vec()),
-            Markdown::Codeblock(lang, code) => {
-                translate_codeblock(lang.to_string(), code.to_string())
+use std::collections::BTreeMap;
+use std::collections::HashMap;
+use std::fmt::Write;
+use std::path::{Path, PathBuf};
+
+pub mod asciidoc;
+pub mod confluence;
+pub mod docbook;
+pub mod gemtext;
+pub mod jira;
+pub mod latex;
+pub mod man;
+pub mod markdown;
+pub mod render;
+pub mod rst;
+pub mod term;
+pub mod text;
+
+#[derive(Clone, Debug)]
+pub struct Options {
+    // escape `&`, `<`, `>` and quotes in text and attribute values.
+    // turn this off only for trusted input
+    pub escape: bool,
+    // wrap the fragment in a complete html document
+    pub standalone: bool,
+    // stylesheets linked from the standalone document
+    pub css: Vec<String>,
+    // highlight code blocks, with the named theme from `highlight::theme_names`
+    #[cfg(feature = "highlight")]
+    pub highlight: Option<String>,
+    // `class="language-rust"` on code blocks, as highlight.js and Prism expect
+    pub language_class: bool,
+    // load KaTeX in the standalone document to render the math
+    pub katex: bool,
+    // load mermaid.js in the standalone document to draw the diagrams
+    pub diagrams: bool,
+    // a `¶` link to itself after each heading
+    pub heading_anchors: bool,
+    // mark footnotes with `*`, `†`, `‡`, ... instead of numbers
+    pub footnote_symbols: bool,
+    // `<img>` instead of the characters for the emoji shortcodes
+    pub emoji_images: bool,
+    // write the `<!-- -->` comments, which are dropped by default
+    pub keep_comments: bool,
+    // where `[[Page Name]]` links to, `/wiki/{}.html` for example; the slug is appended without `{}`
+    pub wiki_base: String,
+    // write XHTML, which closes every void element as `<br />` and gives the attributes values,
+    // instead of HTML5, which leaves them open as `<br>`
+    pub xhtml: bool,
+    // every block element on a line of its own, indented by how deep it is
+    pub pretty: bool,
+    // wrap the text of each list item in `<p>`, as in a loose list
+    pub wrap_lists: bool,
+    // added to the level of every heading, which stops at `<h6>`
+    pub heading_offset: usize,
+    // put before the classes we generate, such as `footnotes` and `admonition`
+    pub class_prefix: String,
+    // the elements written instead of others, `b` to `strong` for example.
+    // only the inline elements, `p` and `blockquote` can be renamed
+    pub tags: BTreeMap<String, String>,
+    // `data-sourcepos` on the top-level blocks, when they are translated with their spans
+    pub sourcepos: bool,
+    // keep only the elements and the attributes of `sanitize` in the raw html, and drop the urls
+    // and the attributes which could run scripts, for untrusted input. the text is escaped too
+    pub sanitize: bool,
+    // the relative links to `.md` files point to the files with this extension instead,
+    // as `build` writes them
+    pub link_extension: Option<String>,
+    // `loading="lazy"` on the images
+    pub lazy_images: bool,
+    // an image alone in a paragraph is written as a `<figure>`, captioned with its title or alt text
+    pub figures: bool,
+    // `width` and `height` on the local images, read from the files
+    #[cfg(feature = "image-size")]
+    pub image_sizes: bool,
+    // the directory of the document, which the local images are read from
+    pub base_dir: Option<PathBuf>,
+    // embed the local images as `data:` urls, and the local stylesheets of the standalone
+    // document in `<style>`, so that the html is a file of its own
+    pub self_contained: bool,
+    // drop the `javascript:`, `vbscript:` and `data:` urls of the links and the images
+    pub block_script_urls: bool,
+    // the relative urls of the links and the images are resolved against this
+    pub base_url: Option<String>,
+    // `http://` urls become `https://`
+    pub force_https: bool,
+    // the attributes of the links to other sites than `base_url`, such as `target="_blank"`,
+    // or to any site when there is no base
+    pub external_link_attributes: BTreeMap<String, String>,
+    // the class of the links to other sites, for a stylesheet to mark them with an icon
+    pub external_link_class: Option<String>,
+    // called with every url after the policies above, which returns the url to write instead
+    // or `None` to keep it
+    pub rewrite_link: Option<fn(&LinkContext) -> Option<String>>,
+}
+
+// what the url of a link is written for
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum LinkKind {
+    Link,
+    Image,
+    WikiLink,
+}
+
+// what `rewrite_link` is given for each url
+#[derive(Clone, Debug, PartialEq)]
+pub struct LinkContext<'a> {
+    pub url: &'a str,
+    pub kind: LinkKind,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Options {
+            escape: true,
+            standalone: false,
+            css: vec![],
+            #[cfg(feature = "highlight")]
+            highlight: None,
+            language_class: false,
+            katex: false,
+            diagrams: false,
+            heading_anchors: false,
+            footnote_symbols: false,
+            emoji_images: false,
+            keep_comments: false,
+            wiki_base: String::new(),
+            xhtml: false,
+            pretty: false,
+            wrap_lists: false,
+            heading_offset: 0,
+            class_prefix: String::new(),
+            tags: BTreeMap::new(),
+            sourcepos: false,
+            sanitize: false,
+            link_extension: None,
+            lazy_images: false,
+            figures: false,
+            #[cfg(feature = "image-size")]
+            image_sizes: false,
+            base_dir: None,
+            self_contained: false,
+            block_script_urls: false,
+            base_url: None,
+            force_https: false,
+            external_link_attributes: BTreeMap::new(),
+            external_link_class: None,
+            rewrite_link: None,
+        }
+    }
+}
+
+// builds the options of the html output one by one, for the library users:
+// `HtmlRenderer::new().xhtml(true).tag("b", "strong").render(md)`
+#[derive(Clone, Debug, Default)]
+pub struct HtmlRenderer {
+    options: Options,
+    // where the paragraph being written starts, and where its text does, for `figures`
+    paragraph: Option<(usize, usize)>,
+    // where the last image starts and ends, and its caption
+    image: Option<(usize, usize, String)>,
+    // the element such as `<script>` whose content is dropped, until its closing tag, for `sanitize`
+    dropped: Option<String>,
+    // how many references to each footnote are written, which are `fnref-1`, `fnref-1-2` and so on
+    footnote_refs: HashMap<String, usize>,
+}
+
+impl HtmlRenderer {
+    pub fn new() -> Self {
+        HtmlRenderer::default()
+    }
+
+    pub fn with_options(options: Options) -> Self {
+        HtmlRenderer {
+            options,
+            ..HtmlRenderer::default()
+        }
+    }
+
+    pub fn options(&self) -> &Options {
+        &self.options
+    }
+
+    pub fn escape(mut self, escape: bool) -> Self {
+        self.options.escape = escape;
+        self
+    }
+
+    pub fn standalone(mut self, standalone: bool) -> Self {
+        self.options.standalone = standalone;
+        self
+    }
+
+    pub fn xhtml(mut self, xhtml: bool) -> Self {
+        self.options.xhtml = xhtml;
+        self
+    }
+
+    pub fn pretty(mut self, pretty: bool) -> Self {
+        self.options.pretty = pretty;
+        self
+    }
+
+    pub fn wrap_lists(mut self, wrap_lists: bool) -> Self {
+        self.options.wrap_lists = wrap_lists;
+        self
+    }
+
+    pub fn heading_offset(mut self, offset: usize) -> Self {
+        self.options.heading_offset = offset;
+        self
+    }
+
+    pub fn class_prefix(mut self, prefix: &str) -> Self {
+        self.options.class_prefix = prefix.to_string();
+        self
+    }
+
+    // writes `to` wherever `from` would be written
+    pub fn tag(mut self, from: &str, to: &str) -> Self {
+        self.options.tags.insert(from.to_string(), to.to_string());
+        self
+    }
+
+    pub fn sourcepos(mut self, sourcepos: bool) -> Self {
+        self.options.sourcepos = sourcepos;
+        self
+    }
+
+    pub fn sanitize(mut self, sanitize: bool) -> Self {
+        self.options.sanitize = sanitize;
+        self
+    }
+
+    pub fn lazy_images(mut self, lazy: bool) -> Self {
+        self.options.lazy_images = lazy;
+        self
+    }
+
+    pub fn figures(mut self, figures: bool) -> Self {
+        self.options.figures = figures;
+        self
+    }
+
+    #[cfg(feature = "image-size")]
+    pub fn image_sizes(mut self, sizes: bool) -> Self {
+        self.options.image_sizes = sizes;
+        self
+    }
+
+    pub fn base_dir(mut self, dir: &Path) -> Self {
+        self.options.base_dir = Some(dir.to_path_buf());
+        self
+    }
+
+    pub fn self_contained(mut self, self_contained: bool) -> Self {
+        self.options.self_contained = self_contained;
+        self
+    }
+
+    pub fn link_extension(mut self, extension: &str) -> Self {
+        self.options.link_extension = Some(extension.to_string());
+        self
+    }
+
+    pub fn block_script_urls(mut self, block: bool) -> Self {
+        self.options.block_script_urls = block;
+        self
+    }
+
+    pub fn base_url(mut self, base: &str) -> Self {
+        self.options.base_url = Some(base.to_string());
+        self
+    }
+
+    pub fn force_https(mut self, force: bool) -> Self {
+        self.options.force_https = force;
+        self
+    }
+
+    // `target="_blank"` for example, on the links to other sites
+    pub fn external_link_attribute(mut self, name: &str, value: &str) -> Self {
+        self.options
+            .external_link_attributes
+            .insert(name.to_string(), value.to_string());
+        self
+    }
+
+    pub fn external_link_class(mut self, class: &str) -> Self {
+        self.options.external_link_class = Some(class.to_string());
+        self
+    }
+
+    // `rewrite` is given the url of every link, image and wiki link, and returns the one to write
+    pub fn rewrite_link(mut self, rewrite: fn(&LinkContext) -> Option<String>) -> Self {
+        self.options.rewrite_link = Some(rewrite);
+        self
+    }
+
+    pub fn render(&self, md: Vec<Markdown>) -> String {
+        translate(md, &self.options)
+    }
+
+    pub fn render_context(&self, md: Vec<Markdown>) -> BTreeMap<String, String> {
+        translate_context(md, &self.options)
+    }
+
+    // the blocks of `parse_spanned`, marked with `data-sourcepos` when it is on
+    pub fn render_spanned(&self, md: Vec<Spanned<Markdown>>) -> String {
+        let (md, spans): (Vec<Markdown>, Vec<Span>) =
+            md.into_iter().map(|bit| (bit.node, bit.span)).unzip();
+        translate_with_spans(md, &spans, &self.options)
+    }
+}
+
+// the leaf translators write their text escaped, the containers write their tags around the content
+impl Render for HtmlRenderer {
+    fn heading_start(&mut self, out: &mut String, level: usize, attributes: &Attributes) {
+        let size = (level + self.options.heading_offset).min(6);
+        let _ = write!(out, "<h{}", size);
+        translate_attributes(out, attributes, &self.options);
+        out.push('>');
+    }
+
+    fn heading_end(&mut self, out: &mut String, level: usize, attributes: &Attributes) {
+        let options = &self.options;
+        if let (Some(id), true) = (&attributes.id, options.heading_anchors) {
+            let _ = write!(out, "<a class=\"{}\" href=\"#", class("anchor", options));
+            escape_into(out, id, options);
+            out.push_str("\">¶</a>");
+        }
+        let _ = write!(out, "</h{}>", (level + options.heading_offset).min(6));
+    }
+
+    fn paragraph_start(&mut self, out: &mut String) {
+        let before = out.len();
+        open(out, "p", &self.options);
+        if self.options.figures {
+            self.paragraph = Some((before, out.len()));
+        }
+    }
+
+    // a paragraph of nothing but an image becomes a figure, captioned with its title or alt text
+    fn paragraph_end(&mut self, out: &mut String) {
+        let options = &self.options;
+        if let (Some((before, start)), Some((image_start, image_end, caption))) =
+            (self.paragraph.take(), self.image.take())
+        {
+            if image_start == start && image_end == out.len() {
+                let image = out.split_off(start);
+                out.truncate(before);
+                out.push_str("<figure>");
+                out.push_str(&image);
+                if !caption.is_empty() {
+                    element(out, "figcaption", &caption, options);
+                }
+                out.push_str("</figure>");
+                return;
+            }
+        }
+        close(out, "p", options);
+    }
+
+    fn soft_break(&mut self, out: &mut String) {
+        out.push(' ');
+    }
+
+    fn list_start(&mut self, out: &mut String, start: Option<usize>) {
+        match start {
+            None => out.push_str("<ul>"),
+            Some(1) => out.push_str("<ol>"),
+            Some(start) => {
+                let _ = write!(out, "<ol start=\"{}\">", start);
+            }
+        }
+    }
+
+    fn list_end(&mut self, out: &mut String, start: Option<usize>) {
+        match start {
+            None => out.push_str("</ul>"),
+            Some(_) => out.push_str("</ol>"),
+        }
+    }
+
+    fn item_start(&mut self, out: &mut String) {
+        out.push_str("<li>");
+    }
+
+    fn item_text_start(&mut self, out: &mut String) {
+        if self.options.wrap_lists {
+            open(out, "p", &self.options);
+        }
+    }
+
+    fn item_text_end(&mut self, out: &mut String) {
+        if self.options.wrap_lists {
+            close(out, "p", &self.options);
+        }
+    }
+
+    fn item_end(&mut self, out: &mut String) {
+        out.push_str("</li>");
+    }
+
+    fn definition_list_start(&mut self, out: &mut String) {
+        out.push_str("<dl>");
+    }
+
+    fn definition_list_end(&mut self, out: &mut String) {
+        out.push_str("</dl>");
+    }
+
+    fn term_start(&mut self, out: &mut String) {
+        out.push_str("<dt>");
+    }
+
+    fn term_end(&mut self, out: &mut String) {
+        out.push_str("</dt>");
+    }
+
+    fn definition_start(&mut self, out: &mut String) {
+        out.push_str("<dd>");
+    }
+
+    fn definition_end(&mut self, out: &mut String) {
+        out.push_str("</dd>");
+    }
+
+    fn codeblock(&mut self, out: &mut String, lang: &str, code: &str, _: &str) {
+        let options = &self.options;
+        if DIAGRAMS.contains(&lang) {
+            translate_diagram(out, lang, code, options);
+        } else {
+            let class = translate_code_class(lang, options);
+            if class.is_empty() {
+                out.push_str("<pre><code>");
+            } else {
+                let _ = write!(out, "<pre><code class=\"{}\">", class);
+            }
+            translate_code(out, lang, code, options);
+            out.push_str("</code></pre>");
+        }
+    }
+
+    fn horizontal_rule(&mut self, out: &mut String) {
+        out.push_str("<hr");
+        out.push_str(void_end(&self.options));
+        out.push('>');
+    }
+
+    fn table_start(&mut self, out: &mut String, _: &[Align]) {
+        out.push_str("<table>");
+    }
+
+    fn table_head_start(&mut self, out: &mut String) {
+        out.push_str("<thead>");
+    }
+
+    fn table_head_end(&mut self, out: &mut String) {
+        out.push_str("</thead>");
+    }
+
+    fn table_body_start(&mut self, out: &mut String) {
+        out.push_str("<tbody>");
+    }
+
+    fn table_body_end(&mut self, out: &mut String) {
+        out.push_str("</tbody>");
+    }
+
+    fn table_row_start(&mut self, out: &mut String, _: bool) {
+        out.push_str("<tr>");
+    }
+
+    fn table_row_end(&mut self, out: &mut String, _: bool) {
+        out.push_str("</tr>");
+    }
+
+    fn table_cell_start(&mut self, out: &mut String, _: usize, align: Align, header: bool) {
+        out.push_str(if header { "<th" } else { "<td" });
+        out.push_str(translate_align(align));
+        out.push('>');
+    }
+
+    fn table_cell_end(&mut self, out: &mut String, header: bool) {
+        out.push_str(if header { "</th>" } else { "</td>" });
+    }
+
+    fn table_end(&mut self, out: &mut String) {
+        out.push_str("</table>");
+    }
+
+    fn blockquote_start(&mut self, out: &mut String) {
+        open(out, "blockquote", &self.options);
+    }
+
+    fn blockquote_end(&mut self, out: &mut String) {
+        close(out, "blockquote", &self.options);
+    }
+
+    fn admonition_start(&mut self, out: &mut String, kind: &str, title: &Option<String>) {
+        let options = &self.options;
+        out.push_str("<div class=\"");
+        escape_into(
+            out,
+            &class(&format!("admonition {}", kind), options),
+            options,
+        );
+        let _ = write!(
+            out,
+            "\"><p class=\"{}\">",
+            class("admonition-title", options)
+        );
+        escape_into(out, &admonition_title(kind, title), options);
+        out.push_str("</p>");
+    }
+
+    fn admonition_end(&mut self, out: &mut String) {
+        out.push_str("</div>");
+    }
+
+    fn div_start(&mut self, out: &mut String, attributes: &Attributes) {
+        out.push_str("<div");
+        translate_attributes(out, attributes, &self.options);
+        out.push('>');
+    }
+
+    fn div_end(&mut self, out: &mut String) {
+        out.push_str("</div>");
+    }
+
+    fn details_start(&mut self, out: &mut String, open: bool) {
+        out.push_str(match (open, self.options.xhtml) {
+            (false, _) => "<details>",
+            (true, false) => "<details open>",
+            (true, true) => "<details open=\"open\">",
+        });
+    }
+
+    fn summary_start(&mut self, out: &mut String) {
+        out.push_str("<summary>");
+    }
+
+    fn summary_end(&mut self, out: &mut String) {
+        out.push_str("</summary>");
+    }
+
+    fn details_end(&mut self, out: &mut String) {
+        out.push_str("</details>");
+    }
+
+    fn math_block(&mut self, out: &mut String, tex: &str) {
+        let _ = write!(
+            out,
+            "<div class=\"{}\">\\[",
+            class("math display", &self.options)
+        );
+        escape_into(out, tex, &self.options);
+        out.push_str("\\]</div>");
+    }
+
+    // an ordered list cannot count in symbols, so they are written in the items
+    fn footnotes_start(&mut self, out: &mut String) {
+        let options = &self.options;
+        let _ = write!(
+            out,
+            "<section class=\"{}\"><ol{}>",
+            class("footnotes", options),
+            if options.footnote_symbols {
+                " style=\"list-style: none\""
+            } else {
+                ""
             }
-            Markdown::Line(line) => translate_line(line.to_vec()),
-            Markdown::HorizontalRule => translate_horizontal_rule(),
+        );
+    }
+
+    fn footnote_start(&mut self, out: &mut String, number: usize) {
+        let _ = write!(out, "<li id=\"fn-{}\">", number);
+        if self.options.footnote_symbols {
+            let _ = write!(
+                out,
+                "<sup>{}</sup> ",
+                footnote_marker(number, &self.options)
+            );
+        }
+    }
+
+    // a link back to each reference, numbered from the second one as GFM does
+    fn footnote_end(&mut self, out: &mut String, number: usize) {
+        let refs = self.footnote_refs.get(&number.to_string()).copied();
+        for k in 1..=refs.unwrap_or(1) {
+            let _ = write!(
+                out,
+                " <a href=\"#{}\" class=\"{}\">↩{}</a>",
+                footnote_ref_id(number, k),
+                class("footnote-backref", &self.options),
+                if k > 1 {
+                    format!("<sup>{}</sup>", k)
+                } else {
+                    String::new()
+                }
+            );
+        }
+        out.push_str("</li>");
+    }
+
+    fn footnotes_end(&mut self, out: &mut String) {
+        out.push_str("</ol></section>");
+    }
+
+    fn html(&mut self, out: &mut String, html: &str) {
+        if self.options.sanitize {
+            out.push_str(&sanitize::sanitize_pieces(html, &mut self.dropped));
+        } else {
+            out.push_str(html);
+        }
+    }
+
+    fn comment(&mut self, out: &mut String, text: &str) {
+        if self.options.keep_comments {
+            let _ = write!(out, "<!--{}-->", text);
+        }
+    }
+
+    fn text(&mut self, out: &mut String, text: &str) {
+        if self.dropped.is_none() {
+            escape_into(out, text, &self.options);
+        }
+    }
+
+    fn bold_start(&mut self, out: &mut String) {
+        open(out, "b", &self.options);
+    }
+
+    fn bold_end(&mut self, out: &mut String) {
+        close(out, "b", &self.options);
+    }
+
+    fn italic_start(&mut self, out: &mut String) {
+        open(out, "i", &self.options);
+    }
+
+    fn italic_end(&mut self, out: &mut String) {
+        close(out, "i", &self.options);
+    }
+
+    fn mark_start(&mut self, out: &mut String) {
+        open(out, "mark", &self.options);
+    }
+
+    fn mark_end(&mut self, out: &mut String) {
+        close(out, "mark", &self.options);
+    }
+
+    fn strike(&mut self, out: &mut String, text: &str) {
+        element(out, "s", text, &self.options);
+    }
+
+    fn superscript(&mut self, out: &mut String, text: &str) {
+        element(out, "sup", text, &self.options);
+    }
+
+    fn subscript(&mut self, out: &mut String, text: &str) {
+        element(out, "sub", text, &self.options);
+    }
+
+    fn kbd(&mut self, out: &mut String, keys: &[String]) {
+        for (k, key) in keys.iter().enumerate() {
+            if k > 0 {
+                out.push('+');
+            }
+            element(out, "kbd", key, &self.options);
+        }
+    }
+
+    fn ruby(&mut self, out: &mut String, base: &str, readings: &[String]) {
+        translate_ruby(out, base, readings, &self.options);
+    }
+
+    fn inline_code(&mut self, out: &mut String, code: &str) {
+        if self.dropped.is_none() {
+            element(out, "code", code, &self.options);
+        }
+    }
+
+    fn link_start(&mut self, out: &mut String, url: &str, title: &Option<String>) {
+        out.push_str("<a");
+        let url = translate_url_attribute(out, "href", url, LinkKind::Link, &self.options);
+        if let Some(url) = url {
+            translate_external_link(out, &url, &self.options);
+        }
+        translate_title_attribute(out, title, &self.options);
+        out.push('>');
+    }
+
+    fn link_end(&mut self, out: &mut String) {
+        out.push_str("</a>");
+    }
+
+    fn image(&mut self, out: &mut String, alt: &str, url: &str, title: &Option<String>) {
+        let options = &self.options;
+        let start = out.len();
+        out.push_str("<img");
+        translate_url_attribute(out, "src", url, LinkKind::Image, options);
+        out.push_str(" alt=\"");
+        escape_into(out, alt, options);
+        out.push('"');
+        translate_title_attribute(out, title, options);
+        #[cfg(feature = "image-size")]
+        translate_image_size(out, url, options);
+        if options.lazy_images {
+            out.push_str(" loading=\"lazy\"");
+        }
+        out.push_str(void_end(options));
+        out.push('>');
+        if options.figures {
+            let caption = title.clone().unwrap_or_else(|| alt.to_string());
+            self.image = Some((start, out.len(), caption));
+        }
+    }
+
+    fn wikilink(&mut self, out: &mut String, target: &str, label: &Option<String>) {
+        let options = &self.options;
+        let _ = write!(out, "<a class=\"{}\"", class("wikilink", options));
+        let url = wikilink_url(target, options);
+        translate_url_attribute(out, "href", &url, LinkKind::WikiLink, options);
+        out.push('>');
+        escape_into(out, &wikilink_text(target, label), options);
+        out.push_str("</a>");
+    }
+
+    fn footnote_ref(&mut self, out: &mut String, number: &str) {
+        let refs = self.footnote_refs.entry(number.to_string()).or_default();
+        *refs += 1;
+        let options = &self.options;
+        let marker = match number.parse() {
+            Ok(number) => footnote_marker(number, options),
+            Err(_) => number.to_string(),
+        };
+        let _ = write!(
+            out,
+            "<sup class=\"{}\"><a href=\"#fn-{}\" id=\"{}\">{}</a></sup>",
+            class("footnote-ref", options),
+            number,
+            footnote_ref_id(number, *refs),
+            marker
+        );
+    }
+
+    fn math(&mut self, out: &mut String, tex: &str) {
+        let _ = write!(
+            out,
+            "<span class=\"{}\">\\(",
+            class("math inline", &self.options)
+        );
+        escape_into(out, tex, &self.options);
+        out.push_str("\\)</span>");
+    }
+
+    fn emoji(&mut self, out: &mut String, name: &str) {
+        translate_emoji(out, name, &self.options);
+    }
+
+    fn line_break(&mut self, out: &mut String) {
+        let _ = write!(out, "<br{}>", void_end(&self.options));
+    }
+
+    // `data-sourcepos="1:1-2:5"` on the element of the block, where the end is inclusive
+    fn spanned(&mut self, out: &mut String, start: usize, span: &Span) {
+        let block = &out[start..];
+        if !self.options.sourcepos || !block.starts_with('<') || block.starts_with("<!") {
+            return;
+        }
+        let k = start + block.find([' ', '>', '/']).unwrap_or(block.len());
+        out.insert_str(
+            k,
+            &format!(
+                " data-sourcepos=\"{}:{}-{}:{}\"",
+                span.start.line,
+                span.start.column,
+                span.end.line,
+                span.end.column.saturating_sub(1)
+            ),
+        );
+    }
+}
+
+pub fn translate(md: Vec<Markdown>, options: &Options) -> String {
+    translate_with_spans(md, &[], options)
+}
+
+// `spans` are of the top-level blocks, as `parse_spanned` gives, for `sourcepos`
+pub fn translate_with_spans(md: Vec<Markdown>, spans: &[Span], options: &Options) -> String {
+    if options.standalone {
+        let title = translate_title(&md);
+        let meta = translate_meta(&md, options);
+        let body = translate_body(md, spans, options);
+        translate_document(title.unwrap_or_default(), &meta, body, options)
+    } else {
+        translate_body(md, spans, options)
+    }
+}
+
+// the values a template can refer to: every front matter field, `title`, `body`,
+// `excerpt`, the html of `excerpt::excerpt`, and `meta`, the social meta tags.
+// all of them but the html are escaped
+pub fn translate_context(md: Vec<Markdown>, options: &Options) -> BTreeMap<String, String> {
+    translate_context_with_spans(md, &[], options)
+}
+
+pub fn translate_context_with_spans(
+    md: Vec<Markdown>,
+    spans: &[Span],
+    options: &Options,
+) -> BTreeMap<String, String> {
+    let mut context: BTreeMap<String, String> = md
+        .iter()
+        .filter_map(|bit| match bit {
+            Markdown::FrontMatter(front_matter) => Some(front_matter.clone()),
+            _ => None,
+        })
+        .flatten()
+        .map(|(key, value)| (key, escape(&value, options)))
+        .collect();
+    let title = translate_title(&md).unwrap_or_default();
+    context.insert(String::from("title"), escape(&title, options));
+    context.insert(String::from("meta"), translate_meta(&md, options));
+    let excerpt = excerpt::excerpt(&md);
+    context.insert(
+        String::from("excerpt"),
+        translate_body(excerpt, &[], options),
+    );
+    if let Some(css) = highlight_css(options) {
+        context.insert(String::from("highlight_css"), css.to_string());
+    }
+    context.insert(String::from("body"), translate_body(md, spans, options));
+    context
+}
+
+fn translate_body(md: Vec<Markdown>, spans: &[Span], options: &Options) -> String {
+    let body =
+        render::render_with_spans(md, spans, &mut HtmlRenderer::with_options(options.clone()));
+    if options.pretty {
+        pretty::pretty(&body)
+    } else {
+        body
+    }
+}
+
+// the Open Graph and Twitter card tags of the `title`, `description`, `image` and `author`
+// of the front matter. the title is the first heading and the description the text of
+// the first paragraph when the front matter has none
+pub(crate) fn translate_meta(md: &[Markdown], options: &Options) -> String {
+    let front_matter = md.iter().find_map(|bit| match bit {
+        Markdown::FrontMatter(front_matter) => Some(front_matter),
+        _ => None,
+    });
+    let field = |key: &str| {
+        front_matter
+            .and_then(|front_matter| front_matter.get(key))
+            .cloned()
+    };
+    let description = field("description").or_else(|| {
+        md.iter().find_map(|bit| match bit {
+            Markdown::Paragraph(lines) => Some(summarize(&join_lines(lines, " ", plain_text))),
+            _ => None,
+        })
+    });
+    let image = field("image").and_then(|url| rewrite_url(&url, LinkKind::Image, options));
+    let card = if image.is_some() {
+        "summary_large_image"
+    } else {
+        "summary"
+    };
+    let tags = [
+        ("property", "og:type", Some(String::from("article"))),
+        ("property", "og:title", translate_title(md)),
+        ("property", "og:description", description.clone()),
+        ("property", "og:image", image.clone()),
+        ("name", "twitter:card", Some(String::from(card))),
+        ("name", "twitter:title", translate_title(md)),
+        ("name", "twitter:description", description.clone()),
+        ("name", "twitter:image", image),
+        ("name", "description", description),
+        ("name", "author", field("author")),
+    ];
+    let mut meta = String::new();
+    for (attribute, name, content) in tags {
+        if let Some(content) = content.filter(|content| !content.is_empty()) {
+            meta.push_str(&format!(
+                "<meta {}=\"{}\" content=\"{}\"{}>\n",
+                attribute,
+                name,
+                escape(&content, options),
+                void_end(options)
+            ));
+        }
+    }
+    meta
+}
+
+// the text up to the end of the word around 200 characters, which the cards show of a description
+fn summarize(text: &str) -> String {
+    const LENGTH: usize = 200;
+    if text.chars().count() <= LENGTH {
+        return text.to_string();
+    }
+    let mut summary = String::new();
+    for word in text.split_whitespace() {
+        if !summary.is_empty() && summary.chars().count() + word.chars().count() >= LENGTH {
+            break;
+        }
+        if !summary.is_empty() {
+            summary.push(' ');
+        }
+        summary.push_str(word);
+    }
+    summary.push('…');
+    summary
+}
+
+// the title of the document is taken from the front matter, or else the first heading
+pub(crate) fn translate_title(md: &[Markdown]) -> Option<String> {
+    let from_front_matter = md.iter().find_map(|bit| match bit {
+        Markdown::FrontMatter(front_matter) => front_matter.get("title").cloned(),
+        _ => None,
+    });
+    from_front_matter.or_else(|| {
+        md.iter().find_map(|bit| match bit {
+            Markdown::Heading(_, text, _) => Some(plain_text(text)),
+            _ => None,
+        })
+    })
+}
+
+// gives every heading without an id one made from its text.
+// a slug which is already taken, by then or by an explicit id, gets `-1`, `-2`, ... appended
+pub(crate) fn assign_heading_ids(md: Vec<Markdown>) -> Vec<Markdown> {
+    fn explicit(md: &[Markdown], slugs: &mut Slugs) {
+        for bit in md {
+            match bit {
+                Markdown::Heading(_, _, attributes) => {
+                    if let Some(id) = &attributes.id {
+                        slugs.insert(id.clone());
+                    }
+                }
+                Markdown::Blockquote(inner)
+                | Markdown::Admonition(_, _, inner)
+                | Markdown::Div(_, inner)
+                | Markdown::Details(_, _, inner) => explicit(inner, slugs),
+                _ => {}
+            }
+        }
+    }
+    fn assign(md: Vec<Markdown>, slugs: &mut Slugs) -> Vec<Markdown> {
+        md.into_iter()
+            .map(|bit| match bit {
+                Markdown::Heading(size, text, mut attributes) => {
+                    if attributes.id.is_none() {
+                        attributes.id = Some(slugs.unique(&plain_text(&text)));
+                    }
+                    Markdown::Heading(size, text, attributes)
+                }
+                Markdown::Blockquote(inner) => Markdown::Blockquote(assign(inner, slugs)),
+                Markdown::Admonition(kind, title, inner) => {
+                    Markdown::Admonition(kind, title, assign(inner, slugs))
+                }
+                Markdown::Div(attributes, inner) => Markdown::Div(attributes, assign(inner, slugs)),
+                Markdown::Details(summary, open, inner) => {
+                    Markdown::Details(summary, open, assign(inner, slugs))
+                }
+                bit => bit,
+            })
+            .collect()
+    }
+    let mut slugs = Slugs::default();
+    explicit(&md, &mut slugs);
+    assign(md, &mut slugs)
+}
+
+// the text without any markup, for places where html is not allowed
+pub(crate) fn plain_text(text: &MarkdownText) -> String {
+    text.iter()
+        .map(|part| match part {
+            MarkdownInline::Bold(text)
+            | MarkdownInline::Italic(text)
+            | MarkdownInline::Mark(text) => plain_text(text),
+            MarkdownInline::Strike(text)
+            | MarkdownInline::Superscript(text)
+            | MarkdownInline::Subscript(text)
+            | MarkdownInline::InlineCode(text)
+            | MarkdownInline::Math(text)
+            | MarkdownInline::Plaintext(text) => text.to_string(),
+            MarkdownInline::Kbd(keys) => keys.join("+"),
+            MarkdownInline::Ruby(base, _) => base.to_string(),
+            MarkdownInline::Link(text, _, _) | MarkdownInline::Image(text, _, _) => {
+                plain_text(text)
+            }
+            MarkdownInline::Emoji(name) => emoji_text(name),
+            MarkdownInline::WikiLink(target, label) => wikilink_text(target, label),
+            MarkdownInline::FootnoteRef(_)
+            | MarkdownInline::Html(_)
+            | MarkdownInline::Comment(_) => String::new(),
+            MarkdownInline::LineBreak => String::from(" "),
         })
         .collect::<Vec<String>>()
         .join("")
 }
 
-fn translate_horizontal_rule() -> String {
-    format!("<hr />")
+// the label of a wiki link, which is its target unless given
+pub(crate) fn wikilink_text(target: &str, label: &Option<String>) -> String {
+    label.clone().unwrap_or_else(|| target.to_string())
+}
+
+// the page and the heading of `Page#Heading` are slugified,
+// and the page is put in the `{}` of `wiki_base`, or appended to it
+fn wikilink_url(target: &str, options: &Options) -> String {
+    let (page, heading) = target.split_once('#').unwrap_or((target, ""));
+    let mut url = if page.is_empty() {
+        String::new()
+    } else if options.wiki_base.contains("{}") {
+        options.wiki_base.replace("{}", &slugify(page))
+    } else {
+        format!("{}{}", options.wiki_base, slugify(page))
+    };
+    if !heading.is_empty() {
+        url += &format!("#{}", slugify(heading));
+    }
+    url
+}
+
+// `漢字(かんじ)`, for the formats without ruby
+pub(crate) fn ruby_text(base: &str, readings: &[String]) -> String {
+    format!("{}({})", base, readings.concat())
+}
+
+// the emoji of a shortcode, or the shortcode itself when the name is unknown
+pub(crate) fn emoji_text(name: &str) -> String {
+    emoji::lookup(name).map_or_else(|| format!(":{}:", name), String::from)
+}
+
+// the title of an admonition, which is its capitalized kind unless given
+pub(crate) fn admonition_title(kind: &str, title: &Option<String>) -> String {
+    title.clone().unwrap_or_else(|| {
+        let mut chars = kind.chars();
+        chars
+            .next()
+            .map_or(String::new(), |c| c.to_uppercase().chain(chars).collect())
+    })
+}
+
+// joins the lines of a paragraph with `separator`,
+// except after a hard line break, which already ends its line
+pub(crate) fn join_lines<F>(lines: &[MarkdownText], separator: &str, mut f: F) -> String
+where
+    F: FnMut(&MarkdownText) -> String,
+{
+    let mut text = String::new();
+    for (k, line) in lines.iter().enumerate() {
+        if k > 0 && lines[k - 1].last() != Some(&MarkdownInline::LineBreak) {
+            text += separator;
+        }
+        text += &f(line);
+    }
+    text
+}
+
+// KaTeX and its auto-render extension, which finds the `\\( \\)` and `\\[ \\]` we write
+const KATEX: &str = "<link rel=\"stylesheet\" href=\"https://cdn.jsdelivr.net/npm/katex@0.16.9/dist/katex.min.css\">
+<script defer src=\"https://cdn.jsdelivr.net/npm/katex@0.16.9/dist/katex.min.js\"></script>
+<script defer src=\"https://cdn.jsdelivr.net/npm/katex@0.16.9/dist/contrib/auto-render.min.js\" onload=\"renderMathInElement(document.body)\"></script>
+";
+
+const MERMAID: &str = "<script type=\"module\">import mermaid from \"https://cdn.jsdelivr.net/npm/mermaid@10/dist/mermaid.esm.min.mjs\"; mermaid.initialize({ startOnLoad: true });</script>
+";
+
+fn translate_document(title: String, meta: &str, body: String, options: &Options) -> String {
+    let stylesheets = options
+        .css
+        .iter()
+        .map(|url| match embedded_stylesheet(url, options) {
+            Some(css) => format!("<style>\n{}</style>\n", css),
+            None => format!(
+                "<link rel=\"stylesheet\" href=\"{}\"{}>\n",
+                escape(url, options),
+                void_end(options)
+            ),
+        })
+        .collect::<Vec<String>>()
+        .join("");
+    let style = highlight_css(options)
+        .map(|css| format!("<style>{}</style>\n", css))
+        .unwrap_or_default();
+    let katex = if options.katex { KATEX } else { "" };
+    let mermaid = if options.diagrams { MERMAID } else { "" };
+    format!(
+        "<!DOCTYPE html>\n<html{}>\n<head>\n<meta charset=\"utf-8\"{}>\n<title>{}</title>\n{}{}{}{}{}</head>\n<body>\n{}\n</body>\n</html>",
+        if options.xhtml {
+            " xmlns=\"http://www.w3.org/1999/xhtml\""
+        } else {
+            ""
+        },
+        void_end(options),
+        escape(&title, options),
+        meta,
+        stylesheets,
+        style,
+        katex,
+        mermaid,
+        body
+    )
+}
+
+// the content of a local file the document links to, when it is to be embedded.
+// it is read from the directory of the document, as the links are relative to it
+fn embedded_file(path: &str, options: &Options) -> Option<Vec<u8>> {
+    if !options.self_contained {
+        return None;
+    }
+    let path = match &options.base_dir {
+        Some(dir) => dir.join(path),
+        None => PathBuf::from(path),
+    };
+    cache::read(&path);
+    std::fs::read(path).ok()
+}
+
+fn embedded_stylesheet(url: &str, options: &Options) -> Option<String> {
+    let content = embedded_file(url::local_path(url)?, options)?;
+    String::from_utf8(content).ok()
+}
+
+// a local image as a `data:` url
+fn embedded_image(url: &str, options: &Options) -> Option<String> {
+    let path = url::local_path(url)?;
+    let media_type = url::image_type(path)?;
+    let content = embedded_file(path, options)?;
+    Some(url::data_url(media_type, &content))
+}
+
+// ` /` closing `<br />` and the like in xhtml
+fn void_end(options: &Options) -> &'static str {
+    if options.xhtml {
+        " /"
+    } else {
+        ""
+    }
+}
+
+// the element written for `name`, which can be renamed in the options
+fn tag<'a>(name: &'a str, options: &'a Options) -> &'a str {
+    options.tags.get(name).map_or(name, String::as_str)
+}
+
+fn open(out: &mut String, name: &str, options: &Options) {
+    out.push('<');
+    out.push_str(tag(name, options));
+    out.push('>');
+}
+
+fn close(out: &mut String, name: &str, options: &Options) {
+    out.push_str("</");
+    out.push_str(tag(name, options));
+    out.push('>');
+}
+
+// the text escaped in the element
+fn element(out: &mut String, name: &str, text: &str, options: &Options) {
+    open(out, name, options);
+    escape_into(out, text, options);
+    close(out, name, options);
+}
+
+// the classes we generate, each of them prefixed
+fn class(names: &str, options: &Options) -> String {
+    names
+        .split(' ')
+        .map(|name| format!("{}{}", options.class_prefix, name))
+        .collect::<Vec<String>>()
+        .join(" ")
+}
+
+fn escape(text: &str, options: &Options) -> String {
+    let mut out = String::with_capacity(text.len());
+    escape_into(&mut out, text, options);
+    out
+}
+
+// the text is copied a run at a time, up to each character to escape
+fn escape_into(out: &mut String, text: &str, options: &Options) {
+    if !options.escape && !options.sanitize {
+        out.push_str(text);
+        return;
+    }
+    let mut last = 0;
+    for (k, c) in text.bytes().enumerate() {
+        let entity = match c {
+            b'&' => "&amp;",
+            b'<' => "&lt;",
+            b'>' => "&gt;",
+            b'"' => "&quot;",
+            b'\'' => "&#39;",
+            _ => continue,
+        };
+        out.push_str(&text[last..k]);
+        out.push_str(entity);
+        last = k + 1;
+    }
+    out.push_str(&text[last..]);
+}
+
+// ` id="x" class="a b" key="value"`, with a leading space unless there is nothing
+fn translate_attributes(out: &mut String, attributes: &Attributes, options: &Options) {
+    if let Some(id) = &attributes.id {
+        out.push_str(" id=\"");
+        escape_into(out, id, options);
+        out.push('"');
+    }
+    if !attributes.classes.is_empty() {
+        out.push_str(" class=\"");
+        escape_into(out, &attributes.classes.join(" "), options);
+        out.push('"');
+    }
+    for (key, value) in &attributes.pairs {
+        if options.sanitize && !sanitize::is_safe_attribute(key, value) {
+            continue;
+        }
+        out.push(' ');
+        escape_into(out, key, options);
+        out.push_str("=\"");
+        escape_into(out, value, options);
+        out.push('"');
+    }
 }
 
-fn translate_boldtext(boldtext: String) -> String {
-    format!("<b>{}</b>", boldtext)
+// the images are the ones GitHub serves, named by the code points
+fn translate_emoji(out: &mut String, name: &str, options: &Options) {
+    match emoji::lookup(name) {
+        Some(emoji) if options.emoji_images => {
+            let _ = write!(
+                out,
+                "<img class=\"{}\" alt=\"{}\" src=\"https://github.githubassets.com/images/icons/emoji/unicode/{}.png\"{}>",
+                class("emoji", options),
+                emoji,
+                emoji::codepoints(emoji),
+                void_end(options)
+            );
+        }
+        _ => escape_into(out, &emoji_text(name), options),
+    }
 }
 
-fn translate_italic(italic: String) -> String {
-    format!("<i>{}</i>", italic)
+// a reading for each character is put right after it
+fn translate_ruby(out: &mut String, base: &str, readings: &[String], options: &Options) {
+    out.push_str("<ruby>");
+    if readings.len() == 1 {
+        escape_into(out, base, options);
+        out.push_str("<rt>");
+        escape_into(out, &readings[0], options);
+        out.push_str("</rt>");
+    } else {
+        let mut buffer = [0; 4];
+        for (c, reading) in base.chars().zip(readings) {
+            escape_into(out, c.encode_utf8(&mut buffer), options);
+            out.push_str("<rt>");
+            escape_into(out, reading, options);
+            out.push_str("</rt>");
+        }
+    }
+    out.push_str("</ruby>");
 }
 
-fn translate_strike(italic: String) -> String {
-    format!("<s>{}</s>", italic)
+fn translate_title_attribute(out: &mut String, title: &Option<String>, options: &Options) {
+    if let Some(title) = title {
+        out.push_str(" title=\"");
+        escape_into(out, title, options);
+        out.push('"');
+    }
 }
 
-fn translate_inline_code(code: String) -> String {
-    format!("<code>{}</code>", code)
+// ` width="640" height="480"`, read from the file of a local image
+#[cfg(feature = "image-size")]
+fn translate_image_size(out: &mut String, url: &str, options: &Options) {
+    if !options.image_sizes {
+        return;
+    }
+    let path = match (url::local_path(url), &options.base_dir) {
+        (Some(path), Some(dir)) => dir.join(path),
+        (Some(path), None) => std::path::PathBuf::from(path),
+        (None, _) => return,
+    };
+    cache::read(&path);
+    if let Ok(size) = imagesize::size(path) {
+        let _ = write!(out, " width=\"{}\" height=\"{}\"", size.width, size.height);
+    }
 }
 
-fn translate_link(text: String, url: String) -> String {
-    format!("<a href=\"{}\">{}</a>", url, text)
+// ` href="url"` with the url rewritten, left out when it is blocked
+fn translate_url_attribute(
+    out: &mut String,
+    name: &str,
+    url: &str,
+    kind: LinkKind,
+    options: &Options,
+) -> Option<String> {
+    let url = match rewrite_url(url, kind, options) {
+        Some(url) if !options.sanitize || sanitize::is_safe_url(&url) => url,
+        _ => return None,
+    };
+    let url = match kind {
+        LinkKind::Image => embedded_image(&url, options).unwrap_or(url),
+        _ => url,
+    };
+    let _ = write!(out, " {}=\"", name);
+    escape_into(out, &url, options);
+    out.push('"');
+    Some(url)
 }
 
-fn translate_image(text: String, url: String) -> String {
-    format!("<img src=\"{}\" alt=\"{}\" />", url, text)
+// the class and the attributes of the options on a link to another site
+fn translate_external_link(out: &mut String, url: &str, options: &Options) {
+    let decorated =
+        options.external_link_class.is_some() || !options.external_link_attributes.is_empty();
+    if !decorated || !url::is_external(url, options.base_url.as_deref()) {
+        return;
+    }
+    if let Some(class) = &options.external_link_class {
+        out.push_str(" class=\"");
+        escape_into(out, class, options);
+        out.push('"');
+    }
+    for (name, value) in &options.external_link_attributes {
+        out.push(' ');
+        escape_into(out, name, options);
+        out.push_str("=\"");
+        escape_into(out, value, options);
+        out.push('"');
+    }
 }
 
-fn translate_list_elements(lines: Vec<MarkdownText>) -> String {
-    lines
-        .iter()
-        .map(|line| format!("<li>{}</li>", translate_text(line.to_vec())))
-        .collect::<Vec<String>>()
-        .join("")
+// the url after the policies of the options and then `rewrite_link`, or none when it is blocked
+fn rewrite_url(url: &str, kind: LinkKind, options: &Options) -> Option<String> {
+    if options.block_script_urls && url::is_script(url) {
+        return None;
+    }
+    let mut url = match (&options.link_extension, kind) {
+        (Some(extension), LinkKind::Link) => url::replace_extension(url, "md", extension),
+        _ => url.to_string(),
+    };
+    if let Some(base) = &options.base_url {
+        url = url::resolve(base, &url);
+    }
+    if options.force_https {
+        url = url::force_https(&url);
+    }
+    let context = LinkContext { url: &url, kind };
+    match options.rewrite_link.and_then(|rewrite| rewrite(&context)) {
+        Some(rewritten) => Some(rewritten),
+        None => Some(url),
+    }
+}
+
+// the languages of code blocks which are drawn as diagrams instead
+const DIAGRAMS: [&str; 4] = ["mermaid", "graphviz", "dot", "plantuml"];
+
+// mermaid.js draws `div.mermaid` by itself, the others are left to client-side renderers
+fn translate_diagram(out: &mut String, lang: &str, code: &str, options: &Options) {
+    if lang == "mermaid" {
+        out.push_str("<div class=\"mermaid\">");
+        escape_into(out, code, options);
+        out.push_str("</div>");
+    } else {
+        let _ = write!(out, "<pre class=\"language-{}\">", lang);
+        escape_into(out, code, options);
+        out.push_str("</pre>");
+    }
 }
 
-fn translate_header(size: usize, text: MarkdownText) -> String {
-    format!("<h{}>{}</h{}>", size, translate_text(text), size)
+fn translate_code_class(lang: &str, options: &Options) -> String {
+    if options.language_class && !lang.is_empty() {
+        format!("language-{}", escape(lang, options))
+    } else {
+        escape(lang, options)
+    }
 }
 
-fn translate_unordered_list(lines: Vec<MarkdownText>) -> String {
-    format!("<ul>{}</ul>", translate_list_elements(lines.to_vec()))
+#[cfg(feature = "highlight")]
+fn highlight_css(options: &Options) -> Option<&'static str> {
+    options
+        .highlight
+        .as_ref()
+        .and_then(|theme| highlight::theme_css(theme))
 }
 
-fn translate_ordered_list(lines: Vec<MarkdownText>) -> String {
-    format!("<ol>{}</ol>", translate_list_elements(lines.to_vec()))
+#[cfg(not(feature = "highlight"))]
+fn highlight_css(_: &Options) -> Option<&'static str> {
+    None
 }
 
-fn translate_codeblock(lang: String, code: String) -> String {
-    if lang.is_empty() {
-        format!("<pre><code>{}</code></pre>", code)
-    } else {
-        format!("<pre><code class=\"{}\">{}</code></pre>", lang, code)
+// the code is escaped, and wrapped in `<span>`s when highlighting is on and the language is known
+fn translate_code(out: &mut String, lang: &str, code: &str, options: &Options) {
+    #[cfg(feature = "highlight")]
+    if let Some(html) = options
+        .highlight
+        .as_ref()
+        .and_then(|_| highlight::highlight(lang, code))
+    {
+        out.push_str(&html);
+        return;
     }
+    let _ = lang;
+    escape_into(out, code, options);
 }
 
-fn translate_line(text: MarkdownText) -> String {
-    let line = translate_text(text);
-    if line.len() > 0 {
-        format!("<p>{}</p>", line)
-    } else {
-        format!("{}", line)
+fn translate_align(align: Align) -> &'static str {
+    match align {
+        Align::None => "",
+        Align::Left => " align=\"left\"",
+        Align::Center => " align=\"center\"",
+        Align::Right => " align=\"right\"",
     }
 }
 
-fn translate_text(text: MarkdownText) -> String {
-    text.iter()
-        .map(|part| match part {
-            MarkdownInline::Bold(text) => translate_boldtext(text.to_string()),
-            MarkdownInline::Italic(text) => translate_italic(text.to_string()),
-            MarkdownInline::Strike(text) => translate_strike(text.to_string()),
-            MarkdownInline::InlineCode(code) => translate_inline_code(code.to_string()),
-            MarkdownInline::Link(text, url) => translate_link(text.to_string(), url.to_string()),
-            MarkdownInline::Image(text, url) => translate_image(text.to_string(), url.to_string()),
-            MarkdownInline::Plaintext(text) => text.to_string(),
+// applies `f` to every inline text in the document, including nested blocks
+pub(crate) fn map_text<F>(md: Vec<Markdown>, f: &mut F) -> Vec<Markdown>
+where
+    F: FnMut(MarkdownText) -> MarkdownText,
+{
+    let map_items = |items: Vec<ListItem>, f: &mut F| -> Vec<ListItem> {
+        items
+            .into_iter()
+            .map(|(line, children)| (f(line), map_text(children, f)))
+            .collect()
+    };
+    md.into_iter()
+        .map(|bit| match bit {
+            Markdown::Heading(size, line, attributes) => {
+                Markdown::Heading(size, f(line), attributes)
+            }
+            Markdown::UnorderedList(items) => Markdown::UnorderedList(map_items(items, f)),
+            Markdown::OrderedList(start, items) => {
+                Markdown::OrderedList(start, map_items(items, f))
+            }
+            Markdown::DefinitionList(definitions) => Markdown::DefinitionList(
+                definitions
+                    .into_iter()
+                    .map(|(term, texts)| (f(term), texts.into_iter().map(&mut *f).collect()))
+                    .collect(),
+            ),
+            Markdown::Line(line) => Markdown::Line(f(line)),
+            Markdown::Paragraph(lines) => {
+                Markdown::Paragraph(lines.into_iter().map(&mut *f).collect())
+            }
+            Markdown::Table(header, aligns, rows) => Markdown::Table(
+                header.into_iter().map(&mut *f).collect(),
+                aligns,
+                rows.into_iter()
+                    .map(|row| row.into_iter().map(&mut *f).collect())
+                    .collect(),
+            ),
+            Markdown::Blockquote(inner) => Markdown::Blockquote(map_text(inner, f)),
+            Markdown::Admonition(kind, title, inner) => {
+                Markdown::Admonition(kind, title, map_text(inner, f))
+            }
+            Markdown::Div(attributes, inner) => Markdown::Div(attributes, map_text(inner, f)),
+            Markdown::Details(summary, open, inner) => {
+                Markdown::Details(f(summary), open, map_text(inner, f))
+            }
+            Markdown::FootnoteDefinition(label, text) => {
+                Markdown::FootnoteDefinition(label, f(text))
+            }
+            bit => bit,
         })
-        .collect::<Vec<String>>()
-        .join("")
+        .collect()
+}
+
+pub(crate) fn take_footnote_definitions(
+    md: Vec<Markdown>,
+    definitions: &mut HashMap<String, MarkdownText>,
+) -> Vec<Markdown> {
+    md.into_iter()
+        .filter_map(|bit| match bit {
+            Markdown::FootnoteDefinition(label, text) => {
+                definitions.entry(label).or_insert(text);
+                None
+            }
+            Markdown::Blockquote(inner) => Some(Markdown::Blockquote(take_footnote_definitions(
+                inner,
+                definitions,
+            ))),
+            Markdown::Admonition(kind, title, inner) => Some(Markdown::Admonition(
+                kind,
+                title,
+                take_footnote_definitions(inner, definitions),
+            )),
+            Markdown::Div(attributes, inner) => Some(Markdown::Div(
+                attributes,
+                take_footnote_definitions(inner, definitions),
+            )),
+            Markdown::Details(summary, open, inner) => Some(Markdown::Details(
+                summary,
+                open,
+                take_footnote_definitions(inner, definitions),
+            )),
+            bit => Some(bit),
+        })
+        .collect()
+}
+
+// footnotes are numbered in the order they are first referenced.
+// the references are rewritten to their numbers and the definitions are taken out of the body,
+// references without a definition are left as they were written
+pub(crate) fn collect_footnotes(md: Vec<Markdown>) -> (Vec<Markdown>, Vec<MarkdownText>) {
+    let mut definitions = HashMap::new();
+    let body = take_footnote_definitions(md, &mut definitions);
+    let mut labels: Vec<String> = vec![];
+    let body = map_text(body, &mut |text: MarkdownText| {
+        text.into_iter()
+            .map(|part| match part {
+                MarkdownInline::FootnoteRef(label) if definitions.contains_key(&label) => {
+                    let number = match labels.iter().position(|l| *l == label) {
+                        Some(k) => k + 1,
+                        None => {
+                            labels.push(label);
+                            labels.len()
+                        }
+                    };
+                    MarkdownInline::FootnoteRef(number.to_string())
+                }
+                MarkdownInline::FootnoteRef(label) => {
+                    MarkdownInline::Plaintext(format!("[^{}]", label))
+                }
+                part => part,
+            })
+            .collect()
+    });
+    let notes = labels
+        .iter()
+        .map(|label| definitions[label].to_vec())
+        .collect();
+    (body, notes)
+}
+
+// the blocks before and after the first `[FOOTNOTES]`, the second ones are empty without it
+pub(crate) fn split_at_footnotes(mut md: Vec<Markdown>) -> (Vec<Markdown>, Vec<Markdown>) {
+    match md.iter().position(|bit| *bit == Markdown::Footnotes) {
+        Some(k) => {
+            let rest = md.split_off(k + 1);
+            md.pop();
+            (md, rest)
+        }
+        None => (md, vec![]),
+    }
+}
+
+const FOOTNOTE_SYMBOLS: [&str; 6] = ["*", "†", "‡", "§", "‖", "¶"];
+
+// `fnref-1` for the first reference to a footnote, and `fnref-1-2` for the second
+fn footnote_ref_id(number: impl std::fmt::Display, k: usize) -> String {
+    if k == 1 {
+        format!("fnref-{}", number)
+    } else {
+        format!("fnref-{}-{}", number, k)
+    }
+}
+
+// the marker of the footnote numbered `number`, the symbols are doubled after the sixth
+
+fn footnote_marker(number: usize, options: &Options) -> String {
+    if options.footnote_symbols {
+        let k = number - 1;
+        FOOTNOTE_SYMBOLS[k % FOOTNOTE_SYMBOLS.len()].repeat(k / FOOTNOTE_SYMBOLS.len() + 1)
+    } else {
+        number.to_string()
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    fn translate_text(text: MarkdownText, options: &Options) -> String {
+        render::render_text(&text, &mut HtmlRenderer::with_options(options.clone()))
+    }
+
+    fn translate_blocks(md: Vec<Markdown>, options: &Options) -> String {
+        render::render_blocks(&md, &mut HtmlRenderer::with_options(options.clone()))
+    }
+
+    #[test]
+    fn test_escape() {
+        assert_eq!(
+            escape("a < b & \"c\" > 'd'", &Options::default()),
+            String::from("a &lt; b &amp; &quot;c&quot; &gt; &#39;d&#39;")
+        );
+        assert_eq!(
+            escape(
+                "a < b",
+                &Options {
+                    escape: false,
+                    ..Options::default()
+                }
+            ),
+            String::from("a < b")
+        );
+    }
+
+    #[test]
+    fn test_translate_text_escaped() {
+        assert_eq!(
+            translate_text(
+                vec![
+                    MarkdownInline::Plaintext(String::from("1 < 2 ")),
+                    MarkdownInline::InlineCode(String::from("<script>")),
+                    MarkdownInline::Link(vec![MarkdownInline::Plaintext(String::from("a&b"))], String::from("/?a=1&b=\"2\""), None),
+                    MarkdownInline::Html(String::from("<br>")),
+                ],
+                &Options::default()
+            ),
+            String::from("1 &lt; 2 <code>&lt;script&gt;</code><a href=\"/?a=1&amp;b=&quot;2&quot;\">a&amp;b</a><br>")
+        );
+    }
+
+    #[test]
+    fn test_translate_title() {
+        let mut front_matter = std::collections::BTreeMap::new();
+        front_matter.insert(String::from("title"), String::from("From Front Matter"));
+        let heading = Markdown::Heading(
+            2,
+            vec![
+                MarkdownInline::Plaintext(String::from("The ")),
+                MarkdownInline::Bold(vec![MarkdownInline::Plaintext(String::from("Heading"))]),
+            ],
+            Attributes::default(),
+        );
+        assert_eq!(
+            translate_title(&[Markdown::HorizontalRule, heading.clone()]),
+            Some(String::from("The Heading"))
+        );
+        assert_eq!(
+            translate_title(&[heading, Markdown::FrontMatter(front_matter)]),
+            Some(String::from("From Front Matter"))
+        );
+        assert_eq!(translate_title(&[Markdown::HorizontalRule]), None);
+    }
+
+    #[test]
+    fn test_translate_context() {
+        let mut front_matter = BTreeMap::new();
+        front_matter.insert(String::from("author"), String::from("<me>"));
+        let context = translate_context(
+            vec![
+                Markdown::FrontMatter(front_matter),
+                Markdown::Heading(
+                    1,
+                    vec![MarkdownInline::Plaintext(String::from("T"))],
+                    Attributes::default(),
+                ),
+            ],
+            &Options::default(),
+        );
+        assert_eq!(context["author"], "&lt;me&gt;");
+        assert_eq!(context["title"], "T");
+        assert_eq!(context["body"], "<h1 id=\"t\">T</h1>");
+        assert_eq!(context["excerpt"], "");
+    }
+
+    #[test]
+    fn test_translate_meta() {
+        let md = crate::parse("---\nimage: a.png\n---\n# T\n\nfirst *para*\n\nsecond\n").unwrap();
+        let options = Options {
+            base_url: Some(String::from("https://a.com/")),
+            ..Options::default()
+        };
+        let meta = translate_meta(&md, &options);
+        assert!(meta.starts_with(
+            "<meta property=\"og:type\" content=\"article\">\n\
+             <meta property=\"og:title\" content=\"T\">\n\
+             <meta property=\"og:description\" content=\"first para\">\n\
+             <meta property=\"og:image\" content=\"https://a.com/a.png\">\n\
+             <meta name=\"twitter:card\" content=\"summary_large_image\">\n"
+        ));
+        assert!(!meta.contains("author"));
+        let long = "word ".repeat(100);
+        let summary = summarize(long.trim());
+        assert!(summary.ends_with("word…"));
+        assert!(summary.chars().count() <= 201);
+    }
+
+    #[test]
+    fn test_translate_document() {
+        let options = Options {
+            standalone: true,
+            css: vec![String::from("style.css")],
+            ..Options::default()
+        };
+        assert_eq!(
+            translate_document(String::from("A & B"), "", String::from("<p>x</p>"), &options),
+            String::from("<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>A &amp; B</title>\n<link rel=\"stylesheet\" href=\"style.css\">\n</head>\n<body>\n<p>x</p>\n</body>\n</html>")
+        );
+        let options = Options {
+            standalone: true,
+            katex: true,
+            diagrams: true,
+            ..Options::default()
+        };
+        let document = translate_document(String::new(), "", String::new(), &options);
+        assert!(document.contains("katex.min.js"));
+        assert!(document.contains("mermaid.initialize"));
+    }
+
     #[test]
     fn test_translate_boldtext() {
         assert_eq!(
-            translate_boldtext(String::from("bold af")),
+            translate_text(
+                vec![MarkdownInline::Bold(vec![MarkdownInline::Plaintext(
+                    String::from("bold af")
+                )])],
+                &Options::default()
+            ),
             String::from("<b>bold af</b>")
         );
     }
@@ -113,7 +1743,12 @@ mod tests {
     #[test]
     fn test_translate_italic() {
         assert_eq!(
-            translate_italic(String::from("italic af")),
+            translate_text(
+                vec![MarkdownInline::Italic(vec![MarkdownInline::Plaintext(
+                    String::from("italic af")
+                )])],
+                &Options::default()
+            ),
             String::from("<i>italic af</i>")
         );
     }
@@ -121,7 +1756,10 @@ mod tests {
     #[test]
     fn test_translate_strike() {
         assert_eq!(
-            translate_strike(String::from("hoge")),
+            translate_text(
+                vec![MarkdownInline::Strike(String::from("hoge"))],
+                &Options::default()
+            ),
             String::from("<s>hoge</s>")
         );
     }
@@ -129,78 +1767,514 @@ mod tests {
     #[test]
     fn test_translate_inline_code() {
         assert_eq!(
-            translate_inline_code(String::from("code af")),
+            translate_text(
+                vec![MarkdownInline::InlineCode(String::from("code af"))],
+                &Options::default()
+            ),
             String::from("<code>code af</code>")
         );
     }
 
+    #[test]
+    fn test_html_renderer() {
+        let md = vec![
+            Markdown::Heading(
+                1,
+                vec![MarkdownInline::Bold(vec![MarkdownInline::Plaintext(
+                    String::from("T"),
+                )])],
+                Attributes::default(),
+            ),
+            Markdown::UnorderedList(vec![(
+                vec![
+                    MarkdownInline::Plaintext(String::from("a")),
+                    MarkdownInline::LineBreak,
+                    MarkdownInline::Math(String::from("x")),
+                ],
+                vec![],
+            )]),
+        ];
+        assert_eq!(
+            HtmlRenderer::new().render(md.clone()),
+            "<h1 id=\"t\"><b>T</b></h1><ul><li>a<br><span class=\"math inline\">\\(x\\)</span></li></ul>"
+        );
+        let renderer = HtmlRenderer::new()
+            .xhtml(true)
+            .wrap_lists(true)
+            .heading_offset(1)
+            .class_prefix("md-")
+            .tag("b", "strong");
+        assert_eq!(
+            renderer.render(md),
+            "<h2 id=\"t\"><strong>T</strong></h2><ul><li><p>a<br /><span class=\"md-math md-inline\">\\(x\\)</span></p></li></ul>"
+        );
+    }
+
+    #[test]
+    fn test_translate_xhtml_pretty() {
+        let md = crate::parse("# T\n\n- a ![i](i.png) :smile:\nb  \nc\n\n---\n").unwrap();
+        let options = Options {
+            emoji_images: true,
+            ..Options::default()
+        };
+        let html = HtmlRenderer::with_options(options.clone()).render(md.clone());
+        assert!(html.contains("<li>a <img src=\"i.png\" alt=\"i\"> <img class=\"emoji\""));
+        assert!(html.ends_with(".png\"></li></ul><p>b<br>c</p><hr>"));
+        let xhtml = HtmlRenderer::with_options(options)
+            .xhtml(true)
+            .render(md.clone());
+        assert!(xhtml.contains("<li>a <img src=\"i.png\" alt=\"i\" /> <img class=\"emoji\""));
+        assert!(xhtml.ends_with(".png\" /></li></ul><p>b<br />c</p><hr />"));
+        let md = crate::parse("# T\n\n- a ![i](i.png)\n\n---\n").unwrap();
+        assert_eq!(
+            HtmlRenderer::new().pretty(true).render(md),
+            "<h1 id=\"t\">T</h1>\n<ul>\n  <li>a <img src=\"i.png\" alt=\"i\"></li>\n</ul>\n<hr>"
+        );
+    }
+
+    #[test]
+    fn test_translate_sourcepos() {
+        let md = crate::parse_spanned("# T\n\na\nb\n\n---\n\n<!-- c -->\n").unwrap();
+        assert_eq!(
+            HtmlRenderer::new().sourcepos(true).render_spanned(md.clone()),
+            "<h1 data-sourcepos=\"1:1-1:3\" id=\"t\">T</h1><p data-sourcepos=\"3:1-4:1\">a b</p><hr data-sourcepos=\"6:1-6:3\">"
+        );
+        assert_eq!(
+            HtmlRenderer::new().render_spanned(md),
+            "<h1 id=\"t\">T</h1><p>a b</p><hr>"
+        );
+    }
+
     #[test]
     fn test_translate_link() {
         assert_eq!(
-            translate_link(
-                String::from("click me!"),
-                String::from("https://github.com")
+            translate_text(
+                vec![MarkdownInline::Link(
+                    vec![MarkdownInline::Plaintext(String::from("click me!"))],
+                    String::from("https://github.com"),
+                    None
+                )],
+                &Options::default()
             ),
             String::from("<a href=\"https://github.com\">click me!</a>")
         );
+        assert_eq!(
+            translate_text(
+                vec![MarkdownInline::Link(
+                    vec![MarkdownInline::Plaintext(String::from("x"))],
+                    String::from("u"),
+                    Some(String::from("hint"))
+                )],
+                &Options::default()
+            ),
+            String::from("<a href=\"u\" title=\"hint\">x</a>")
+        );
     }
 
     #[test]
     fn test_translate_image() {
         assert_eq!(
-            translate_image(String::from("alt text"), String::from("https://github.com")),
-            String::from("<img src=\"https://github.com\" alt=\"alt text\" />")
+            translate_text(
+                vec![MarkdownInline::Image(
+                    vec![MarkdownInline::Plaintext(String::from("alt text"))],
+                    String::from("https://github.com"),
+                    None
+                )],
+                &Options::default()
+            ),
+            String::from("<img src=\"https://github.com\" alt=\"alt text\">")
+        );
+        assert_eq!(
+            translate_text(
+                vec![MarkdownInline::Image(
+                    vec![MarkdownInline::Plaintext(String::from("a"))],
+                    String::from("i.png"),
+                    Some(String::from("hint"))
+                )],
+                &Options::default()
+            ),
+            String::from("<img src=\"i.png\" alt=\"a\" title=\"hint\">")
         );
     }
 
     #[test]
     fn test_translate_text() {
-        let x = translate_text(vec![
-            MarkdownInline::Plaintext(String::from(
-                "Foobar is a Python library for dealing with word pluralization.",
-            )),
-            MarkdownInline::Bold(String::from("bold")),
-            MarkdownInline::Italic(String::from("italic")),
-            MarkdownInline::InlineCode(String::from("code")),
-            MarkdownInline::Link(String::from("tag"), String::from("https://link.com")),
-            MarkdownInline::Image(String::from("tag"), String::from("https://link.com")),
-            MarkdownInline::Plaintext(String::from(". the end!")),
-        ]);
-        assert_eq!(x, String::from("Foobar is a Python library for dealing with word pluralization.<b>bold</b><i>italic</i><code>code</code><a href=\"https://link.com\">tag</a><img src=\"https://link.com\" alt=\"tag\" />. the end!"));
-        let x = translate_text(vec![]);
+        let x = translate_text(
+            vec![
+                MarkdownInline::Plaintext(String::from(
+                    "Foobar is a Python library for dealing with word pluralization.",
+                )),
+                MarkdownInline::Bold(vec![MarkdownInline::Plaintext(String::from("bold"))]),
+                MarkdownInline::Italic(vec![MarkdownInline::Plaintext(String::from("italic"))]),
+                MarkdownInline::InlineCode(String::from("code")),
+                MarkdownInline::Link(
+                    vec![MarkdownInline::Plaintext(String::from("tag"))],
+                    String::from("https://link.com"),
+                    None,
+                ),
+                MarkdownInline::Image(
+                    vec![MarkdownInline::Plaintext(String::from("tag"))],
+                    String::from("https://link.com"),
+                    None,
+                ),
+                MarkdownInline::Plaintext(String::from(". the end!")),
+            ],
+            &Options::default(),
+        );
+        assert_eq!(x, String::from("Foobar is a Python library for dealing with word pluralization.<b>bold</b><i>italic</i><code>code</code><a href=\"https://link.com\">tag</a><img src=\"https://link.com\" alt=\"tag\">. the end!"));
+        let x = translate_text(vec![], &Options::default());
         assert_eq!(x, String::from(""));
     }
 
+    #[test]
+    fn test_wikilink_url() {
+        let options = Options {
+            wiki_base: String::from("/wiki/{}.html"),
+            ..Options::default()
+        };
+        assert_eq!(wikilink_url("Page Name", &options), "/wiki/page-name.html");
+        assert_eq!(
+            wikilink_url("Page#How to", &options),
+            "/wiki/page.html#how-to"
+        );
+        assert_eq!(wikilink_url("#Local", &options), "#local");
+        assert_eq!(wikilink_url("Page", &Options::default()), "page");
+    }
+
+    #[test]
+    fn test_translate_sanitized() {
+        let options = Options {
+            escape: false,
+            sanitize: true,
+            ..Options::default()
+        };
+        let text = vec![
+            MarkdownInline::Html(String::from("<span onclick=\"x()\" class=\"a\">")),
+            MarkdownInline::Plaintext(String::from("<b>")),
+            MarkdownInline::Link(
+                vec![MarkdownInline::Plaintext(String::from("a"))],
+                String::from("javascript:alert(1)"),
+                None,
+            ),
+            MarkdownInline::Image(vec![], String::from("vbscript:x"), None),
+            MarkdownInline::Html(String::from("</span>")),
+        ];
+        assert_eq!(
+            translate_text(text, &options),
+            "<span class=\"a\">&lt;b&gt;<a>a</a><img alt=\"\"></span>"
+        );
+        let attributes = Attributes {
+            pairs: vec![
+                (String::from("onload"), String::from("x()")),
+                (String::from("data-x"), String::from("1")),
+            ],
+            ..Attributes::default()
+        };
+        assert_eq!(
+            translate_blocks(
+                vec![
+                    Markdown::Html(String::from("<script>alert(1)</script>")),
+                    Markdown::Div(attributes, vec![]),
+                ],
+                &options
+            ),
+            "<div data-x=\"1\"></div>"
+        );
+        // the tags are separate nodes of the line, and the text between them is dropped too
+        for (source, html) in [
+            ("<script>alert(1)</script>\n", "<p></p>"),
+            ("a <style>x</style> b\n", "<p>a  b</p>"),
+            ("<textarea>`c` **d**</textarea>e\n", "<p><b></b>e</p>"),
+        ] {
+            let md = crate::parse(source).unwrap();
+            assert_eq!(translate(md, &options), html, "{}", source);
+        }
+    }
+
+    #[test]
+    fn test_rewrite_url() {
+        let text = || {
+            vec![
+                MarkdownInline::Link(
+                    vec![MarkdownInline::Plaintext(String::from("a"))],
+                    String::from("../a.html"),
+                    None,
+                ),
+                MarkdownInline::Image(vec![], String::from("http://b.com/b.png"), None),
+                MarkdownInline::Link(vec![], String::from("javascript:x()"), None),
+                MarkdownInline::WikiLink(String::from("Page"), None),
+            ]
+        };
+        let options = Options {
+            block_script_urls: true,
+            base_url: Some(String::from("https://a.com/docs/intro/")),
+            force_https: true,
+            ..Options::default()
+        };
+        assert_eq!(
+            translate_text(text(), &options),
+            "<a href=\"https://a.com/docs/a.html\">a</a><img src=\"https://b.com/b.png\" alt=\"\"><a></a><a class=\"wikilink\" href=\"https://a.com/docs/intro/page\">Page</a>"
+        );
+        fn rewrite(link: &LinkContext) -> Option<String> {
+            match link.kind {
+                LinkKind::Image => Some(format!("/cdn/{}", link.url)),
+                _ => None,
+            }
+        }
+        let options = Options {
+            rewrite_link: Some(rewrite),
+            ..Options::default()
+        };
+        assert_eq!(
+            translate_text(text(), &options),
+            "<a href=\"../a.html\">a</a><img src=\"/cdn/http://b.com/b.png\" alt=\"\"><a href=\"javascript:x()\"></a><a class=\"wikilink\" href=\"page\">Page</a>"
+        );
+        let text = vec![
+            MarkdownInline::Link(vec![], String::from("posts/a.md#setup"), None),
+            MarkdownInline::Image(vec![], String::from("a.md"), None),
+        ];
+        let options = Options {
+            link_extension: Some(String::from("html")),
+            ..Options::default()
+        };
+        assert_eq!(
+            translate_text(text, &options),
+            "<a href=\"posts/a.html#setup\"></a><img src=\"a.md\" alt=\"\">"
+        );
+    }
+
+    #[test]
+    fn test_translate_external_links() {
+        let link = |url: &str| MarkdownInline::Link(vec![], String::from(url), None);
+        let text = || vec![link("https://b.com/"), link("/about"), link("mailto:a@b.c")];
+        let mut options = Options {
+            external_link_class: Some(String::from("external")),
+            ..Options::default()
+        };
+        options
+            .external_link_attributes
+            .insert(String::from("target"), String::from("_blank"));
+        options
+            .external_link_attributes
+            .insert(String::from("rel"), String::from("noopener noreferrer"));
+        assert_eq!(
+            translate_text(text(), &options),
+            "<a href=\"https://b.com/\" class=\"external\" rel=\"noopener noreferrer\" target=\"_blank\"></a>\
+             <a href=\"/about\"></a><a href=\"mailto:a@b.c\"></a>"
+        );
+        let options = Options {
+            base_url: Some(String::from("https://b.com/docs/")),
+            ..options
+        };
+        assert_eq!(
+            translate_text(vec![link("https://b.com/"), link("https://c.com/")], &options),
+            "<a href=\"https://b.com/\"></a>\
+             <a href=\"https://c.com/\" class=\"external\" rel=\"noopener noreferrer\" target=\"_blank\"></a>"
+        );
+    }
+
+    #[test]
+    fn test_translate_images() {
+        let image = |title: Option<&str>| {
+            MarkdownInline::Image(
+                vec![MarkdownInline::Plaintext(String::from("a <cat>"))],
+                String::from("cat.png"),
+                title.map(String::from),
+            )
+        };
+        let options = Options {
+            lazy_images: true,
+            figures: true,
+            ..Options::default()
+        };
+        assert_eq!(
+            translate_blocks(
+                vec![
+                    Markdown::Paragraph(vec![vec![image(None)]]),
+                    Markdown::Paragraph(vec![vec![image(Some("Cat"))]]),
+                    Markdown::Paragraph(vec![vec![
+                        MarkdownInline::Plaintext(String::from("see ")),
+                        image(None)
+                    ]]),
+                ],
+                &options
+            ),
+            "<figure><img src=\"cat.png\" alt=\"a &lt;cat&gt;\" loading=\"lazy\"><figcaption>a &lt;cat&gt;</figcaption></figure>\
+             <figure><img src=\"cat.png\" alt=\"a &lt;cat&gt;\" title=\"Cat\" loading=\"lazy\"><figcaption>Cat</figcaption></figure>\
+             <p>see <img src=\"cat.png\" alt=\"a &lt;cat&gt;\" loading=\"lazy\"></p>"
+        );
+    }
+
+    #[cfg(feature = "image-size")]
+    #[test]
+    fn test_translate_image_size() {
+        let dir = std::env::temp_dir().join(format!("prose-image-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let mut png = b"\x89PNG\r\n\x1a\n\0\0\0\x0dIHDR".to_vec();
+        png.extend([640u32.to_be_bytes(), 480u32.to_be_bytes()].concat());
+        png.extend(b"\x08\x02\0\0\0\0\0\0\0");
+        std::fs::write(dir.join("a.png"), png).unwrap();
+        let options = Options {
+            image_sizes: true,
+            base_dir: Some(dir.clone()),
+            ..Options::default()
+        };
+        let image = |url: &str| vec![MarkdownInline::Image(vec![], String::from(url), None)];
+        assert_eq!(
+            translate_text(image("a.png"), &options),
+            "<img src=\"a.png\" alt=\"\" width=\"640\" height=\"480\">"
+        );
+        assert_eq!(
+            translate_text(image("b.png"), &options),
+            "<img src=\"b.png\" alt=\"\">"
+        );
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_translate_self_contained() {
+        let dir = std::env::temp_dir().join(format!("prose-embed-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("a.gif"), b"GIF89a").unwrap();
+        std::fs::write(dir.join("a.css"), "p {}\n").unwrap();
+        let options = Options {
+            self_contained: true,
+            sanitize: true,
+            base_dir: Some(dir.clone()),
+            ..Options::default()
+        };
+        let image = |url: &str| vec![MarkdownInline::Image(vec![], String::from(url), None)];
+        assert_eq!(
+            translate_text(image("a.gif"), &options),
+            "<img src=\"data:image/gif;base64,R0lGODlh\" alt=\"\">"
+        );
+        assert_eq!(
+            translate_text(image("b.gif"), &options),
+            "<img src=\"b.gif\" alt=\"\">"
+        );
+        assert_eq!(
+            translate_text(image("https://example.com/a.gif"), &options),
+            "<img src=\"https://example.com/a.gif\" alt=\"\">"
+        );
+        let options = Options {
+            standalone: true,
+            css: vec![
+                String::from("a.css"),
+                String::from("https://example.com/a.css"),
+            ],
+            ..options
+        };
+        let document = translate_document(String::new(), "", String::new(), &options);
+        assert!(document.contains("<style>\np {}\n</style>\n"));
+        assert!(document.contains("<link rel=\"stylesheet\" href=\"https://example.com/a.css\">"));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_translate_comment() {
+        let comment = || vec![MarkdownInline::Comment(String::from(" x "))];
+        assert_eq!(translate_text(comment(), &Options::default()), "");
+        let options = Options {
+            keep_comments: true,
+            ..Options::default()
+        };
+        assert_eq!(translate_text(comment(), &options), "<!-- x -->");
+    }
+
+    #[test]
+    fn test_translate_emoji() {
+        let emoji = |name: &str| vec![MarkdownInline::Emoji(String::from(name))];
+        assert_eq!(translate_text(emoji("tada"), &Options::default()), "🎉");
+        let options = Options {
+            emoji_images: true,
+            ..Options::default()
+        };
+        assert_eq!(
+            translate_text(emoji("tada"), &options),
+            "<img class=\"emoji\" alt=\"🎉\" src=\"https://github.githubassets.com/images/icons/emoji/unicode/1f389.png\">"
+        );
+        assert_eq!(translate_text(emoji("<x>"), &options), ":&lt;x&gt;:");
+    }
+
     #[test]
     fn test_translate_header() {
         assert_eq!(
-            translate_header(1, vec![MarkdownInline::Plaintext(String::from("Foobar"))]),
+            translate_blocks(
+                vec![Markdown::Heading(
+                    1,
+                    vec![MarkdownInline::Plaintext(String::from("Foobar"))],
+                    Attributes::default()
+                )],
+                &Options::default()
+            ),
             String::from("<h1>Foobar</h1>")
         );
+        let attributes = Attributes {
+            id: Some(String::from("foo-bar")),
+            ..Attributes::default()
+        };
+        let options = Options {
+            heading_anchors: true,
+            ..Options::default()
+        };
+        assert_eq!(
+            translate_blocks(
+                vec![Markdown::Heading(
+                    2,
+                    vec![MarkdownInline::Plaintext(String::from("Foo"))],
+                    attributes
+                )],
+                &options
+            ),
+            String::from("<h2 id=\"foo-bar\">Foo<a class=\"anchor\" href=\"#foo-bar\">¶</a></h2>")
+        );
     }
 
     #[test]
-    fn test_translate_list_elements() {
+    fn test_translate_list_item() {
+        let options = Options {
+            wrap_lists: true,
+            ..Options::default()
+        };
         assert_eq!(
-            translate_list_elements(vec![
-                vec![MarkdownInline::Plaintext(String::from("Foobar"))],
-                vec![MarkdownInline::Plaintext(String::from("Foobar"))],
-                vec![MarkdownInline::Plaintext(String::from("Foobar"))],
-                vec![MarkdownInline::Plaintext(String::from("Foobar"))],
-            ]),
-            String::from("<li>Foobar</li><li>Foobar</li><li>Foobar</li><li>Foobar</li>")
+            translate_blocks(
+                vec![Markdown::UnorderedList(vec![
+                    (
+                        vec![MarkdownInline::Plaintext(String::from("a"))],
+                        vec![Markdown::UnorderedList(vec![])]
+                    ),
+                    (vec![], vec![]),
+                ])],
+                &options
+            ),
+            String::from("<ul><li><p>a</p><ul></ul></li><li></li></ul>")
         );
     }
 
     #[test]
     fn test_translate_unordered_list() {
         assert_eq!(
-            translate_unordered_list(vec![
-                vec![MarkdownInline::Plaintext(String::from("Foobar"))],
-                vec![MarkdownInline::Plaintext(String::from("Foobar"))],
-                vec![MarkdownInline::Plaintext(String::from("Foobar"))],
-                vec![MarkdownInline::Plaintext(String::from("Foobar"))],
-            ]),
+            translate_blocks(
+                vec![Markdown::UnorderedList(vec![
+                    (
+                        vec![MarkdownInline::Plaintext(String::from("Foobar"))],
+                        vec![]
+                    ),
+                    (
+                        vec![MarkdownInline::Plaintext(String::from("Foobar"))],
+                        vec![]
+                    ),
+                    (
+                        vec![MarkdownInline::Plaintext(String::from("Foobar"))],
+                        vec![]
+                    ),
+                    (
+                        vec![MarkdownInline::Plaintext(String::from("Foobar"))],
+                        vec![]
+                    ),
+                ])],
+                &Options::default()
+            ),
             String::from("<ul><li>Foobar</li><li>Foobar</li><li>Foobar</li><li>Foobar</li></ul>")
         );
     }
@@ -208,38 +2282,228 @@ mod tests {
     #[test]
     fn test_translate_ordered_list() {
         assert_eq!(
-            translate_ordered_list(vec![
-                vec![MarkdownInline::Plaintext(String::from("Foobar"))],
-                vec![MarkdownInline::Plaintext(String::from("Foobar"))],
-                vec![MarkdownInline::Plaintext(String::from("Foobar"))],
-                vec![MarkdownInline::Plaintext(String::from("Foobar"))],
-            ]),
+            translate_blocks(
+                vec![Markdown::OrderedList(
+                    1,
+                    vec![
+                        (
+                            vec![MarkdownInline::Plaintext(String::from("Foobar"))],
+                            vec![]
+                        ),
+                        (
+                            vec![MarkdownInline::Plaintext(String::from("Foobar"))],
+                            vec![]
+                        ),
+                        (
+                            vec![MarkdownInline::Plaintext(String::from("Foobar"))],
+                            vec![]
+                        ),
+                        (
+                            vec![MarkdownInline::Plaintext(String::from("Foobar"))],
+                            vec![]
+                        ),
+                    ]
+                )],
+                &Options::default()
+            ),
             String::from("<ol><li>Foobar</li><li>Foobar</li><li>Foobar</li><li>Foobar</li></ol>")
         );
     }
 
+    #[test]
+    fn test_translate_nested_list() {
+        assert_eq!(
+            translate_blocks(
+                vec![Markdown::UnorderedList(vec![(
+                    vec![MarkdownInline::Plaintext(String::from("a"))],
+                    vec![Markdown::OrderedList(
+                        1,
+                        vec![(vec![MarkdownInline::Plaintext(String::from("b"))], vec![])]
+                    )]
+                )])],
+                &Options::default()
+            ),
+            String::from("<ul><li>a<ol><li>b</li></ol></li></ul>")
+        );
+    }
+
     #[test]
     fn test_translate_codeblock() {
         assert_eq!(
-            translate_codeblock(String::new(), String::from("import signal")),
+            translate_blocks(
+                vec![Markdown::Codeblock(
+                    String::new(),
+                    String::from("import signal"),
+                    String::new()
+                )],
+                &Options::default()
+            ),
             String::from("<pre><code>import signal</code></pre>")
         );
         assert_eq!(
-            translate_codeblock(String::from("python"), String::from("import signal")),
+            translate_blocks(
+                vec![Markdown::Codeblock(
+                    String::from("python"),
+                    String::from("import signal"),
+                    String::new()
+                )],
+                &Options::default()
+            ),
             String::from("<pre><code class=\"python\">import signal</code></pre>")
         );
     }
 
+    #[test]
+    fn test_translate_code_class() {
+        let options = Options {
+            language_class: true,
+            ..Options::default()
+        };
+        assert_eq!(translate_code_class("rust", &options), "language-rust");
+        assert_eq!(translate_code_class("", &options), "");
+        assert_eq!(translate_code_class("rust", &Options::default()), "rust");
+    }
+
+    #[test]
+    fn test_translate_code() {
+        let translate_code = |lang: &str, code: &str, options: &Options| {
+            let mut out = String::new();
+            super::translate_code(&mut out, lang, code, options);
+            out
+        };
+        #[cfg(feature = "highlight")]
+        {
+            let options = Options {
+                highlight: Some(String::from("light")),
+                ..Options::default()
+            };
+            assert!(translate_code("rust", "let s = \"<\";", &options)
+                .starts_with("<span class=\"hl-source hl-rust\"><span class=\"hl-storage hl-type hl-rust\">let</span>"));
+            assert_eq!(
+                translate_code("unknown", "a < b", &options),
+                String::from("a &lt; b")
+            );
+        }
+        assert_eq!(
+            translate_code("rust", "let <", &Options::default()),
+            String::from("let &lt;")
+        );
+    }
+
     #[test]
     fn test_translate_line() {
         assert_eq!(
-            translate_line(vec![
-                MarkdownInline::Plaintext(String::from("Foobar")),
-                MarkdownInline::Bold(String::from("Foobar")),
-                MarkdownInline::Italic(String::from("Foobar")),
-                MarkdownInline::InlineCode(String::from("Foobar")),
-            ]),
+            translate_blocks(
+                vec![Markdown::Line(vec![
+                    MarkdownInline::Plaintext(String::from("Foobar")),
+                    MarkdownInline::Bold(vec![MarkdownInline::Plaintext(String::from("Foobar"))]),
+                    MarkdownInline::Italic(vec![MarkdownInline::Plaintext(String::from("Foobar"))]),
+                    MarkdownInline::InlineCode(String::from("Foobar")),
+                ])],
+                &Options::default()
+            ),
             String::from("<p>Foobar<b>Foobar</b><i>Foobar</i><code>Foobar</code></p>")
         );
     }
+
+    #[test]
+    fn test_translate_blockquote() {
+        assert_eq!(
+            translate_blocks(
+                vec![Markdown::Blockquote(vec![
+                    Markdown::Line(vec![MarkdownInline::Plaintext(String::from("a"))]),
+                    Markdown::Blockquote(vec![Markdown::Line(vec![MarkdownInline::Plaintext(
+                        String::from("b")
+                    )])]),
+                ])],
+                &Options::default()
+            ),
+            String::from("<blockquote><p>a</p><blockquote><p>b</p></blockquote></blockquote>")
+        );
+    }
+
+    #[test]
+    fn test_translate_paragraph() {
+        assert_eq!(
+            translate_blocks(
+                vec![Markdown::Paragraph(vec![
+                    vec![MarkdownInline::Plaintext(String::from("Foo"))],
+                    vec![
+                        MarkdownInline::Plaintext(String::from("bar ")),
+                        MarkdownInline::Bold(vec![MarkdownInline::Plaintext(String::from("baz"))]),
+                    ],
+                ])],
+                &Options::default()
+            ),
+            String::from("<p>Foo bar <b>baz</b></p>")
+        );
+    }
+
+    #[test]
+    fn test_translate_footnotes() {
+        let text = |s: &str| vec![MarkdownInline::Plaintext(String::from(s))];
+        assert_eq!(
+            translate(vec![
+                Markdown::Paragraph(vec![vec![
+                    MarkdownInline::Plaintext(String::from("a")),
+                    MarkdownInline::FootnoteRef(String::from("y")),
+                    MarkdownInline::FootnoteRef(String::from("x")),
+                    MarkdownInline::FootnoteRef(String::from("z")),
+                ]]),
+                Markdown::FootnoteDefinition(String::from("x"), text("X")),
+                Markdown::FootnoteDefinition(String::from("y"), text("Y")),
+            ], &Options::default()),
+            String::from("<p>a<sup class=\"footnote-ref\"><a href=\"#fn-1\" id=\"fnref-1\">1</a></sup><sup class=\"footnote-ref\"><a href=\"#fn-2\" id=\"fnref-2\">2</a></sup>[^z]</p><section class=\"footnotes\"><ol><li id=\"fn-1\">Y <a href=\"#fnref-1\" class=\"footnote-backref\">↩</a></li><li id=\"fn-2\">X <a href=\"#fnref-2\" class=\"footnote-backref\">↩</a></li></ol></section>")
+        );
+        let options = Options {
+            footnote_symbols: true,
+            ..Options::default()
+        };
+        assert_eq!(
+            translate_text(
+                vec![MarkdownInline::FootnoteRef(String::from("8"))],
+                &options
+            ),
+            "<sup class=\"footnote-ref\"><a href=\"#fn-8\" id=\"fnref-8\">††</a></sup>"
+        );
+        assert_eq!(
+            translate(
+                vec![
+                    Markdown::Line(vec![MarkdownInline::FootnoteRef(String::from("x"))]),
+                    Markdown::FootnoteDefinition(String::from("x"), text("X")),
+                ],
+                &options
+            ),
+            "<p><sup class=\"footnote-ref\"><a href=\"#fn-1\" id=\"fnref-1\">*</a></sup></p><section class=\"footnotes\"><ol style=\"list-style: none\"><li id=\"fn-1\"><sup>*</sup> X <a href=\"#fnref-1\" class=\"footnote-backref\">↩</a></li></ol></section>"
+        );
+        // every reference has an id of its own, and a link back to it
+        let md = crate::parse("a[^1] b[^1]\n\n[^1]: n\n").unwrap();
+        assert_eq!(
+            translate(md, &Options::default()),
+            "<p>a<sup class=\"footnote-ref\"><a href=\"#fn-1\" id=\"fnref-1\">1</a></sup> b<sup class=\"footnote-ref\"><a href=\"#fn-1\" id=\"fnref-1-2\">1</a></sup></p><section class=\"footnotes\"><ol><li id=\"fn-1\">n <a href=\"#fnref-1\" class=\"footnote-backref\">↩</a> <a href=\"#fnref-1-2\" class=\"footnote-backref\">↩<sup>2</sup></a></li></ol></section>"
+        );
+    }
+
+    #[test]
+    fn test_translate_table() {
+        let cell = |s: &str| vec![MarkdownInline::Plaintext(String::from(s))];
+        assert_eq!(
+            translate_blocks(
+                vec![Markdown::Table(
+                    vec![cell("a"), cell("b")],
+                    vec![Align::Left, Align::None],
+                    vec![vec![cell("1"), cell("2")], vec![cell("3")]]
+                )],
+                &Options::default()
+            ),
+            String::from("<table><thead><tr><th align=\"left\">a</th><th>b</th></tr></thead><tbody><tr><td align=\"left\">1</td><td>2</td></tr><tr><td align=\"left\">3</td><td></td></tr></tbody></table>")
+        );
+        assert_eq!(
+            translate_blocks(
+                vec![Markdown::Table(vec![cell("a")], vec![Align::Right], vec![])],
+                &Options::default()
+            ),
+            String::from("<table><thead><tr><th align=\"right\">a</th></tr></thead></table>")
+        );
+    }
 }
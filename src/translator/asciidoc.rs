@@ -0,0 +1,353 @@
+use crate::entity::{Align, Attributes, Markdown};
+use crate::translator::admonition_title;
+use crate::translator::render::{self, Render};
+
+// the kinds of admonitions which AsciiDoc has
+const ADMONITIONS: [&str; 5] = ["caution", "important", "note", "tip", "warning"];
+
+// the characters of the inline markup, the macros and the cells of AsciiDoc
+const SPECIAL: &str = "*_`#^~+{}[]<>|\\";
+
+// the characters which start a block at the start of a line, as `.Title` or `- item`
+const LINE_START: &str = ".-=/:";
+
+// AsciiDoc, where the nesting of a list is in its markers, such as `**` for a nested item,
+// and the blocks after the text of an item are attached to it with `+`
+#[derive(Clone, Debug, Default)]
+pub struct AsciidocRenderer {
+    // the lists being written, with the number of the first item of an ordered one
+    lists: Vec<Option<usize>>,
+    // the text of an item is written, so that the next block is attached to it
+    continuation: bool,
+    // what closes each link being written
+    links: Vec<&'static str>,
+    // whether each details being written is open
+    details: Vec<bool>,
+}
+
+pub fn translate(md: Vec<Markdown>) -> String {
+    render::render(md, &mut AsciidocRenderer::default())
+        .trim_end()
+        .to_string()
+}
+
+impl AsciidocRenderer {
+    fn block_start(&mut self, out: &mut String) {
+        if self.continuation {
+            out.push_str("+\n");
+        }
+    }
+
+    // a blank line would end the list a block is attached to
+    fn block_end(&self, out: &mut String) {
+        out.push('\n');
+        if self.lists.is_empty() {
+            out.push('\n');
+        }
+    }
+}
+
+impl Render for AsciidocRenderer {
+    fn heading_start(&mut self, out: &mut String, level: usize, attributes: &Attributes) {
+        if let Some(id) = &attributes.id {
+            out.push_str(&format!("[[{}]]\n", id));
+        }
+        // a single `=` is the title of the document
+        out.push_str(&"=".repeat(level.min(5) + 1));
+        out.push(' ');
+    }
+
+    fn heading_end(&mut self, out: &mut String, _: usize, _: &Attributes) {
+        out.push_str("\n\n");
+    }
+
+    fn paragraph_start(&mut self, out: &mut String) {
+        self.block_start(out);
+    }
+
+    fn paragraph_end(&mut self, out: &mut String) {
+        self.block_end(out);
+    }
+
+    fn list_start(&mut self, out: &mut String, start: Option<usize>) {
+        if let Some(start) = start.filter(|start| *start != 1) {
+            out.push_str(&format!("[start={}]\n", start));
+        }
+        self.lists.push(start);
+        self.continuation = false;
+    }
+
+    fn list_end(&mut self, out: &mut String, _: Option<usize>) {
+        self.lists.pop();
+        if self.lists.is_empty() {
+            out.push('\n');
+        }
+    }
+
+    fn item_start(&mut self, out: &mut String) {
+        let marker = match self.lists.last() {
+            Some(Some(_)) => '.',
+            _ => '*',
+        };
+        out.extend(std::iter::repeat_n(marker, self.lists.len()));
+        out.push(' ');
+    }
+
+    fn item_text_end(&mut self, out: &mut String) {
+        out.push('\n');
+        self.continuation = true;
+    }
+
+    fn item_end(&mut self, _: &mut String) {
+        self.continuation = false;
+    }
+
+    fn term_end(&mut self, out: &mut String) {
+        out.push_str("::\n");
+    }
+
+    fn definition_list_start(&mut self, out: &mut String) {
+        self.block_start(out);
+    }
+
+    fn definition_list_end(&mut self, out: &mut String) {
+        self.block_end(out);
+    }
+
+    fn codeblock(&mut self, out: &mut String, lang: &str, code: &str, _: &str) {
+        self.block_start(out);
+        if !lang.is_empty() {
+            out.push_str(&format!("[source,{}]\n", lang));
+        }
+        out.push_str("----\n");
+        out.push_str(code);
+        if !code.ends_with('\n') {
+            out.push('\n');
+        }
+        out.push_str("----");
+        self.block_end(out);
+    }
+
+    fn horizontal_rule(&mut self, out: &mut String) {
+        out.push_str("'''\n\n");
+    }
+
+    fn table_start(&mut self, out: &mut String, aligns: &[Align]) {
+        self.block_start(out);
+        let cols: Vec<&str> = aligns
+            .iter()
+            .map(|align| match align {
+                Align::Center => "^",
+                Align::Right => ">",
+                Align::None | Align::Left => "<",
+            })
+            .collect();
+        out.push_str(&format!(
+            "[cols=\"{}\",options=\"header\"]\n|===\n",
+            cols.join(",")
+        ));
+    }
+
+    fn table_cell_start(&mut self, out: &mut String, column: usize, _: Align, _: bool) {
+        out.push_str(if column == 0 { "|" } else { " |" });
+    }
+
+    fn table_end(&mut self, out: &mut String) {
+        out.push_str("|===");
+        self.block_end(out);
+    }
+
+    fn blockquote_start(&mut self, out: &mut String) {
+        self.block_start(out);
+        out.push_str("____\n");
+    }
+
+    fn blockquote_end(&mut self, out: &mut String) {
+        out.push_str("____");
+        self.block_end(out);
+    }
+
+    fn admonition_start(&mut self, out: &mut String, kind: &str, title: &Option<String>) {
+        self.block_start(out);
+        if title.is_some() || !ADMONITIONS.contains(&kind) {
+            out.push_str(&format!(".{}\n", admonition_title(kind, title)));
+        }
+        let kind = if ADMONITIONS.contains(&kind) {
+            kind
+        } else {
+            "note"
+        };
+        out.push_str(&format!("[{}]\n====\n", kind.to_uppercase()));
+    }
+
+    fn admonition_end(&mut self, out: &mut String) {
+        out.push_str("====");
+        self.block_end(out);
+    }
+
+    fn details_start(&mut self, out: &mut String, open: bool) {
+        self.block_start(out);
+        self.details.push(open);
+    }
+
+    fn summary_start(&mut self, out: &mut String) {
+        out.push('.');
+    }
+
+    fn summary_end(&mut self, out: &mut String) {
+        let open = if self.details.last() == Some(&true) {
+            "%open"
+        } else {
+            ""
+        };
+        out.push_str(&format!("\n[%collapsible{}]\n====\n", open));
+    }
+
+    fn details_end(&mut self, out: &mut String) {
+        self.details.pop();
+        out.push_str("====");
+        self.block_end(out);
+    }
+
+    fn math_block(&mut self, out: &mut String, tex: &str) {
+        self.block_start(out);
+        out.push_str(&format!("[stem]\n++++\n{}\n++++", tex));
+        self.block_end(out);
+    }
+
+    fn footnotes_start(&mut self, out: &mut String) {
+        out.push_str("'''\n\n");
+    }
+
+    fn footnote_end(&mut self, out: &mut String, _: usize) {
+        out.push_str("\n\n");
+    }
+
+    // text with any markup in it is written in a passthrough, which only escapes `<`, `>` and `&`
+    fn text(&mut self, out: &mut String, text: &str) {
+        let line_start = out.is_empty() || out.ends_with('\n');
+        let label = text
+            .split_once(": ")
+            .is_some_and(|(label, _)| ADMONITIONS.contains(&label.to_lowercase().as_str()));
+        if text.contains(|c| SPECIAL.contains(c))
+            || line_start && (text.starts_with(|c| LINE_START.contains(c)) || label)
+        {
+            out.push_str(&format!("pass:c[{}]", text.replace(']', "\\]")));
+        } else {
+            out.push_str(text);
+        }
+    }
+
+    fn bold_start(&mut self, out: &mut String) {
+        out.push_str("**");
+    }
+
+    fn bold_end(&mut self, out: &mut String) {
+        out.push_str("**");
+    }
+
+    fn italic_start(&mut self, out: &mut String) {
+        out.push_str("__");
+    }
+
+    fn italic_end(&mut self, out: &mut String) {
+        out.push_str("__");
+    }
+
+    fn mark_start(&mut self, out: &mut String) {
+        out.push_str("##");
+    }
+
+    fn mark_end(&mut self, out: &mut String) {
+        out.push_str("##");
+    }
+
+    fn strike(&mut self, out: &mut String, text: &str) {
+        out.push_str(&format!("[.line-through]#{}#", text));
+    }
+
+    fn superscript(&mut self, out: &mut String, text: &str) {
+        out.push_str(&format!("^{}^", text));
+    }
+
+    fn subscript(&mut self, out: &mut String, text: &str) {
+        out.push_str(&format!("~{}~", text));
+    }
+
+    fn kbd(&mut self, out: &mut String, keys: &[String]) {
+        out.push_str(&format!("kbd:[{}]", keys.join("+")));
+    }
+
+    fn inline_code(&mut self, out: &mut String, code: &str) {
+        out.push_str(&format!("`+{}+`", code));
+    }
+
+    // a cross reference for an anchor of the document, and the macro for any other url
+    fn link_start(&mut self, out: &mut String, url: &str, _: &Option<String>) {
+        if let Some(id) = url.strip_prefix('#') {
+            out.push_str(&format!("<<{},", id));
+            self.links.push(">>");
+        } else {
+            if !url.contains("://") && !url.starts_with("mailto:") {
+                out.push_str("link:");
+            }
+            out.push_str(url);
+            out.push('[');
+            self.links.push("]");
+        }
+    }
+
+    fn link_end(&mut self, out: &mut String) {
+        out.push_str(self.links.pop().unwrap_or("]"));
+    }
+
+    fn image(&mut self, out: &mut String, alt: &str, url: &str, _: &Option<String>) {
+        out.push_str(&format!("image:{}[{}]", url, alt));
+    }
+
+    fn footnote_ref(&mut self, out: &mut String, number: &str) {
+        out.push_str(&format!("^[{}]^", number));
+    }
+
+    fn math(&mut self, out: &mut String, tex: &str) {
+        out.push_str(&format!("stem:[{}]", tex));
+    }
+
+    fn line_break(&mut self, out: &mut String) {
+        out.push_str(" +\n");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_translate() {
+        let md = crate::parse(
+            "# Title\n\nsome **bold** and `code`, [link](https://a.com) [up](#title)[^1]\n\n- a\n  ```\n  x\n  ```\n- b\n  1. c\n\n> quote\n\n```rust\nfn f() {}\n```\n\n| a | b |\n|---|--:|\n| 1 | 2 |\n\n::: tip\n![x](x.png)\n:::\n\n[^1]: note\n",
+        )
+        .unwrap();
+        assert_eq!(
+            translate(md),
+            "[[title]]\n== Title\n\n\
+             some **bold** and `+code+`, https://a.com[link] <<title,up>>^[1]^\n\n\
+             * a\n+\n----\nx\n----\n* b\n.. c\n\n\
+             ____\nquote\n\n____\n\n\
+             [source,rust]\n----\nfn f() {}\n----\n\n\
+             [cols=\"<,>\",options=\"header\"]\n|===\n|a |b\n|1 |2\n|===\n\n\
+             [TIP]\n====\nimage:x.png[x]\n\n====\n\n\
+             '''\n\n[1] note"
+        );
+    }
+
+    #[test]
+    fn test_translate_text() {
+        let md = crate::parse("\\*not\\* \\_x\\_ [y] a|b\n\n.Title\n\nNote: n\n\nplain. text\n")
+            .unwrap();
+        assert_eq!(
+            translate(md),
+            "pass:c[*not* _x_ [y\\] a|b]\n\npass:c[.Title]\n\npass:c[Note: n]\n\nplain. text"
+        );
+    }
+}
@@ -0,0 +1,203 @@
+use crate::entity::{Align, Attributes, Markdown, Span};
+use crate::pretty;
+use crate::translator::jira::panel;
+use crate::translator::render::{self, Render};
+use crate::translator::{admonition_title, HtmlRenderer, Options};
+
+// the storage format of Confluence: the XHTML of the html translator,
+// where the code blocks and the admonitions are the macros of Confluence.
+// the raw html is escaped, since it is seldom well-formed XML and Confluence would not keep it
+pub struct ConfluenceRenderer {
+    html: HtmlRenderer,
+}
+
+pub fn translate(md: Vec<Markdown>, options: &Options) -> String {
+    let options = Options {
+        standalone: false,
+        xhtml: true,
+        ..options.clone()
+    };
+    let mut renderer = ConfluenceRenderer {
+        html: HtmlRenderer::with_options(options.clone()),
+    };
+    let body = render::render(md, &mut renderer);
+    if options.pretty {
+        pretty::pretty(&body)
+    } else {
+        body
+    }
+}
+
+// the callbacks which write the same as the html translator
+macro_rules! html {
+    ($($name:ident($($arg:ident: $ty:ty),*);)*) => {
+        $(fn $name(&mut self, out: &mut String, $($arg: $ty),*) {
+            self.html.$name(out, $($arg),*)
+        })*
+    };
+}
+
+impl Render for ConfluenceRenderer {
+    html! {
+        heading_start(level: usize, attributes: &Attributes);
+        heading_end(level: usize, attributes: &Attributes);
+        paragraph_start();
+        paragraph_end();
+        soft_break();
+        list_start(start: Option<usize>);
+        list_end(start: Option<usize>);
+        item_start();
+        item_text_start();
+        item_text_end();
+        item_end();
+        definition_list_start();
+        definition_list_end();
+        term_start();
+        term_end();
+        definition_start();
+        definition_end();
+        horizontal_rule();
+        table_start(aligns: &[Align]);
+        table_head_start();
+        table_head_end();
+        table_body_start();
+        table_body_end();
+        table_row_start(header: bool);
+        table_row_end(header: bool);
+        table_cell_start(column: usize, align: Align, header: bool);
+        table_cell_end(header: bool);
+        table_end();
+        blockquote_start();
+        blockquote_end();
+        div_start(attributes: &Attributes);
+        div_end();
+        details_start(open: bool);
+        summary_start();
+        summary_end();
+        details_end();
+        math_block(tex: &str);
+        footnotes_start();
+        footnote_start(number: usize);
+        footnote_end(number: usize);
+        footnotes_end();
+        comment(text: &str);
+        text(text: &str);
+        bold_start();
+        bold_end();
+        italic_start();
+        italic_end();
+        mark_start();
+        mark_end();
+        strike(text: &str);
+        superscript(text: &str);
+        subscript(text: &str);
+        kbd(keys: &[String]);
+        ruby(base: &str, readings: &[String]);
+        inline_code(code: &str);
+        link_start(url: &str, title: &Option<String>);
+        link_end();
+        image(alt: &str, url: &str, title: &Option<String>);
+        wikilink(target: &str, label: &Option<String>);
+        footnote_ref(number: &str);
+        math(tex: &str);
+        emoji(name: &str);
+        line_break();
+        spanned(start: usize, span: &Span);
+    }
+
+    fn codeblock(&mut self, out: &mut String, lang: &str, code: &str, _: &str) {
+        out.push_str(&code_macro(lang, code));
+    }
+
+    fn admonition_start(&mut self, out: &mut String, kind: &str, title: &Option<String>) {
+        out.push_str(&format!(
+            "<ac:structured-macro ac:name=\"{}\"><ac:parameter ac:name=\"title\">{}</ac:parameter><ac:rich-text-body>",
+            panel(kind),
+            escape(&admonition_title(kind, title)),
+        ));
+    }
+
+    fn admonition_end(&mut self, out: &mut String) {
+        out.push_str("</ac:rich-text-body></ac:structured-macro>");
+    }
+
+    fn html(&mut self, out: &mut String, html: &str) {
+        out.push_str(&escape(html));
+    }
+}
+
+// the code as it is in CDATA, which cannot have `]]>` but in two sections
+fn code_macro(lang: &str, code: &str) -> String {
+    let language = if lang.is_empty() {
+        String::new()
+    } else {
+        format!(
+            "<ac:parameter ac:name=\"language\">{}</ac:parameter>",
+            escape(lang)
+        )
+    };
+    format!(
+        "<ac:structured-macro ac:name=\"code\">{}<ac:plain-text-body><![CDATA[{}]]></ac:plain-text-body></ac:structured-macro>",
+        language,
+        code.replace("]]>", "]]]]><![CDATA[>")
+    )
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // the namespaces of the macros, which the pages of Confluence declare around the content
+    fn parse_xml(content: &str) -> Result<(), String> {
+        let document = format!(
+            "<body xmlns:ac=\"http://atlassian.com/content\">{}</body>",
+            content
+        );
+        roxmltree::Document::parse(&document)
+            .map(|_| ())
+            .map_err(|err| err.to_string())
+    }
+
+    #[test]
+    fn test_translate() {
+        let md =
+            crate::parse("# A\n\n- x\n  ```rust\n  a]]>b\n  ```\n\n::: tip\n**t**\n:::\n\n---\n")
+                .unwrap();
+        assert_eq!(
+            translate(md, &Options::default()),
+            "<h1 id=\"a\">A</h1>\
+             <ul><li>x<ac:structured-macro ac:name=\"code\"><ac:parameter ac:name=\"language\">rust</ac:parameter>\
+             <ac:plain-text-body><![CDATA[a]]]]><![CDATA[>b\n]]></ac:plain-text-body></ac:structured-macro></li></ul>\
+             <ac:structured-macro ac:name=\"tip\"><ac:parameter ac:name=\"title\">Tip</ac:parameter>\
+             <ac:rich-text-body><p><b>t</b></p></ac:rich-text-body></ac:structured-macro><hr />"
+        );
+    }
+
+    #[test]
+    fn test_translate_xml() {
+        let md = crate::parse(
+            "<div>x\n\na<br>b & <img src=x>\n\n```rust\nfn main() {}\n```\n\n::: warning <T>\n> ::: tip\n> ```\n> ]]>\n> ```\n> :::\n:::\n\n![i](a.png) [l](b?c=1&d=2)\n",
+        )
+        .unwrap();
+        for sanitize in [false, true] {
+            let options = Options {
+                sanitize,
+                ..Options::default()
+            };
+            let content = translate(md.clone(), &options);
+            assert_eq!(parse_xml(&content), Ok(()), "{}", content);
+            assert!(content.contains(
+                "<ac:structured-macro ac:name=\"code\"><ac:parameter ac:name=\"language\">rust</ac:parameter>\
+                 <ac:plain-text-body><![CDATA[fn main() {}\n]]></ac:plain-text-body></ac:structured-macro>"
+            ));
+            assert!(content.starts_with("&lt;div&gt;x"));
+        }
+    }
+}
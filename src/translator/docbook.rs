@@ -0,0 +1,356 @@
+use crate::entity::Align;
+use crate::entity::ListItem;
+use crate::entity::Markdown;
+use crate::entity::MarkdownInline;
+use crate::entity::MarkdownText;
+use crate::translator::{
+    admonition_title, assign_heading_ids, emoji_text, join_lines, ruby_text,
+    take_footnote_definitions, translate_title, wikilink_text, Options,
+};
+
+use std::collections::HashMap;
+
+// footnotes are written where they are referred to, as in LaTeX
+type Notes = HashMap<String, MarkdownText>;
+
+// DocBook 5, where the headings of the document open sections which the next heading
+// of the same level or above closes
+pub fn translate(md: Vec<Markdown>, options: &Options) -> String {
+    let title = translate_title(&md);
+    let mut notes = HashMap::new();
+    let body = take_footnote_definitions(assign_heading_ids(md), &mut notes);
+    let body = translate_sections(body, &notes);
+    if options.standalone {
+        translate_document(title, body)
+    } else {
+        body
+    }
+}
+
+fn translate_document(title: Option<String>, body: String) -> String {
+    let info = title
+        .map(|title| format!("<info><title>{}</title></info>\n", escape(&title)))
+        .unwrap_or_default();
+    format!(
+        "<?xml version=\"1.0\" encoding=\"utf-8\"?>\n<article xmlns=\"http://docbook.org/ns/docbook\" xmlns:xlink=\"http://www.w3.org/1999/xlink\" version=\"5.0\">\n{}{}</article>\n",
+        info, body
+    )
+}
+
+// the characters which XML 1.0 forbids, such as U+0001, even as references, are replaced
+fn escape(text: &str) -> String {
+    text.replace(|c| !is_xml_char(c), "\u{fffd}")
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn is_xml_char(c: char) -> bool {
+    matches!(c, '\t' | '\n' | '\r' | '\u{20}'..='\u{fffd}' | '\u{10000}'..)
+}
+
+// `xml:id` is a name of XML, which cannot start with a digit as a slug can
+fn is_xml_id(id: &str) -> bool {
+    id.starts_with(|c: char| c.is_alphabetic() || c == '_')
+}
+
+fn translate_sections(md: Vec<Markdown>, notes: &Notes) -> String {
+    let mut out = String::new();
+    let mut levels: Vec<usize> = vec![];
+    for bit in md {
+        match bit {
+            Markdown::Heading(size, text, attributes) => {
+                while levels.last().is_some_and(|level| *level >= size) {
+                    levels.pop();
+                    out.push_str("</section>\n");
+                }
+                levels.push(size);
+                let id = attributes
+                    .id
+                    .filter(|id| is_xml_id(id))
+                    .map(|id| format!(" xml:id=\"{}\"", escape(&id)))
+                    .unwrap_or_default();
+                out.push_str(&format!(
+                    "<section{}>\n<title>{}</title>\n",
+                    id,
+                    translate_text(text, notes)
+                ));
+            }
+            bit => out.push_str(&translate_blocks(vec![bit], notes)),
+        }
+    }
+    for _ in levels {
+        out.push_str("</section>\n");
+    }
+    out
+}
+
+fn translate_blocks(md: Vec<Markdown>, notes: &Notes) -> String {
+    md.iter()
+        .map(|bit| match bit {
+            // a heading in a quote or a list, which cannot open a section
+            Markdown::Heading(_, line, _) => format!(
+                "<bridgehead>{}</bridgehead>\n",
+                translate_text(line.to_vec(), notes)
+            ),
+            Markdown::UnorderedList(items) => translate_list(None, items.to_vec(), notes),
+            Markdown::OrderedList(start, items) => {
+                translate_list(Some(*start), items.to_vec(), notes)
+            }
+            Markdown::DefinitionList(definitions) => {
+                let entries: String = definitions
+                    .iter()
+                    .map(|(term, texts)| {
+                        let texts: String = texts
+                            .iter()
+                            .map(|text| {
+                                format!("<para>{}</para>", translate_text(text.to_vec(), notes))
+                            })
+                            .collect();
+                        format!(
+                            "<varlistentry><term>{}</term><listitem>{}</listitem></varlistentry>\n",
+                            translate_text(term.to_vec(), notes),
+                            texts
+                        )
+                    })
+                    .collect();
+                format!("<variablelist>\n{}</variablelist>\n", entries)
+            }
+            Markdown::Codeblock(lang, code, _) => translate_codeblock(lang, code),
+            Markdown::Line(line) => translate_paragraph(vec![line.to_vec()], notes),
+            Markdown::Paragraph(lines) => translate_paragraph(lines.to_vec(), notes),
+            Markdown::Table(header, aligns, rows) => {
+                translate_table(header.to_vec(), aligns.to_vec(), rows.to_vec(), notes)
+            }
+            Markdown::Blockquote(inner) => format!(
+                "<blockquote>\n{}</blockquote>\n",
+                translate_blocks(inner.to_vec(), notes)
+            ),
+            Markdown::Admonition(kind, title, inner) => {
+                let element = match kind.as_str() {
+                    "caution" | "important" | "note" | "tip" | "warning" => kind.as_str(),
+                    "danger" | "error" => "warning",
+                    _ => "note",
+                };
+                format!(
+                    "<{0}>\n<title>{1}</title>\n{2}</{0}>\n",
+                    element,
+                    escape(&admonition_title(kind, title)),
+                    translate_blocks(inner.to_vec(), notes)
+                )
+            }
+            Markdown::Div(_, inner) => translate_blocks(inner.to_vec(), notes),
+            Markdown::Details(summary, _, inner) => format!(
+                "<sidebar>\n<title>{}</title>\n{}</sidebar>\n",
+                translate_text(summary.to_vec(), notes),
+                translate_blocks(inner.to_vec(), notes)
+            ),
+            Markdown::MathBlock(tex) => format!(
+                "<informalequation><mathphrase>{}</mathphrase></informalequation>\n",
+                escape(tex)
+            ),
+            // DocBook has no rule between blocks
+            Markdown::HorizontalRule
+            | Markdown::FootnoteDefinition(_, _)
+            | Markdown::Footnotes
+            | Markdown::FrontMatter(_)
+            | Markdown::Html(_)
+            | Markdown::Comment(_) => String::new(),
+        })
+        .collect::<Vec<String>>()
+        .join("")
+}
+
+fn translate_list(start: Option<usize>, items: Vec<ListItem>, notes: &Notes) -> String {
+    let items = items
+        .iter()
+        .map(|(line, children)| {
+            format!(
+                "<listitem><para>{}</para>{}</listitem>\n",
+                translate_text(line.to_vec(), notes),
+                translate_blocks(children.to_vec(), notes)
+            )
+        })
+        .collect::<Vec<String>>()
+        .join("");
+    match start {
+        None => format!("<itemizedlist>\n{}</itemizedlist>\n", items),
+        Some(1) => format!("<orderedlist>\n{}</orderedlist>\n", items),
+        Some(start) => format!(
+            "<orderedlist startingnumber=\"{}\">\n{}</orderedlist>\n",
+            start, items
+        ),
+    }
+}
+
+fn translate_codeblock(lang: &str, code: &str) -> String {
+    let language = if lang.is_empty() {
+        String::new()
+    } else {
+        format!(" language=\"{}\"", escape(lang))
+    };
+    format!(
+        "<programlisting{}>{}</programlisting>\n",
+        language,
+        escape(code)
+    )
+}
+
+fn translate_paragraph(lines: Vec<MarkdownText>, notes: &Notes) -> String {
+    let text = join_lines(&lines, "\n", |line| translate_text(line.to_vec(), notes));
+    if text.is_empty() {
+        text
+    } else {
+        format!("<para>{}</para>\n", text)
+    }
+}
+
+fn translate_table(
+    header: Vec<MarkdownText>,
+    aligns: Vec<Align>,
+    rows: Vec<Vec<MarkdownText>>,
+    notes: &Notes,
+) -> String {
+    let row = |cells: Vec<MarkdownText>| {
+        let cells = (0..aligns.len())
+            .map(|k| {
+                let align = match aligns[k] {
+                    Align::Left => " align=\"left\"",
+                    Align::Center => " align=\"center\"",
+                    Align::Right => " align=\"right\"",
+                    Align::None => "",
+                };
+                format!(
+                    "<entry{}>{}</entry>",
+                    align,
+                    translate_text(cells.get(k).cloned().unwrap_or_default(), notes)
+                )
+            })
+            .collect::<Vec<String>>()
+            .join("");
+        format!("<row>{}</row>\n", cells)
+    };
+    let body = rows.into_iter().map(row).collect::<Vec<String>>().join("");
+    format!(
+        "<informaltable><tgroup cols=\"{}\">\n<thead>\n{}</thead>\n<tbody>\n{}</tbody>\n</tgroup></informaltable>\n",
+        aligns.len(),
+        row(header),
+        body
+    )
+}
+
+fn translate_text(text: MarkdownText, notes: &Notes) -> String {
+    text.iter()
+        .map(|part| match part {
+            MarkdownInline::Bold(text) => format!(
+                "<emphasis role=\"strong\">{}</emphasis>",
+                translate_text(text.to_vec(), notes)
+            ),
+            MarkdownInline::Italic(text) => {
+                format!("<emphasis>{}</emphasis>", translate_text(text.to_vec(), notes))
+            }
+            MarkdownInline::Strike(text) => format!(
+                "<emphasis role=\"strikethrough\">{}</emphasis>",
+                escape(text)
+            ),
+            MarkdownInline::Mark(text) => format!(
+                "<emphasis role=\"highlight\">{}</emphasis>",
+                translate_text(text.to_vec(), notes)
+            ),
+            MarkdownInline::Superscript(text) => format!("<superscript>{}</superscript>", escape(text)),
+            MarkdownInline::Subscript(text) => format!("<subscript>{}</subscript>", escape(text)),
+            MarkdownInline::Kbd(keys) => format!(
+                "<keycombo>{}</keycombo>",
+                keys.iter()
+                    .map(|key| format!("<keycap>{}</keycap>", escape(key)))
+                    .collect::<String>()
+            ),
+            MarkdownInline::Ruby(base, readings) => escape(&ruby_text(base, readings)),
+            MarkdownInline::InlineCode(code) => format!("<code>{}</code>", escape(code)),
+            MarkdownInline::Link(text, url, _) => match url.strip_prefix('#') {
+                Some(id) if is_xml_id(id) => format!(
+                    "<link linkend=\"{}\">{}</link>",
+                    escape(id),
+                    translate_text(text.to_vec(), notes)
+                ),
+                _ => format!(
+                    "<link xlink:href=\"{}\">{}</link>",
+                    escape(url),
+                    translate_text(text.to_vec(), notes)
+                ),
+            },
+            MarkdownInline::Image(alt, url, _) => format!(
+                "<inlinemediaobject><imageobject><imagedata fileref=\"{}\"/></imageobject><textobject><phrase>{}</phrase></textobject></inlinemediaobject>",
+                escape(url),
+                translate_text(alt.to_vec(), notes)
+            ),
+            MarkdownInline::Plaintext(text) => escape(text),
+            // the references in a footnote, which may be to itself, are left as they are written
+            MarkdownInline::FootnoteRef(label) => match notes.get(label) {
+                Some(note) => format!(
+                    "<footnote><para>{}</para></footnote>",
+                    translate_text(note.to_vec(), &Notes::new())
+                ),
+                None => escape(&format!("[^{}]", label)),
+            },
+            MarkdownInline::Math(tex) => format!(
+                "<inlineequation><mathphrase>{}</mathphrase></inlineequation>",
+                escape(tex)
+            ),
+            MarkdownInline::Emoji(name) => escape(&emoji_text(name)),
+            MarkdownInline::WikiLink(target, label) => {
+                format!("<emphasis>{}</emphasis>", escape(&wikilink_text(target, label)))
+            }
+            MarkdownInline::Html(_) | MarkdownInline::Comment(_) => String::new(),
+            MarkdownInline::LineBreak => String::from("\n"),
+        })
+        .collect::<Vec<String>>()
+        .join("")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn is_well_formed(xml: &str) -> bool {
+        roxmltree::Document::parse(xml).is_ok()
+    }
+
+    #[test]
+    fn test_translate() {
+        let md = crate::parse(
+            "---\ntitle: T & U\n---\nintro\n\n# A\n\n```rust\nif a < b {}\n```\n\n## B\n\n- x[^1]\n  1. y\n\n| a | b |\n|---|--:|\n| 1 | 2 |\n\n# C\n\n> see [A](#a) and ![i](i.png)\n\n::: warning\n**w**\n:::\n\n[^1]: note\n",
+        )
+        .unwrap();
+        let xml = translate(
+            md,
+            &Options {
+                standalone: true,
+                ..Options::default()
+            },
+        );
+        assert!(is_well_formed(&xml), "{}", xml);
+        assert!(xml.contains("<info><title>T &amp; U</title></info>\n<para>intro</para>\n"));
+        assert!(xml.contains("<section xml:id=\"a\">\n<title>A</title>\n<programlisting language=\"rust\">if a &lt; b {}\n</programlisting>\n<section xml:id=\"b\">"));
+        assert!(xml.contains("</informaltable>\n</section>\n</section>\n<section xml:id=\"c\">"));
+        assert!(xml.contains(
+            "<listitem><para>x<footnote><para>note</para></footnote></para><orderedlist>"
+        ));
+        assert!(xml.contains("<entry align=\"right\">2</entry>"));
+        assert!(xml.contains("<link linkend=\"a\">A</link>"));
+        assert!(xml.contains("<warning>\n<title>Warning</title>\n<para><emphasis role=\"strong\">w</emphasis></para>\n</warning>"));
+        assert!(xml.ends_with("</section>\n</article>\n"));
+        assert!(!is_well_formed("<a><b></a></b>"));
+
+        let xml = translate(
+            crate::parse("# a\u{1}b\n\nc\u{b}d `e\u{ffff}`\n").unwrap(),
+            &Options {
+                standalone: true,
+                ..Options::default()
+            },
+        );
+        assert!(is_well_formed(&xml), "{}", xml);
+        assert!(xml.contains("<para>c\u{fffd}d <code>e\u{fffd}</code></para>"));
+    }
+}
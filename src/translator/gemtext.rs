@@ -0,0 +1,246 @@
+use crate::entity::ListItem;
+use crate::entity::Markdown;
+use crate::entity::MarkdownInline;
+use crate::entity::MarkdownText;
+use crate::translator::{
+    admonition_title, collect_footnotes, emoji_text, join_lines, plain_text, ruby_text,
+    split_at_footnotes, wikilink_text,
+};
+
+// gemtext has no inline markup and no nesting:
+// the text of links is kept in place and every link is repeated as a `=>` line after its block,
+// and nested list items are flattened into one list
+
+pub fn translate(md: Vec<Markdown>) -> String {
+    let (body, notes) = collect_footnotes(md);
+    let (body, rest) = split_at_footnotes(body);
+    let mut output = translate_blocks(body);
+    if !notes.is_empty() {
+        output += "## Notes\n\n";
+        for (k, note) in notes.iter().enumerate() {
+            output += &format!("[{}] {}\n", k + 1, translate_text(note));
+        }
+        output += "\n";
+        output += &translate_links(notes.iter());
+    }
+    output += &translate_blocks(rest);
+    output.trim_end().to_string()
+}
+
+fn translate_blocks(md: Vec<Markdown>) -> String {
+    md.iter()
+        .map(|bit| match bit {
+            Markdown::Heading(size, line, _) => {
+                format!("{} {}\n\n", "#".repeat(*size.min(&3)), translate_text(line))
+            }
+            Markdown::UnorderedList(items) | Markdown::OrderedList(_, items) => {
+                let mut lines = vec![];
+                flatten_list(items, &mut lines);
+                format!(
+                    "{}{}\n",
+                    lines
+                        .iter()
+                        .map(|line| format!("* {}\n", translate_text(line)))
+                        .collect::<String>(),
+                    translate_links(lines.iter())
+                )
+            }
+            Markdown::Codeblock(lang, code, _) => format!("```{}\n{}```\n\n", lang, code),
+            Markdown::Line(line) => translate_paragraph(&[line.to_vec()]),
+            Markdown::Paragraph(lines) => translate_paragraph(lines),
+            Markdown::DefinitionList(definitions) => definitions
+                .iter()
+                .map(|(term, texts)| {
+                    format!(
+                        "{}\n{}\n{}",
+                        translate_text(term),
+                        texts
+                            .iter()
+                            .map(|text| format!("* {}\n", translate_text(text)))
+                            .collect::<String>(),
+                        translate_links(std::iter::once(term).chain(texts.iter()))
+                    )
+                })
+                .collect(),
+            Markdown::HorizontalRule => String::from("---\n\n"),
+            Markdown::Table(header, _, rows) => translate_table(header, rows),
+            Markdown::Blockquote(inner) => translate_blockquote(inner.to_vec()),
+            Markdown::Admonition(kind, title, inner) => format!(
+                "{}:\n{}",
+                admonition_title(kind, title),
+                translate_blocks(inner.to_vec())
+            ),
+            Markdown::Div(_, inner) => translate_blocks(inner.to_vec()),
+            Markdown::Details(summary, _, inner) => format!(
+                "{}:\n{}",
+                translate_text(summary),
+                translate_blocks(inner.to_vec())
+            ),
+            Markdown::MathBlock(tex) => format!("```\n{}\n```\n\n", tex),
+            Markdown::FootnoteDefinition(_, _)
+            | Markdown::Footnotes
+            | Markdown::FrontMatter(_)
+            | Markdown::Html(_)
+            | Markdown::Comment(_) => String::new(),
+        })
+        .collect::<Vec<String>>()
+        .join("")
+}
+
+fn flatten_list(items: &[ListItem], lines: &mut Vec<MarkdownText>) {
+    for (line, children) in items {
+        lines.push(line.to_vec());
+        for child in children {
+            if let Markdown::UnorderedList(items) | Markdown::OrderedList(_, items) = child {
+                flatten_list(items, lines);
+            }
+        }
+    }
+}
+
+// a paragraph becomes one long line, which gemini clients wrap by themselves
+fn translate_paragraph(lines: &[MarkdownText]) -> String {
+    let text = join_lines(lines, " ", translate_text);
+    if text.is_empty() {
+        text
+    } else {
+        format!("{}\n\n{}", text, translate_links(lines.iter()))
+    }
+}
+
+fn translate_blockquote(inner: Vec<Markdown>) -> String {
+    let lines = inner
+        .iter()
+        .flat_map(|bit| match bit {
+            Markdown::Paragraph(lines) => lines.to_vec(),
+            Markdown::Line(line) | Markdown::Heading(_, line, _) => vec![line.to_vec()],
+            _ => vec![],
+        })
+        .collect::<Vec<MarkdownText>>();
+    format!(
+        "{}\n{}",
+        lines
+            .iter()
+            .map(|line| format!("> {}\n", translate_text(line)))
+            .collect::<String>(),
+        translate_links(lines.iter())
+    )
+}
+
+// tables have no counterpart, so they are laid out as preformatted text
+fn translate_table(header: &[MarkdownText], rows: &[Vec<MarkdownText>]) -> String {
+    let row = |cells: &[MarkdownText]| {
+        cells
+            .iter()
+            .map(plain_text)
+            .collect::<Vec<String>>()
+            .join(" | ")
+    };
+    let body = rows
+        .iter()
+        .map(|cells| format!("{}\n", row(cells)))
+        .collect::<String>();
+    format!("```\n{}\n{}```\n\n", row(header), body)
+}
+
+fn translate_text(text: &MarkdownText) -> String {
+    text.iter()
+        .map(|part| match part {
+            MarkdownInline::Bold(text)
+            | MarkdownInline::Italic(text)
+            | MarkdownInline::Mark(text) => translate_text(text),
+            MarkdownInline::Kbd(keys) => keys.join("+"),
+            MarkdownInline::Ruby(base, readings) => ruby_text(base, readings),
+            MarkdownInline::Strike(text)
+            | MarkdownInline::Superscript(text)
+            | MarkdownInline::Subscript(text)
+            | MarkdownInline::InlineCode(text)
+            | MarkdownInline::Math(text)
+            | MarkdownInline::Plaintext(text) => text.to_string(),
+            MarkdownInline::Link(text, _, _) | MarkdownInline::Image(text, _, _) => {
+                translate_text(text)
+            }
+            MarkdownInline::FootnoteRef(number) => format!("[{}]", number),
+            MarkdownInline::Emoji(name) => emoji_text(name),
+            MarkdownInline::WikiLink(target, label) => wikilink_text(target, label),
+            MarkdownInline::Html(_) | MarkdownInline::Comment(_) => String::new(),
+            MarkdownInline::LineBreak => String::from("\n"),
+        })
+        .collect::<Vec<String>>()
+        .join("")
+}
+
+fn translate_links<'a, I>(lines: I) -> String
+where
+    I: Iterator<Item = &'a MarkdownText>,
+{
+    let links = lines
+        .flatten()
+        .filter_map(|part| match part {
+            MarkdownInline::Link(text, url, _) | MarkdownInline::Image(text, url, _) => {
+                let text = translate_text(text);
+                if &text == url {
+                    Some(format!("=> {}\n", url))
+                } else {
+                    Some(format!("=> {} {}\n", url, text))
+                }
+            }
+            _ => None,
+        })
+        .collect::<String>();
+    if links.is_empty() {
+        links
+    } else {
+        format!("{}\n", links)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::entity::Attributes;
+
+    fn text(s: &str) -> MarkdownText {
+        vec![MarkdownInline::Plaintext(String::from(s))]
+    }
+
+    #[test]
+    fn test_translate_paragraph() {
+        assert_eq!(
+            translate_paragraph(&[
+                vec![
+                    MarkdownInline::Plaintext(String::from("see ")),
+                    MarkdownInline::Link(
+                        vec![MarkdownInline::Plaintext(String::from("this"))],
+                        String::from("gemini://a.org"),
+                        None
+                    ),
+                ],
+                vec![MarkdownInline::Bold(vec![MarkdownInline::Plaintext(
+                    String::from("and")
+                )])],
+            ]),
+            "see this and\n\n=> gemini://a.org this\n\n"
+        );
+    }
+
+    #[test]
+    fn test_translate() {
+        assert_eq!(
+            translate(vec![
+                Markdown::Heading(4, text("deep"), Attributes::default()),
+                Markdown::UnorderedList(vec![(
+                    text("a"),
+                    vec![Markdown::OrderedList(1, vec![(text("b"), vec![])])]
+                )]),
+                Markdown::Codeblock(String::from("sh"), String::from("ls\n"), String::new()),
+                Markdown::Table(
+                    vec![text("x"), text("y")],
+                    vec![],
+                    vec![vec![text("1"), text("2")]]
+                ),
+            ]),
+            "### deep\n\n* a\n* b\n\n```sh\nls\n```\n\n```\nx | y\n1 | 2\n```"
+        );
+    }
+}
@@ -0,0 +1,229 @@
+use crate::entity::{Align, Attributes, Markdown};
+use crate::translator::admonition_title;
+use crate::translator::render::{self, Render};
+
+// the wiki markup of Jira and of the older Confluence, where a line break in a paragraph is kept,
+// so the lines of a paragraph are joined
+#[derive(Clone, Debug, Default)]
+pub struct JiraRenderer {
+    // the markers of the lists being written, `*` or `#`, which an item repeats
+    lists: String,
+    // the urls of the links being written
+    links: Vec<String>,
+    // the macros of the admonitions being written
+    panels: Vec<&'static str>,
+}
+
+pub fn translate(md: Vec<Markdown>) -> String {
+    render::render(md, &mut JiraRenderer::default())
+        .trim_end()
+        .to_string()
+}
+
+// the panel macro for a kind of admonition
+pub(crate) fn panel(kind: &str) -> &'static str {
+    match kind {
+        "tip" | "hint" => "tip",
+        "warning" | "caution" | "danger" | "error" => "warning",
+        "important" | "attention" => "note",
+        _ => "info",
+    }
+}
+
+fn escape(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        if matches!(c, '\\' | '*' | '_' | '{' | '}' | '[' | ']' | '|') {
+            out.push('\\');
+        }
+        out.push(c);
+    }
+    out
+}
+
+impl Render for JiraRenderer {
+    fn heading_start(&mut self, out: &mut String, level: usize, _: &Attributes) {
+        out.push_str(&format!("h{}. ", level.min(6)));
+    }
+
+    fn soft_break(&mut self, out: &mut String) {
+        out.push(' ');
+    }
+
+    fn list_start(&mut self, _: &mut String, start: Option<usize>) {
+        self.lists.push(if start.is_some() { '#' } else { '*' });
+    }
+
+    fn list_end(&mut self, out: &mut String, _: Option<usize>) {
+        self.lists.pop();
+        if self.lists.is_empty() {
+            out.push('\n');
+        }
+    }
+
+    fn item_start(&mut self, out: &mut String) {
+        out.push_str(&self.lists);
+        out.push(' ');
+    }
+
+    fn term_start(&mut self, out: &mut String) {
+        out.push('*');
+    }
+
+    fn term_end(&mut self, out: &mut String) {
+        out.push_str("*\n");
+    }
+
+    fn definition_start(&mut self, _: &mut String) {}
+
+    fn codeblock(&mut self, out: &mut String, lang: &str, code: &str, _: &str) {
+        if lang.is_empty() {
+            out.push_str("{code}\n");
+        } else {
+            out.push_str(&format!("{{code:{}}}\n", lang));
+        }
+        out.push_str(code);
+        if !code.ends_with('\n') {
+            out.push('\n');
+        }
+        out.push_str("{code}\n\n");
+    }
+
+    fn horizontal_rule(&mut self, out: &mut String) {
+        out.push_str("----\n\n");
+    }
+
+    fn table_cell_start(&mut self, out: &mut String, _: usize, _: Align, header: bool) {
+        out.push_str(if header { "||" } else { "|" });
+    }
+
+    fn table_row_end(&mut self, out: &mut String, header: bool) {
+        out.push_str(if header { "||\n" } else { "|\n" });
+    }
+
+    fn blockquote_start(&mut self, out: &mut String) {
+        out.push_str("{quote}\n");
+    }
+
+    fn blockquote_end(&mut self, out: &mut String) {
+        out.push_str("{quote}\n\n");
+    }
+
+    fn admonition_start(&mut self, out: &mut String, kind: &str, title: &Option<String>) {
+        let panel = panel(kind);
+        self.panels.push(panel);
+        out.push_str(&format!(
+            "{{{}:title={}}}\n",
+            panel,
+            admonition_title(kind, title)
+        ));
+    }
+
+    // the blank line after the last block is left out in the macro
+    fn admonition_end(&mut self, out: &mut String) {
+        out.truncate(out.trim_end_matches('\n').len());
+        out.push_str(&format!(
+            "\n{{{}}}\n\n",
+            self.panels.pop().unwrap_or("info")
+        ));
+    }
+
+    fn summary_start(&mut self, out: &mut String) {
+        out.push_str("{expand:");
+    }
+
+    fn summary_end(&mut self, out: &mut String) {
+        out.push_str("}\n");
+    }
+
+    fn details_end(&mut self, out: &mut String) {
+        out.push_str("{expand}\n\n");
+    }
+
+    fn math_block(&mut self, out: &mut String, tex: &str) {
+        out.push_str(&format!("{{noformat}}\n{}\n{{noformat}}\n\n", tex));
+    }
+
+    fn footnotes_start(&mut self, out: &mut String) {
+        out.push_str("----\n\n");
+    }
+
+    fn text(&mut self, out: &mut String, text: &str) {
+        out.push_str(&escape(text));
+    }
+
+    fn bold_start(&mut self, out: &mut String) {
+        out.push('*');
+    }
+
+    fn bold_end(&mut self, out: &mut String) {
+        out.push('*');
+    }
+
+    fn italic_start(&mut self, out: &mut String) {
+        out.push('_');
+    }
+
+    fn italic_end(&mut self, out: &mut String) {
+        out.push('_');
+    }
+
+    fn strike(&mut self, out: &mut String, text: &str) {
+        out.push_str(&format!("-{}-", escape(text)));
+    }
+
+    fn superscript(&mut self, out: &mut String, text: &str) {
+        out.push_str(&format!("^{}^", escape(text)));
+    }
+
+    fn subscript(&mut self, out: &mut String, text: &str) {
+        out.push_str(&format!("~{}~", escape(text)));
+    }
+
+    fn inline_code(&mut self, out: &mut String, code: &str) {
+        out.push_str(&format!("{{{{{}}}}}", code));
+    }
+
+    fn link_start(&mut self, out: &mut String, url: &str, _: &Option<String>) {
+        self.links.push(url.to_string());
+        out.push('[');
+    }
+
+    fn link_end(&mut self, out: &mut String) {
+        let url = self.links.pop().unwrap_or_default();
+        out.push('|');
+        out.push_str(&url);
+        out.push(']');
+    }
+
+    fn image(&mut self, out: &mut String, alt: &str, url: &str, _: &Option<String>) {
+        if alt.is_empty() {
+            out.push_str(&format!("!{}!", url));
+        } else {
+            out.push_str(&format!("!{}|alt={}!", url, alt.replace(['|', '!'], " ")));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_translate() {
+        let md = crate::parse(
+            "# Release 1.2\n\nsome **bold** and *it*,\n`code` [link](https://a.com) ~~old~~\n\n- a\n  1. b\n- c\n\n```rust\nfn f() {}\n```\n\n| a | b |\n|---|---|\n| 1 | x_y |\n\n> quote\n\n::: warning\nmind\n:::\n",
+        )
+        .unwrap();
+        assert_eq!(
+            translate(md),
+            "h1. Release 1.2\n\n\
+             some *bold* and _it_, {{code}} [link|https://a.com] -old-\n\n\
+             * a\n*# b\n* c\n\n\
+             {code:rust}\nfn f() {}\n{code}\n\n\
+             ||a||b||\n|1|x\\_y|\n\n\
+             {quote}\nquote\n\n{quote}\n\n\
+             {warning:title=Warning}\nmind\n{warning}"
+        );
+    }
+}
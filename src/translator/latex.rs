@@ -0,0 +1,346 @@
+use crate::entity::Align;
+use crate::entity::ListItem;
+use crate::entity::Markdown;
+use crate::entity::MarkdownInline;
+use crate::entity::MarkdownText;
+use crate::translator::{
+    admonition_title, emoji_text, join_lines, ruby_text, take_footnote_definitions,
+    translate_title, wikilink_text, Options,
+};
+
+use std::collections::HashMap;
+
+// footnotes are native in LaTeX, so the definitions are looked up at the references
+type Notes = HashMap<String, MarkdownText>;
+
+pub fn translate(md: Vec<Markdown>, options: &Options) -> String {
+    let title = translate_title(&md);
+    let mut notes = HashMap::new();
+    let body = take_footnote_definitions(md, &mut notes);
+    let body = translate_blocks(body, &notes);
+    if options.standalone {
+        translate_document(title, body)
+    } else {
+        body
+    }
+}
+
+fn translate_document(title: Option<String>, body: String) -> String {
+    let title = title
+        .map(|title| format!("\\title{{{}}}\n\\date{{}}\n", escape(&title)))
+        .unwrap_or_default();
+    let maketitle = if title.is_empty() {
+        ""
+    } else {
+        "\\maketitle\n"
+    };
+    format!(
+        "\\documentclass{{article}}\n\\usepackage[utf8]{{inputenc}}\n\\usepackage{{graphicx}}\n\\usepackage{{hyperref}}\n\\usepackage[normalem]{{ulem}}\n\\usepackage{{xcolor}}\n\\usepackage{{soul}}\n{}\\begin{{document}}\n{}{}\\end{{document}}\n",
+        title, maketitle, body
+    )
+}
+
+fn escape(text: &str) -> String {
+    text.chars()
+        .map(|c| match c {
+            '\\' => String::from("\\textbackslash{}"),
+            '~' => String::from("\\textasciitilde{}"),
+            '^' => String::from("\\textasciicircum{}"),
+            '&' | '%' | '$' | '#' | '_' | '{' | '}' => format!("\\{}", c),
+            c => c.to_string(),
+        })
+        .collect()
+}
+
+fn translate_blocks(md: Vec<Markdown>, notes: &Notes) -> String {
+    md.iter()
+        .map(|bit| match bit {
+            Markdown::Heading(size, line, _) => translate_header(*size, line.to_vec(), notes),
+            Markdown::UnorderedList(items) => translate_list(None, items.to_vec(), notes),
+            Markdown::DefinitionList(definitions) => {
+                let items: String = definitions
+                    .iter()
+                    .map(|(term, texts)| {
+                        let texts = texts
+                            .iter()
+                            .map(|text| translate_text(text.to_vec(), notes))
+                            .collect::<Vec<String>>()
+                            .join("\n\n");
+                        format!(
+                            "\\item[{}] {}\n",
+                            translate_text(term.to_vec(), notes),
+                            texts
+                        )
+                    })
+                    .collect();
+                format!("\\begin{{description}}\n{}\\end{{description}}\n\n", items)
+            }
+            Markdown::OrderedList(start, items) => {
+                translate_list(Some(*start), items.to_vec(), notes)
+            }
+            Markdown::Codeblock(_, code, _) => translate_codeblock(code.to_string()),
+            Markdown::Line(line) => translate_paragraph(vec![line.to_vec()], notes),
+            Markdown::Paragraph(lines) => translate_paragraph(lines.to_vec(), notes),
+            Markdown::HorizontalRule => String::from("\\noindent\\rule{\\linewidth}{0.4pt}\n\n"),
+            Markdown::Table(header, aligns, rows) => {
+                translate_table(header.to_vec(), aligns.to_vec(), rows.to_vec(), notes)
+            }
+            Markdown::Blockquote(inner) => format!(
+                "\\begin{{quote}}\n{}\\end{{quote}}\n\n",
+                translate_blocks(inner.to_vec(), notes)
+            ),
+            Markdown::Admonition(kind, title, inner) => format!(
+                "\\begin{{quote}}\n\\textbf{{{}}}\n\n{}\\end{{quote}}\n\n",
+                escape(&admonition_title(kind, title)),
+                translate_blocks(inner.to_vec(), notes)
+            ),
+            Markdown::Div(_, inner) => translate_blocks(inner.to_vec(), notes),
+            Markdown::Details(summary, _, inner) => format!(
+                "\\begin{{quote}}\n\\textbf{{{}}}\n\n{}\\end{{quote}}\n\n",
+                translate_text(summary.to_vec(), notes),
+                translate_blocks(inner.to_vec(), notes)
+            ),
+            Markdown::MathBlock(tex) => format!("\\[\n{}\n\\]\n\n", tex),
+            Markdown::FootnoteDefinition(_, _)
+            | Markdown::Footnotes
+            | Markdown::FrontMatter(_)
+            | Markdown::Html(_)
+            | Markdown::Comment(_) => String::new(),
+        })
+        .collect::<Vec<String>>()
+        .join("")
+}
+
+fn translate_header(size: usize, text: MarkdownText, notes: &Notes) -> String {
+    let command = match size {
+        1 => "section",
+        2 => "subsection",
+        3 => "subsubsection",
+        4 => "paragraph",
+        _ => "subparagraph",
+    };
+    format!("\\{}{{{}}}\n\n", command, translate_text(text, notes))
+}
+
+// enumerate counts from the start of an ordered list, itemize is for the others
+fn translate_list(start: Option<usize>, items: Vec<ListItem>, notes: &Notes) -> String {
+    let items = items
+        .iter()
+        .map(|(line, children)| {
+            format!(
+                "\\item {}\n{}",
+                translate_text(line.to_vec(), notes),
+                translate_blocks(children.to_vec(), notes)
+            )
+        })
+        .collect::<Vec<String>>()
+        .join("");
+    match start {
+        None => format!("\\begin{{itemize}}\n{}\\end{{itemize}}\n\n", items),
+        Some(1) => format!("\\begin{{enumerate}}\n{}\\end{{enumerate}}\n\n", items),
+        Some(start) => format!(
+            "\\begin{{enumerate}}\n\\setcounter{{enumi}}{{{}}}\n{}\\end{{enumerate}}\n\n",
+            start - 1,
+            items
+        ),
+    }
+}
+
+fn translate_codeblock(code: String) -> String {
+    format!("\\begin{{verbatim}}\n{}\\end{{verbatim}}\n\n", code)
+}
+
+fn translate_paragraph(lines: Vec<MarkdownText>, notes: &Notes) -> String {
+    let text = join_lines(&lines, "\n", |line| translate_text(line.to_vec(), notes));
+    if text.is_empty() {
+        text
+    } else {
+        format!("{}\n\n", text)
+    }
+}
+
+fn translate_table(
+    header: Vec<MarkdownText>,
+    aligns: Vec<Align>,
+    rows: Vec<Vec<MarkdownText>>,
+    notes: &Notes,
+) -> String {
+    let spec = aligns
+        .iter()
+        .map(|align| match align {
+            Align::Center => "c",
+            Align::Right => "r",
+            Align::None | Align::Left => "l",
+        })
+        .collect::<Vec<&str>>()
+        .join("");
+    let row = |cells: Vec<MarkdownText>| {
+        let cells = (0..aligns.len())
+            .map(|k| translate_text(cells.get(k).cloned().unwrap_or_default(), notes))
+            .collect::<Vec<String>>()
+            .join(" & ");
+        format!("{} \\\\\n", cells)
+    };
+    let body = rows.into_iter().map(row).collect::<Vec<String>>().join("");
+    format!(
+        "\\begin{{tabular}}{{{}}}\n\\hline\n{}\\hline\n{}\\hline\n\\end{{tabular}}\n\n",
+        spec,
+        row(header),
+        body
+    )
+}
+
+fn translate_text(text: MarkdownText, notes: &Notes) -> String {
+    text.iter()
+        .map(|part| match part {
+            MarkdownInline::Bold(text) => {
+                format!("\\textbf{{{}}}", translate_text(text.to_vec(), notes))
+            }
+            MarkdownInline::Italic(text) => {
+                format!("\\emph{{{}}}", translate_text(text.to_vec(), notes))
+            }
+            MarkdownInline::Strike(text) => format!("\\sout{{{}}}", escape(text)),
+            MarkdownInline::Mark(text) => {
+                format!("\\hl{{{}}}", translate_text(text.to_vec(), notes))
+            }
+            MarkdownInline::Superscript(text) => format!("\\textsuperscript{{{}}}", escape(text)),
+            MarkdownInline::Subscript(text) => format!("\\textsubscript{{{}}}", escape(text)),
+            MarkdownInline::Kbd(keys) => keys
+                .iter()
+                .map(|key| format!("\\fbox{{\\texttt{{{}}}}}", escape(key)))
+                .collect::<Vec<String>>()
+                .join("+"),
+            MarkdownInline::Ruby(base, readings) => escape(&ruby_text(base, readings)),
+            MarkdownInline::InlineCode(code) => format!("\\texttt{{{}}}", escape(code)),
+            MarkdownInline::Link(text, url, _) => {
+                format!(
+                    "\\href{{{}}}{{{}}}",
+                    escape(url),
+                    translate_text(text.to_vec(), notes)
+                )
+            }
+            MarkdownInline::Image(_, url, _) => format!("\\includegraphics{{{}}}", url),
+            MarkdownInline::Plaintext(text) => escape(text),
+            // the references in a footnote, which may be to itself, are left as they are written
+            MarkdownInline::FootnoteRef(label) => match notes.get(label) {
+                Some(note) => format!(
+                    "\\footnote{{{}}}",
+                    translate_text(note.to_vec(), &Notes::new())
+                ),
+                None => escape(&format!("[^{}]", label)),
+            },
+            MarkdownInline::Math(tex) => format!("${}$", tex),
+            MarkdownInline::Emoji(name) => escape(&emoji_text(name)),
+            MarkdownInline::WikiLink(target, label) => {
+                format!("\\emph{{{}}}", escape(&wikilink_text(target, label)))
+            }
+            MarkdownInline::Html(_) | MarkdownInline::Comment(_) => String::new(),
+            MarkdownInline::LineBreak => String::from("\\\\\n"),
+        })
+        .collect::<Vec<String>>()
+        .join("")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::entity::Attributes;
+
+    fn text(s: &str) -> MarkdownText {
+        vec![MarkdownInline::Plaintext(String::from(s))]
+    }
+
+    #[test]
+    fn test_escape() {
+        assert_eq!(
+            escape("50% of $x_1 & {y}~^\\"),
+            "50\\% of \\$x\\_1 \\& \\{y\\}\\textasciitilde{}\\textasciicircum{}\\textbackslash{}"
+        );
+    }
+
+    #[test]
+    fn test_translate_header() {
+        let notes = HashMap::new();
+        assert_eq!(
+            translate_header(1, text("Intro"), &notes),
+            "\\section{Intro}\n\n"
+        );
+        assert_eq!(
+            translate_header(3, text("Deep"), &notes),
+            "\\subsubsection{Deep}\n\n"
+        );
+    }
+
+    #[test]
+    fn test_translate_list() {
+        let notes = HashMap::new();
+        assert_eq!(
+            translate_list(
+                None,
+                vec![(
+                    text("a"),
+                    vec![Markdown::OrderedList(1, vec![(text("b"), vec![])])]
+                )],
+                &notes
+            ),
+            "\\begin{itemize}\n\\item a\n\\begin{enumerate}\n\\item b\n\\end{enumerate}\n\n\\end{itemize}\n\n"
+        );
+    }
+
+    #[test]
+    fn test_translate_table() {
+        let notes = HashMap::new();
+        assert_eq!(
+            translate_table(
+                vec![text("a"), text("b")],
+                vec![Align::None, Align::Right],
+                vec![vec![text("1")]],
+                &notes
+            ),
+            "\\begin{tabular}{lr}\n\\hline\na & b \\\\\n\\hline\n1 &  \\\\\n\\hline\n\\end{tabular}\n\n"
+        );
+    }
+
+    #[test]
+    fn test_translate_text() {
+        let mut notes = HashMap::new();
+        notes.insert(String::from("1"), text("note"));
+        assert_eq!(
+            translate_text(
+                vec![
+                    MarkdownInline::Bold(vec![MarkdownInline::Plaintext(String::from("b"))]),
+                    MarkdownInline::Italic(vec![MarkdownInline::Plaintext(String::from("i"))]),
+                    MarkdownInline::InlineCode(String::from("a_b")),
+                    MarkdownInline::Link(vec![MarkdownInline::Plaintext(String::from("t"))], String::from("https://x.com/#a"), None),
+                    MarkdownInline::FootnoteRef(String::from("1")),
+                    MarkdownInline::FootnoteRef(String::from("2")),
+                ],
+                &notes
+            ),
+            "\\textbf{b}\\emph{i}\\texttt{a\\_b}\\href{https://x.com/\\#a}{t}\\footnote{note}[\\textasciicircum{}2]"
+        );
+        // a note which refers to itself
+        notes.insert(
+            String::from("2"),
+            vec![MarkdownInline::FootnoteRef(String::from("2"))],
+        );
+        assert_eq!(
+            translate_text(vec![MarkdownInline::FootnoteRef(String::from("2"))], &notes),
+            "\\footnote{[\\textasciicircum{}2]}"
+        );
+    }
+
+    #[test]
+    fn test_translate() {
+        assert_eq!(
+            translate(
+                vec![
+                    Markdown::Heading(1, text("T"), Attributes::default()),
+                    Markdown::Codeblock(String::from("rust"), String::from("a_b\n"), String::new()),
+                ],
+                &Options::default()
+            ),
+            "\\section{T}\n\n\\begin{verbatim}\na_b\n\\end{verbatim}\n\n"
+        );
+    }
+}
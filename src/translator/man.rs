@@ -0,0 +1,267 @@
+use crate::entity::Align;
+use crate::entity::ListItem;
+use crate::entity::Markdown;
+use crate::entity::MarkdownInline;
+use crate::entity::MarkdownText;
+use crate::translator::{
+    admonition_title, collect_footnotes, emoji_text, join_lines, plain_text, ruby_text,
+    translate_title, wikilink_text,
+};
+
+// man(7) output. the page title comes from the front matter or the first heading,
+// and the manual section from the `section` field of the front matter
+pub fn translate(md: Vec<Markdown>) -> String {
+    let title = translate_title(&md).unwrap_or_default();
+    let section = md
+        .iter()
+        .find_map(|bit| match bit {
+            Markdown::FrontMatter(front_matter) => front_matter.get("section").cloned(),
+            _ => None,
+        })
+        .unwrap_or_else(|| String::from("1"));
+    let (body, notes) = collect_footnotes(md);
+    let mut output = format!(
+        ".TH \"{}\" \"{}\"\n{}",
+        escape(&title.to_uppercase()),
+        escape(&section),
+        translate_blocks(body)
+    );
+    if !notes.is_empty() {
+        output += ".SH NOTES\n";
+        for (k, note) in notes.iter().enumerate() {
+            output += &format!(".IP [{}] 4\n{}\n", k + 1, translate_text(note.to_vec()));
+        }
+    }
+    output.trim_end().to_string()
+}
+
+// lines starting with `.` or `'` would be taken as requests
+fn escape(text: &str) -> String {
+    let text = text.replace('\\', "\\e").replace('-', "\\-");
+    if text.starts_with(['.', '\'']) {
+        format!("\\&{}", text)
+    } else {
+        text
+    }
+}
+
+fn translate_blocks(md: Vec<Markdown>) -> String {
+    md.iter()
+        .map(|bit| match bit {
+            Markdown::Heading(1, line, _) => format!(".SH \"{}\"\n", translate_text(line.to_vec())),
+            Markdown::Heading(_, line, _) => format!(".SS \"{}\"\n", translate_text(line.to_vec())),
+            Markdown::UnorderedList(items) => translate_list(items.to_vec(), None),
+            Markdown::DefinitionList(definitions) => definitions
+                .iter()
+                .map(|(term, texts)| {
+                    let texts = texts
+                        .iter()
+                        .map(|text| translate_text(text.to_vec()))
+                        .collect::<Vec<String>>()
+                        .join("\n.br\n");
+                    format!(".TP\n.B {}\n{}\n", translate_text(term.to_vec()), texts)
+                })
+                .collect(),
+            Markdown::OrderedList(start, items) => translate_list(items.to_vec(), Some(*start)),
+            Markdown::Codeblock(_, code, _) => translate_codeblock(code.to_string()),
+            Markdown::Line(line) => translate_paragraph(vec![line.to_vec()]),
+            Markdown::Paragraph(lines) => translate_paragraph(lines.to_vec()),
+            Markdown::HorizontalRule => String::from(".PP\n* * *\n"),
+            Markdown::Table(header, aligns, rows) => {
+                translate_table(header.to_vec(), aligns.to_vec(), rows.to_vec())
+            }
+            Markdown::Blockquote(inner) => {
+                format!(".RS\n{}.RE\n", translate_blocks(inner.to_vec()))
+            }
+            Markdown::Admonition(kind, title, inner) => format!(
+                ".PP\n.B {}\n.RS\n{}.RE\n",
+                escape(&admonition_title(kind, title)),
+                translate_blocks(inner.to_vec())
+            ),
+            Markdown::Div(_, inner) => translate_blocks(inner.to_vec()),
+            Markdown::Details(summary, _, inner) => format!(
+                ".PP\n.B {}\n.RS\n{}.RE\n",
+                translate_text(summary.to_vec()),
+                translate_blocks(inner.to_vec())
+            ),
+            Markdown::MathBlock(tex) => translate_codeblock(format!("{}\n", tex)),
+            Markdown::FootnoteDefinition(_, _)
+            | Markdown::Footnotes
+            | Markdown::FrontMatter(_)
+            | Markdown::Html(_)
+            | Markdown::Comment(_) => String::new(),
+        })
+        .collect::<Vec<String>>()
+        .join("")
+}
+
+fn translate_paragraph(lines: Vec<MarkdownText>) -> String {
+    let lines: Vec<MarkdownText> = lines
+        .into_iter()
+        .filter(|line| !translate_text(line.to_vec()).is_empty())
+        .collect();
+    let text = join_lines(&lines, "\n", |line| translate_text(line.to_vec()));
+    if text.is_empty() {
+        text
+    } else {
+        format!(".PP\n{}\n", text)
+    }
+}
+
+fn translate_codeblock(code: String) -> String {
+    let code = code
+        .lines()
+        .map(|line| format!("{}\n", escape(line)))
+        .collect::<String>();
+    format!(".PP\n.RS 4\n.nf\n{}.fi\n.RE\n", code)
+}
+
+// nested lists are indented relative to their parent item
+fn translate_list(items: Vec<ListItem>, start: Option<usize>) -> String {
+    items
+        .iter()
+        .enumerate()
+        .map(|(k, (line, children))| {
+            let tag = if let Some(start) = start {
+                format!(".IP {}. 4", start + k)
+            } else {
+                String::from(".IP \\(bu 2")
+            };
+            let children = if children.is_empty() {
+                String::new()
+            } else {
+                format!(".RS\n{}.RE\n", translate_blocks(children.to_vec()))
+            };
+            format!("{}\n{}\n{}", tag, translate_text(line.to_vec()), children)
+        })
+        .collect::<Vec<String>>()
+        .join("")
+}
+
+// tables are left to tbl(1)
+fn translate_table(
+    header: Vec<MarkdownText>,
+    aligns: Vec<Align>,
+    rows: Vec<Vec<MarkdownText>>,
+) -> String {
+    let spec = aligns
+        .iter()
+        .map(|align| match align {
+            Align::Center => "c",
+            Align::Right => "r",
+            Align::None | Align::Left => "l",
+        })
+        .collect::<Vec<&str>>()
+        .join(" ");
+    let row = |cells: Vec<MarkdownText>| {
+        let cells = (0..aligns.len())
+            .map(|k| translate_text(cells.get(k).cloned().unwrap_or_default()))
+            .collect::<Vec<String>>()
+            .join("\t");
+        format!("{}\n", cells)
+    };
+    let body = rows.into_iter().map(row).collect::<Vec<String>>().join("");
+    format!(".TS\nallbox;\n{}.\n{}{}.TE\n", spec, row(header), body)
+}
+
+fn translate_text(text: MarkdownText) -> String {
+    let text = text
+        .iter()
+        .map(|part| match part {
+            MarkdownInline::Bold(text) => format!("\\fB{}\\fR", translate_text(text.to_vec())),
+            MarkdownInline::Italic(text) => format!("\\fI{}\\fR", translate_text(text.to_vec())),
+            MarkdownInline::Strike(text) => escape(text),
+            MarkdownInline::Mark(text) => format!("\\fB{}\\fR", translate_text(text.to_vec())),
+            MarkdownInline::Superscript(text) => format!("^{}", escape(text)),
+            MarkdownInline::Subscript(text) => format!("_{}", escape(text)),
+            MarkdownInline::Kbd(keys) => format!("\\fB{}\\fR", escape(&keys.join("+"))),
+            MarkdownInline::Ruby(base, readings) => escape(&ruby_text(base, readings)),
+            MarkdownInline::InlineCode(code) => format!("\\fB{}\\fR", escape(code)),
+            MarkdownInline::Link(text, url, _) if plain_text(text) == *url => {
+                format!("\\fI{}\\fR", escape(url))
+            }
+            MarkdownInline::Link(text, url, _) => {
+                format!(
+                    "{} <\\fI{}\\fR>",
+                    translate_text(text.to_vec()),
+                    escape(url)
+                )
+            }
+            MarkdownInline::Image(text, _, _) => translate_text(text.to_vec()),
+            MarkdownInline::Plaintext(text) => text.replace('\\', "\\e").replace('-', "\\-"),
+            MarkdownInline::FootnoteRef(number) => format!("[{}]", number),
+            MarkdownInline::Math(tex) => escape(tex),
+            MarkdownInline::Emoji(name) => escape(&emoji_text(name)),
+            MarkdownInline::WikiLink(target, label) => {
+                format!("\\fI{}\\fR", escape(&wikilink_text(target, label)))
+            }
+            MarkdownInline::Html(_) | MarkdownInline::Comment(_) => String::new(),
+            MarkdownInline::LineBreak => String::from("\n.br\n"),
+        })
+        .collect::<Vec<String>>()
+        .join("");
+    if text.starts_with(['.', '\'']) {
+        format!("\\&{}", text)
+    } else {
+        text
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::entity::Attributes;
+
+    fn text(s: &str) -> MarkdownText {
+        vec![MarkdownInline::Plaintext(String::from(s))]
+    }
+
+    #[test]
+    fn test_escape() {
+        assert_eq!(escape("a-b\\c"), "a\\-b\\ec");
+        assert_eq!(escape(".start"), "\\&.start");
+    }
+
+    #[test]
+    fn test_translate_text() {
+        assert_eq!(
+            translate_text(vec![
+                MarkdownInline::Plaintext(String::from(".run ")),
+                MarkdownInline::Bold(vec![MarkdownInline::Plaintext(String::from("--all"))]),
+                MarkdownInline::Link(
+                    vec![MarkdownInline::Plaintext(String::from("site"))],
+                    String::from("https://x.com"),
+                    None
+                ),
+            ]),
+            "\\&.run \\fB\\-\\-all\\fRsite <\\fIhttps://x.com\\fR>"
+        );
+    }
+
+    #[test]
+    fn test_translate_list() {
+        assert_eq!(
+            translate_list(
+                vec![(
+                    text("a"),
+                    vec![Markdown::UnorderedList(vec![(text("b"), vec![])])]
+                )],
+                Some(1)
+            ),
+            ".IP 1. 4\na\n.RS\n.IP \\(bu 2\nb\n.RE\n"
+        );
+    }
+
+    #[test]
+    fn test_translate() {
+        assert_eq!(
+            translate(vec![
+                Markdown::Heading(1, text("prose"), Attributes::default()),
+                Markdown::Heading(2, text("Options"), Attributes::default()),
+                Markdown::Paragraph(vec![text("a"), text("b")]),
+                Markdown::Codeblock(String::new(), String::from("$ prose\n"), String::new()),
+            ]),
+            ".TH \"PROSE\" \"1\"\n.SH \"prose\"\n.SS \"Options\"\n.PP\na\nb\n.PP\n.RS 4\n.nf\n$ prose\n.fi\n.RE"
+        );
+    }
+}
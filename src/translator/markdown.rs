@@ -0,0 +1,810 @@
+use crate::entity::Align;
+use crate::entity::Attributes;
+use crate::entity::ListItem;
+use crate::entity::Markdown;
+use crate::entity::MarkdownInline;
+use crate::entity::MarkdownText;
+use crate::parser::{is_literal, parse_autolink, ESCAPABLE};
+
+// writes the document back as canonical markdown:
+// `-` for bullets, `1.` numbering, `*`/`**` emphasis, fenced code blocks,
+// and paragraphs wrapped at `WIDTH` columns.
+// formatting the output again gives the same output.
+//
+// a list right after one of the same kind would be read as its continuation,
+// so it takes the other marker, `*` or `1)`, and a definition list is kept apart by `SEPARATOR`.
+// likewise an emphasis in the same emphasis takes the other delimiter, `__` or `_`
+
+const WIDTH: usize = 80;
+
+const SEPARATOR: &str = "<!-- -->\n";
+
+pub fn translate(md: Vec<Markdown>) -> String {
+    let mut blocks = translate_blocks(&md);
+    // a `---` at the top would open a front matter
+    if let Some(Markdown::HorizontalRule) = md.first() {
+        blocks[0] = String::from("***\n");
+    }
+    blocks.join("\n").trim_end().to_string()
+}
+
+// every block ends with a newline; they are separated by blank lines at the top,
+// but not inside list items, where a blank line would end the item
+fn translate_blocks(md: &[Markdown]) -> Vec<String> {
+    md.iter()
+        .enumerate()
+        .filter_map(|(k, bit)| {
+            // how many lists of the same kind are right before this one
+            let run = md[..k]
+                .iter()
+                .rev()
+                .take_while(|before| same_list(before, bit))
+                .count();
+            let block = match bit {
+                Markdown::Heading(size, line, attributes)
+                    if *attributes != Attributes::default() =>
+                {
+                    format!(
+                        "{} {} {}\n",
+                        "#".repeat(*size),
+                        translate_text(line),
+                        translate_attributes(attributes)
+                    )
+                }
+                Markdown::Heading(size, line, _) => {
+                    format!("{} {}\n", "#".repeat(*size), translate_text(line))
+                }
+                Markdown::UnorderedList(items) => translate_list(items, None, run % 2 == 1),
+                Markdown::OrderedList(start, items) => {
+                    translate_list(items, Some(*start), run % 2 == 1)
+                }
+                Markdown::Codeblock(lang, code, attributes) => {
+                    let fence = fence(code);
+                    let info = if attributes.is_empty() {
+                        lang.to_string()
+                    } else {
+                        format!("{} {}", lang, attributes)
+                    };
+                    format!("{}{}\n{}{}\n", fence, info, code, fence)
+                }
+                Markdown::Line(line) => translate_paragraph(&[line.to_vec()]),
+                Markdown::Paragraph(lines) => translate_paragraph(lines),
+                Markdown::DefinitionList(definitions) => (run > 0)
+                    .then_some(String::from(SEPARATOR))
+                    .into_iter()
+                    .chain(definitions.iter().map(|(term, texts)| {
+                        let texts: String = texts
+                            .iter()
+                            .map(|text| format!(": {}\n", translate_text(text)))
+                            .collect();
+                        format!("{}\n{}", translate_text(term), texts)
+                    }))
+                    .collect(),
+                Markdown::HorizontalRule => String::from("---\n"),
+                Markdown::Footnotes => String::from("[FOOTNOTES]\n"),
+                Markdown::Table(header, aligns, rows) => translate_table(header, aligns, rows),
+                Markdown::Blockquote(inner) => translate_blocks(inner)
+                    .join("\n")
+                    .lines()
+                    .map(|line| match line {
+                        "" => String::from(">\n"),
+                        // the spaces may be a part of a code block or the indent of a list
+                        line => format!("> {}\n", line),
+                    })
+                    .collect(),
+                Markdown::FootnoteDefinition(label, text) => {
+                    format!("[^{}]: {}\n", label, translate_text(text))
+                }
+                Markdown::FrontMatter(front_matter) => format!(
+                    "---\n{}---\n",
+                    front_matter
+                        .iter()
+                        .map(|(key, value)| format!("{}: {}\n", key, value))
+                        .collect::<String>()
+                ),
+                Markdown::Html(html) => format!("{}\n", html.trim_end()),
+                Markdown::Comment(text) => format!("<!--{}-->\n", text),
+                Markdown::MathBlock(tex) => format!("$$\n{}\n$$\n", tex),
+                Markdown::Div(attributes, inner) => format!(
+                    "::: {}\n{}:::\n",
+                    translate_attributes(attributes),
+                    translate_blocks(inner).join("\n")
+                ),
+                Markdown::Details(summary, open, inner) => format!(
+                    "???{} {}\n{}???\n",
+                    if *open { "+" } else { "" },
+                    translate_text(summary),
+                    translate_blocks(inner).join("\n")
+                ),
+                Markdown::Admonition(kind, title, inner) => format!(
+                    "::: {}{}\n{}:::\n",
+                    kind,
+                    title
+                        .as_ref()
+                        .map_or(String::new(), |title| format!(" {}", title)),
+                    translate_blocks(inner).join("\n")
+                ),
+            };
+            if block.trim().is_empty() {
+                None
+            } else {
+                Some(block)
+            }
+        })
+        .collect()
+}
+
+// whether the two blocks are lists which would be read as one
+fn same_list(a: &Markdown, b: &Markdown) -> bool {
+    matches!(
+        (a, b),
+        (Markdown::UnorderedList(_), Markdown::UnorderedList(_))
+            | (Markdown::OrderedList(..), Markdown::OrderedList(..))
+            | (Markdown::DefinitionList(_), Markdown::DefinitionList(_))
+    )
+}
+
+fn translate_list(items: &[ListItem], start: Option<usize>, alternate: bool) -> String {
+    items
+        .iter()
+        .enumerate()
+        .map(|(k, (line, children))| {
+            let marker = match (start, alternate) {
+                (Some(start), false) => format!("{}.", start + k),
+                (Some(start), true) => format!("{})", start + k),
+                (None, false) => String::from("-"),
+                (None, true) => String::from("*"),
+            };
+            let indent = " ".repeat(marker.len() + 1);
+            let children: String = translate_blocks(children)
+                .concat()
+                .lines()
+                .map(|line| format!("{}{}\n", indent, line))
+                .collect();
+            format!("{} {}\n{}", marker, translate_text(line), children)
+        })
+        .collect()
+}
+
+fn translate_paragraph(lines: &[MarkdownText]) -> String {
+    let words: Vec<String> = lines
+        .iter()
+        .flat_map(words)
+        .filter(|word| !word.is_empty())
+        .collect();
+    let mut output = String::new();
+    let mut width = 0;
+    for word in words {
+        let len = word.chars().count();
+        if word == "\n" {
+            output += "\n";
+            width = 0;
+        } else if width == 0 {
+            output += &word;
+            width = len;
+        } else if width + 1 + len <= WIDTH || !can_start_line(&word) {
+            output += " ";
+            output += &word;
+            width += 1 + len;
+        } else {
+            output += "\n";
+            output += &word;
+            width = len;
+        }
+    }
+    if output.is_empty() {
+        output
+    } else {
+        format!("{}\n", output)
+    }
+}
+
+// a word which would be read as the start of a block is never moved to the head of a line
+fn can_start_line(word: &str) -> bool {
+    !(word.starts_with(['#', '-', '>', '|', '+', '=', '<', ':'])
+        || word.starts_with("```")
+        || word.starts_with("[^")
+        || word
+            .trim_end_matches(['.', ')'])
+            .chars()
+            .all(|c| c.is_ascii_digit()))
+}
+
+// the line is broken only at the spaces of plain text, so inline markup is never split
+fn words(text: &MarkdownText) -> Vec<String> {
+    let mut words = vec![String::new()];
+    for part in text {
+        match part {
+            MarkdownInline::Plaintext(text) => {
+                let escaped = escape(text);
+                let mut pieces = escaped.split(' ');
+                if let Some(first) = pieces.next() {
+                    words.last_mut().unwrap().push_str(first);
+                }
+                for piece in pieces {
+                    words.push(piece.to_string());
+                }
+            }
+            // the backslash ends the current word and the line is broken right after it
+            MarkdownInline::LineBreak => {
+                words.last_mut().unwrap().push('\\');
+                words.push(String::from("\n"));
+                words.push(String::new());
+            }
+            part => words
+                .last_mut()
+                .unwrap()
+                .push_str(&translate_text(&vec![part.clone()])),
+        }
+    }
+    words
+}
+
+// the characters which the parser would read as markup get a backslash,
+// but `_` is left alone inside a word, where it cannot be emphasis
+fn escape(text: &str) -> String {
+    let chars: Vec<(usize, char)> = text.char_indices().collect();
+    chars
+        .iter()
+        .enumerate()
+        .map(|(k, (at, c))| match c {
+            '_' if k > 0
+                && k + 1 < chars.len()
+                && chars[k - 1].1.is_alphanumeric()
+                && chars[k + 1].1.is_alphanumeric() =>
+            {
+                String::from("_")
+            }
+            c if ESCAPABLE.contains(*c) && !is_literal(&text[*at..]) => format!("\\{}", c),
+            c => c.to_string(),
+        })
+        .collect()
+}
+
+fn translate_align(align: &Align) -> &'static str {
+    match align {
+        Align::None => "---",
+        Align::Left => ":--",
+        Align::Center => ":-:",
+        Align::Right => "--:",
+    }
+}
+
+fn translate_table(
+    header: &[MarkdownText],
+    aligns: &[Align],
+    rows: &[Vec<MarkdownText>],
+) -> String {
+    let row = |cells: &[MarkdownText]| {
+        format!(
+            "| {} |\n",
+            (0..header.len())
+                .map(|k| cells.get(k).map(translate_cell).unwrap_or_default())
+                .collect::<Vec<String>>()
+                .join(" | ")
+        )
+    };
+    format!(
+        "{}|{}|\n{}",
+        row(header),
+        aligns
+            .iter()
+            .map(translate_align)
+            .collect::<Vec<&str>>()
+            .join("|"),
+        rows.iter().map(|cells| row(cells)).collect::<String>()
+    )
+}
+
+// whether `<text>` reads back as the link
+fn is_autolink(text: &str, url: &str) -> bool {
+    match parse_autolink(&format!("<{}>", text)) {
+        Ok(("", (_, autolink))) => autolink == url,
+        _ => false,
+    }
+}
+
+// the text, with a backslash before any `|` which would end the cell
+fn translate_cell(text: &MarkdownText) -> String {
+    let mut cell = String::new();
+    let mut escaped = false;
+    for c in translate_text(text).chars() {
+        if c == '|' && !escaped {
+            cell.push('\\');
+        }
+        escaped = c == '\\' && !escaped;
+        cell.push(c);
+    }
+    cell
+}
+
+// longer than any run of backticks starting a line of the code, so it cannot close the block
+fn fence(code: &str) -> String {
+    let longest = code
+        .lines()
+        .map(|line| line.trim_start().chars().take_while(|c| *c == '`').count())
+        .max()
+        .unwrap_or(0);
+    "`".repeat(longest.max(2) + 1)
+}
+
+fn translate_attributes(attributes: &Attributes) -> String {
+    let id = attributes.id.iter().map(|id| format!("#{}", id));
+    let classes = attributes.classes.iter().map(|class| format!(".{}", class));
+    let pairs = attributes.pairs.iter().map(|(key, value)| {
+        if value.is_empty() || value.contains(char::is_whitespace) {
+            format!("{}=\"{}\"", key, value)
+        } else {
+            format!("{}={}", key, value)
+        }
+    });
+    format!(
+        "{{{}}}",
+        id.chain(classes)
+            .chain(pairs)
+            .collect::<Vec<String>>()
+            .join(" ")
+    )
+}
+
+// titles cannot escape their quotes, so they pick one they do not contain
+fn quote(title: &str) -> String {
+    if !title.contains('"') {
+        format!("\"{}\"", title)
+    } else if !title.contains('\'') {
+        format!("'{}'", title)
+    } else {
+        format!("({})", title)
+    }
+}
+
+fn translate_text(text: &MarkdownText) -> String {
+    translate_emphasized(text, "**", "*")
+}
+
+// the text in an emphasis, whose delimiters `strong` and `emphasis` an inner one of its kind does not use
+fn translate_emphasized(text: &MarkdownText, strong: &str, emphasis: &str) -> String {
+    let translate_text = |text| translate_emphasized(text, strong, emphasis);
+    text.iter()
+        .map(|part| match part {
+            MarkdownInline::Bold(text) => {
+                let inner = if strong == "**" { "__" } else { "**" };
+                format!(
+                    "{0}{1}{0}",
+                    strong,
+                    translate_emphasized(text, inner, emphasis)
+                )
+            }
+            MarkdownInline::Italic(text) => {
+                let inner = if emphasis == "*" { "_" } else { "*" };
+                format!(
+                    "{0}{1}{0}",
+                    emphasis,
+                    translate_emphasized(text, strong, inner)
+                )
+            }
+            MarkdownInline::Strike(text) => format!("~~{}~~", text),
+            MarkdownInline::Mark(text) => format!("=={}==", translate_text(text)),
+            MarkdownInline::Superscript(text) => format!("^{}^", text),
+            MarkdownInline::Subscript(text) => format!("~{}~", text),
+            MarkdownInline::Kbd(keys) => format!("++{}++", keys.join("+")),
+            MarkdownInline::Ruby(base, readings) => format!("{{{}|{}}}", base, readings.join("|")),
+            MarkdownInline::InlineCode(code) => format!("`{}`", code),
+            MarkdownInline::Link(text, url, None) => match text.as_slice() {
+                [MarkdownInline::Plaintext(text)] if is_autolink(text, url) => {
+                    format!("<{}>", text)
+                }
+                _ => format!("[{}]({})", translate_text(text), url),
+            },
+            MarkdownInline::Link(text, url, Some(title)) => {
+                format!("[{}]({} {})", translate_text(text), url, quote(title))
+            }
+            MarkdownInline::Image(text, url, None) => {
+                format!("![{}]({})", translate_text(text), url)
+            }
+            MarkdownInline::Image(text, url, Some(title)) => {
+                format!("![{}]({} {})", translate_text(text), url, quote(title))
+            }
+            MarkdownInline::Plaintext(text) => escape(text),
+            MarkdownInline::FootnoteRef(label) => format!("[^{}]", label),
+            MarkdownInline::Html(html) => html.to_string(),
+            MarkdownInline::Comment(text) => format!("<!--{}-->", text),
+            MarkdownInline::Math(tex) => format!("${}$", tex),
+            MarkdownInline::Emoji(name) => format!(":{}:", name),
+            MarkdownInline::WikiLink(target, None) => format!("[[{}]]", target),
+            MarkdownInline::WikiLink(target, Some(label)) => format!("[[{}|{}]]", target, label),
+            MarkdownInline::LineBreak => String::from("\\\n"),
+        })
+        .collect::<Vec<String>>()
+        .join("")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::normalize::normalize;
+    use crate::parser::parse_markdown;
+    use proptest::collection::vec;
+    use proptest::option;
+    use proptest::prelude::*;
+
+    fn text(s: &str) -> MarkdownText {
+        vec![MarkdownInline::Plaintext(String::from(s))]
+    }
+
+    #[test]
+    fn test_translate_text() {
+        assert_eq!(
+            translate_text(&vec![
+                MarkdownInline::Plaintext(String::from("2 * 3 ")),
+                MarkdownInline::Bold(vec![MarkdownInline::Plaintext(String::from("b"))]),
+                MarkdownInline::Link(
+                    vec![MarkdownInline::Plaintext(String::from("a@b.c"))],
+                    String::from("mailto:a@b.c"),
+                    None
+                ),
+                MarkdownInline::Link(
+                    vec![MarkdownInline::Plaintext(String::from("t"))],
+                    String::from("u"),
+                    None
+                ),
+                MarkdownInline::Image(
+                    vec![MarkdownInline::Plaintext(String::from("i"))],
+                    String::from("i.png"),
+                    Some(String::from("say \"hi\""))
+                ),
+            ]),
+            "2 \\* 3 **b**<a@b.c>[t](u)![i](i.png 'say \"hi\"')"
+        );
+    }
+
+    #[test]
+    fn test_translate_paragraph() {
+        let long = "word ".repeat(20);
+        assert_eq!(
+            translate_paragraph(&[
+                text(&long),
+                vec![MarkdownInline::Italic(vec![MarkdownInline::Plaintext(
+                    String::from("x y")
+                )])]
+            ]),
+            format!(
+                "{}\n{}*x y*\n",
+                "word ".repeat(16).trim_end(),
+                "word ".repeat(4)
+            )
+        );
+        assert_eq!(
+            translate_paragraph(&[text(&format!("{}- not a list", "a ".repeat(40)))]),
+            format!("{}-\nnot a list\n", "a ".repeat(40))
+        );
+        assert_eq!(
+            translate_paragraph(&[text(&format!("{}: not a definition", "a ".repeat(40)))]),
+            format!("{}:\nnot a definition\n", "a ".repeat(40))
+        );
+    }
+
+    #[test]
+    fn test_fence() {
+        assert_eq!(fence("a\n"), "```");
+        assert_eq!(fence("```rust\n````\n"), "`````");
+    }
+
+    #[test]
+    fn test_translate_list() {
+        assert_eq!(
+            translate_list(
+                &[
+                    (
+                        text("a"),
+                        vec![Markdown::UnorderedList(vec![(text("b"), vec![])])]
+                    ),
+                    (text("c"), vec![]),
+                ],
+                Some(1),
+                false
+            ),
+            "1. a\n   - b\n2. c\n"
+        );
+        assert_eq!(
+            translate_list(&[(text("a"), vec![]), (text("b"), vec![])], Some(9), false),
+            "9. a\n10. b\n"
+        );
+        let list = |start| match start {
+            Some(start) => Markdown::OrderedList(start, vec![(text("a"), vec![])]),
+            None => Markdown::UnorderedList(vec![(text("a"), vec![])]),
+        };
+        let definitions = Markdown::DefinitionList(vec![(text("t"), vec![text("d")])]);
+        assert_eq!(
+            translate(vec![
+                list(None),
+                list(None),
+                list(None),
+                list(Some(2)),
+                list(Some(2))
+            ]),
+            "- a\n\n* a\n\n- a\n\n2. a\n\n2) a"
+        );
+        assert_eq!(
+            translate(vec![definitions.clone(), definitions]),
+            "t\n: d\n\n<!-- -->\nt\n: d"
+        );
+    }
+
+    #[test]
+    fn test_translate_table() {
+        assert_eq!(
+            translate_table(
+                &[text("a"), text("b")],
+                &[Align::Left, Align::None],
+                &[vec![text("1")]]
+            ),
+            "| a | b |\n|:--|---|\n| 1 |  |\n"
+        );
+    }
+
+    #[test]
+    fn test_escape() {
+        assert_eq!(
+            escape("*a* [b] c_d _e_ Hi!"),
+            "\\*a\\* \\[b] c_d \\_e\\_ Hi!"
+        );
+        assert_eq!(
+            escape("C:\\ <b> ~/x :tada: $5"),
+            "C:\\\\ \\<b> ~/x \\:tada: $5"
+        );
+        assert_eq!(escape("AT&T &amp; &copy"), "AT&T \\&amp; &copy");
+        let text = "\\* ~~no~~ `x` <i> :+1: &lt; \\";
+        let md = parse_markdown(&format!("{}\n", escape(text))).unwrap().1;
+        assert_eq!(
+            md,
+            vec![Markdown::Line(vec![MarkdownInline::Plaintext(
+                String::from(text)
+            )])]
+        );
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let source = "---\ntitle: t\n---\n# Title\n\nsome *text* with a [link](u)\nand `code`  \nbroken\n\n1. one\n2. two\n  - nested\n\n> quoted\n> > twice\n\n| a | b |\n|:-:|--:|\n| 1 | 2 |\n\n```rust\nfn main() {}\n```\n\nnote[^1]\n\n[^1]: here\n";
+        let md = normalize(parse_markdown(source).unwrap().1);
+        let formatted = translate(md);
+        assert_eq!(
+            formatted,
+            "---\ntitle: t\n---\n\n# Title\n\nsome *text* with a [link](u) and `code`\\\nbroken\n\n1. one\n2. two\n   - nested\n\n> quoted\n>\n> > twice\n\n| a | b |\n|:-:|--:|\n| 1 | 2 |\n\n```rust\nfn main() {}\n```\n\nnote[^1]\n\n[^1]: here"
+        );
+        let again = normalize(parse_markdown(&format!("{}\n", formatted)).unwrap().1);
+        assert_eq!(translate(again), formatted);
+    }
+
+    // the same document, whichever lines the paragraphs are wrapped at,
+    // and however the plain text is split
+    // without the comments which keep two definition lists apart
+    fn canonical(md: Vec<Markdown>) -> Vec<Markdown> {
+        md.into_iter()
+            .filter(|block| *block != Markdown::Comment(String::from(" ")))
+            .map(canonical_block)
+            .collect()
+    }
+
+    fn canonical_block(block: Markdown) -> Markdown {
+        let items = |items: Vec<ListItem>| {
+            items
+                .into_iter()
+                .map(|(line, children)| (canonical_text(line), canonical(children)))
+                .collect()
+        };
+        match block {
+            Markdown::Paragraph(lines) => {
+                let mut joined = vec![];
+                for line in lines {
+                    if !joined.is_empty() && joined.last() != Some(&MarkdownInline::LineBreak) {
+                        joined.push(MarkdownInline::Plaintext(String::from(" ")));
+                    }
+                    joined.extend(line);
+                }
+                Markdown::Paragraph(vec![canonical_text(joined)])
+            }
+            Markdown::Heading(size, text, attributes) => {
+                Markdown::Heading(size, canonical_text(text), attributes)
+            }
+            Markdown::UnorderedList(list) => Markdown::UnorderedList(items(list)),
+            Markdown::OrderedList(start, list) => Markdown::OrderedList(start, items(list)),
+            Markdown::Blockquote(inner) => Markdown::Blockquote(canonical(inner)),
+            Markdown::Admonition(kind, title, inner) => {
+                Markdown::Admonition(kind, title, canonical(inner))
+            }
+            Markdown::Details(summary, open, inner) => {
+                Markdown::Details(canonical_text(summary), open, canonical(inner))
+            }
+            Markdown::FootnoteDefinition(label, text) => {
+                Markdown::FootnoteDefinition(label, canonical_text(text))
+            }
+            Markdown::DefinitionList(definitions) => Markdown::DefinitionList(
+                definitions
+                    .into_iter()
+                    .map(|(term, texts)| {
+                        (
+                            canonical_text(term),
+                            texts.into_iter().map(canonical_text).collect(),
+                        )
+                    })
+                    .collect(),
+            ),
+            Markdown::Table(header, aligns, rows) => Markdown::Table(
+                header.into_iter().map(canonical_text).collect(),
+                aligns,
+                rows.into_iter()
+                    .map(|row| row.into_iter().map(canonical_text).collect())
+                    .collect(),
+            ),
+            block => block,
+        }
+    }
+
+    fn canonical_text(text: MarkdownText) -> MarkdownText {
+        let mut merged: MarkdownText = vec![];
+        for part in text {
+            let part = match part {
+                MarkdownInline::Bold(text) => MarkdownInline::Bold(canonical_text(text)),
+                MarkdownInline::Italic(text) => MarkdownInline::Italic(canonical_text(text)),
+                MarkdownInline::Mark(text) => MarkdownInline::Mark(canonical_text(text)),
+                MarkdownInline::Link(text, url, title) => {
+                    MarkdownInline::Link(canonical_text(text), url, title)
+                }
+                MarkdownInline::Image(text, url, title) => {
+                    MarkdownInline::Image(canonical_text(text), url, title)
+                }
+                part => part,
+            };
+            match (merged.last_mut(), part) {
+                (Some(MarkdownInline::Plaintext(last)), MarkdownInline::Plaintext(text)) => {
+                    last.push_str(&text)
+                }
+                (_, part) => merged.push(part),
+            }
+        }
+        merged
+    }
+
+    // words between single spaces, with the punctuation which the writer has to escape
+    fn plaintext() -> impl Strategy<Value = String> {
+        vec("[a-z0-9]{1,5}|[*_`~=^+{}$:&!<>#|.\\\\\\[\\]-]", 1..4).prop_map(|words| words.join(" "))
+    }
+
+    fn inline() -> impl Strategy<Value = MarkdownInline> {
+        let leaf = prop_oneof![
+            plaintext().prop_map(MarkdownInline::Plaintext),
+            "[a-z]{1,5}( [a-z]{1,5})?".prop_map(MarkdownInline::InlineCode),
+            "[a-z]{1,5}".prop_map(MarkdownInline::Strike),
+            "[a-z0-9]{1,3}".prop_map(MarkdownInline::Superscript),
+            "[a-z0-9]{1,3}".prop_map(MarkdownInline::Subscript),
+            "[a-z]( [+-] [a-z0-9])?".prop_map(MarkdownInline::Math),
+            prop::sample::select(vec!["smile", "tada", "+1"])
+                .prop_map(|name| MarkdownInline::Emoji(name.to_string())),
+            vec("[A-Z][a-z]{0,3}", 1..3).prop_map(MarkdownInline::Kbd),
+            // one reading, or one for each character
+            ("[a-z]{1,4}", "[a-z]{1,3}", any::<bool>()).prop_map(|(base, reading, each)| {
+                let readings = match each {
+                    true => base.chars().map(|_| reading.clone()).collect(),
+                    false => vec![reading],
+                };
+                MarkdownInline::Ruby(base, readings)
+            }),
+            "[a-z0-9]{1,4}".prop_map(MarkdownInline::FootnoteRef),
+            ("[A-Z][a-z]{1,5}", option::of("[a-z]{1,5}"))
+                .prop_map(|(target, label)| MarkdownInline::WikiLink(target, label)),
+            (
+                "[a-z]{1,5}",
+                "[a-z]{1,5}",
+                option::of("[a-z]{1,5}( [a-z]{1,5})?")
+            )
+                .prop_map(|(text, url, title)| {
+                    MarkdownInline::Link(vec![MarkdownInline::Plaintext(text)], url, title)
+                }),
+            ("[a-z]{1,5}", "[a-z]{1,5}\\.png", option::of("[a-z]{1,5}")).prop_map(
+                |(text, url, title)| {
+                    MarkdownInline::Image(vec![MarkdownInline::Plaintext(text)], url, title)
+                }
+            ),
+        ];
+        leaf.prop_recursive(2, 8, 3, |inner| {
+            prop_oneof![
+                inline_text(inner.clone()).prop_map(MarkdownInline::Bold),
+                inline_text(inner.clone()).prop_map(MarkdownInline::Italic),
+                inline_text(inner).prop_map(MarkdownInline::Mark),
+            ]
+        })
+    }
+
+    // the markup is kept apart by words between spaces, so that the delimiters of one do not run
+    // into the next, and the `__` or `_` of an emphasis in the same emphasis never is inside a word
+    fn inline_text(
+        inline: impl Strategy<Value = MarkdownInline>,
+    ) -> impl Strategy<Value = MarkdownText> {
+        vec(inline, 1..4).prop_map(|parts| {
+            let mut text = vec![MarkdownInline::Plaintext(String::from("a "))];
+            for (k, part) in parts.into_iter().enumerate() {
+                if k > 0 {
+                    text.push(MarkdownInline::Plaintext(String::from(" z ")));
+                }
+                text.push(part);
+            }
+            text.push(MarkdownInline::Plaintext(String::from(" z")));
+            canonical_text(text)
+        })
+    }
+
+    fn line() -> impl Strategy<Value = MarkdownText> {
+        inline_text(inline())
+    }
+
+    // a `|` ends a cell, even in a ruby or a wiki link
+    fn cell() -> impl Strategy<Value = MarkdownText> {
+        line().prop_filter("`|` in a cell", |text| {
+            let text = format!("{:?}", text);
+            !text.contains("Ruby") && !text.contains("WikiLink")
+        })
+    }
+
+    fn block() -> impl Strategy<Value = Markdown> {
+        let leaf =
+            prop_oneof![
+                (1..=6usize, line()).prop_map(|(size, text)| Markdown::Heading(
+                    size,
+                    text,
+                    Attributes::default()
+                )),
+                vec(line(), 1..3).prop_map(Markdown::Paragraph),
+                ("[a-z]{0,4}", "([a-z ]{0,10}\n){1,3}")
+                    .prop_map(|(lang, code)| Markdown::Codeblock(lang, code, String::new())),
+                Just(Markdown::HorizontalRule),
+                "[a-z]( [+^] [a-z0-9])*".prop_map(Markdown::MathBlock),
+                ("[a-z0-9]{1,4}", line())
+                    .prop_map(|(label, text)| Markdown::FootnoteDefinition(label, text)),
+                (1..4usize).prop_flat_map(|width| (
+                    vec(cell(), width),
+                    vec(
+                        prop::sample::select(vec![
+                            Align::None,
+                            Align::Left,
+                            Align::Center,
+                            Align::Right
+                        ]),
+                        width
+                    ),
+                    vec(vec(cell(), width), 0..3),
+                )
+                    .prop_map(|(header, aligns, rows)| Markdown::Table(header, aligns, rows))),
+                vec((line(), vec(line(), 1..3)), 1..3).prop_map(Markdown::DefinitionList),
+            ];
+        leaf.prop_recursive(3, 16, 3, |inner| {
+            let items = || vec((line(), vec(inner.clone(), 0..2)), 1..3);
+            prop_oneof![
+                vec(inner.clone(), 1..3).prop_map(Markdown::Blockquote),
+                items().prop_map(Markdown::UnorderedList),
+                (1..20usize, items())
+                    .prop_map(|(start, items)| Markdown::OrderedList(start, items)),
+                (
+                    prop::sample::select(vec!["note", "warning"]),
+                    option::of("[A-Z][a-z]{1,5}"),
+                    vec(inner.clone(), 1..3)
+                )
+                    .prop_map(|(kind, title, inner)| {
+                        Markdown::Admonition(kind.to_string(), title, inner)
+                    }),
+                (line(), any::<bool>(), vec(inner, 1..3))
+                    .prop_map(|(summary, open, inner)| Markdown::Details(summary, open, inner)),
+            ]
+        })
+    }
+
+    proptest! {
+        #[test]
+        fn test_round_trip_any(md in vec(block(), 1..4)) {
+            let written = translate(md.clone());
+            let parsed = normalize(parse_markdown(&format!("{}\n", written)).unwrap().1);
+            prop_assert_eq!(canonical(parsed), canonical(md), "{}", written);
+        }
+    }
+}
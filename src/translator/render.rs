@@ -0,0 +1,885 @@
+use crate::entity::Align;
+use crate::entity::Attributes;
+use crate::entity::Markdown;
+use crate::entity::MarkdownInline;
+use crate::entity::MarkdownText;
+use crate::entity::{Span, Spanned};
+use crate::event::{Event, Tag};
+use crate::translator::{
+    admonition_title, assign_heading_ids, collect_footnotes, emoji_text, plain_text, ruby_text,
+    split_at_footnotes, wikilink_text,
+};
+
+use std::collections::HashMap;
+
+// a backend is a set of callbacks, one for each kind of node, which all write to one buffer.
+// a container is opened and closed around its content,
+// and the callbacks left out write the text without any markup.
+// the html translator is one of them; Slack, Jira and so on can be written the same way
+pub trait Render {
+    fn heading_start(&mut self, out: &mut String, level: usize, attributes: &Attributes) {
+        let _ = (out, level, attributes);
+    }
+
+    fn heading_end(&mut self, out: &mut String, level: usize, attributes: &Attributes) {
+        let _ = (level, attributes);
+        out.push_str("\n\n");
+    }
+
+    fn paragraph_start(&mut self, out: &mut String) {
+        let _ = out;
+    }
+
+    fn paragraph_end(&mut self, out: &mut String) {
+        out.push_str("\n\n");
+    }
+
+    // between the lines of a paragraph, unless the first one ends with a hard break
+    fn soft_break(&mut self, out: &mut String) {
+        out.push('\n');
+    }
+
+    // `start` is the number of the first item of an ordered list, and `None` for the others
+    fn list_start(&mut self, out: &mut String, start: Option<usize>) {
+        let _ = (out, start);
+    }
+
+    fn list_end(&mut self, out: &mut String, start: Option<usize>) {
+        let _ = start;
+        out.push('\n');
+    }
+
+    // an item is its text, left out with its start and end when empty, then its nested blocks
+    fn item_start(&mut self, out: &mut String) {
+        out.push_str("- ");
+    }
+
+    fn item_text_start(&mut self, out: &mut String) {
+        let _ = out;
+    }
+
+    fn item_text_end(&mut self, out: &mut String) {
+        out.push('\n');
+    }
+
+    fn item_end(&mut self, out: &mut String) {
+        let _ = out;
+    }
+
+    fn definition_list_start(&mut self, out: &mut String) {
+        let _ = out;
+    }
+
+    fn definition_list_end(&mut self, out: &mut String) {
+        out.push('\n');
+    }
+
+    fn term_start(&mut self, out: &mut String) {
+        let _ = out;
+    }
+
+    fn term_end(&mut self, out: &mut String) {
+        out.push('\n');
+    }
+
+    fn definition_start(&mut self, out: &mut String) {
+        out.push_str("  ");
+    }
+
+    fn definition_end(&mut self, out: &mut String) {
+        out.push('\n');
+    }
+
+    // the code is given as it is written, not escaped
+    fn codeblock(&mut self, out: &mut String, lang: &str, code: &str, info: &str) {
+        let _ = (lang, info);
+        out.push_str(code);
+        out.push('\n');
+    }
+
+    fn horizontal_rule(&mut self, out: &mut String) {
+        out.push_str("* * *\n\n");
+    }
+
+    // every row has a cell for each of `aligns`, and the body is left out without any row
+    fn table_start(&mut self, out: &mut String, aligns: &[Align]) {
+        let _ = (out, aligns);
+    }
+
+    fn table_head_start(&mut self, out: &mut String) {
+        let _ = out;
+    }
+
+    fn table_head_end(&mut self, out: &mut String) {
+        let _ = out;
+    }
+
+    fn table_body_start(&mut self, out: &mut String) {
+        let _ = out;
+    }
+
+    fn table_body_end(&mut self, out: &mut String) {
+        let _ = out;
+    }
+
+    fn table_row_start(&mut self, out: &mut String, header: bool) {
+        let _ = (out, header);
+    }
+
+    fn table_row_end(&mut self, out: &mut String, header: bool) {
+        let _ = header;
+        out.push('\n');
+    }
+
+    fn table_cell_start(&mut self, out: &mut String, column: usize, align: Align, header: bool) {
+        let _ = (align, header);
+        if column > 0 {
+            out.push('\t');
+        }
+    }
+
+    fn table_cell_end(&mut self, out: &mut String, header: bool) {
+        let _ = (out, header);
+    }
+
+    fn table_end(&mut self, out: &mut String) {
+        out.push('\n');
+    }
+
+    fn blockquote_start(&mut self, out: &mut String) {
+        let _ = out;
+    }
+
+    fn blockquote_end(&mut self, out: &mut String) {
+        let _ = out;
+    }
+
+    fn admonition_start(&mut self, out: &mut String, kind: &str, title: &Option<String>) {
+        out.push_str(&admonition_title(kind, title));
+        out.push_str(":\n");
+    }
+
+    fn admonition_end(&mut self, out: &mut String) {
+        let _ = out;
+    }
+
+    fn div_start(&mut self, out: &mut String, attributes: &Attributes) {
+        let _ = (out, attributes);
+    }
+
+    fn div_end(&mut self, out: &mut String) {
+        let _ = out;
+    }
+
+    // the summary comes right after the start
+    fn details_start(&mut self, out: &mut String, open: bool) {
+        let _ = (out, open);
+    }
+
+    fn summary_start(&mut self, out: &mut String) {
+        let _ = out;
+    }
+
+    fn summary_end(&mut self, out: &mut String) {
+        out.push_str(":\n");
+    }
+
+    fn details_end(&mut self, out: &mut String) {
+        let _ = out;
+    }
+
+    fn math_block(&mut self, out: &mut String, tex: &str) {
+        out.push_str(tex);
+        out.push_str("\n\n");
+    }
+
+    // the notes in the order they are numbered, never started without any
+    fn footnotes_start(&mut self, out: &mut String) {
+        let _ = out;
+    }
+
+    fn footnote_start(&mut self, out: &mut String, number: usize) {
+        out.push('[');
+        out.push_str(&number.to_string());
+        out.push_str("] ");
+    }
+
+    fn footnote_end(&mut self, out: &mut String, number: usize) {
+        let _ = number;
+        out.push('\n');
+    }
+
+    fn footnotes_end(&mut self, out: &mut String) {
+        let _ = out;
+    }
+
+    // the raw html of a block or within a line
+    fn html(&mut self, out: &mut String, html: &str) {
+        let _ = (out, html);
+    }
+
+    fn comment(&mut self, out: &mut String, text: &str) {
+        let _ = (out, text);
+    }
+
+    // plain text, not escaped yet
+    fn text(&mut self, out: &mut String, text: &str) {
+        out.push_str(text);
+    }
+
+    fn bold_start(&mut self, out: &mut String) {
+        let _ = out;
+    }
+
+    fn bold_end(&mut self, out: &mut String) {
+        let _ = out;
+    }
+
+    fn italic_start(&mut self, out: &mut String) {
+        let _ = out;
+    }
+
+    fn italic_end(&mut self, out: &mut String) {
+        let _ = out;
+    }
+
+    fn mark_start(&mut self, out: &mut String) {
+        let _ = out;
+    }
+
+    fn mark_end(&mut self, out: &mut String) {
+        let _ = out;
+    }
+
+    fn strike(&mut self, out: &mut String, text: &str) {
+        self.text(out, text)
+    }
+
+    fn superscript(&mut self, out: &mut String, text: &str) {
+        self.text(out, text)
+    }
+
+    fn subscript(&mut self, out: &mut String, text: &str) {
+        self.text(out, text)
+    }
+
+    fn kbd(&mut self, out: &mut String, keys: &[String]) {
+        self.text(out, &keys.join("+"))
+    }
+
+    fn ruby(&mut self, out: &mut String, base: &str, readings: &[String]) {
+        self.text(out, &ruby_text(base, readings))
+    }
+
+    fn inline_code(&mut self, out: &mut String, code: &str) {
+        self.text(out, code)
+    }
+
+    fn link_start(&mut self, out: &mut String, url: &str, title: &Option<String>) {
+        let _ = (out, url, title);
+    }
+
+    fn link_end(&mut self, out: &mut String) {
+        let _ = out;
+    }
+
+    // the alt text is given without markup
+    fn image(&mut self, out: &mut String, alt: &str, url: &str, title: &Option<String>) {
+        let _ = (url, title);
+        self.text(out, alt)
+    }
+
+    fn wikilink(&mut self, out: &mut String, target: &str, label: &Option<String>) {
+        self.text(out, &wikilink_text(target, label))
+    }
+
+    // the number of a footnote, or the label of one which is not defined
+    fn footnote_ref(&mut self, out: &mut String, number: &str) {
+        out.push('[');
+        out.push_str(number);
+        out.push(']');
+    }
+
+    fn math(&mut self, out: &mut String, tex: &str) {
+        self.text(out, tex)
+    }
+
+    fn emoji(&mut self, out: &mut String, name: &str) {
+        self.text(out, &emoji_text(name))
+    }
+
+    fn line_break(&mut self, out: &mut String) {
+        out.push('\n');
+    }
+
+    // a top-level block has just been written from `out[start..]`, and this is where it is in the source
+    fn spanned(&mut self, out: &mut String, start: usize, span: &Span) {
+        let _ = (out, start, span);
+    }
+}
+
+// the whole document: the headings are given ids and the footnotes are numbered,
+// then written where `[FOOTNOTES]` is or at the end
+pub fn render<R: Render + ?Sized>(md: Vec<Markdown>, r: &mut R) -> String {
+    render_with_spans(md, &[], r)
+}
+
+// the same as `render`, where each top-level block is passed to `spanned` with its span
+pub fn render_spanned<R: Render + ?Sized>(md: Vec<Spanned<Markdown>>, r: &mut R) -> String {
+    let (md, spans): (Vec<Markdown>, Vec<Span>) =
+        md.into_iter().map(|bit| (bit.node, bit.span)).unzip();
+    render_with_spans(md, &spans, r)
+}
+
+// `spans` are of the top-level blocks, or empty
+pub(crate) fn render_with_spans<R: Render + ?Sized>(
+    md: Vec<Markdown>,
+    spans: &[Span],
+    r: &mut R,
+) -> String {
+    // the definitions are taken out of the body along with their spans
+    let mut spans: Vec<Span> = md
+        .iter()
+        .zip(spans)
+        .filter(|(bit, _)| !matches!(bit, Markdown::FootnoteDefinition(_, _)))
+        .map(|(_, span)| *span)
+        .collect();
+    let (body, notes) = collect_footnotes(assign_heading_ids(md));
+    let (body, rest) = split_at_footnotes(body);
+    let rest_spans = if spans.len() > body.len() {
+        spans.split_off(body.len() + 1)
+    } else {
+        vec![]
+    };
+    let mut out = String::new();
+    write_spanned_blocks(&mut out, &body, &spans, r);
+    if !notes.is_empty() {
+        r.footnotes_start(&mut out);
+        for (k, note) in notes.iter().enumerate() {
+            r.footnote_start(&mut out, k + 1);
+            write_text(&mut out, note, r);
+            r.footnote_end(&mut out, k + 1);
+        }
+        r.footnotes_end(&mut out);
+    }
+    write_spanned_blocks(&mut out, &rest, &rest_spans, r);
+    out
+}
+
+fn write_spanned_blocks<R: Render + ?Sized>(
+    out: &mut String,
+    md: &[Markdown],
+    spans: &[Span],
+    r: &mut R,
+) {
+    for (k, bit) in md.iter().enumerate() {
+        let start = out.len();
+        write_block(out, bit, r);
+        if let Some(span) = spans.get(k) {
+            r.spanned(out, start, span);
+        }
+    }
+}
+
+pub fn render_blocks<R: Render + ?Sized>(md: &[Markdown], r: &mut R) -> String {
+    let mut out = String::new();
+    write_blocks(&mut out, md, r);
+    out
+}
+
+pub fn render_text<R: Render + ?Sized>(text: &MarkdownText, r: &mut R) -> String {
+    let mut out = String::new();
+    write_text(&mut out, text, r);
+    out
+}
+
+pub fn write_blocks<R: Render + ?Sized>(out: &mut String, md: &[Markdown], r: &mut R) {
+    for bit in md {
+        write_block(out, bit, r);
+    }
+}
+
+fn write_block<R: Render + ?Sized>(out: &mut String, bit: &Markdown, r: &mut R) {
+    match bit {
+        Markdown::Heading(level, text, attributes) => {
+            r.heading_start(out, *level, attributes);
+            write_text(out, text, r);
+            r.heading_end(out, *level, attributes);
+        }
+        Markdown::UnorderedList(items) | Markdown::OrderedList(_, items) => {
+            let start = match bit {
+                Markdown::OrderedList(start, _) => Some(*start),
+                _ => None,
+            };
+            r.list_start(out, start);
+            for (text, children) in items {
+                r.item_start(out);
+                write_unless_empty(out, r, R::item_text_start, R::item_text_end, |out, r| {
+                    write_text(out, text, r)
+                });
+                write_blocks(out, children, r);
+                r.item_end(out);
+            }
+            r.list_end(out, start);
+        }
+        Markdown::DefinitionList(definitions) => {
+            r.definition_list_start(out);
+            for (term, texts) in definitions {
+                r.term_start(out);
+                write_text(out, term, r);
+                r.term_end(out);
+                for text in texts {
+                    r.definition_start(out);
+                    write_text(out, text, r);
+                    r.definition_end(out);
+                }
+            }
+            r.definition_list_end(out);
+        }
+        Markdown::Line(text) => {
+            write_unless_empty(out, r, R::paragraph_start, R::paragraph_end, |out, r| {
+                write_text(out, text, r)
+            })
+        }
+        Markdown::Paragraph(lines) => {
+            r.paragraph_start(out);
+            for (k, line) in lines.iter().enumerate() {
+                if k > 0 && lines[k - 1].last() != Some(&MarkdownInline::LineBreak) {
+                    r.soft_break(out);
+                }
+                write_text(out, line, r);
+            }
+            r.paragraph_end(out);
+        }
+        Markdown::Codeblock(lang, code, info) => r.codeblock(out, lang, code, info),
+        Markdown::HorizontalRule => r.horizontal_rule(out),
+        Markdown::Table(header, aligns, rows) => {
+            r.table_start(out, aligns);
+            r.table_head_start(out);
+            write_table_row(out, header, aligns, true, r);
+            r.table_head_end(out);
+            if !rows.is_empty() {
+                r.table_body_start(out);
+                for row in rows {
+                    write_table_row(out, row, aligns, false, r);
+                }
+                r.table_body_end(out);
+            }
+            r.table_end(out);
+        }
+        Markdown::Blockquote(inner) => {
+            r.blockquote_start(out);
+            write_blocks(out, inner, r);
+            r.blockquote_end(out);
+        }
+        Markdown::Admonition(kind, title, inner) => {
+            r.admonition_start(out, kind, title);
+            write_blocks(out, inner, r);
+            r.admonition_end(out);
+        }
+        Markdown::Div(attributes, inner) => {
+            r.div_start(out, attributes);
+            write_blocks(out, inner, r);
+            r.div_end(out);
+        }
+        Markdown::Details(summary, open, inner) => {
+            r.details_start(out, *open);
+            r.summary_start(out);
+            write_text(out, summary, r);
+            r.summary_end(out);
+            write_blocks(out, inner, r);
+            r.details_end(out);
+        }
+        Markdown::MathBlock(tex) => r.math_block(out, tex),
+        Markdown::Html(html) => r.html(out, html),
+        Markdown::Comment(text) => r.comment(out, text),
+        Markdown::FootnoteDefinition(_, _) | Markdown::Footnotes | Markdown::FrontMatter(_) => {}
+    }
+}
+
+// the content between `start` and `end`, which are taken back when nothing is written in between
+fn write_unless_empty<R, F>(
+    out: &mut String,
+    r: &mut R,
+    start: fn(&mut R, &mut String),
+    end: fn(&mut R, &mut String),
+    content: F,
+) where
+    R: Render + ?Sized,
+    F: FnOnce(&mut String, &mut R),
+{
+    let before = out.len();
+    start(r, out);
+    let inner = out.len();
+    content(out, r);
+    if out.len() == inner {
+        out.truncate(before);
+    } else {
+        end(r, out);
+    }
+}
+
+// rows are padded or truncated to the number of columns in the header
+fn write_table_row<R: Render + ?Sized>(
+    out: &mut String,
+    cells: &[MarkdownText],
+    aligns: &[Align],
+    header: bool,
+    r: &mut R,
+) {
+    r.table_row_start(out, header);
+    for (k, align) in aligns.iter().enumerate() {
+        r.table_cell_start(out, k, *align, header);
+        if let Some(cell) = cells.get(k) {
+            write_text(out, cell, r);
+        }
+        r.table_cell_end(out, header);
+    }
+    r.table_row_end(out, header);
+}
+
+pub fn write_text<R: Render + ?Sized>(out: &mut String, text: &MarkdownText, r: &mut R) {
+    for part in text {
+        match part {
+            MarkdownInline::Bold(inner) => {
+                r.bold_start(out);
+                write_text(out, inner, r);
+                r.bold_end(out);
+            }
+            MarkdownInline::Italic(inner) => {
+                r.italic_start(out);
+                write_text(out, inner, r);
+                r.italic_end(out);
+            }
+            MarkdownInline::Mark(inner) => {
+                r.mark_start(out);
+                write_text(out, inner, r);
+                r.mark_end(out);
+            }
+            MarkdownInline::Link(inner, url, title) => {
+                r.link_start(out, url, title);
+                write_text(out, inner, r);
+                r.link_end(out);
+            }
+            MarkdownInline::Image(alt, url, title) => r.image(out, &plain_text(alt), url, title),
+            MarkdownInline::Strike(text) => r.strike(out, text),
+            MarkdownInline::Superscript(text) => r.superscript(out, text),
+            MarkdownInline::Subscript(text) => r.subscript(out, text),
+            MarkdownInline::Kbd(keys) => r.kbd(out, keys),
+            MarkdownInline::Ruby(base, readings) => r.ruby(out, base, readings),
+            MarkdownInline::InlineCode(code) => r.inline_code(out, code),
+            MarkdownInline::Plaintext(text) => r.text(out, text),
+            MarkdownInline::FootnoteRef(number) => r.footnote_ref(out, number),
+            MarkdownInline::Html(html) => r.html(out, html),
+            MarkdownInline::Comment(text) => r.comment(out, text),
+            MarkdownInline::WikiLink(target, label) => r.wikilink(out, target, label),
+            MarkdownInline::Math(tex) => r.math(out, tex),
+            MarkdownInline::Emoji(name) => r.emoji(out, name),
+            MarkdownInline::LineBreak => r.line_break(out),
+        }
+    }
+}
+
+// a container being read from the stream
+struct Frame {
+    tag: Tag,
+    // where an item started and where its text starts, until the text ends
+    item_text: Option<(usize, usize)>,
+    // the cells written so far in a row, and the rows in a table
+    count: usize,
+    // the output put aside while a footnote or a cell out of the table is read
+    saved: Option<String>,
+}
+
+// the blocks which end the text of a list item
+fn is_block(event: &Event) -> bool {
+    match event {
+        Event::Start(tag) | Event::End(tag) => !matches!(
+            tag,
+            Tag::Bold | Tag::Italic | Tag::Mark | Tag::Link(_, _) | Tag::Image(_, _)
+        ),
+        Event::Codeblock(_, _, _)
+        | Event::MathBlock(_)
+        | Event::HorizontalRule
+        | Event::Html(_)
+        | Event::Comment(_)
+        | Event::Footnotes => true,
+        _ => false,
+    }
+}
+
+fn plain_event(event: &Event) -> String {
+    match event {
+        Event::Text(text)
+        | Event::Code(text)
+        | Event::Strike(text)
+        | Event::Superscript(text)
+        | Event::Subscript(text)
+        | Event::Math(text) => text.to_string(),
+        Event::Kbd(keys) => keys.join("+"),
+        Event::Ruby(base, _) => base.to_string(),
+        Event::WikiLink(target, label) => wikilink_text(target, label),
+        Event::Emoji(name) => emoji_text(name),
+        Event::HardBreak | Event::SoftBreak => String::from(" "),
+        _ => String::new(),
+    }
+}
+
+// the same as `render`, reading the document from a stream of events.
+// the footnotes are numbered as they are referenced and written at the end,
+// since the definitions may come after the whole body
+pub fn render_events<I, R>(events: I, r: &mut R) -> String
+where
+    I: IntoIterator<Item = Event>,
+    R: Render + ?Sized,
+{
+    let mut out = String::new();
+    let mut stack: Vec<Frame> = vec![];
+    let mut labels: Vec<String> = vec![];
+    let mut notes: HashMap<String, String> = HashMap::new();
+    // the alt text of the image being read, and how deep in its markup we are
+    let mut alt: Option<(String, usize)> = None;
+    for event in events {
+        if let Some((text, depth)) = &mut alt {
+            match &event {
+                Event::Start(_) => *depth += 1,
+                Event::End(Tag::Image(url, title)) if *depth == 0 => {
+                    r.image(&mut out, text, url, title);
+                    alt = None;
+                }
+                Event::End(_) => *depth -= 1,
+                event => text.push_str(&plain_event(event)),
+            }
+            continue;
+        }
+        if let Some(frame) = stack.last_mut() {
+            if let (Some((before, inner)), true) = (frame.item_text, is_block(&event)) {
+                if out.len() == inner {
+                    out.truncate(before);
+                } else {
+                    r.item_text_end(&mut out);
+                }
+                frame.item_text = None;
+            }
+        }
+        match event {
+            Event::Start(Tag::Image(_, _)) => alt = Some((String::new(), 0)),
+            Event::Start(tag) => {
+                let mut frame = Frame {
+                    tag,
+                    item_text: None,
+                    count: 0,
+                    saved: None,
+                };
+                match &frame.tag {
+                    Tag::Heading(level, attributes) => {
+                        r.heading_start(&mut out, *level, attributes)
+                    }
+                    Tag::Paragraph => r.paragraph_start(&mut out),
+                    Tag::List(start) => r.list_start(&mut out, *start),
+                    Tag::Item => {
+                        r.item_start(&mut out);
+                        let before = out.len();
+                        r.item_text_start(&mut out);
+                        frame.item_text = Some((before, out.len()));
+                    }
+                    Tag::DefinitionList => r.definition_list_start(&mut out),
+                    Tag::Term => r.term_start(&mut out),
+                    Tag::Definition => r.definition_start(&mut out),
+                    Tag::Table(aligns) => r.table_start(&mut out, aligns),
+                    Tag::TableHead => {
+                        r.table_head_start(&mut out);
+                        r.table_row_start(&mut out, true);
+                    }
+                    Tag::TableRow => {
+                        if let Some(table) = stack.last_mut() {
+                            if table.count == 0 {
+                                r.table_body_start(&mut out);
+                            }
+                            table.count += 1;
+                        }
+                        r.table_row_start(&mut out, false);
+                    }
+                    Tag::TableCell => {
+                        let n = stack.len();
+                        let header = stack[n - 1].tag == Tag::TableHead;
+                        let column = stack[n - 1].count;
+                        stack[n - 1].count += 1;
+                        match &stack[n - 2].tag {
+                            Tag::Table(aligns) if column < aligns.len() => {
+                                r.table_cell_start(&mut out, column, aligns[column], header)
+                            }
+                            _ => frame.saved = Some(std::mem::take(&mut out)),
+                        }
+                    }
+                    Tag::Blockquote => r.blockquote_start(&mut out),
+                    Tag::Admonition(kind, title) => r.admonition_start(&mut out, kind, title),
+                    Tag::Div(attributes) => r.div_start(&mut out, attributes),
+                    Tag::Details(open) => r.details_start(&mut out, *open),
+                    Tag::Summary => r.summary_start(&mut out),
+                    Tag::FootnoteDefinition(_) => frame.saved = Some(std::mem::take(&mut out)),
+                    Tag::Bold => r.bold_start(&mut out),
+                    Tag::Italic => r.italic_start(&mut out),
+                    Tag::Mark => r.mark_start(&mut out),
+                    Tag::Link(url, title) => r.link_start(&mut out, url, title),
+                    Tag::Image(_, _) => {}
+                }
+                stack.push(frame);
+            }
+            Event::End(_) => {
+                let frame = match stack.pop() {
+                    Some(frame) => frame,
+                    None => continue,
+                };
+                match frame.tag {
+                    Tag::Heading(level, attributes) => r.heading_end(&mut out, level, &attributes),
+                    Tag::Paragraph => r.paragraph_end(&mut out),
+                    Tag::List(start) => r.list_end(&mut out, start),
+                    Tag::Item => r.item_end(&mut out),
+                    Tag::DefinitionList => r.definition_list_end(&mut out),
+                    Tag::Term => r.term_end(&mut out),
+                    Tag::Definition => r.definition_end(&mut out),
+                    Tag::Table(_) => {
+                        if frame.count > 0 {
+                            r.table_body_end(&mut out);
+                        }
+                        r.table_end(&mut out);
+                    }
+                    Tag::TableHead | Tag::TableRow => {
+                        let header = frame.tag == Tag::TableHead;
+                        if let Some(Tag::Table(aligns)) = stack.last().map(|table| &table.tag) {
+                            for (k, align) in aligns.iter().enumerate().skip(frame.count) {
+                                r.table_cell_start(&mut out, k, *align, header);
+                                r.table_cell_end(&mut out, header);
+                            }
+                        }
+                        r.table_row_end(&mut out, header);
+                        if header {
+                            r.table_head_end(&mut out);
+                        }
+                    }
+                    Tag::TableCell => match frame.saved {
+                        Some(saved) => out = saved,
+                        None => {
+                            let header = stack.last().map(|row| &row.tag) == Some(&Tag::TableHead);
+                            r.table_cell_end(&mut out, header);
+                        }
+                    },
+                    Tag::Blockquote => r.blockquote_end(&mut out),
+                    Tag::Admonition(_, _) => r.admonition_end(&mut out),
+                    Tag::Div(_) => r.div_end(&mut out),
+                    Tag::Details(_) => r.details_end(&mut out),
+                    Tag::Summary => r.summary_end(&mut out),
+                    Tag::FootnoteDefinition(label) => {
+                        let note = std::mem::replace(&mut out, frame.saved.unwrap_or_default());
+                        notes.entry(label).or_insert(note);
+                    }
+                    Tag::Bold => r.bold_end(&mut out),
+                    Tag::Italic => r.italic_end(&mut out),
+                    Tag::Mark => r.mark_end(&mut out),
+                    Tag::Link(_, _) => r.link_end(&mut out),
+                    Tag::Image(_, _) => {}
+                }
+            }
+            Event::Text(text) => r.text(&mut out, &text),
+            Event::Code(code) => r.inline_code(&mut out, &code),
+            Event::Strike(text) => r.strike(&mut out, &text),
+            Event::Superscript(text) => r.superscript(&mut out, &text),
+            Event::Subscript(text) => r.subscript(&mut out, &text),
+            Event::Kbd(keys) => r.kbd(&mut out, &keys),
+            Event::Ruby(base, readings) => r.ruby(&mut out, &base, &readings),
+            Event::WikiLink(target, label) => r.wikilink(&mut out, &target, &label),
+            Event::Math(tex) => r.math(&mut out, &tex),
+            Event::Emoji(name) => r.emoji(&mut out, &name),
+            Event::FootnoteRef(label) => {
+                let number = match labels.iter().position(|l| *l == label) {
+                    Some(k) => k + 1,
+                    None => {
+                        labels.push(label);
+                        labels.len()
+                    }
+                };
+                r.footnote_ref(&mut out, &number.to_string())
+            }
+            Event::InlineHtml(html) | Event::Html(html) => r.html(&mut out, &html),
+            Event::InlineComment(text) | Event::Comment(text) => r.comment(&mut out, &text),
+            Event::SoftBreak => r.soft_break(&mut out),
+            Event::HardBreak => r.line_break(&mut out),
+            Event::Codeblock(lang, code, info) => r.codeblock(&mut out, &lang, &code, &info),
+            Event::MathBlock(tex) => r.math_block(&mut out, &tex),
+            Event::HorizontalRule => r.horizontal_rule(&mut out),
+            Event::FrontMatter(_) | Event::Footnotes => {}
+        }
+    }
+    if !labels.is_empty() {
+        r.footnotes_start(&mut out);
+        for (k, label) in labels.iter().enumerate() {
+            r.footnote_start(&mut out, k + 1);
+            out.push_str(&notes.remove(label).unwrap_or_default());
+            r.footnote_end(&mut out, k + 1);
+        }
+        r.footnotes_end(&mut out);
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::event::Parser;
+    use crate::translator::HtmlRenderer;
+
+    // only the emphasis is marked, as Slack does
+    struct Slack;
+
+    impl Render for Slack {
+        fn bold_start(&mut self, out: &mut String) {
+            out.push('*');
+        }
+
+        fn bold_end(&mut self, out: &mut String) {
+            out.push('*');
+        }
+
+        fn italic_start(&mut self, out: &mut String) {
+            out.push('_');
+        }
+
+        fn italic_end(&mut self, out: &mut String) {
+            out.push('_');
+        }
+
+        fn link_start(&mut self, out: &mut String, url: &str, _: &Option<String>) {
+            out.push('<');
+            out.push_str(url);
+            out.push('|');
+        }
+
+        fn link_end(&mut self, out: &mut String) {
+            out.push('>');
+        }
+    }
+
+    #[test]
+    fn test_render() {
+        let md = crate::parse("# Title\n\n- **a** [b](u)\n  1. *c*[^1]\n\n[^1]: note\n").unwrap();
+        assert_eq!(
+            render(md, &mut Slack),
+            "Title\n\n- *a* <u|b>\n- _c_[1]\n\n\n[1] note\n"
+        );
+    }
+
+    #[test]
+    fn test_render_events() {
+        let content = "# T {#t}\n\nb **c**  \nd [e](f)\n\n- g\n  1. h\n\nt\n: u\n: v\n\n| a | b |\n|---|:-:|\n| 1 |\n| 2 | 3 | 4 |\n\n> ??? s\n> ![*i*](j)\n\nk[^n]\n\n[^n]: note\n";
+        let html = HtmlRenderer::new();
+        assert_eq!(
+            render_events(Parser::new(content), &mut html.clone()),
+            html.render(crate::parse(content).unwrap())
+        );
+        assert_eq!(
+            render_events(Parser::new(content), &mut Slack),
+            render(crate::parse(content).unwrap(), &mut Slack)
+        );
+    }
+}
@@ -0,0 +1,332 @@
+use crate::entity::{Align, Attributes, Markdown};
+use crate::translator::admonition_title;
+use crate::translator::render::{self, Render};
+
+// the directives of docutils for the kinds of admonitions
+const ADMONITIONS: [&str; 9] = [
+    "attention",
+    "caution",
+    "danger",
+    "error",
+    "hint",
+    "important",
+    "note",
+    "tip",
+    "warning",
+];
+
+// the underlines of the headings by their levels
+const UNDERLINES: [char; 6] = ['=', '-', '~', '^', '"', '\''];
+
+// reStructuredText, where the content of a container is indented under its marker
+// once it is written
+#[derive(Clone, Debug, Default)]
+pub struct RstRenderer {
+    // where each open container started in the output
+    starts: Vec<usize>,
+    // the lists being written, with the number of the next item of an ordered one
+    lists: Vec<Option<usize>>,
+    // the urls of the links being written
+    links: Vec<String>,
+    // the urls and the alt texts of the images, which are substitutions defined after the document
+    images: Vec<(String, String)>,
+    // the column of the cell being written
+    column: usize,
+}
+
+pub fn translate(md: Vec<Markdown>) -> String {
+    let mut r = RstRenderer::default();
+    let mut output = render::render(md, &mut r);
+    for (k, (url, alt)) in r.images.iter().enumerate() {
+        output.push_str(&format!(
+            ".. |image{}| image:: {}\n   :alt: {}\n",
+            k + 1,
+            url,
+            alt
+        ));
+    }
+    output.trim_end().to_string()
+}
+
+fn escape(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        if matches!(c, '\\' | '*' | '`' | '_' | '|') {
+            out.push('\\');
+        }
+        out.push(c);
+    }
+    out
+}
+
+// the output from `start` with `first` before its first line and `rest` before the others
+fn indent(out: &mut String, start: usize, first: &str, rest: &str) {
+    let content = out.split_off(start);
+    for (k, line) in content.trim_end_matches('\n').split('\n').enumerate() {
+        let prefix = if k == 0 { first } else { rest };
+        if line.is_empty() {
+            out.push_str(prefix.trim_end());
+        } else {
+            out.push_str(prefix);
+            out.push_str(line);
+        }
+        out.push('\n');
+    }
+}
+
+impl RstRenderer {
+    fn start(&mut self, out: &str) {
+        self.starts.push(out.len());
+    }
+
+    fn end(&mut self, out: &mut String, first: &str, rest: &str) {
+        let start = self.starts.pop().unwrap_or(out.len());
+        indent(out, start, first, rest);
+    }
+}
+
+impl Render for RstRenderer {
+    fn heading_start(&mut self, out: &mut String, _: usize, _: &Attributes) {
+        self.start(out);
+    }
+
+    fn heading_end(&mut self, out: &mut String, level: usize, _: &Attributes) {
+        let start = self.starts.pop().unwrap_or(out.len());
+        let width = out[start..].chars().count();
+        let underline = UNDERLINES[level.clamp(1, 6) - 1];
+        out.push('\n');
+        out.extend(std::iter::repeat_n(underline, width));
+        out.push_str("\n\n");
+    }
+
+    fn list_start(&mut self, _: &mut String, start: Option<usize>) {
+        self.lists.push(start);
+    }
+
+    fn list_end(&mut self, out: &mut String, _: Option<usize>) {
+        self.lists.pop();
+        out.push('\n');
+    }
+
+    fn item_start(&mut self, out: &mut String) {
+        self.start(out);
+    }
+
+    fn item_text_end(&mut self, out: &mut String) {
+        out.push_str("\n\n");
+    }
+
+    // an item of more than one block is followed by a blank line, which ends its nested list
+    fn item_end(&mut self, out: &mut String) {
+        let marker = match self.lists.last_mut() {
+            Some(Some(number)) => {
+                *number += 1;
+                format!("{}. ", *number - 1)
+            }
+            _ => String::from("- "),
+        };
+        let start = *self.starts.last().unwrap_or(&out.len());
+        let blocks = out[start..].trim_end_matches('\n').contains("\n\n");
+        self.end(out, &marker, &" ".repeat(marker.len()));
+        if blocks {
+            out.push('\n');
+        }
+    }
+
+    fn definition_list_end(&mut self, _: &mut String) {}
+
+    fn definition_start(&mut self, out: &mut String) {
+        self.start(out);
+    }
+
+    fn definition_end(&mut self, out: &mut String) {
+        self.end(out, "    ", "    ");
+        out.push('\n');
+    }
+
+    fn codeblock(&mut self, out: &mut String, lang: &str, code: &str, _: &str) {
+        if lang.is_empty() {
+            out.push_str("::\n\n");
+        } else {
+            out.push_str(&format!(".. code-block:: {}\n\n", lang));
+        }
+        let start = out.len();
+        out.push_str(code);
+        indent(out, start, "   ", "   ");
+        out.push('\n');
+    }
+
+    fn horizontal_rule(&mut self, out: &mut String) {
+        out.push_str("----\n\n");
+    }
+
+    fn table_start(&mut self, out: &mut String, _: &[Align]) {
+        out.push_str(".. list-table::\n   :header-rows: 1\n\n");
+    }
+
+    fn table_row_end(&mut self, _: &mut String, _: bool) {}
+
+    fn table_cell_start(&mut self, out: &mut String, column: usize, _: Align, _: bool) {
+        self.column = column;
+        self.start(out);
+    }
+
+    fn table_cell_end(&mut self, out: &mut String, _: bool) {
+        let first = if self.column == 0 {
+            "   * - "
+        } else {
+            "     - "
+        };
+        self.end(out, first, "       ");
+    }
+
+    // an empty comment first, so that the quote is not read as a part of the block before it
+    fn blockquote_start(&mut self, out: &mut String) {
+        out.push_str("..\n\n");
+        self.start(out);
+    }
+
+    fn blockquote_end(&mut self, out: &mut String) {
+        self.end(out, "    ", "    ");
+        out.push('\n');
+    }
+
+    fn admonition_start(&mut self, out: &mut String, kind: &str, title: &Option<String>) {
+        if title.is_none() && ADMONITIONS.contains(&kind) {
+            out.push_str(&format!(".. {}::\n\n", kind));
+        } else {
+            out.push_str(&format!(
+                ".. admonition:: {}\n\n",
+                admonition_title(kind, title)
+            ));
+        }
+        self.start(out);
+    }
+
+    fn admonition_end(&mut self, out: &mut String) {
+        self.end(out, "   ", "   ");
+        out.push('\n');
+    }
+
+    // docutils has nothing to fold, so the details are an admonition titled with the summary
+    fn summary_start(&mut self, out: &mut String) {
+        out.push_str(".. admonition:: ");
+    }
+
+    fn summary_end(&mut self, out: &mut String) {
+        out.push_str("\n\n");
+        self.start(out);
+    }
+
+    fn details_end(&mut self, out: &mut String) {
+        self.end(out, "   ", "   ");
+        out.push('\n');
+    }
+
+    fn math_block(&mut self, out: &mut String, tex: &str) {
+        out.push_str(".. math::\n\n");
+        let start = out.len();
+        out.push_str(tex);
+        indent(out, start, "   ", "   ");
+        out.push('\n');
+    }
+
+    fn footnote_start(&mut self, out: &mut String, _: usize) {
+        self.start(out);
+    }
+
+    fn footnote_end(&mut self, out: &mut String, number: usize) {
+        self.end(out, &format!(".. [{}] ", number), "   ");
+        out.push('\n');
+    }
+
+    fn text(&mut self, out: &mut String, text: &str) {
+        out.push_str(&escape(text));
+    }
+
+    fn bold_start(&mut self, out: &mut String) {
+        out.push_str("**");
+    }
+
+    fn bold_end(&mut self, out: &mut String) {
+        out.push_str("**");
+    }
+
+    fn italic_start(&mut self, out: &mut String) {
+        out.push('*');
+    }
+
+    fn italic_end(&mut self, out: &mut String) {
+        out.push('*');
+    }
+
+    fn superscript(&mut self, out: &mut String, text: &str) {
+        out.push_str(&format!(":sup:`{}`", text));
+    }
+
+    fn subscript(&mut self, out: &mut String, text: &str) {
+        out.push_str(&format!(":sub:`{}`", text));
+    }
+
+    fn kbd(&mut self, out: &mut String, keys: &[String]) {
+        out.push_str(&format!(":kbd:`{}`", keys.join("+")));
+    }
+
+    fn inline_code(&mut self, out: &mut String, code: &str) {
+        out.push_str(&format!("``{}``", code));
+    }
+
+    // anonymous, so that two links of the same text do not clash
+    fn link_start(&mut self, out: &mut String, url: &str, _: &Option<String>) {
+        self.links.push(url.to_string());
+        out.push('`');
+    }
+
+    fn link_end(&mut self, out: &mut String) {
+        let url = self.links.pop().unwrap_or_default();
+        out.push_str(&format!(" <{}>`__", url));
+    }
+
+    fn image(&mut self, out: &mut String, alt: &str, url: &str, _: &Option<String>) {
+        self.images.push((url.to_string(), alt.to_string()));
+        out.push_str(&format!("|image{}|", self.images.len()));
+    }
+
+    fn footnote_ref(&mut self, out: &mut String, number: &str) {
+        if number.chars().all(|c| c.is_ascii_digit()) {
+            out.push_str(&format!("\\ [{}]_", number));
+        } else {
+            out.push_str(&escape(&format!("[^{}]", number)));
+        }
+    }
+
+    fn math(&mut self, out: &mut String, tex: &str) {
+        out.push_str(&format!(":math:`{}`", tex));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_translate() {
+        let md = crate::parse(
+            "# Title\n\nsome **bold** and `code`, a_b [link](https://a.com)[^1]\n\n## Sub\n\n- a\n  1. b\n  2. c\n- d\n\n> quote\n\n```rust\nfn f() {}\n```\n\n| a | b |\n|---|---|\n| 1 | 2 |\n\n::: warning\nmind ![x](x.png)\n:::\n\n[^1]: note\n",
+        )
+        .unwrap();
+        assert_eq!(
+            translate(md),
+            "Title\n=====\n\n\
+             some **bold** and ``code``, a\\_b `link <https://a.com>`__\\ [1]_\n\n\
+             Sub\n---\n\n\
+             - a\n\n  1. b\n  2. c\n\n- d\n\n\
+             ..\n\n    quote\n\n\
+             .. code-block:: rust\n\n   fn f() {}\n\n\
+             .. list-table::\n   :header-rows: 1\n\n   * - a\n     - b\n   * - 1\n     - 2\n\n\
+             .. warning::\n\n   mind |image1|\n\n\
+             .. [1] note\n\n\
+             .. |image1| image:: x.png\n   :alt: x"
+        );
+    }
+}
@@ -0,0 +1,304 @@
+use crate::entity::Definition;
+use crate::entity::ListItem;
+use crate::entity::Markdown;
+use crate::entity::MarkdownInline;
+use crate::entity::MarkdownText;
+use crate::translator::{
+    admonition_title, collect_footnotes, emoji_text, join_lines, plain_text, split_at_footnotes,
+    wikilink_text,
+};
+
+const RESET: &str = "\x1b[0m";
+const BOLD: (&str, &str) = ("\x1b[1m", "\x1b[22m");
+const ITALIC: (&str, &str) = ("\x1b[3m", "\x1b[23m");
+const UNDERLINE: (&str, &str) = ("\x1b[4m", "\x1b[24m");
+const STRIKE: (&str, &str) = ("\x1b[9m", "\x1b[29m");
+const DIM: (&str, &str) = ("\x1b[2m", "\x1b[22m");
+const REVERSE: (&str, &str) = ("\x1b[7m", "\x1b[27m");
+
+pub fn translate(md: Vec<Markdown>) -> String {
+    let (body, notes) = collect_footnotes(md);
+    let (body, rest) = split_at_footnotes(body);
+    let mut output = translate_blocks(body);
+    if !notes.is_empty() {
+        output += &translate_horizontal_rule();
+        for (k, note) in notes.iter().enumerate() {
+            output += &format!(
+                "{}[{}]{} {}\n",
+                DIM.0,
+                k + 1,
+                DIM.1,
+                translate_text(note.to_vec())
+            );
+        }
+        output += "\n";
+    }
+    output += &translate_blocks(rest);
+    output.trim_end().to_string()
+}
+
+fn style(text: &str, (start, end): (&str, &str)) -> String {
+    format!("{}{}{}", start, text, end)
+}
+
+fn indent(text: &str, prefix: &str) -> String {
+    text.lines()
+        .map(|line| format!("{}{}\n", prefix, line))
+        .collect()
+}
+
+fn translate_blocks(md: Vec<Markdown>) -> String {
+    md.iter()
+        .map(|bit| match bit {
+            Markdown::Heading(size, line, _) => translate_header(*size, line.to_vec()),
+            Markdown::UnorderedList(items) => translate_list(items.to_vec(), None),
+            Markdown::OrderedList(start, items) => translate_list(items.to_vec(), Some(*start)),
+            Markdown::Codeblock(_, code, _) => format!("{}\n", indent(&style(code, DIM), "    ")),
+            Markdown::Line(line) => translate_paragraph(vec![line.to_vec()]),
+            Markdown::Paragraph(lines) => translate_paragraph(lines.to_vec()),
+            Markdown::DefinitionList(definitions) => {
+                translate_definition_list(definitions.to_vec())
+            }
+            Markdown::HorizontalRule => translate_horizontal_rule(),
+            Markdown::Table(header, _, rows) => translate_table(header.to_vec(), rows.to_vec()),
+            Markdown::Blockquote(inner) => {
+                let inner = translate_blocks(inner.to_vec());
+                format!("{}\n", indent(inner.trim_end(), &style("│ ", DIM)))
+            }
+            Markdown::Admonition(kind, title, inner) => {
+                let inner = translate_blocks(inner.to_vec());
+                format!(
+                    "{}\n{}\n",
+                    style(&admonition_title(kind, title), BOLD),
+                    indent(inner.trim_end(), &style("│ ", DIM))
+                )
+            }
+            Markdown::Div(_, inner) => translate_blocks(inner.to_vec()),
+            Markdown::Details(summary, _, inner) => {
+                let inner = translate_blocks(inner.to_vec());
+                format!(
+                    "{}\n{}\n",
+                    style(&format!("▸ {}", translate_text(summary.to_vec())), BOLD),
+                    indent(inner.trim_end(), &style("│ ", DIM))
+                )
+            }
+            Markdown::MathBlock(tex) => format!("{}\n", indent(&style(tex, DIM), "    ")),
+            Markdown::FootnoteDefinition(_, _)
+            | Markdown::Footnotes
+            | Markdown::FrontMatter(_)
+            | Markdown::Html(_)
+            | Markdown::Comment(_) => String::new(),
+        })
+        .collect::<Vec<String>>()
+        .join("")
+}
+
+fn translate_header(size: usize, text: MarkdownText) -> String {
+    let color = match size {
+        1 => "\x1b[1;4;35m",
+        2 => "\x1b[1;36m",
+        _ => "\x1b[1;33m",
+    };
+    format!(
+        "{}{} {}{}\n\n",
+        color,
+        "#".repeat(size),
+        translate_text(text),
+        RESET
+    )
+}
+
+fn translate_horizontal_rule() -> String {
+    format!("{}\n\n", style(&"─".repeat(40), DIM))
+}
+
+fn translate_definition_list(definitions: Vec<Definition>) -> String {
+    let items = definitions
+        .iter()
+        .map(|(term, texts)| {
+            let texts: String = texts
+                .iter()
+                .map(|text| indent(&translate_text(text.to_vec()), "    "))
+                .collect();
+            format!("{}\n{}", style(&translate_text(term.to_vec()), BOLD), texts)
+        })
+        .collect::<Vec<String>>()
+        .join("\n");
+    format!("{}\n", items)
+}
+
+fn translate_list(items: Vec<ListItem>, start: Option<usize>) -> String {
+    let items = items
+        .iter()
+        .enumerate()
+        .map(|(k, (line, children))| {
+            let marker = if let Some(start) = start {
+                format!("{}.", start + k)
+            } else {
+                String::from("•")
+            };
+            let children = translate_blocks(children.to_vec());
+            format!(
+                "{} {}\n{}",
+                marker,
+                translate_text(line.to_vec()),
+                indent(children.trim_end(), "  ")
+            )
+        })
+        .collect::<Vec<String>>()
+        .join("");
+    format!("{}\n", items)
+}
+
+fn translate_paragraph(lines: Vec<MarkdownText>) -> String {
+    let text = join_lines(&lines, " ", |line| translate_text(line.to_vec()));
+    if text.is_empty() {
+        text
+    } else {
+        format!("{}\n\n", text)
+    }
+}
+
+// the columns are padded to the width of their widest cell, measured without the styles
+fn translate_table(header: Vec<MarkdownText>, rows: Vec<Vec<MarkdownText>>) -> String {
+    let columns = header.len();
+    let mut widths: Vec<usize> = header
+        .iter()
+        .map(|cell| plain_text(cell).chars().count())
+        .collect();
+    for row in &rows {
+        for (k, cell) in row.iter().take(columns).enumerate() {
+            widths[k] = widths[k].max(plain_text(cell).chars().count());
+        }
+    }
+    let row = |cells: &Vec<MarkdownText>, bold: bool| {
+        let cells = (0..columns)
+            .map(|k| {
+                let cell = cells.get(k).cloned().unwrap_or_default();
+                let pad = " ".repeat(widths[k] - plain_text(&cell).chars().count());
+                let text = translate_text(cell);
+                let text = if bold { style(&text, BOLD) } else { text };
+                format!("{}{}", text, pad)
+            })
+            .collect::<Vec<String>>()
+            .join(" │ ");
+        format!("{}\n", cells.trim_end())
+    };
+    let rule = widths
+        .iter()
+        .map(|width| "─".repeat(*width))
+        .collect::<Vec<String>>()
+        .join("─┼─");
+    let body = rows
+        .iter()
+        .map(|cells| row(cells, false))
+        .collect::<Vec<String>>()
+        .join("");
+    format!("{}{}\n{}\n", row(&header, true), rule, body)
+}
+
+// links are OSC 8 hyperlinks, which most terminals make clickable
+fn translate_text(text: MarkdownText) -> String {
+    text.iter()
+        .map(|part| match part {
+            MarkdownInline::Bold(text) => style(&translate_text(text.to_vec()), BOLD),
+            MarkdownInline::Italic(text) => style(&translate_text(text.to_vec()), ITALIC),
+            MarkdownInline::Strike(text) => style(text, STRIKE),
+            MarkdownInline::Mark(text) => style(&translate_text(text.to_vec()), REVERSE),
+            MarkdownInline::Superscript(text) => format!("^{}", text),
+            MarkdownInline::Subscript(text) => format!("_{}", text),
+            MarkdownInline::Kbd(keys) => keys
+                .iter()
+                .map(|key| style(&format!("[{}]", key), BOLD))
+                .collect::<Vec<String>>()
+                .join("+"),
+            MarkdownInline::Ruby(base, readings) => {
+                format!(
+                    "{}{}",
+                    base,
+                    style(&format!("({})", readings.concat()), DIM)
+                )
+            }
+            MarkdownInline::InlineCode(code) => style(code, DIM),
+            MarkdownInline::Link(text, url, _) => format!(
+                "\x1b]8;;{}\x1b\\{}\x1b]8;;\x1b\\",
+                url,
+                style(&translate_text(text.to_vec()), UNDERLINE)
+            ),
+            MarkdownInline::Image(text, _, _) => {
+                style(&format!("[image: {}]", plain_text(text)), DIM)
+            }
+            MarkdownInline::Plaintext(text) => text.to_string(),
+            MarkdownInline::FootnoteRef(number) => style(&format!("[{}]", number), DIM),
+            MarkdownInline::Math(tex) => style(tex, DIM),
+            MarkdownInline::Emoji(name) => emoji_text(name),
+            MarkdownInline::WikiLink(target, label) => {
+                style(&wikilink_text(target, label), UNDERLINE)
+            }
+            MarkdownInline::Html(_) | MarkdownInline::Comment(_) => String::new(),
+            MarkdownInline::LineBreak => String::from("\n"),
+        })
+        .collect::<Vec<String>>()
+        .join("")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::entity::Attributes;
+
+    fn text(s: &str) -> MarkdownText {
+        vec![MarkdownInline::Plaintext(String::from(s))]
+    }
+
+    #[test]
+    fn test_translate_text() {
+        assert_eq!(
+            translate_text(vec![
+                MarkdownInline::Bold(vec![MarkdownInline::Plaintext(String::from("b"))]),
+                MarkdownInline::InlineCode(String::from("c")),
+                MarkdownInline::Link(vec![MarkdownInline::Plaintext(String::from("t"))], String::from("https://x.com"), None),
+            ]),
+            "\x1b[1mb\x1b[22m\x1b[2mc\x1b[22m\x1b]8;;https://x.com\x1b\\\x1b[4mt\x1b[24m\x1b]8;;\x1b\\"
+        );
+    }
+
+    #[test]
+    fn test_translate_list() {
+        assert_eq!(
+            translate_list(
+                vec![
+                    (
+                        text("a"),
+                        vec![Markdown::OrderedList(1, vec![(text("b"), vec![])])]
+                    ),
+                    (text("c"), vec![]),
+                ],
+                None
+            ),
+            "• a\n  1. b\n• c\n\n"
+        );
+    }
+
+    #[test]
+    fn test_translate_table() {
+        assert_eq!(
+            translate_table(
+                vec![text("a"), text("bb")],
+                vec![vec![text("ccc"), text("d")], vec![text("e")]]
+            ),
+            "\x1b[1ma\x1b[22m   │ \x1b[1mbb\x1b[22m\n────┼───\nccc │ d\ne   │\n\n"
+        );
+    }
+
+    #[test]
+    fn test_translate() {
+        assert_eq!(
+            translate(vec![
+                Markdown::Heading(2, text("T"), Attributes::default()),
+                Markdown::Blockquote(vec![Markdown::Paragraph(vec![text("q")])]),
+            ]),
+            "\x1b[1;36m## T\x1b[0m\n\n\x1b[2m│ \x1b[22mq"
+        );
+    }
+}
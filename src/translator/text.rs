@@ -0,0 +1,283 @@
+use crate::entity::Definition;
+use crate::entity::ListItem;
+use crate::entity::Markdown;
+use crate::entity::MarkdownInline;
+use crate::entity::MarkdownText;
+use crate::translator::{
+    admonition_title, collect_footnotes, emoji_text, join_lines, ruby_text, split_at_footnotes,
+    wikilink_text,
+};
+
+pub fn translate(md: Vec<Markdown>) -> String {
+    let (body, notes) = collect_footnotes(md);
+    let (body, rest) = split_at_footnotes(body);
+    let mut output = translate_blocks(body);
+    if !notes.is_empty() {
+        output += &translate_horizontal_rule();
+        for (k, note) in notes.iter().enumerate() {
+            output += &format!("[{}] {}\n", k + 1, translate_text(note.to_vec()));
+        }
+        output += "\n";
+    }
+    output += &translate_blocks(rest);
+    output.trim_end().to_string()
+}
+
+fn indent(text: &str, prefix: &str) -> String {
+    text.lines()
+        .map(|line| format!("{}{}", prefix, line).trim_end().to_string() + "\n")
+        .collect()
+}
+
+fn translate_blocks(md: Vec<Markdown>) -> String {
+    md.iter()
+        .map(|bit| match bit {
+            Markdown::Heading(size, line, _) => translate_header(*size, line.to_vec()),
+            Markdown::UnorderedList(items) => translate_list(items.to_vec(), None),
+            Markdown::OrderedList(start, items) => translate_list(items.to_vec(), Some(*start)),
+            Markdown::Codeblock(_, code, _) => format!("{}\n", indent(code, "    ")),
+            Markdown::Line(line) => translate_paragraph(vec![line.to_vec()]),
+            Markdown::Paragraph(lines) => translate_paragraph(lines.to_vec()),
+            Markdown::DefinitionList(definitions) => {
+                translate_definition_list(definitions.to_vec())
+            }
+            Markdown::HorizontalRule => translate_horizontal_rule(),
+            Markdown::Table(header, _, rows) => translate_table(header.to_vec(), rows.to_vec()),
+            Markdown::Blockquote(inner) => {
+                let inner = translate_blocks(inner.to_vec());
+                format!("{}\n", indent(inner.trim_end(), "> "))
+            }
+            Markdown::Admonition(kind, title, inner) => {
+                let inner = translate_blocks(inner.to_vec());
+                format!(
+                    "{}:\n{}\n",
+                    admonition_title(kind, title),
+                    indent(inner.trim_end(), "  ")
+                )
+            }
+            Markdown::Div(_, inner) => translate_blocks(inner.to_vec()),
+            Markdown::Details(summary, _, inner) => {
+                let inner = translate_blocks(inner.to_vec());
+                format!(
+                    "{}:\n{}\n",
+                    translate_text(summary.to_vec()),
+                    indent(inner.trim_end(), "  ")
+                )
+            }
+            Markdown::MathBlock(tex) => format!("{}\n", indent(tex, "    ")),
+            Markdown::FootnoteDefinition(_, _)
+            | Markdown::Footnotes
+            | Markdown::FrontMatter(_)
+            | Markdown::Html(_)
+            | Markdown::Comment(_) => String::new(),
+        })
+        .collect::<Vec<String>>()
+        .join("")
+}
+
+// the top two levels are underlined as in setext headings, the rest is left as a line
+fn translate_header(size: usize, text: MarkdownText) -> String {
+    let text = translate_text(text);
+    match size {
+        1 | 2 => {
+            let underline = if size == 1 { "=" } else { "-" };
+            format!("{}\n{}\n\n", text, underline.repeat(text.chars().count()))
+        }
+        _ => format!("{}\n\n", text),
+    }
+}
+
+fn translate_horizontal_rule() -> String {
+    format!("{}\n\n", "-".repeat(40))
+}
+
+// nested blocks are indented to line up with the text of their item
+fn translate_definition_list(definitions: Vec<Definition>) -> String {
+    let items = definitions
+        .iter()
+        .map(|(term, texts)| {
+            let texts: String = texts
+                .iter()
+                .map(|text| indent(&translate_text(text.to_vec()), "    "))
+                .collect();
+            format!("{}\n{}", translate_text(term.to_vec()), texts)
+        })
+        .collect::<Vec<String>>()
+        .join("\n");
+    format!("{}\n", items)
+}
+
+fn translate_list(items: Vec<ListItem>, start: Option<usize>) -> String {
+    let items = items
+        .iter()
+        .enumerate()
+        .map(|(k, (line, children))| {
+            let marker = if let Some(start) = start {
+                format!("{}.", start + k)
+            } else {
+                String::from("-")
+            };
+            let children = translate_blocks(children.to_vec());
+            format!(
+                "{} {}\n{}",
+                marker,
+                translate_text(line.to_vec()),
+                indent(children.trim_end(), &" ".repeat(marker.len() + 1))
+            )
+        })
+        .collect::<Vec<String>>()
+        .join("");
+    format!("{}\n", items)
+}
+
+fn translate_paragraph(lines: Vec<MarkdownText>) -> String {
+    let text = join_lines(&lines, " ", |line| translate_text(line.to_vec()));
+    if text.is_empty() {
+        text
+    } else {
+        format!("{}\n\n", text)
+    }
+}
+
+fn translate_table(header: Vec<MarkdownText>, rows: Vec<Vec<MarkdownText>>) -> String {
+    let columns = header.len();
+    let header: Vec<String> = header.into_iter().map(translate_text).collect();
+    let rows: Vec<Vec<String>> = rows
+        .into_iter()
+        .map(|row| {
+            (0..columns)
+                .map(|k| translate_text(row.get(k).cloned().unwrap_or_default()))
+                .collect()
+        })
+        .collect();
+    let mut widths: Vec<usize> = header.iter().map(|cell| cell.chars().count()).collect();
+    for row in &rows {
+        for (k, cell) in row.iter().enumerate() {
+            widths[k] = widths[k].max(cell.chars().count());
+        }
+    }
+    let row = |cells: &Vec<String>| {
+        let cells = cells
+            .iter()
+            .enumerate()
+            .map(|(k, cell)| format!("{}{}", cell, " ".repeat(widths[k] - cell.chars().count())))
+            .collect::<Vec<String>>()
+            .join(" | ");
+        format!("{}\n", cells.trim_end())
+    };
+    let rule = widths
+        .iter()
+        .map(|width| "-".repeat(*width))
+        .collect::<Vec<String>>()
+        .join("-+-");
+    let body = rows.iter().map(row).collect::<Vec<String>>().join("");
+    format!("{}{}\n{}\n", row(&header), rule, body)
+}
+
+// links keep their destination after the text, unless the text is the destination itself
+fn translate_text(text: MarkdownText) -> String {
+    text.iter()
+        .map(|part| match part {
+            MarkdownInline::Link(text, url, _) | MarkdownInline::Image(text, url, _) => {
+                let text = translate_text(text.to_vec());
+                if &text == url {
+                    url.to_string()
+                } else {
+                    format!("{} ({})", text, url)
+                }
+            }
+            MarkdownInline::Bold(text)
+            | MarkdownInline::Italic(text)
+            | MarkdownInline::Mark(text) => translate_text(text.to_vec()),
+            MarkdownInline::Kbd(keys) => keys.join("+"),
+            MarkdownInline::Ruby(base, readings) => ruby_text(base, readings),
+            MarkdownInline::Strike(text)
+            | MarkdownInline::Superscript(text)
+            | MarkdownInline::Subscript(text)
+            | MarkdownInline::InlineCode(text)
+            | MarkdownInline::Plaintext(text) => text.to_string(),
+            MarkdownInline::FootnoteRef(number) => format!("[{}]", number),
+            MarkdownInline::Math(tex) => tex.to_string(),
+            MarkdownInline::Emoji(name) => emoji_text(name),
+            MarkdownInline::WikiLink(target, label) => wikilink_text(target, label),
+            MarkdownInline::Html(_) | MarkdownInline::Comment(_) => String::new(),
+            MarkdownInline::LineBreak => String::from("\n"),
+        })
+        .collect::<Vec<String>>()
+        .join("")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::entity::Attributes;
+
+    fn text(s: &str) -> MarkdownText {
+        vec![MarkdownInline::Plaintext(String::from(s))]
+    }
+
+    #[test]
+    fn test_translate_text() {
+        assert_eq!(
+            translate_text(vec![
+                MarkdownInline::Bold(vec![MarkdownInline::Plaintext(String::from("b"))]),
+                MarkdownInline::InlineCode(String::from("c")),
+                MarkdownInline::Link(
+                    vec![MarkdownInline::Plaintext(String::from("t"))],
+                    String::from("https://x.com"),
+                    None
+                ),
+                MarkdownInline::Plaintext(String::from(" ")),
+                MarkdownInline::Link(
+                    vec![MarkdownInline::Plaintext(String::from("https://y.com"))],
+                    String::from("https://y.com"),
+                    None
+                ),
+            ]),
+            "bct (https://x.com) https://y.com"
+        );
+    }
+
+    #[test]
+    fn test_translate_list() {
+        assert_eq!(
+            translate_list(
+                vec![
+                    (
+                        text("a"),
+                        vec![Markdown::UnorderedList(vec![(text("b"), vec![])])]
+                    ),
+                    (text("c"), vec![]),
+                ],
+                Some(1)
+            ),
+            "1. a\n   - b\n2. c\n\n"
+        );
+    }
+
+    #[test]
+    fn test_translate_table() {
+        assert_eq!(
+            translate_table(
+                vec![text("a"), text("bb")],
+                vec![vec![text("ccc"), text("d")], vec![text("e")]]
+            ),
+            "a   | bb\n----+---\nccc | d\ne   |\n\n"
+        );
+    }
+
+    #[test]
+    fn test_translate() {
+        assert_eq!(
+            translate(vec![
+                Markdown::Heading(1, text("Title"), Attributes::default()),
+                Markdown::Blockquote(vec![Markdown::Paragraph(vec![
+                    text("q"),
+                    vec![MarkdownInline::FootnoteRef(String::from("x"))]
+                ])]),
+                Markdown::FootnoteDefinition(String::from("x"), text("note")),
+            ]),
+            "Title\n=====\n\n> q [1]\n\n----------------------------------------\n\n[1] note"
+        );
+    }
+}
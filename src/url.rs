@@ -0,0 +1,326 @@
+use crate::sanitize;
+
+// the schemes of the urls which run a script or carry a document of their own when followed
+const SCRIPTS: [&str; 3] = ["data", "javascript", "vbscript"];
+
+// the scheme of the url in lowercase, as a browser reads it:
+// after the character references are decoded and the whitespace is dropped.
+// none for a relative url, such as `page.html` or `/a:b`
+pub fn scheme(url: &str) -> Option<String> {
+    let url: String = sanitize::decode(url)
+        .chars()
+        .filter(|c| !c.is_ascii_whitespace() && !c.is_control())
+        .collect();
+    let k = url.find([':', '/', '?', '#'])?;
+    if url[k..].starts_with(':') && k > 0 {
+        Some(url[..k].to_ascii_lowercase())
+    } else {
+        None
+    }
+}
+
+// `javascript:`, `vbscript:` or `data:`
+pub fn is_script(url: &str) -> bool {
+    scheme(url).is_some_and(|scheme| SCRIPTS.contains(&scheme.as_str()))
+}
+
+// `http://` becomes `https://`, the other urls are kept as they are
+pub fn force_https(url: &str) -> String {
+    match url.get(..7) {
+        Some(http) if http.eq_ignore_ascii_case("http://") => format!("https://{}", &url[7..]),
+        _ => url.to_string(),
+    }
+}
+
+// the path of a file next to the document, without the query and the fragment:
+// none for the urls with a scheme, or which start at the host or the root of the site
+pub fn local_path(url: &str) -> Option<&str> {
+    let path = &url[..url.find(['?', '#']).unwrap_or(url.len())];
+    if path.is_empty() || path.starts_with('/') || scheme(url).is_some() {
+        None
+    } else {
+        Some(path)
+    }
+}
+
+// the media type of an image, from the extension of its path
+pub fn image_type(path: &str) -> Option<&'static str> {
+    let (_, extension) = path.rsplit_once('.')?;
+    Some(match extension.to_ascii_lowercase().as_str() {
+        "avif" => "image/avif",
+        "bmp" => "image/bmp",
+        "gif" => "image/gif",
+        "ico" => "image/x-icon",
+        "jpg" | "jpeg" => "image/jpeg",
+        "png" => "image/png",
+        "svg" => "image/svg+xml",
+        "webp" => "image/webp",
+        _ => return None,
+    })
+}
+
+// `data:image/png;base64,...`, the content in the url itself
+pub fn data_url(media_type: &str, content: &[u8]) -> String {
+    const DIGITS: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut url = format!("data:{};base64,", media_type);
+    url.reserve(content.len().div_ceil(3) * 4);
+    for chunk in content.chunks(3) {
+        let bits = chunk.iter().enumerate().fold(0u32, |bits, (k, byte)| {
+            bits | (*byte as u32) << (16 - 8 * k)
+        });
+        for k in 0..4 {
+            if k <= chunk.len() {
+                url.push(DIGITS[(bits >> (18 - 6 * k) & 63) as usize] as char);
+            } else {
+                url.push('=');
+            }
+        }
+    }
+    url
+}
+
+// `page.md#usage` becomes `page.html#usage` when `from` is `md` and `to` is `html`,
+// for the relative urls only
+pub fn replace_extension(url: &str, from: &str, to: &str) -> String {
+    let (path, rest) = url.split_at(url.find(['?', '#']).unwrap_or(url.len()));
+    match path
+        .strip_suffix(from)
+        .and_then(|stem| stem.strip_suffix('.'))
+    {
+        Some(stem) if scheme(url).is_none() && !url.starts_with("//") && !stem.is_empty() => {
+            format!("{}.{}{}", stem, to, rest)
+        }
+        _ => url.to_string(),
+    }
+}
+
+// the host of an `http:` or `https:` url, or of one starting at the host such as `//a.com/x`,
+// in lowercase and without the user and the port
+pub fn host(url: &str) -> Option<String> {
+    let rest = match scheme(url) {
+        Some(scheme) if scheme == "http" || scheme == "https" => &url[url.find(':')? + 1..],
+        Some(_) => return None,
+        None => url,
+    };
+    let authority = rest.strip_prefix("//")?;
+    let authority = &authority[..authority.find(['/', '?', '#']).unwrap_or(authority.len())];
+    let host = authority
+        .rsplit_once('@')
+        .map_or(authority, |(_, host)| host);
+    let host = match host.rsplit_once(':') {
+        Some((host, port)) if !host.ends_with(':') && port.bytes().all(|c| c.is_ascii_digit()) => {
+            host
+        }
+        _ => host,
+    };
+    Some(host.to_ascii_lowercase()).filter(|host| !host.is_empty())
+}
+
+// whether the url points to another site than `base`, or to any site when there is no base
+pub fn is_external(url: &str, base: Option<&str>) -> bool {
+    match (host(url), base.and_then(host)) {
+        (Some(host), Some(base)) => host != base,
+        (host, _) => host.is_some(),
+    }
+}
+
+// the url resolved against `base` as a browser does, when it has no scheme.
+// a link to a fragment of the same page is kept as it is
+pub fn resolve(base: &str, url: &str) -> String {
+    let relative = !url.is_empty() && !url.starts_with('#') && scheme(url).is_none();
+    let Some(k) = base
+        .find(':')
+        .filter(|_| relative && scheme(base).is_some())
+    else {
+        return url.to_string();
+    };
+    if url.starts_with("//") {
+        return format!("{}{}", &base[..=k], url);
+    }
+    let rest = &base[k + 1..];
+    let (authority, path) = match rest.strip_prefix("//") {
+        Some(host) => {
+            let end = host.find(['/', '?', '#']).map_or(rest.len(), |n| n + 2);
+            rest.split_at(end)
+        }
+        None => ("", rest),
+    };
+    let path = &path[..path.find(['?', '#']).unwrap_or(path.len())];
+    let path = if path.is_empty() && !authority.is_empty() {
+        "/"
+    } else {
+        path
+    };
+    let merged = if url.starts_with('/') {
+        url.to_string()
+    } else if url.starts_with('?') {
+        format!("{}{}", path, url)
+    } else {
+        format!("{}{}", &path[..path.rfind('/').map_or(0, |n| n + 1)], url)
+    };
+    format!(
+        "{}{}{}",
+        &base[..=k],
+        authority,
+        remove_dot_segments(&merged)
+    )
+}
+
+// the url with a `/` at the end of its path, as the root of a site, against which
+// `resolve` keeps the urls inside of it: `https://a.com/blog` is `https://a.com/blog/`
+pub fn directory(url: &str) -> String {
+    let (path, rest) = url.split_at(url.find(['?', '#']).unwrap_or(url.len()));
+    if path.ends_with('/') {
+        url.to_string()
+    } else {
+        format!("{}/{}", path, rest)
+    }
+}
+
+// `/a/./b/../c` is `/a/c`; the query and the fragment are left alone
+fn remove_dot_segments(url: &str) -> String {
+    let (path, rest) = url.split_at(url.find(['?', '#']).unwrap_or(url.len()));
+    let segments: Vec<&str> = path.split('/').collect();
+    let mut kept: Vec<&str> = vec![];
+    for (k, segment) in segments.iter().enumerate() {
+        match *segment {
+            "." | ".." => {
+                if *segment == ".." && kept.len() > 1 {
+                    kept.pop();
+                }
+                // `a/..` is the directory, so it keeps its slash
+                if k + 1 == segments.len() {
+                    kept.push("");
+                }
+            }
+            segment => kept.push(segment),
+        }
+    }
+    format!("{}{}", kept.join("/"), rest)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_scheme() {
+        assert_eq!(scheme("HTTPS://a.com"), Some(String::from("https")));
+        assert_eq!(scheme(" java\tscript:x"), Some(String::from("javascript")));
+        assert_eq!(scheme("javascript&#58;x"), Some(String::from("javascript")));
+        assert_eq!(scheme("page.html"), None);
+        assert_eq!(scheme("/a:b"), None);
+        assert_eq!(scheme(":x"), None);
+        assert!(is_script("data:text/html,x"));
+        assert!(!is_script("mailto:a@b.c"));
+    }
+
+    #[test]
+    fn test_force_https() {
+        assert_eq!(force_https("http://a.com/x"), "https://a.com/x");
+        assert_eq!(force_https("HTTP://a.com"), "https://a.com");
+        assert_eq!(force_https("https://a.com"), "https://a.com");
+        assert_eq!(force_https("page.html"), "page.html");
+    }
+
+    #[test]
+    fn test_local_path() {
+        assert_eq!(local_path("img/a.png?v=2"), Some("img/a.png"));
+        assert_eq!(local_path("../a.png"), Some("../a.png"));
+        assert_eq!(local_path("/img/a.png"), None);
+        assert_eq!(local_path("//cdn.com/a.png"), None);
+        assert_eq!(local_path("https://a.com/a.png"), None);
+        assert_eq!(local_path("#a"), None);
+    }
+
+    #[test]
+    fn test_data_url() {
+        assert_eq!(image_type("a/b.PNG"), Some("image/png"));
+        assert_eq!(image_type("b.svg"), Some("image/svg+xml"));
+        assert_eq!(image_type("b.txt"), None);
+        assert_eq!(image_type("png"), None);
+        assert_eq!(data_url("text/plain", b""), "data:text/plain;base64,");
+        assert_eq!(data_url("text/plain", b"f"), "data:text/plain;base64,Zg==");
+        assert_eq!(data_url("text/plain", b"fo"), "data:text/plain;base64,Zm8=");
+        assert_eq!(
+            data_url("text/plain", b"foo"),
+            "data:text/plain;base64,Zm9v"
+        );
+        assert_eq!(
+            data_url("image/png", b"\x89PNG\xff"),
+            "data:image/png;base64,iVBOR/8="
+        );
+    }
+
+    #[test]
+    fn test_replace_extension() {
+        let html = |url| replace_extension(url, "md", "html");
+        assert_eq!(html("guide.md"), "guide.html");
+        assert_eq!(html("../posts/a.md#setup"), "../posts/a.html#setup");
+        assert_eq!(html("/a.md?raw=1"), "/a.html?raw=1");
+        assert_eq!(
+            html("https://github.com/a/b/README.md"),
+            "https://github.com/a/b/README.md"
+        );
+        assert_eq!(html("//cdn.com/a.md"), "//cdn.com/a.md");
+        assert_eq!(html("a.mdx"), "a.mdx");
+        assert_eq!(html("#a.md"), "#a.md");
+        assert_eq!(html(".md"), ".md");
+    }
+
+    #[test]
+    fn test_host() {
+        assert_eq!(host("https://A.com/x"), Some(String::from("a.com")));
+        assert_eq!(host("http://u:p@a.com:8080?q"), Some(String::from("a.com")));
+        assert_eq!(host("//cdn.com"), Some(String::from("cdn.com")));
+        assert_eq!(host("http://[::1]:80/"), Some(String::from("[::1]")));
+        assert_eq!(host("mailto:a@b.c"), None);
+        assert_eq!(host("/a/b"), None);
+        assert_eq!(host("page.html"), None);
+        assert_eq!(host("https:///x"), None);
+        assert!(is_external("https://b.com/", Some("https://a.com/docs/")));
+        assert!(!is_external("http://A.com/x", Some("https://a.com/docs/")));
+        assert!(is_external("//b.com/x", None));
+        assert!(!is_external("/x", None));
+        assert!(!is_external("mailto:a@b.c", None));
+    }
+
+    #[test]
+    fn test_directory() {
+        assert_eq!(directory("https://a.com/blog"), "https://a.com/blog/");
+        assert_eq!(directory("https://a.com/blog/"), "https://a.com/blog/");
+        assert_eq!(directory("https://a.com"), "https://a.com/");
+        assert_eq!(directory("/docs?v=1"), "/docs/?v=1");
+        assert_eq!(
+            resolve(&directory("https://ex.com/blog"), "sub/b.html"),
+            "https://ex.com/blog/sub/b.html"
+        );
+    }
+
+    #[test]
+    fn test_resolve() {
+        let base = "https://a.com/docs/guide/intro.html?x=1#top";
+        assert_eq!(
+            resolve(base, "setup.html"),
+            "https://a.com/docs/guide/setup.html"
+        );
+        assert_eq!(resolve(base, "../api/"), "https://a.com/docs/api/");
+        assert_eq!(resolve(base, "./a/../b#c"), "https://a.com/docs/guide/b#c");
+        assert_eq!(resolve(base, "/img/a.png"), "https://a.com/img/a.png");
+        assert_eq!(resolve(base, "/../a"), "https://a.com/a");
+        assert_eq!(resolve(base, ".."), "https://a.com/docs/");
+        assert_eq!(
+            resolve(base, "?y=2"),
+            "https://a.com/docs/guide/intro.html?y=2"
+        );
+        assert_eq!(resolve(base, "//cdn.com/x.js"), "https://cdn.com/x.js");
+        assert_eq!(resolve(base, "#local"), "#local");
+        assert_eq!(resolve(base, "mailto:a@b.c"), "mailto:a@b.c");
+        assert_eq!(resolve("https://a.com", "x"), "https://a.com/x");
+        assert_eq!(
+            resolve("https://a.com/blog/", "post"),
+            "https://a.com/blog/post"
+        );
+        assert_eq!(resolve("/relative/base/", "x"), "x");
+    }
+}
@@ -0,0 +1,184 @@
+use crate::entity::Markdown;
+#[cfg(feature = "highlight")]
+use crate::highlight;
+use crate::json::{self, Json};
+use crate::parser::ParseOptions;
+use crate::translator;
+
+use wasm_bindgen::prelude::*;
+
+// the JS of `wasm-pack build --features wasm`, where the options are an object
+// such as `{dialect: "gfm", enable: ["math"], standalone: true, baseUrl: "/docs/"}`:
+//
+//     import init, { parse, toHtml } from "prose";
+//     await init();
+//     const html = toHtml("# Hello", { headingAnchors: true });
+
+// the AST, as the objects of `--to json`
+#[wasm_bindgen]
+pub fn parse(content: &str, options: JsValue) -> Result<JsValue, JsError> {
+    let (parse, _) = options_of(&options)?;
+    let markdown = parse_markdown(content, parse)?;
+    js_sys::JSON::parse(&json::to_json(&markdown)).map_err(|_| JsError::new("Invalid JSON AST"))
+}
+
+#[wasm_bindgen(js_name = toHtml)]
+pub fn to_html(content: &str, options: JsValue) -> Result<String, JsError> {
+    let (parse, html) = options_of(&options)?;
+    let markdown = parse_markdown(content, parse)?;
+    Ok(translator::translate(markdown, &html))
+}
+
+fn options_of(value: &JsValue) -> Result<(ParseOptions, translator::Options), JsError> {
+    let json = match js_sys::JSON::stringify(value)
+        .ok()
+        .and_then(|s| s.as_string())
+    {
+        Some(s) => json::parse(&s).map_err(|err| JsError::new(&err))?,
+        // `undefined` has no JSON
+        None => Json::Null,
+    };
+    options(&json).map_err(|err| JsError::new(&err))
+}
+
+// the lines which cannot be parsed are kept as text, as the command line does,
+// but a document over the limits is an error
+fn parse_markdown(content: &str, options: ParseOptions) -> Result<Vec<Markdown>, JsError> {
+    let (markdown, warnings) = crate::parse_with_options(content, options);
+    match warnings.iter().find(|warning| warning.limit.is_some()) {
+        Some(error) => Err(JsError::new(&error.to_string())),
+        None => Ok(markdown),
+    }
+}
+
+// the options of the object, in camelCase. the ones left out or `null` are the defaults
+fn options(json: &Json) -> Result<(ParseOptions, translator::Options), String> {
+    let mut parse = ParseOptions::default();
+    let mut html = translator::Options::default();
+    let entries = match json {
+        Json::Object(entries) => entries,
+        Json::Null => return Ok((parse, html)),
+        _ => return Err(String::from("The options are not an object")),
+    };
+    for (key, value) in entries {
+        if *value == Json::Null {
+            continue;
+        }
+        let flag = || match value {
+            Json::Bool(b) => Ok(*b),
+            _ => Err(format!("The option {} is not a boolean", key)),
+        };
+        let string = || match value {
+            Json::String(s) => Ok(s.clone()),
+            _ => Err(format!("The option {} is not a string", key)),
+        };
+        let strings = || match value {
+            Json::Array(items) => items
+                .iter()
+                .map(|item| match item {
+                    Json::String(s) => Ok(s.clone()),
+                    _ => Err(format!("The option {} is not a list of strings", key)),
+                })
+                .collect::<Result<Vec<String>, String>>(),
+            _ => Err(format!("The option {} is not a list of strings", key)),
+        };
+        match key.as_str() {
+            "dialect" => parse.dialect = string()?.parse()?,
+            "enable" => {
+                parse.enabled = strings()?
+                    .iter()
+                    .map(|name| name.parse())
+                    .collect::<Result<_, String>>()?
+            }
+            "disable" => {
+                parse.disabled = strings()?
+                    .iter()
+                    .map(|name| name.parse())
+                    .collect::<Result<_, String>>()?
+            }
+            "singleTildeStrike" => parse.single_tilde_strike = flag()?,
+            "escape" => html.escape = flag()?,
+            "standalone" => html.standalone = flag()?,
+            "css" => html.css = strings()?,
+            #[cfg(feature = "highlight")]
+            "highlight" => {
+                let theme = string()?;
+                if highlight::theme_css(&theme).is_none() {
+                    return Err(format!("Unknown highlight theme: {}", theme));
+                }
+                html.highlight = Some(theme);
+            }
+            "languageClass" => html.language_class = flag()?,
+            "katex" => html.katex = flag()?,
+            "diagrams" => html.diagrams = flag()?,
+            "headingAnchors" => html.heading_anchors = flag()?,
+            "footnoteSymbols" => html.footnote_symbols = flag()?,
+            "emojiImages" => html.emoji_images = flag()?,
+            "keepComments" => html.keep_comments = flag()?,
+            "wikiBase" => html.wiki_base = string()?,
+            "xhtml" => html.xhtml = flag()?,
+            "pretty" => html.pretty = flag()?,
+            "wrapLists" => html.wrap_lists = flag()?,
+            "headingOffset" => {
+                html.heading_offset = match value {
+                    Json::Number(n) if n.is_u64() => n.as_u64().unwrap_or_default() as usize,
+                    _ => return Err(format!("The option {} is not a number", key)),
+                }
+            }
+            "classPrefix" => html.class_prefix = string()?,
+            "sanitize" => html.sanitize = flag()?,
+            "lazyImages" => html.lazy_images = flag()?,
+            "figures" => html.figures = flag()?,
+            "blockScriptUrls" => html.block_script_urls = flag()?,
+            "baseUrl" => html.base_url = Some(string()?),
+            "forceHttps" => html.force_https = flag()?,
+            "externalLinkClass" => html.external_link_class = Some(string()?),
+            _ => return Err(format!("Unknown option {}", key)),
+        }
+    }
+    Ok((parse, html))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::{Dialect, Extension};
+
+    #[test]
+    fn test_options() {
+        let json = json::parse(
+            r#"{"dialect": "gfm", "enable": ["math"], "standalone": true,
+                "headingOffset": 1, "baseUrl": "/docs/", "css": ["a.css"], "wikiBase": null}"#,
+        )
+        .unwrap();
+        let (parse, html) = options(&json).unwrap();
+        assert_eq!(parse.dialect, Dialect::Gfm);
+        assert!(parse.has(Extension::Math));
+        assert!(html.standalone);
+        assert_eq!(html.heading_offset, 1);
+        assert_eq!(html.base_url, Some(String::from("/docs/")));
+        assert_eq!(html.css, ["a.css"]);
+        assert_eq!(html.wiki_base, translator::Options::default().wiki_base);
+        let error = |s: &str| options(&json::parse(s).unwrap()).err();
+        assert_eq!(
+            error(r#"{"xhtml": "yes"}"#),
+            Some(String::from("The option xhtml is not a boolean"))
+        );
+        assert_eq!(
+            error(r#"{"headingOffset": -1}"#),
+            Some(String::from("The option headingOffset is not a number"))
+        );
+        assert_eq!(
+            error(r#"{"colour": true}"#),
+            Some(String::from("Unknown option colour"))
+        );
+        assert_eq!(
+            error("[]"),
+            Some(String::from("The options are not an object"))
+        );
+        assert!(error(r#"{"enable": ["nope"]}"#)
+            .unwrap()
+            .starts_with("Unknown extension nope"));
+        assert!(options(&Json::Null).is_ok());
+    }
+}
@@ -0,0 +1,80 @@
+// a zip archive of the files by their paths, in their order and stored as they are,
+// which an EPUB needs for its `mimetype` first and is enough for the rest
+pub fn write(files: &[(String, Vec<u8>)]) -> Vec<u8> {
+    let mut out = vec![];
+    let mut central = vec![];
+    for (name, content) in files {
+        let offset = out.len() as u32;
+        let crc = crc32(content);
+        let mut header = vec![];
+        // the version needed, the flags with the names in UTF-8, stored, and 1980-01-01 00:00
+        header.extend(10u16.to_le_bytes());
+        header.extend(0x0800u16.to_le_bytes());
+        header.extend(0u16.to_le_bytes());
+        header.extend(0u16.to_le_bytes());
+        header.extend(0x0021u16.to_le_bytes());
+        header.extend(crc.to_le_bytes());
+        header.extend((content.len() as u32).to_le_bytes());
+        header.extend((content.len() as u32).to_le_bytes());
+        header.extend((name.len() as u16).to_le_bytes());
+        header.extend(0u16.to_le_bytes());
+
+        out.extend(0x04034b50u32.to_le_bytes());
+        out.extend(&header);
+        out.extend(name.as_bytes());
+        out.extend(content);
+
+        central.extend(0x02014b50u32.to_le_bytes());
+        central.extend(20u16.to_le_bytes());
+        central.extend(&header);
+        // no comment, the first disk, and no attributes
+        central.extend(0u16.to_le_bytes());
+        central.extend(0u16.to_le_bytes());
+        central.extend(0u16.to_le_bytes());
+        central.extend(0u32.to_le_bytes());
+        central.extend(offset.to_le_bytes());
+        central.extend(name.as_bytes());
+    }
+    let offset = out.len() as u32;
+    out.extend(&central);
+    out.extend(0x06054b50u32.to_le_bytes());
+    out.extend(0u16.to_le_bytes());
+    out.extend(0u16.to_le_bytes());
+    out.extend((files.len() as u16).to_le_bytes());
+    out.extend((files.len() as u16).to_le_bytes());
+    out.extend((central.len() as u32).to_le_bytes());
+    out.extend(offset.to_le_bytes());
+    out.extend(0u16.to_le_bytes());
+    out
+}
+
+fn crc32(bytes: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for byte in bytes {
+        crc ^= *byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 == 1 {
+                (crc >> 1) ^ 0xedb88320
+            } else {
+                crc >> 1
+            };
+        }
+    }
+    !crc
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_write() {
+        assert_eq!(crc32(b"123456789"), 0xcbf43926);
+        let zip = write(&[(String::from("a.txt"), b"hello".to_vec())]);
+        assert_eq!(&zip[..4], b"PK\x03\x04");
+        assert_eq!(&zip[30..40], b"a.txthello");
+        assert_eq!(&zip[40..44], b"PK\x01\x02");
+        assert_eq!(zip.len(), 30 + 10 + 46 + 5 + 22);
+        assert_eq!(&zip[zip.len() - 22..zip.len() - 18], b"PK\x05\x06");
+    }
+}
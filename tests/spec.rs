@@ -0,0 +1,173 @@
+// runs prose against the examples of the CommonMark and GFM specs (version 0.28),
+// vendored in `tests/spec` as `spec_tests.py --dump-tests` writes them.
+// the pass rates of the sections are printed with `cargo test --test spec -- --nocapture`,
+// and the tests fail when fewer examples pass than they did
+
+use prose::json::{self, Json};
+use prose::translator::{self, Options};
+use prose::{Dialect, ParseOptions};
+
+use std::collections::BTreeMap;
+use std::panic;
+
+struct Example {
+    markdown: String,
+    html: String,
+    section: String,
+}
+
+fn examples(path: &str) -> Vec<Example> {
+    let path = format!("{}/{}", env!("CARGO_MANIFEST_DIR"), path);
+    let content = std::fs::read_to_string(&path).unwrap();
+    let field = |example: &Json, key: &str| match example.get(key) {
+        Some(Json::String(s)) => s.clone(),
+        _ => panic!("no {} in an example of {}", key, path),
+    };
+    match json::parse(&content).unwrap() {
+        Json::Array(examples) => examples
+            .iter()
+            .map(|example| Example {
+                markdown: field(example, "markdown"),
+                html: field(example, "html"),
+                section: field(example, "section"),
+            })
+            .collect(),
+        _ => panic!("{} is not an array", path),
+    }
+}
+
+// the elements and the attributes the specs expect
+fn render(markdown: &str, dialect: Dialect) -> String {
+    let options = ParseOptions {
+        dialect,
+        ..ParseOptions::default()
+    };
+    let (markdown, _) = prose::parse_with_options(markdown, options);
+    let mut tags = BTreeMap::new();
+    for (from, to) in [("b", "strong"), ("i", "em"), ("s", "del")] {
+        tags.insert(from.to_string(), to.to_string());
+    }
+    let options = Options {
+        language_class: true,
+        keep_comments: true,
+        tags,
+        ..Options::default()
+    };
+    translator::translate(markdown, &options)
+}
+
+// more or less what `normalize.py` of the specs does:
+// the whitespace between tags or after `<br>` is dropped, and the rest of it is a space,
+// except in `<pre>`. the void elements are not closed, and the ids prose gives to headings are dropped
+fn normalize(html: &str) -> String {
+    let html = html.trim().replace(" />", ">");
+    let mut out = String::new();
+    let mut rest = html.as_str();
+    let mut pre = false;
+    while let Some(k) = rest.find(|c: char| c.is_whitespace() || c == '<') {
+        out.push_str(&rest[..k]);
+        rest = &rest[k..];
+        if rest.starts_with('<') {
+            if rest.starts_with("<pre") || rest.starts_with("</pre") {
+                pre = rest.starts_with("<pre");
+            }
+            let bytes = rest.as_bytes();
+            let heading = bytes.len() > 2 && bytes[1] == b'h' && bytes[2].is_ascii_digit();
+            match rest.find(" id=\"") {
+                Some(3) if heading => {
+                    let end = rest[8..].find('"').map_or(rest.len(), |k| 9 + k);
+                    out.push_str(&rest[..3]);
+                    rest = &rest[end..];
+                }
+                _ => {
+                    out.push('<');
+                    rest = &rest[1..];
+                }
+            }
+            continue;
+        }
+        let space = rest.len() - rest.trim_start().len();
+        if pre {
+            out.push_str(&rest[..space]);
+        } else if !((out.ends_with('>') && rest[space..].starts_with('<')) || out.ends_with("<br>"))
+        {
+            out.push(' ');
+        }
+        rest = &rest[space..];
+    }
+    out.push_str(rest);
+    out
+}
+
+// the number of examples which pass, printing the rates of each section
+fn run(path: &str, dialect: Dialect) -> usize {
+    let examples = examples(path);
+    let mut sections: Vec<(String, usize, usize)> = vec![];
+    for example in &examples {
+        let passed = panic::catch_unwind(|| render(&example.markdown, dialect))
+            .is_ok_and(|html| normalize(&html) == normalize(&example.html));
+        match sections.last_mut() {
+            Some((section, ok, total)) if *section == example.section => {
+                *ok += passed as usize;
+                *total += 1;
+            }
+            _ => sections.push((example.section.clone(), passed as usize, 1)),
+        }
+    }
+    let passed = sections.iter().map(|(_, ok, _)| ok).sum();
+    for (section, ok, total) in &sections {
+        println!("{:>4}/{:<4} {}", ok, total, section);
+    }
+    println!(
+        "{}: {}/{} passed ({:.1}%)",
+        path,
+        passed,
+        examples.len(),
+        100.0 * passed as f64 / examples.len() as f64
+    );
+    passed
+}
+
+#[test]
+fn test_normalize() {
+    assert_eq!(
+        normalize("<h1 id=\"a-b\">a  b</h1>\n<p>x<br />\ny\nz</p>\n"),
+        "<h1>a b</h1><p>x<br>y z</p>"
+    );
+    assert_eq!(
+        normalize("<pre><code>a\n</code></pre>\n<hr />\n"),
+        "<pre><code>a\n</code></pre><hr>"
+    );
+}
+
+// raise these as the parser gets closer to the specs
+const COMMONMARK_PASSED: usize = 318;
+const GFM_PASSED: usize = 329;
+
+#[test]
+fn test_commonmark() {
+    assert!(run("tests/spec/commonmark.json", Dialect::CommonMark) >= COMMONMARK_PASSED);
+}
+
+#[test]
+fn test_gfm() {
+    assert!(run("tests/spec/gfm.json", Dialect::Gfm) >= GFM_PASSED);
+}
+
+// the examples are valid markdown, which `prose::parse` and `--strict` must not refuse
+#[test]
+fn test_no_warnings() {
+    for (path, dialect) in [
+        ("tests/spec/commonmark.json", Dialect::CommonMark),
+        ("tests/spec/gfm.json", Dialect::Gfm),
+    ] {
+        for example in examples(path) {
+            let options = ParseOptions {
+                dialect,
+                ..ParseOptions::default()
+            };
+            let (_, warnings) = prose::parse_with_options(&example.markdown, options);
+            assert_eq!(warnings, vec![], "{:?}", example.markdown);
+        }
+    }
+}